@@ -345,6 +345,10 @@ pub struct Irreversible {
     castle_permissions: u8,
     phase: Phase,
     psqt: EvaluationScore,
+    //Phase scores of the knights, bishops, rooks and queens of each side
+    non_pawn_material: [i16; 2],
+    //All pieces on the board, kings and pawns included
+    piece_count: u8,
 }
 impl Irreversible {
     pub fn new(
@@ -354,6 +358,8 @@ impl Irreversible {
         castle_permissions: u8,
         phase: Phase,
         psqt: EvaluationScore,
+        non_pawn_material: [i16; 2],
+        piece_count: u8,
     ) -> Self {
         Irreversible {
             hash,
@@ -362,6 +368,8 @@ impl Irreversible {
             castle_permissions,
             phase,
             psqt,
+            non_pawn_material,
+            piece_count,
         }
     }
 }
@@ -412,6 +420,14 @@ impl GameState {
     pub fn get_en_passant(&self) -> u64 {
         self.irreversible.en_passant
     }
+    //The en passant square only takes part in the hash if a pawn of the side to move attacks it, otherwise
+    //the position is the same as the one without en passant square
+    pub fn capturable_en_passant(&self) -> u64 {
+        let pawns = self.get_piece(PieceType::Pawn, self.color_to_move);
+        self.get_en_passant()
+            & (pawn_east_targets(self.color_to_move, pawns)
+                | pawn_west_targets(self.color_to_move, pawns))
+    }
     pub fn get_half_moves(&self) -> usize {
         self.irreversible.half_moves as usize
     }
@@ -421,6 +437,12 @@ impl GameState {
     pub fn get_psqt(&self) -> EvaluationScore {
         self.irreversible.psqt
     }
+    pub fn get_non_pawn_material(&self, side: usize) -> i16 {
+        self.irreversible.non_pawn_material[side]
+    }
+    pub fn get_piece_count(&self) -> usize {
+        self.irreversible.piece_count as usize
+    }
 
     pub fn get_piece(&self, piece_type: PieceType, side: usize) -> u64 {
         self.piece_bb[piece_type as usize] & self.color_bb[side]
@@ -518,7 +540,7 @@ impl GameState {
         }
         self.irreversible.hash ^=
             ZOBRIST_KEYS.castle_permissions[self.castle_permissions() as usize];
-        if self.get_en_passant() != 0u64 {
+        if self.capturable_en_passant() != 0u64 {
             let file = self.get_en_passant().trailing_zeros() as usize % 8;
             self.irreversible.hash ^= ZOBRIST_KEYS.en_passant[file];
         }
@@ -548,65 +570,98 @@ impl GameState {
         );
         self.irreversible.psqt = p_w - p_b
     }
+    pub fn initialize_material(&mut self) {
+        let (non_pawn_material, piece_count) = self.recount_material();
+        self.irreversible.non_pawn_material = non_pawn_material;
+        self.irreversible.piece_count = piece_count;
+    }
+    //The material counters from scratch, they are kept up to date incrementally by make_move
+    pub fn recount_material(&self) -> ([i16; 2], u8) {
+        let mut non_pawn_material = [0; 2];
+        for (side, material) in non_pawn_material.iter_mut().enumerate() {
+            for pt in [
+                PieceType::Knight,
+                PieceType::Bishop,
+                PieceType::Rook,
+                PieceType::Queen,
+            ]
+            .iter()
+            {
+                *material += self.get_piece_amt(*pt, side) as i16 * pt.to_phase_score();
+            }
+        }
+        (non_pawn_material, self.get_all_pieces().count_ones() as u8)
+    }
     pub fn initialize_phase(&mut self) {
         self.irreversible.phase = Phase::from_state(self);
     }
     pub fn initialize(&mut self) {
         self.initialize_zobrist_hash();
         self.initialize_psqt();
+        self.initialize_material();
         self.initialize_phase();
     }
     pub fn from_fen(fen: &str) -> GameState {
-        let vec: Vec<&str> = fen.trim().split(' ').collect();
+        GameState::try_from_fen(fen).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    //Like from_fen, but malformed input (e.g. from a GUI) is reported instead of panicking
+    pub fn try_from_fen(fen: &str) -> std::result::Result<GameState, String> {
+        let invalid = |reason: &str| Err(format!("Invalid FEN ({}): {}", reason, fen));
+        let vec: Vec<&str> = fen.split_whitespace().collect();
         if vec.len() < 4 {
-            panic!("Invalid FEN");
+            return invalid("less than 4 fields");
         }
         //Parse through FEN
         //Pieces
         let pieces: Vec<&str> = vec[0].split('/').collect();
         if pieces.len() != 8 {
-            panic!("Invalid FEN");
+            return invalid("not 8 ranks");
         }
         //Iterate over all 8 ranks
         let mut piece_bb: [u64; 6] = [0u64; 6];
         let mut color_bb: [u64; 2] = [0u64; 2];
-        for (rank, rank_str) in pieces.iter().enumerate().take(8) {
+        for (rank, rank_str) in pieces.iter().enumerate() {
             let mut file: usize = 0;
-            let mut rank_str_idx: usize = 0;
-            while file < 8 {
+            for x in rank_str.chars() {
+                if file >= 8 {
+                    return invalid("rank too long");
+                }
                 let idx = (7 - rank) * 8 + file;
-                let next_char = rank_str.chars().nth(rank_str_idx);
-                rank_str_idx += 1;
-                match next_char {
-                    Some(x) => {
-                        if ['p', 'n', 'b', 'r', 'q', 'k'].contains(&x.to_ascii_lowercase()) {
-                            let side = if x.is_uppercase() { WHITE } else { BLACK };
-                            let piece_type = match x.to_lowercase().next().unwrap() {
-                                'p' => PieceType::Pawn,
-                                'n' => PieceType::Knight,
-                                'b' => PieceType::Bishop,
-                                'r' => PieceType::Rook,
-                                'q' => PieceType::Queen,
-                                'k' => PieceType::King,
-                                _ => panic!("Invalid fen"),
-                            };
-                            color_bb[side] |= square(idx);
-                            piece_bb[piece_type as usize] |= square(idx);
-                            file += 1;
-                        } else {
-                            file += x.to_string().parse::<usize>().expect("Invalid Fen!");
-                        }
+                let piece_type = match x.to_ascii_lowercase() {
+                    'p' => PieceType::Pawn,
+                    'n' => PieceType::Knight,
+                    'b' => PieceType::Bishop,
+                    'r' => PieceType::Rook,
+                    'q' => PieceType::Queen,
+                    'k' => PieceType::King,
+                    '1'..='8' => {
+                        file += x.to_digit(10).unwrap() as usize;
+                        continue;
                     }
-                    None => panic!("Invalid FEN"),
+                    _ => return invalid("unknown piece"),
                 };
+                let side = if x.is_uppercase() { WHITE } else { BLACK };
+                color_bb[side] |= square(idx);
+                piece_bb[piece_type as usize] |= square(idx);
+                file += 1;
+            }
+            if file != 8 {
+                return invalid("rank doesn't have 8 files");
             }
         }
+        let kings = piece_bb[PieceType::King as usize];
+        if (kings & color_bb[WHITE]).count_ones() != 1
+            || (kings & color_bb[BLACK]).count_ones() != 1
+        {
+            return invalid("each side needs exactly one king");
+        }
 
         //Side to move
         let color_to_move = match vec[1] {
             "w" => WHITE,
             "b" => BLACK,
-            _ => panic!("Invalid FEN!"),
+            _ => return invalid("side to move"),
         };
 
         //Castling-Abilities
@@ -625,20 +680,24 @@ impl GameState {
         }
         //En passant target square
         let en_passant: u64 = if vec[3] != "-" {
-            let mut idx: usize = 0usize;
-            let file = vec[3].chars().next();
-            let rank = vec[3].chars().nth(1);
-            idx += char_to_file(file.expect("Invalid FEN!").to_ascii_lowercase());
-            idx += 8 * char_to_rank(rank.expect("Invalid FEN!"));
-            square(idx)
+            let mut chars = vec[3].chars();
+            match (chars.next(), chars.next(), chars.next()) {
+                (Some(file @ 'a'..='h'), Some(rank @ '1'..='8'), None) => {
+                    square(char_to_file(file) + 8 * char_to_rank(rank))
+                }
+                _ => return invalid("en passant square"),
+            }
         } else {
             0u64
         };
         let (half_moves, full_moves) = if vec.len() > 4 {
-            (
-                vec[4].parse().expect("unable to parse half moves"),
-                vec[5].parse().expect("unable to parse full moves"),
-            )
+            match (
+                vec[4].parse(),
+                vec.get(5).map_or(Ok(1), |full_moves| full_moves.parse()),
+            ) {
+                (Ok(half_moves), Ok(full_moves)) => (half_moves, full_moves),
+                _ => return invalid("move counters"),
+            }
         } else {
             (0, 1)
         };
@@ -653,11 +712,13 @@ impl GameState {
                 castle_permissions,
                 Phase::default(),
                 EvaluationScore(0, 0),
+                [0; 2],
+                0,
             ),
             full_moves,
         );
         res.initialize();
-        res
+        Ok(res)
     }
 
     pub fn to_fen(&self) -> String {
@@ -760,6 +821,8 @@ impl GameState {
                 CASTLE_ALL,
                 Phase::default(),
                 EvaluationScore(0, 0),
+                [0; 2],
+                0,
             ),
             1,
         );
@@ -769,10 +832,7 @@ impl GameState {
 
     #[inline(always)]
     pub fn has_non_pawns(&self, side: usize) -> bool {
-        self.get_piece(PieceType::Bishop, side) != 0u64
-            || self.get_piece(PieceType::Knight, side) != 0u64
-            || self.get_piece(PieceType::Rook, side) != 0u64
-            || self.get_piece(PieceType::Queen, side) != 0u64
+        self.get_non_pawn_material(side) > 0
     }
 
     //Calculates if a given move gives check. Does not necessarily return true even if move gives check
@@ -1021,19 +1081,31 @@ impl GameState {
     }
 }
 
-impl Display for GameState {
-    fn fmt(&self, formatter: &mut Formatter) -> Result {
+impl GameState {
+    //A flipped board is seen from black's side, with a8 in the bottom right corner
+    pub fn board_string(&self, flipped: bool) -> String {
         let mut res_str: String = String::new();
         res_str.push_str("+---+---+---+---+---+---+---+---+\n");
         for rank in 0..8 {
             res_str.push_str("| ");
             for file in 0..8 {
-                let idx = 8 * (7 - rank) + file;
+                let idx = if flipped {
+                    8 * rank + 7 - file
+                } else {
+                    8 * (7 - rank) + file
+                };
                 res_str.push_str(self.get_piece_on(idx));
                 res_str.push_str(" | ");
             }
             res_str.push_str("\n+---+---+---+---+---+---+---+---+\n");
         }
+        res_str
+    }
+}
+
+impl Display for GameState {
+    fn fmt(&self, formatter: &mut Formatter) -> Result {
+        let mut res_str: String = self.board_string(false);
         res_str.push_str("Castle Rights: \n");
         res_str.push_str(&format!("CWK: {}\n", self.castle_white_kingside()));
         res_str.push_str(&format!("CWQ: {}\n", self.castle_white_queenside()));
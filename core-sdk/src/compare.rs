@@ -0,0 +1,133 @@
+use crate::board_representation::game_state::{GameMove, GameState};
+use crate::move_generation::movegen;
+use crate::search::cache::Cache;
+use crate::search::searcher::{search_move, InterThreadCommunicationSystem};
+use crate::search::timecontrol::TimeControl;
+use crate::search::MAX_SEARCH_DEPTH;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+
+//Same hash size as bench, so node counts don't depend on the Hash option
+pub const COMPARISON_HASH_SIZE: usize = 8;
+
+pub struct ComparisonResult {
+    pub fen: String,
+    //None for a position without any legal move, which isn't searched
+    pub bestmove: Option<GameMove>,
+    pub score: i16,
+    pub nodes: u64,
+}
+
+//Searches every position to exactly the given depth on a single thread, without any time checks and with a
+//cleared hash table, so that the node counts of two builds can be compared exactly
+pub fn compare_positions(fens: &[String], depth: usize) -> Vec<ComparisonResult> {
+    let itcs = Arc::new(InterThreadCommunicationSystem::default());
+    InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+    *itcs.cache() = Cache::with_size_threaded(COMPARISON_HASH_SIZE, 1);
+    itcs.uci_options().quiet = true;
+    let depth = depth.clamp(1, MAX_SEARCH_DEPTH);
+    let mut res = Vec::with_capacity(fens.len());
+    for fen in fens.iter() {
+        let state = GameState::from_fen(fen);
+        let mut movelist = movegen::MoveList::default();
+        movegen::generate_moves(&state, false, &mut movelist);
+        if movelist.move_list.is_empty() {
+            res.push(ComparisonResult {
+                fen: fen.clone(),
+                bestmove: None,
+                score: 0,
+                nodes: 0,
+            });
+            continue;
+        }
+        itcs.cache().clear_threaded(1);
+        let score = search_move(
+            Arc::clone(&itcs),
+            depth as i16,
+            state,
+            Vec::new(),
+            TimeControl::Infinite,
+        );
+        res.push(ComparisonResult {
+            fen: fen.clone(),
+            bestmove: itcs.played_pv().pv.pv[0],
+            score,
+            nodes: itcs.get_nodes_sum(),
+        });
+    }
+    res
+}
+
+pub fn run_comparison(path: &str, depth: usize) {
+    let reader = BufReader::new(File::open(path).expect("Unable to open FEN file"));
+    let fens: Vec<String> = reader
+        .lines()
+        .map(|line| line.expect("Unable to read FEN file").trim().to_owned())
+        .filter(|line| !line.is_empty())
+        .collect();
+    let results = compare_positions(&fens, depth);
+    for result in results.iter() {
+        match result.bestmove {
+            Some(bestmove) => println!(
+                "{}: bestmove {:?} score {} nodes {}",
+                result.fen, bestmove, result.score, result.nodes
+            ),
+            None => println!("{}: no legal move", result.fen),
+        }
+    }
+    println!("Positions: {}", results.len());
+    println!(
+        "Nodes: {}",
+        results.iter().map(|result| result.nodes).sum::<u64>()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::compare_positions;
+
+    #[test]
+    fn compare_deterministic_test() {
+        let fens: Vec<String> = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+            "8/5pk1/6p1/8/3R4/6P1/5PK1/8 b - - 0 1",
+        ]
+        .iter()
+        .map(|fen| fen.to_string())
+        .collect();
+        let first = compare_positions(&fens, 6);
+        let second = compare_positions(&fens, 6);
+        assert_eq!(first.len(), 3);
+        for (a, b) in first.iter().zip(second.iter()) {
+            assert!(a.nodes > 0);
+            assert_eq!(a.nodes, b.nodes);
+            assert_eq!(a.score, b.score);
+            assert_eq!(a.bestmove, b.bestmove);
+        }
+    }
+
+    #[test]
+    fn compare_edge_cases_test() {
+        let fens: Vec<String> = [
+            //Checkmated and stalemated
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+            "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1",
+            "8/5pk1/6p1/8/3R4/6P1/5PK1/8 b - - 0 1",
+        ]
+        .iter()
+        .map(|fen| fen.to_string())
+        .collect();
+        let results = compare_positions(&fens, 0);
+        assert_eq!(results.len(), 3);
+        for result in results[..2].iter() {
+            assert!(result.bestmove.is_none());
+            assert_eq!(result.nodes, 0);
+        }
+        //A depth of 0 is searched to depth 1
+        assert!(results[2].bestmove.is_some());
+        assert!(results[2].nodes > 0);
+        assert_eq!(compare_positions(&fens[2..], 1)[0].nodes, results[2].nodes);
+    }
+}
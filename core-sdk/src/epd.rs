@@ -0,0 +1,234 @@
+use crate::board_representation::game_state::{GameMove, GameState};
+use crate::search::cache::Cache;
+use crate::search::searcher::{search_move, InterThreadCommunicationSystem};
+use crate::search::timecontrol::TimeControl;
+use crate::search::MAX_SEARCH_DEPTH;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+
+pub const DEFAULT_EPD_DEPTH: usize = 10;
+
+//A position of an EPD collection, e.g. `<4 fen fields> bm Nf3; id "Suite.001"; ce 35; acd 12;`
+pub struct EPDEntry {
+    pub fen: String,
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+impl EPDEntry {
+    pub fn parse(line: &str) -> Result<EPDEntry, String> {
+        let line = line.trim();
+        let mut fields = Vec::with_capacity(4);
+        let mut rest = line;
+        for _ in 0..4 {
+            rest = rest.trim_start();
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            if end == 0 {
+                return Err(format!("EPD line has less than 4 fen fields: {}", line));
+            }
+            fields.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+        let mut operations = Vec::new();
+        for operation in split_operations(rest)? {
+            let mut tokens = operation.into_iter();
+            if let Some(opcode) = tokens.next() {
+                operations.push((opcode, tokens.collect()));
+            }
+        }
+        Ok(EPDEntry {
+            fen: fields.join(" "),
+            operations,
+        })
+    }
+
+    pub fn operands(&self, opcode: &str) -> Option<&[String]> {
+        self.operations
+            .iter()
+            .find(|(name, _)| name == opcode)
+            .map(|(_, operands)| operands.as_slice())
+    }
+
+    fn single_operand(&self, opcode: &str) -> Option<&str> {
+        self.operands(opcode)
+            .and_then(|operands| operands.first())
+            .map(|operand| operand.as_str())
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.single_operand("id")
+    }
+
+    //Centipawn evaluation from the point of view of the side to move
+    pub fn ce(&self) -> Option<i16> {
+        self.single_operand("ce").and_then(|ce| ce.parse().ok())
+    }
+
+    //Analysis count depth, the depth to search the position to
+    pub fn acd(&self) -> Option<usize> {
+        self.single_operand("acd").and_then(|acd| acd.parse().ok())
+    }
+
+    //Whether the move matches a best move (bm) and avoids all avoid moves (am) of the position. None if the
+    //position has neither, check and annotation suffixes of the SAN operands are ignored
+    pub fn solved_by(&self, mv: GameMove) -> Option<bool> {
+        let strip = |san: &str| san.trim_end_matches(|c| "+#!?".contains(c)).to_owned();
+        let san = strip(&mv.to_san(&GameState::from_fen(&self.fen)));
+        let contains = |operands: &[String]| operands.iter().any(|operand| strip(operand) == san);
+        match (self.operands("bm"), self.operands("am")) {
+            (None, None) => None,
+            (bm, am) => Some(bm.is_none_or(contains) && !am.is_some_and(contains)),
+        }
+    }
+}
+
+//Splits the operations at semicolons and their tokens at whitespace, except inside quoted strings
+fn split_operations(operations: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut res = Vec::new();
+    let mut current_operation = Vec::new();
+    let mut current_token = String::new();
+    let mut quoted = false;
+    for c in operations.chars() {
+        if quoted {
+            if c == '"' {
+                quoted = false;
+                current_operation.push(std::mem::take(&mut current_token));
+            } else {
+                current_token.push(c);
+            }
+        } else if c == '"' {
+            quoted = true;
+        } else if c == ';' || c.is_whitespace() {
+            if !current_token.is_empty() {
+                current_operation.push(std::mem::take(&mut current_token));
+            }
+            if c == ';' && !current_operation.is_empty() {
+                res.push(std::mem::take(&mut current_operation));
+            }
+        } else {
+            current_token.push(c);
+        }
+    }
+    if quoted {
+        return Err(format!(
+            "Unterminated string in EPD operations: {}",
+            operations
+        ));
+    }
+    if !current_token.is_empty() {
+        current_operation.push(current_token);
+    }
+    if !current_operation.is_empty() {
+        return Err(format!(
+            "Missing semicolon in EPD operations: {}",
+            operations
+        ));
+    }
+    Ok(res)
+}
+
+pub struct EPDResult {
+    pub depth: usize,
+    pub score: i16,
+}
+
+pub fn analyse_epd(
+    itcs: &Arc<InterThreadCommunicationSystem>,
+    entry: &EPDEntry,
+    default_depth: usize,
+) -> EPDResult {
+    let depth = entry.acd().unwrap_or(default_depth).min(MAX_SEARCH_DEPTH);
+    let state = GameState::from_fen(&entry.fen);
+    let score = search_move(
+        Arc::clone(itcs),
+        depth as i16,
+        state,
+        Vec::new(),
+        TimeControl::Infinite,
+    );
+    itcs.cache().clear_threaded(1);
+    EPDResult { depth, score }
+}
+
+pub fn run_epd_suite(path: &str, default_depth: usize) {
+    let itcs = Arc::new(InterThreadCommunicationSystem::default());
+    InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+    *itcs.cache() = Cache::with_size_threaded(8, 1);
+    itcs.uci_options().quiet = true;
+    let reader = BufReader::new(File::open(path).expect("Unable to open EPD file"));
+    let (mut positions, mut compared, mut total_deviation) = (0, 0, 0u64);
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.expect("Unable to read EPD file");
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = EPDEntry::parse(&line).unwrap_or_else(|e| panic!("{}", e));
+        let result = analyse_epd(&itcs, &entry, default_depth);
+        positions += 1;
+        let label = entry
+            .id()
+            .map(|id| id.to_owned())
+            .unwrap_or_else(|| format!("#{}", index + 1));
+        let mut res_str = format!(
+            "{}: depth {} bestmove {:?}",
+            label,
+            result.depth,
+            itcs.played_pv().pv.pv[0].expect("No bestmove")
+        );
+        res_str.push_str(&format!(" score {}", result.score));
+        if let Some(ce) = entry.ce() {
+            let deviation = (i32::from(result.score) - i32::from(ce)).abs();
+            res_str.push_str(&format!(" ce {} deviation {}", ce, deviation));
+            compared += 1;
+            total_deviation += deviation as u64;
+        }
+        println!("{}", res_str);
+    }
+    println!("Positions: {}", positions);
+    if compared > 0 {
+        println!(
+            "Average deviation from ce: {:.1}",
+            total_deviation as f64 / compared as f64
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{analyse_epd, EPDEntry};
+    use crate::search::searcher::test_itcs;
+
+    #[test]
+    fn epd_opcodes_test() {
+        let entry = EPDEntry::parse(
+            "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - \
+             bm Qxf7#; id \"Scholar's mate; 1\"; ce 32762;  acd 3; c0 \"Quoted  spaces\" 2;",
+        )
+        .unwrap();
+        assert_eq!(
+            entry.fen,
+            "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq -"
+        );
+        assert_eq!(entry.id(), Some("Scholar's mate; 1"));
+        assert_eq!(entry.ce(), Some(32762));
+        assert_eq!(entry.acd(), Some(3));
+        assert_eq!(entry.operands("bm").unwrap(), ["Qxf7#"]);
+        assert_eq!(entry.operands("c0").unwrap(), ["Quoted  spaces", "2"]);
+        assert_eq!(entry.operations.len(), 5);
+        assert!(entry.operands("am").is_none());
+
+        let bare = EPDEntry::parse("8/8/4k3/8/8/3RK3/8/8 b - -").unwrap();
+        assert!(bare.operations.is_empty());
+        assert_eq!(bare.id(), None);
+        assert!(EPDEntry::parse("8/8/4k3/8/8/3RK3/8/8 b").is_err());
+        assert!(EPDEntry::parse("8/8/4k3/8/8/3RK3/8/8 b - - id \"open;").is_err());
+        assert!(EPDEntry::parse("8/8/4k3/8/8/3RK3/8/8 b - - acd 3").is_err());
+
+        //acd sets the search depth
+        let itcs = test_itcs();
+        let result = analyse_epd(&itcs, &entry, 1);
+        assert_eq!(result.depth, 3);
+        assert_eq!(itcs.played_pv().depth, 3);
+        assert_eq!(analyse_epd(&itcs, &bare, 2).depth, 2);
+    }
+}
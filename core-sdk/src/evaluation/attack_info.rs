@@ -0,0 +1,141 @@
+use crate::bitboards::bitboards::constants::{square, KING_ATTACKS};
+use crate::board_representation::game_state::{GameState, PieceType, BLACK, WHITE};
+use crate::move_generation::movegen::pawn_targets;
+
+pub const PIECE_TYPES: [PieceType; 6] = [
+    PieceType::Pawn,
+    PieceType::Knight,
+    PieceType::Bishop,
+    PieceType::Rook,
+    PieceType::Queen,
+    PieceType::King,
+];
+
+//Attack maps of both sides, computed once per evaluation and shared by the evaluation terms
+pub struct AttackInfo {
+    //Indexed by [side][piece type], sliders x-ray the enemy king like they do for mobility and king safety
+    pub attacks: [[u64; 6]; 2],
+    //Attacks of the knight, bishop, rook or queen standing on the square, x-raying the enemy king
+    pub piece_attacks: [u64; 64],
+    //Same as the attacks from movegen, only the sliders of the side not to move x-ray the king
+    pub defended_by_minors: [u64; 2],
+    pub defended_by_majors: [u64; 2],
+    pub defended: [u64; 2],
+}
+
+impl AttackInfo {
+    pub fn new(g: &GameState) -> Self {
+        let mut res = AttackInfo {
+            attacks: [[0u64; 6]; 2],
+            piece_attacks: [0u64; 64],
+            defended_by_minors: [0u64; 2],
+            defended_by_majors: [0u64; 2],
+            defended: [0u64; 2],
+        };
+        let all_pieces = g.get_all_pieces();
+        for &side in [WHITE, BLACK].iter() {
+            let enemy_king = g.get_piece(PieceType::King, 1 - side);
+            let xray_occupancy = all_pieces ^ enemy_king;
+            res.attacks[side][PieceType::Pawn as usize] =
+                pawn_targets(side, g.get_piece(PieceType::Pawn, side));
+            res.attacks[side][PieceType::King as usize] = KING_ATTACKS[g.get_king_square(side)];
+            for &pt in PIECE_TYPES[1..5].iter() {
+                let mut pieces = g.get_piece(pt, side);
+                let mut defended = 0u64;
+                while pieces != 0u64 {
+                    let idx = pieces.trailing_zeros() as usize;
+                    let attack = pt.attacks(idx, xray_occupancy);
+                    res.piece_attacks[idx] = attack;
+                    res.attacks[side][pt as usize] |= attack;
+                    //The king only blocks the attack if it is hit
+                    defended |= if side == g.get_color_to_move() && attack & enemy_king != 0u64 {
+                        pt.attacks(idx, all_pieces)
+                    } else {
+                        attack
+                    };
+                    pieces ^= square(idx);
+                }
+                if pt == PieceType::Rook || pt == PieceType::Queen {
+                    res.defended_by_majors[side] |= defended;
+                } else {
+                    res.defended_by_minors[side] |= defended;
+                }
+            }
+            res.defended_by_minors[side] |= res.attacks[side][PieceType::Pawn as usize];
+            res.defended[side] = res.defended_by_minors[side]
+                | res.defended_by_majors[side]
+                | res.attacks[side][PieceType::King as usize];
+        }
+        res
+    }
+
+    //All squares attacked by the side, sliders x-ray the enemy king
+    pub fn all_attacks(&self, side: usize) -> u64 {
+        self.attacks[side]
+            .iter()
+            .fold(0u64, |acc, &attack| acc | attack)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AttackInfo, PIECE_TYPES};
+    use crate::bitboards::bitboards::constants::{square, KING_ATTACKS};
+    use crate::board_representation::game_state::{GameState, PieceType, BLACK, WHITE};
+    use crate::move_generation::movegen::pawn_targets;
+
+    #[test]
+    fn attack_info_test() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq -",
+            //Sliders giving check x-ray the king
+            "4k3/8/8/8/8/3Q4/8/r3K3 w - - 0 1",
+            "4k3/4R3/8/8/8/8/8/3qK3 b - - 0 1",
+            "1q2k3/2P5/3N4/4r3/1b6/8/3PP3/3QK1NR b K - 0 1",
+        ];
+        for fen in fens.iter() {
+            let g = GameState::from_fen(fen);
+            let info = AttackInfo::new(&g);
+            let all_pieces = g.get_all_pieces();
+            for &side in [WHITE, BLACK].iter() {
+                let occupancy = all_pieces ^ square(g.get_king_square(1 - side));
+                let mut all_attacks = 0u64;
+                for &pt in PIECE_TYPES.iter() {
+                    let mut expected = 0u64;
+                    let mut pieces = g.get_piece(pt, side);
+                    while pieces != 0u64 {
+                        let idx = pieces.trailing_zeros() as usize;
+                        let attack = match pt {
+                            PieceType::Pawn => pawn_targets(side, square(idx)),
+                            _ => pt.attacks(idx, occupancy),
+                        };
+                        if pt != PieceType::Pawn && pt != PieceType::King {
+                            assert_eq!(info.piece_attacks[idx], attack);
+                        }
+                        expected |= attack;
+                        pieces ^= square(idx);
+                    }
+                    assert_eq!(info.attacks[side][pt as usize], expected);
+                    all_attacks |= expected;
+                }
+                assert_eq!(info.all_attacks(side), all_attacks);
+                assert_eq!(
+                    info.defended_by_minors[side],
+                    g.get_minor_attacks_from_side(side)
+                );
+                assert_eq!(
+                    info.defended_by_majors[side],
+                    g.get_major_attacks_from_side(side)
+                );
+                assert_eq!(
+                    info.defended[side],
+                    g.get_minor_attacks_from_side(side)
+                        | g.get_major_attacks_from_side(side)
+                        | KING_ATTACKS[g.get_king_square(side)]
+                );
+            }
+        }
+    }
+}
@@ -1,3 +1,4 @@
+pub mod attack_info;
 pub mod parameters;
 pub mod params;
 pub mod phase;
@@ -13,6 +14,7 @@ use crate::evaluation::parameters::normal_parameters::*;
 use crate::evaluation::trace::LargeTrace;
 use crate::move_generation::movegen;
 use crate::move_generation::movegen::{pawn_east_targets, pawn_targets, pawn_west_targets};
+use attack_info::AttackInfo;
 use params::*;
 use psqt_evaluation::psqt;
 use psqt_evaluation::BLACK_INDEX;
@@ -21,6 +23,29 @@ use std::ops;
 
 pub const MG: usize = 0;
 pub const EG: usize = 1;
+//Endgame bonus for winning the pawn race in a pure pawn endgame
+pub const UNSTOPPABLE_PASSER_SCORE: i16 = 600;
+//(Bishop square, enemy pawn square) from white's point of view, e.g. the pawn on b6 after Bxa7 b6
+pub const TRAPPED_BISHOP_PATTERNS: [(usize, usize); 4] = [(48, 41), (55, 46), (57, 50), (62, 53)];
+pub const RIM: u64 = FILES[0] | FILES[7] | RANKS[0] | RANKS[7];
+//A minor with at most this many target squares that runs into own pawns blocked by enemy pawns is stuck behind
+//its own pawn chain
+pub const BEHIND_PAWN_CHAIN_MAX_MOBILITY: usize = 2;
+//Scores of fortress draws are scaled towards zero, the king of the strong side has to be further away from the
+//promotion square than this
+pub const FORTRESS_DRAW_SCALE: f32 = 0.03;
+pub const FORTRESS_KING_DISTANCE: usize = 3;
+//Rook endgames with equal rooks and at most a pawn difference are drawish. R+P vs R is scaled down a lot further
+//if the defending king stands in front of the pawn or close to the promotion square of a rook pawn
+pub const ROOK_ENDGAME_SCALE: f32 = 0.8;
+pub const ROOK_ENDGAME_DRAW_SCALE: f32 = 0.15;
+pub const ROOK_PAWN_KING_DISTANCE: usize = 2;
+//In KBN vs K the lone king is driven towards a corner of the bishop's color per step of manhattan distance,
+//while the strong king follows it per step of distance
+pub const KBN_CORNER_SCORE: i16 = 30;
+pub const KBN_KING_SCORE: i16 = 10;
+//Once the half move clock passes this, the score is damped linearly towards zero at the fifty move draw
+pub const FIFTY_MOVE_SCALE_START: usize = 80;
 
 #[derive(Copy, Clone, PartialEq)]
 pub struct EvaluationScore(pub i16, pub i16);
@@ -97,7 +122,98 @@ pub struct EvaluationResult {
     pub trace: LargeTrace,
 }
 
+//White's advantage per evaluation term, before endgame rescaling and phasing
+#[derive(Clone, Debug, Default)]
+pub struct DetailedEval {
+    pub phase: f32,
+    pub tempo: EvaluationScore,
+    pub psqt: EvaluationScore,
+    pub piece_values: EvaluationScore,
+    pub pawns: EvaluationScore,
+    pub knights: EvaluationScore,
+    pub piecewise: EvaluationScore,
+    pub king: EvaluationScore,
+    pub pawn_race: EvaluationScore,
+    pub mating_corner: EvaluationScore,
+    pub final_eval: i16,
+}
+impl DetailedEval {
+    pub fn sum(&self) -> EvaluationScore {
+        self.tempo
+            + self.psqt
+            + self.piece_values
+            + self.pawns
+            + self.knights
+            + self.piecewise
+            + self.king
+            + self.pawn_race
+            + self.mating_corner
+    }
+
+    //Phases the summed terms the same way eval_game_state does
+    pub fn evaluate(&self, g: &GameState) -> i16 {
+        if is_guaranteed_draw(g) {
+            return 0;
+        }
+        let mut res = self.sum();
+        endgame_rescaling(
+            g,
+            &mut res,
+            self.phase,
+            #[cfg(feature = "texel-tuning")]
+            &mut LargeTrace::default(),
+        );
+        fortress_rescaling(
+            g,
+            &mut res,
+            #[cfg(feature = "texel-tuning")]
+            &mut LargeTrace::default(),
+        );
+        rook_endgame_rescaling(
+            g,
+            &mut res,
+            #[cfg(feature = "texel-tuning")]
+            &mut LargeTrace::default(),
+        );
+        fifty_move_rescaling(
+            g,
+            &mut res,
+            #[cfg(feature = "texel-tuning")]
+            &mut LargeTrace::default(),
+        );
+        res.1 = (f64::from(res.1) / 1.5) as i16;
+        res.interpolate(self.phase)
+    }
+}
+impl Display for DetailedEval {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        writeln!(f, "Term            (MG , EG)")?;
+        writeln!(f, "Tempo           {}", self.tempo)?;
+        writeln!(f, "PSQT            {}", self.psqt)?;
+        writeln!(f, "Piece values    {}", self.piece_values)?;
+        writeln!(f, "Pawns           {}", self.pawns)?;
+        writeln!(f, "Knights         {}", self.knights)?;
+        writeln!(f, "Piecewise       {}", self.piecewise)?;
+        writeln!(f, "King            {}", self.king)?;
+        writeln!(f, "Pawn race       {}", self.pawn_race)?;
+        writeln!(f, "Mating corner   {}", self.mating_corner)?;
+        writeln!(f, "Sum             {}", self.sum())?;
+        writeln!(f, "Phase           {}", self.phase)?;
+        write!(f, "Final           cp {}", self.final_eval)
+    }
+}
+
 pub fn eval_game_state(g: &GameState) -> EvaluationResult {
+    evaluate(g).0
+}
+
+//Slightly slower than eval_game_state, so only meant for analysis
+pub fn eval_game_state_detailed(g: &GameState) -> DetailedEval {
+    evaluate(g).1
+}
+
+#[inline(always)]
+fn evaluate(g: &GameState) -> (EvaluationResult, DetailedEval) {
     #[cfg(feature = "display-eval")]
     {
         println!("Evaluating GameState fen: {}", g.to_fen());
@@ -108,6 +224,10 @@ pub fn eval_game_state(g: &GameState) -> EvaluationResult {
         trace: LargeTrace::default(),
     };
     let phase = g.get_phase().phase;
+    let mut detailed = DetailedEval {
+        phase,
+        ..DetailedEval::default()
+    };
     #[cfg(feature = "texel-tuning")]
     {
         result.trace.phase = phase;
@@ -117,7 +237,7 @@ pub fn eval_game_state(g: &GameState) -> EvaluationResult {
         {
             result.trace.is_guaranteed_draw = true;
         }
-        return result;
+        return (result, detailed);
     }
     let mut res = EvaluationScore::default();
 
@@ -127,6 +247,7 @@ pub fn eval_game_state(g: &GameState) -> EvaluationResult {
         TEMPO_BONUS * -1
     };
     res += tempo;
+    detailed.tempo = tempo;
     #[cfg(feature = "display-eval")]
     {
         println!("\nTempo:{}", tempo);
@@ -140,20 +261,7 @@ pub fn eval_game_state(g: &GameState) -> EvaluationResult {
         };
     }
     //Initialize all attacks
-    let (white_defended_by_minors, white_defended_by_majors) = (
-        g.get_minor_attacks_from_side(WHITE),
-        g.get_major_attacks_from_side(WHITE),
-    );
-    let white_defended = white_defended_by_minors
-        | white_defended_by_majors
-        | KING_ATTACKS[g.get_king_square(WHITE)];
-    let (black_defended_by_minors, black_defended_by_majors) = (
-        g.get_minor_attacks_from_side(BLACK),
-        g.get_major_attacks_from_side(BLACK),
-    );
-    let black_defended = black_defended_by_minors
-        | black_defended_by_majors
-        | KING_ATTACKS[g.get_king_square(BLACK)];
+    let attack_info = AttackInfo::new(g);
 
     let psqt_score: EvaluationScore =
         if cfg!(feature = "display-eval") || cfg!(feature = "texel-tuning") {
@@ -180,6 +288,7 @@ pub fn eval_game_state(g: &GameState) -> EvaluationResult {
         println!("\nPSQT Sum: {}", psqt_score);
     }
     res += psqt_score;
+    detailed.psqt = psqt_score;
 
     let (pieces_w, pieces_b) = (
         piece_values(
@@ -205,21 +314,20 @@ pub fn eval_game_state(g: &GameState) -> EvaluationResult {
         );
     }
     res += pieces_w - pieces_b;
+    detailed.piece_values = pieces_w - pieces_b;
 
     let (pawns_w, pawns_b) = (
         pawns(
             true,
             g,
-            white_defended,
-            black_defended,
+            &attack_info,
             #[cfg(feature = "texel-tuning")]
             &mut result.trace,
         ),
         pawns(
             false,
             g,
-            black_defended,
-            white_defended,
+            &attack_info,
             #[cfg(feature = "texel-tuning")]
             &mut result.trace,
         ),
@@ -234,17 +342,20 @@ pub fn eval_game_state(g: &GameState) -> EvaluationResult {
         );
     }
     res += pawns_w - pawns_b;
+    detailed.pawns = pawns_w - pawns_b;
 
     let (knights_w, knights_b) = (
         knights(
             true,
             g,
+            &attack_info,
             #[cfg(feature = "texel-tuning")]
             &mut result.trace,
         ),
         knights(
             false,
             g,
+            &attack_info,
             #[cfg(feature = "texel-tuning")]
             &mut result.trace,
         ),
@@ -259,21 +370,20 @@ pub fn eval_game_state(g: &GameState) -> EvaluationResult {
         );
     }
     res += knights_w - knights_b;
+    detailed.knights = knights_w - knights_b;
 
     let (piecewise_w, piecewise_b) = (
         piecewise(
             true,
             g,
-            black_defended_by_minors,
-            black_defended,
+            &attack_info,
             #[cfg(feature = "texel-tuning")]
             &mut result.trace,
         ),
         piecewise(
             false,
             g,
-            white_defended_by_minors,
-            white_defended,
+            &attack_info,
             #[cfg(feature = "texel-tuning")]
             &mut result.trace,
         ),
@@ -288,6 +398,7 @@ pub fn eval_game_state(g: &GameState) -> EvaluationResult {
         );
     }
     res += piecewise_w - piecewise_b;
+    detailed.piecewise = piecewise_w - piecewise_b;
 
     let (king_w, king_b) = (
         king(
@@ -308,6 +419,31 @@ pub fn eval_game_state(g: &GameState) -> EvaluationResult {
         println!("\nKing Sum: {} - {} -> {}", king_w, king_b, king_w - king_b);
     }
     res += king_w - king_b;
+    detailed.king = king_w - king_b;
+
+    let race = pawn_race(g);
+    detailed.pawn_race = EvaluationScore(0, UNSTOPPABLE_PASSER_SCORE) * race;
+    res += detailed.pawn_race;
+    #[cfg(feature = "texel-tuning")]
+    {
+        result.trace.unstoppable_passer = race as i8;
+    }
+    #[cfg(feature = "display-eval")]
+    {
+        println!("\nPawn race: {}", race);
+    }
+
+    let corner = kbn_mating_corner(g);
+    detailed.mating_corner = EvaluationScore(0, corner);
+    res += detailed.mating_corner;
+    #[cfg(feature = "texel-tuning")]
+    {
+        result.trace.mating_corner = corner;
+    }
+    #[cfg(feature = "display-eval")]
+    {
+        println!("\nMating corner: {}", corner);
+    }
 
     endgame_rescaling(
         g,
@@ -316,6 +452,24 @@ pub fn eval_game_state(g: &GameState) -> EvaluationResult {
         #[cfg(feature = "texel-tuning")]
         &mut result.trace,
     );
+    fortress_rescaling(
+        g,
+        &mut res,
+        #[cfg(feature = "texel-tuning")]
+        &mut result.trace,
+    );
+    rook_endgame_rescaling(
+        g,
+        &mut res,
+        #[cfg(feature = "texel-tuning")]
+        &mut result.trace,
+    );
+    fifty_move_rescaling(
+        g,
+        &mut res,
+        #[cfg(feature = "texel-tuning")]
+        &mut result.trace,
+    );
     res.1 = (f64::from(res.1) / 1.5) as i16;
     //Phasing is done the same way stockfish does it
     let final_res = res.interpolate(phase);
@@ -343,7 +497,8 @@ pub fn eval_game_state(g: &GameState) -> EvaluationResult {
         );
     }
     result.final_eval = final_res;
-    result
+    detailed.final_eval = final_res;
+    (result, detailed)
 }
 pub fn is_guaranteed_draw(g: &GameState) -> bool {
     if g.get_piece_bb(PieceType::Pawn)
@@ -361,6 +516,7 @@ pub fn is_guaranteed_draw(g: &GameState) -> bool {
         if white_knights + white_bishops < 2 || black_knights + black_bishops < 2 {
             if !(white_bishops == 2 && black_bishops == 0)
                 && !(black_bishops == 2 && white_bishops == 0)
+                && kbn_strong_side(g).is_none()
             {
                 return true;
             }
@@ -406,15 +562,344 @@ pub fn endgame_rescaling(
         }
     }
 }
+//The side with a lone bishop and knight against the bare enemy king
+pub fn kbn_strong_side(g: &GameState) -> Option<usize> {
+    if g.get_piece_count() != 4
+        || g.get_piece_bb(PieceType::Pawn)
+            | g.get_piece_bb(PieceType::Rook)
+            | g.get_piece_bb(PieceType::Queen)
+            != 0u64
+    {
+        return None;
+    }
+    [WHITE, BLACK].iter().copied().find(|&side| {
+        g.get_piece_amt(PieceType::Knight, side) == 1
+            && g.get_piece_amt(PieceType::Bishop, side) == 1
+    })
+}
+
+//Endgame bonus from white's point of view for driving the lone king into a corner of the bishop's color,
+//where it can be mated. Bounded well below the mate scores
+pub fn kbn_mating_corner(g: &GameState) -> i16 {
+    let strong_side = match kbn_strong_side(g) {
+        Some(side) => side,
+        None => return 0,
+    };
+    let bishop = g.get_piece(PieceType::Bishop, strong_side).trailing_zeros() as isize;
+    let corners: [isize; 2] = if (bishop / 8 + bishop % 8) % 2 == 0 {
+        [0, 63]
+    } else {
+        [7, 56]
+    };
+    let weak_king = g.get_king_square(1 - strong_side) as isize;
+    let corner_distance = corners
+        .iter()
+        .map(|&corner| (weak_king / 8 - corner / 8).abs() + (weak_king % 8 - corner % 8).abs())
+        .min()
+        .unwrap() as i16;
+    let king_distance = get_distance(g.get_king_square(strong_side) as isize, weak_king) as i16;
+    let bonus = KBN_CORNER_SCORE * (14 - corner_distance) + KBN_KING_SCORE * (7 - king_distance);
+    if strong_side == WHITE {
+        bonus
+    } else {
+        -bonus
+    }
+}
+
+//A queen or rook against a far advanced pawn, which is supported by its king while the enemy king is too far away.
+//Rook and bishop pawns on the seventh rank hold against a queen, any such pawn on the sixth or seventh against a rook
+pub fn is_fortress_draw(g: &GameState) -> bool {
+    let pawns = g.get_piece_bb(PieceType::Pawn);
+    if pawns.count_ones() != 1
+        || g.get_piece_bb(PieceType::Knight) | g.get_piece_bb(PieceType::Bishop) != 0u64
+    {
+        return false;
+    }
+    let weak_side = if g.get_piece(PieceType::Pawn, WHITE) != 0u64 {
+        WHITE
+    } else {
+        BLACK
+    };
+    let strong_side = 1 - weak_side;
+    let queens = g.get_piece_amt(PieceType::Queen, strong_side);
+    let rooks = g.get_piece_amt(PieceType::Rook, strong_side);
+    if queens + rooks != 1
+        || g.get_piece(PieceType::Queen, weak_side) | g.get_piece(PieceType::Rook, weak_side)
+            != 0u64
+    {
+        return false;
+    }
+    let pawn = pawns.trailing_zeros() as usize;
+    let relative_rank = GameState::relative_rank(weak_side, pawn);
+    let file = pawn % 8;
+    let holds = if queens == 1 {
+        relative_rank == 6 && (file == 0 || file == 2 || file == 5 || file == 7)
+    } else {
+        relative_rank >= 5
+    };
+    let promotion_square = if weak_side == WHITE { 56 + file } else { file };
+    holds
+        && get_distance(g.get_king_square(weak_side) as isize, pawn as isize) <= 1
+        && get_distance(
+            g.get_king_square(strong_side) as isize,
+            promotion_square as isize,
+        ) > FORTRESS_KING_DISTANCE
+}
+
+//Only kings and pawns are left and every pawn is blocked. The pawns and the squares they guard wall both kings in,
+//so that neither king can ever get next to an enemy pawn
+pub fn is_blockade_draw(g: &GameState) -> bool {
+    let white_pawns = g.get_piece(PieceType::Pawn, WHITE);
+    let black_pawns = g.get_piece(PieceType::Pawn, BLACK);
+    let pawns = white_pawns | black_pawns;
+    if white_pawns == 0u64
+        || black_pawns == 0u64
+        || g.get_piece_bb(PieceType::Knight)
+            | g.get_piece_bb(PieceType::Bishop)
+            | g.get_piece_bb(PieceType::Rook)
+            | g.get_piece_bb(PieceType::Queen)
+            != 0u64
+    {
+        return false;
+    }
+    if bitboards::north_one(white_pawns) & !pawns != 0u64
+        || bitboards::south_one(black_pawns) & !pawns != 0u64
+        || pawn_targets(WHITE, white_pawns) & black_pawns != 0u64
+        || pawn_targets(BLACK, black_pawns) & white_pawns != 0u64
+    {
+        return false;
+    }
+    !king_reaches(
+        g.get_king_square(WHITE),
+        !pawns & !pawn_targets(BLACK, black_pawns),
+        king_area(black_pawns),
+    ) && !king_reaches(
+        g.get_king_square(BLACK),
+        !pawns & !pawn_targets(WHITE, white_pawns),
+        king_area(white_pawns),
+    )
+}
+
+//The squares of board and all squares next to them
+fn king_area(board: u64) -> u64 {
+    let area = board | bitboards::west_one(board) | bitboards::east_one(board);
+    area | bitboards::north_one(area) | bitboards::south_one(area)
+}
+
+//Floods the squares the king can walk to and checks whether one of them is a target
+fn king_reaches(king_square: usize, walkable: u64, targets: u64) -> bool {
+    let mut region = square(king_square);
+    loop {
+        if region & targets != 0u64 {
+            return true;
+        }
+        let next = region | king_area(region) & walkable;
+        if next == region {
+            return false;
+        }
+        region = next;
+    }
+}
+
+pub fn fortress_rescaling(
+    g: &GameState,
+    res: &mut EvaluationScore,
+    #[cfg(feature = "texel-tuning")] trace: &mut LargeTrace,
+) {
+    if is_fortress_draw(g) || is_blockade_draw(g) {
+        *res = EvaluationScore(
+            (f32::from(res.0) * FORTRESS_DRAW_SCALE) as i16,
+            (f32::from(res.1) * FORTRESS_DRAW_SCALE) as i16,
+        );
+        #[cfg(feature = "texel-tuning")]
+        {
+            trace.fortress_draw = true;
+        }
+    }
+}
+
+pub fn rook_endgame_scale(g: &GameState) -> Option<f32> {
+    if g.get_piece_bb(PieceType::Knight)
+        | g.get_piece_bb(PieceType::Bishop)
+        | g.get_piece_bb(PieceType::Queen)
+        != 0u64
+    {
+        return None;
+    }
+    let rooks = g.get_piece_amt(PieceType::Rook, WHITE);
+    let white_pawns = g.get_piece_amt(PieceType::Pawn, WHITE);
+    let black_pawns = g.get_piece_amt(PieceType::Pawn, BLACK);
+    if rooks == 0
+        || rooks != g.get_piece_amt(PieceType::Rook, BLACK)
+        || white_pawns + black_pawns == 0
+        || (white_pawns as isize - black_pawns as isize).abs() > 1
+    {
+        return None;
+    }
+    if rooks == 1 && white_pawns + black_pawns == 1 {
+        let strong_side = if white_pawns == 1 { WHITE } else { BLACK };
+        let pawn = g.get_piece(PieceType::Pawn, strong_side).trailing_zeros() as usize;
+        let defending_king = g.get_king_square(1 - strong_side);
+        let file = pawn % 8;
+        let promotion_square = if strong_side == WHITE {
+            56 + file
+        } else {
+            file
+        };
+        let in_front = ((defending_king % 8) as isize - file as isize).abs() <= 1
+            && GameState::relative_rank(strong_side, defending_king)
+                > GameState::relative_rank(strong_side, pawn);
+        let rook_pawn_held = (file == 0 || file == 7)
+            && get_distance(defending_king as isize, promotion_square as isize)
+                <= ROOK_PAWN_KING_DISTANCE;
+        if in_front || rook_pawn_held {
+            return Some(ROOK_ENDGAME_DRAW_SCALE);
+        }
+    }
+    Some(ROOK_ENDGAME_SCALE)
+}
+
+pub fn rook_endgame_rescaling(
+    g: &GameState,
+    res: &mut EvaluationScore,
+    #[cfg(feature = "texel-tuning")] trace: &mut LargeTrace,
+) {
+    if let Some(scale) = rook_endgame_scale(g) {
+        *res = EvaluationScore(
+            (f32::from(res.0) * scale) as i16,
+            (f32::from(res.1) * scale) as i16,
+        );
+        #[cfg(feature = "texel-tuning")]
+        {
+            trace.rook_endgame_scale = scale;
+        }
+    }
+}
+
+pub fn fifty_move_scale(g: &GameState) -> Option<f32> {
+    if g.get_half_moves() <= FIFTY_MOVE_SCALE_START {
+        return None;
+    }
+    let plies_left = 100usize.saturating_sub(g.get_half_moves());
+    Some(plies_left as f32 / (100 - FIFTY_MOVE_SCALE_START) as f32)
+}
+
+pub fn fifty_move_rescaling(
+    g: &GameState,
+    res: &mut EvaluationScore,
+    #[cfg(feature = "texel-tuning")] trace: &mut LargeTrace,
+) {
+    if let Some(scale) = fifty_move_scale(g) {
+        *res = EvaluationScore(
+            (f32::from(res.0) * scale) as i16,
+            (f32::from(res.1) * scale) as i16,
+        );
+        #[cfg(feature = "texel-tuning")]
+        {
+            trace.fifty_move_scale = scale;
+        }
+    }
+}
+
+//Returns 1 if white wins the pawn race of a pure pawn endgame, -1 if black does and 0 otherwise
+pub fn pawn_race(g: &GameState) -> i16 {
+    if g.get_piece_bb(PieceType::Knight)
+        | g.get_piece_bb(PieceType::Bishop)
+        | g.get_piece_bb(PieceType::Rook)
+        | g.get_piece_bb(PieceType::Queen)
+        != 0u64
+    {
+        return 0;
+    }
+    match (
+        unstoppable_passer_plies(g, WHITE),
+        unstoppable_passer_plies(g, BLACK),
+    ) {
+        (Some(_), None) => 1,
+        (None, Some(_)) => -1,
+        //If both sides queen, the first queen needs some time to stop the enemy pawn
+        (Some(white), Some(black)) if white + 2 < black => 1,
+        (Some(white), Some(black)) if black + 2 < white => -1,
+        _ => 0,
+    }
+}
+
+//Plies until the side promotes its fastest passer which can't be caught by the enemy king (rule of the square)
+pub fn unstoppable_passer_plies(g: &GameState, side: usize) -> Option<usize> {
+    let white = side == WHITE;
+    let pawns = g.get_piece(PieceType::Pawn, side);
+    let enemy_pawns = g.get_piece(PieceType::Pawn, 1 - side);
+    let mut enemy_front_spans = if white {
+        bitboards::b_front_span(enemy_pawns)
+    } else {
+        bitboards::w_front_span(enemy_pawns)
+    };
+    enemy_front_spans |=
+        bitboards::west_one(enemy_front_spans) | bitboards::east_one(enemy_front_spans);
+    let mut passed_pawns = pawns & !enemy_front_spans;
+    let to_move = g.get_color_to_move() == side;
+    let mut fastest: Option<usize> = None;
+    while passed_pawns != 0u64 {
+        let idx = passed_pawns.trailing_zeros() as usize;
+        passed_pawns ^= square(idx);
+        let front_span = if white {
+            bitboards::w_front_span(square(idx))
+        } else {
+            bitboards::b_front_span(square(idx))
+        };
+        //The frontmost of doubled pawns is the faster one
+        if front_span & pawns != 0u64 {
+            continue;
+        }
+        if !enemy_king_in_square(g, side, idx) {
+            let plies = 2 * promotion_distance(g, side, idx) - to_move as usize;
+            fastest = Some(fastest.map_or(plies, |other| other.min(plies)));
+        }
+    }
+    fastest
+}
+
+//Moves the pawn needs to promote, including the double push and our own king stepping out of the way
+pub fn promotion_distance(g: &GameState, side: usize, idx: usize) -> usize {
+    let relative_rank = GameState::relative_rank(side, idx);
+    let mut distance = 7 - relative_rank;
+    //Double push from the second rank
+    if relative_rank == 1 {
+        distance -= 1;
+    }
+    let front_span = if side == WHITE {
+        bitboards::w_front_span(square(idx))
+    } else {
+        bitboards::b_front_span(square(idx))
+    };
+    if front_span & g.get_piece(PieceType::King, side) != 0u64 {
+        distance += 1;
+    }
+    distance
+}
+
+//Rule of the square: the enemy king catches the pawn if it is inside the square of promotion,
+//or can step into it being the side to move
+pub fn enemy_king_in_square(g: &GameState, side: usize, idx: usize) -> bool {
+    let promotion_square = if side == WHITE { 56 + idx % 8 } else { idx % 8 };
+    let enemy_distance = get_distance(
+        g.get_king_square(1 - side) as isize,
+        promotion_square as isize,
+    );
+    let to_move = g.get_color_to_move() == side;
+    enemy_distance + to_move as usize <= promotion_distance(g, side, idx) + 1
+}
+
 pub fn knights(
     white: bool,
     g: &GameState,
+    attack_info: &AttackInfo,
     #[cfg(feature = "texel-tuning")] trace: &mut LargeTrace,
 ) -> EvaluationScore {
     let mut res = EvaluationScore::default();
     let side = if white { WHITE } else { BLACK };
 
-    let my_pawn_attacks = pawn_targets(side, g.get_piece(PieceType::Pawn, side));
+    let my_pawn_attacks = attack_info.attacks[side][PieceType::Pawn as usize];
 
     let supported_knights = g.get_piece(PieceType::Knight, side) & my_pawn_attacks;
     let supported_knights_amount = supported_knights.count_ones() as i16;
@@ -468,14 +953,13 @@ pub fn knights(
 pub fn piecewise(
     white: bool,
     g: &GameState,
-    enemy_defend_by_minors: u64,
-    enemy_defended: u64,
+    attack_info: &AttackInfo,
     #[cfg(feature = "texel-tuning")] trace: &mut LargeTrace,
 ) -> EvaluationScore {
     let side = if white { WHITE } else { BLACK };
 
-    let defended_by_minors = enemy_defend_by_minors;
-    let defended_squares = enemy_defended;
+    let defended_by_minors = attack_info.defended_by_minors[1 - side];
+    let defended_squares = attack_info.defended[1 - side];
     let my_pieces = g.get_pieces_from_side(side);
 
     let enemy_king_idx = g.get_king_square(1 - side);
@@ -489,17 +973,35 @@ pub fn piecewise(
     let all_pieces = g.get_all_pieces();
     let bishop_checks = PieceType::Bishop.attacks(enemy_king_idx, all_pieces);
     let rook_checks = PieceType::Rook.attacks(enemy_king_idx, all_pieces);
+    let enemy_pawns = g.get_piece(PieceType::Pawn, 1 - side);
+    let enemy_pawn_attacks = attack_info.attacks[1 - side][PieceType::Pawn as usize];
+    let rammed_pawns = g.get_piece(PieceType::Pawn, side)
+        & if white {
+            bitboards::south_one(enemy_pawns)
+        } else {
+            bitboards::north_one(enemy_pawns)
+        };
     //Knights
+    let mut knights_trapped: i16 = 0;
+    let mut knights_behind_chain: i16 = 0;
     let mut knight_attackers: i16 = 0;
     let mut knight_attacker_values = EvaluationScore::default();
     let mut mk = EvaluationScore::default();
     let mut knights = g.get_piece(PieceType::Knight, side);
     while knights != 0u64 {
         let idx = knights.trailing_zeros() as usize;
-        let targets = PieceType::Knight.attacks(idx, all_pieces) & !my_pieces;
+        let targets = attack_info.piece_attacks[idx] & !my_pieces;
 
         let mobility = targets.count_ones() as usize;
         mk += KNIGHT_MOBILITY_BONUS[mobility];
+        if square(idx) & RIM != 0u64 && targets & !enemy_pawn_attacks == 0u64 {
+            knights_trapped += 1;
+        }
+        if mobility <= BEHIND_PAWN_CHAIN_MAX_MOBILITY
+            && attack_info.piece_attacks[idx] & rammed_pawns != 0u64
+        {
+            knights_behind_chain += 1;
+        }
 
         let has_safe_check = (targets & knight_checks & !defended_squares) != 0u64;
         let enemy_king_attacks = targets & enemy_king_attackable;
@@ -522,13 +1024,15 @@ pub fn piecewise(
         knights ^= square(idx);
     }
     //Bishops
+    let mut bishops_trapped: i16 = 0;
+    let mut bishops_behind_chain: i16 = 0;
     let mut bishop_attackers: i16 = 0;
     let mut bishop_attacker_values = EvaluationScore::default();
     let (mut mb, mut mb_diag) = (EvaluationScore::default(), EvaluationScore::default());
     let mut bishops = g.get_piece(PieceType::Bishop, side);
     while bishops != 0u64 {
         let idx = bishops.trailing_zeros() as usize;
-        let bishop_attack = PieceType::Bishop.attacks(idx, all_pieces ^ square(enemy_king_idx));
+        let bishop_attack = attack_info.piece_attacks[idx];
         let diagonally_adjacent_pawns =
             (DIAGONALLY_ADJACENT[idx] & g.get_piece(PieceType::Pawn, side)).count_ones() as usize;
         mb_diag += DIAGONALLY_ADJACENT_SQUARES_WITH_OWN_PAWNS[diagonally_adjacent_pawns];
@@ -536,6 +1040,16 @@ pub fn piecewise(
         let targets = bishop_attack & !my_pieces;
         let mobility = targets.count_ones() as usize;
         mb += BISHOP_MOBILITY_BONUS[mobility];
+        let relative_idx = if white { idx } else { BLACK_INDEX[idx] };
+        if TRAPPED_BISHOP_PATTERNS.iter().any(|&(bishop_sq, pawn_sq)| {
+            bishop_sq == relative_idx
+                && enemy_pawns & square(if white { pawn_sq } else { BLACK_INDEX[pawn_sq] }) != 0u64
+        }) {
+            bishops_trapped += 1;
+        }
+        if mobility <= BEHIND_PAWN_CHAIN_MAX_MOBILITY && bishop_attack & rammed_pawns != 0u64 {
+            bishops_behind_chain += 1;
+        }
 
         let has_safe_check = (targets & bishop_checks & !defended_squares) != 0u64;
         let enemy_king_attacks = targets & enemy_king_attackable;
@@ -560,14 +1074,24 @@ pub fn piecewise(
         bishops ^= square(idx);
     }
     //Rooks
+    //Once the king lost the right to castle to a side, it can only free a rook in that corner slowly
+    let king_idx = g.get_king_square(side);
+    let back_rank = if white { 0 } else { 7 };
+    let (can_castle_kingside, can_castle_queenside) = if white {
+        (g.castle_white_kingside(), g.castle_white_queenside())
+    } else {
+        (g.castle_black_kingside(), g.castle_black_queenside())
+    };
+    let mut rooks_trapped: i16 = 0;
     let mut rook_attackers: i16 = 0;
     let mut rook_attacker_values = EvaluationScore::default();
     let (mut mr, mut rooks_onopen, mut rooks_on_semi_open, mut rooks_onseventh) =
         (EvaluationScore::default(), 0i16, 0i16, 0i16);
+    let (mut rooks_connected, mut rooks_doubled_on_open) = (0i16, 0i16);
     let mut rooks = g.get_piece(PieceType::Rook, side);
     while rooks != 0u64 {
         let idx = rooks.trailing_zeros() as usize;
-        let rook_attack = PieceType::Rook.attacks(idx, all_pieces ^ square(enemy_king_idx));
+        let rook_attack = attack_info.piece_attacks[idx];
         if if white { idx / 8 == 6 } else { idx / 8 == 1 } {
             rooks_onseventh += 1;
         }
@@ -579,10 +1103,31 @@ pub fn piecewise(
             rooks_on_semi_open += 1;
         }
 
+        //Each pair of rooks defending each other is counted once, doubled rooks on a file without own pawns
+        //are worth more than rooks connected e.g. on the back rank
+        let mut defended_rooks = rook_attack & rooks;
+        while defended_rooks != 0u64 {
+            let other = defended_rooks.trailing_zeros() as usize;
+            if other % 8 == idx % 8 && FILES[idx % 8] & g.get_piece(PieceType::Pawn, side) == 0u64 {
+                rooks_doubled_on_open += 1;
+            } else {
+                rooks_connected += 1;
+            }
+            defended_rooks ^= square(other);
+        }
+
         let targets = rook_attack & !my_pieces;
 
         let mobility = targets.count_ones() as usize;
         mr += ROOK_MOBILITY_BONUS[mobility];
+        if mobility <= 3 && idx / 8 == back_rank && king_idx / 8 == back_rank {
+            let (rook_file, king_file) = (idx % 8, king_idx % 8);
+            if (king_file >= 4 && rook_file > king_file && !can_castle_kingside)
+                || (king_file < 4 && rook_file < king_file && !can_castle_queenside)
+            {
+                rooks_trapped += 1;
+            }
+        }
 
         let has_safe_check = (targets & rook_checks & !defended_squares) != 0u64;
         let enemy_king_attacks = targets & enemy_king_attackable;
@@ -612,10 +1157,7 @@ pub fn piecewise(
     let mut queens = g.get_piece(PieceType::Queen, side);
     while queens != 0u64 {
         let idx = queens.trailing_zeros() as usize;
-        let rooklike_attacks = PieceType::Rook.attacks(idx, all_pieces ^ square(enemy_king_idx));
-        let bishoplike_attacks =
-            PieceType::Bishop.attacks(idx, all_pieces ^ square(enemy_king_idx));
-        let queen_attack = rooklike_attacks | bishoplike_attacks;
+        let queen_attack = attack_info.piece_attacks[idx];
 
         if FILES[idx % 8] & g.get_piece_bb(PieceType::Pawn) == 0u64 {
             queens_onopen += 1;
@@ -659,6 +1201,20 @@ pub fn piecewise(
             rooks_on_semi_open as i8 * if side == WHITE { 1 } else { -1 };
         trace.normal_coeffs[IDX_ROOK_ON_SEVENTH] +=
             rooks_onseventh as i8 * if side == WHITE { 1 } else { -1 };
+        trace.normal_coeffs[IDX_ROOKS_CONNECTED] +=
+            rooks_connected as i8 * if side == WHITE { 1 } else { -1 };
+        trace.normal_coeffs[IDX_ROOKS_DOUBLED_ON_OPEN] +=
+            rooks_doubled_on_open as i8 * if side == WHITE { 1 } else { -1 };
+        trace.normal_coeffs[IDX_BISHOP_TRAPPED] +=
+            bishops_trapped as i8 * if side == WHITE { 1 } else { -1 };
+        trace.normal_coeffs[IDX_ROOK_TRAPPED_BY_KING] +=
+            rooks_trapped as i8 * if side == WHITE { 1 } else { -1 };
+        trace.normal_coeffs[IDX_KNIGHT_TRAPPED_ON_RIM] +=
+            knights_trapped as i8 * if side == WHITE { 1 } else { -1 };
+        trace.normal_coeffs[IDX_KNIGHT_BEHIND_PAWN_CHAIN] +=
+            knights_behind_chain as i8 * if side == WHITE { 1 } else { -1 };
+        trace.normal_coeffs[IDX_BISHOP_BEHIND_PAWN_CHAIN] +=
+            bishops_behind_chain as i8 * if side == WHITE { 1 } else { -1 };
         trace.normal_coeffs[IDX_QUEEN_ON_OPEN] +=
             queens_onopen as i8 * if side == WHITE { 1 } else { -1 };
         trace.normal_coeffs[IDX_QUEEN_ON_SEMI_OPEN] +=
@@ -700,6 +1256,13 @@ pub fn piecewise(
         + ROOK_ON_OPEN_FILE_BONUS * rooks_onopen
         + ROOK_ON_SEMI_OPEN_FILE_BONUS * rooks_on_semi_open
         + ROOK_ON_SEVENTH * rooks_onseventh
+        + ROOKS_CONNECTED * rooks_connected
+        + ROOKS_DOUBLED_ON_OPEN_FILE * rooks_doubled_on_open
+        + BISHOP_TRAPPED * bishops_trapped
+        + ROOK_TRAPPED_BY_KING * rooks_trapped
+        + KNIGHT_TRAPPED_ON_RIM * knights_trapped
+        + KNIGHT_BEHIND_PAWN_CHAIN * knights_behind_chain
+        + BISHOP_BEHIND_PAWN_CHAIN * bishops_behind_chain
         + QUEEN_ON_OPEN_FILE_BONUS * queens_onopen
         + QUEEN_ON_SEMI_OPEN_FILE_BONUS * queens_on_semi_open
         + attack;
@@ -737,6 +1300,41 @@ pub fn piecewise(
             rooks_onseventh,
             ROOK_ON_SEVENTH * rooks_onseventh
         );
+        println!(
+            "\tRooks connected: {} -> {}",
+            rooks_connected,
+            ROOKS_CONNECTED * rooks_connected
+        );
+        println!(
+            "\tRooks doubled on open: {} -> {}",
+            rooks_doubled_on_open,
+            ROOKS_DOUBLED_ON_OPEN_FILE * rooks_doubled_on_open
+        );
+        println!(
+            "\tBishops trapped: {} -> {}",
+            bishops_trapped,
+            BISHOP_TRAPPED * bishops_trapped
+        );
+        println!(
+            "\tRooks trapped by king: {} -> {}",
+            rooks_trapped,
+            ROOK_TRAPPED_BY_KING * rooks_trapped
+        );
+        println!(
+            "\tKnights trapped on rim: {} -> {}",
+            knights_trapped,
+            KNIGHT_TRAPPED_ON_RIM * knights_trapped
+        );
+        println!(
+            "\tKnights behind pawn chain: {} -> {}",
+            knights_behind_chain,
+            KNIGHT_BEHIND_PAWN_CHAIN * knights_behind_chain
+        );
+        println!(
+            "\tBishops behind pawn chain: {} -> {}",
+            bishops_behind_chain,
+            BISHOP_BEHIND_PAWN_CHAIN * bishops_behind_chain
+        );
         println!(
             "\tKnight Attackers: Num: {} , Val: {}",
             knight_attackers, knight_attacker_values
@@ -864,12 +1462,12 @@ pub fn get_distance(sq: isize, sq2: isize) -> usize {
 pub fn pawns(
     white: bool,
     g: &GameState,
-    defended: u64,
-    enemy_defended: u64,
+    attack_info: &AttackInfo,
     #[cfg(feature = "texel-tuning")] trace: &mut LargeTrace,
 ) -> EvaluationScore {
     let mut res = EvaluationScore::default();
     let side = if white { WHITE } else { BLACK };
+    let (defended, enemy_defended) = (attack_info.defended[side], attack_info.defended[1 - side]);
     let empty = !g.get_all_pieces();
     let pawns = g.get_piece(PieceType::Pawn, side);
     let enemy_pawns = g.get_piece(PieceType::Pawn, 1 - side);
@@ -890,7 +1488,7 @@ pub fn pawns(
     let (my_west_attacks, my_east_attacks, enemy_pawn_attacks) = (
         pawn_west_targets(side, pawns),
         pawn_east_targets(side, pawns),
-        pawn_targets(1 - side, enemy_pawns),
+        attack_info.attacks[1 - side][PieceType::Pawn as usize],
     );
     let my_pawn_attacks = my_west_attacks | my_east_attacks;
     let (my_pawn_pushes, my_pawn_double_pushes) = (
@@ -902,6 +1500,22 @@ pub fn pawns(
     let enemy_pieces = g.get_pieces_from_side(1 - side);
 
     let doubled_pawns = (pawns & front_span).count_ones() as i16;
+    //Doubled pawns on a file without enemy pawns can still become passers, a third pawn on the file is worse
+    let (mut doubled_half_open, mut tripled_pawns) = (0i16, 0i16);
+    if doubled_pawns > 0 {
+        let mut files = pawn_file_fill & RANKS[0];
+        while files != 0u64 {
+            let file = files.trailing_zeros() as usize;
+            files ^= square(file);
+            let on_file = (pawns & FILES[file]).count_ones() as i16;
+            if on_file > 1 && enemy_pawns & FILES[file] == 0u64 {
+                doubled_half_open += on_file - 1;
+            }
+            if on_file > 2 {
+                tripled_pawns += on_file - 2;
+            }
+        }
+    }
     let isolated_pawns =
         (pawns & !bitboards::west_one(pawn_file_fill) & !bitboards::east_one(pawn_file_fill))
             .count_ones() as i16;
@@ -910,6 +1524,40 @@ pub fn pawns(
         & !is_attackable
         & !enemy_pawns)
         .count_ones() as i16;
+    //A candidate passer isn't passed yet, but has no pawn in front of it and at least as many own pawns next to
+    //or behind it as enemy pawns in front of it on the neighbouring files, so trading those leaves it passed
+    let mut candidate_passers = 0i16;
+    let mut candidates = pawns
+        & enemy_front_spans
+        & !if white {
+            bitboards::b_front_span(pawns | enemy_pawns)
+        } else {
+            bitboards::w_front_span(pawns | enemy_pawns)
+        };
+    while candidates != 0u64 {
+        let idx = candidates.trailing_zeros() as usize;
+        candidates ^= square(idx);
+        let (front, rear) = if white {
+            (
+                bitboards::w_front_span(square(idx)),
+                bitboards::b_front_span(square(idx)),
+            )
+        } else {
+            (
+                bitboards::b_front_span(square(idx)),
+                bitboards::w_front_span(square(idx)),
+            )
+        };
+        let rear = rear | square(idx);
+        let sentries = enemy_pawns & (bitboards::west_one(front) | bitboards::east_one(front));
+        let helpers = pawns & (bitboards::west_one(rear) | bitboards::east_one(rear));
+        if helpers.count_ones() >= sentries.count_ones() {
+            candidate_passers += 1;
+        }
+    }
+    //A lever can capture an enemy pawn which no enemy pawn defends, opening lines without a pawn recapture
+    let pawn_levers =
+        (pawns & pawn_targets(1 - side, enemy_pawns & !enemy_pawn_attacks)).count_ones() as i16;
     let mut supported_pawns = pawns & my_pawn_attacks;
     let _supported_amt = supported_pawns.count_ones() as usize;
     let mut supp = EvaluationScore::default();
@@ -938,8 +1586,12 @@ pub fn pawns(
         + my_pawn_pushes.count_ones()
         + my_pawn_double_pushes.count_ones()) as i16;
     res += PAWN_DOUBLED_VALUE * doubled_pawns
+        + PAWN_DOUBLED_HALF_OPEN_VALUE * doubled_half_open
+        + PAWN_TRIPLED_VALUE * tripled_pawns
         + PAWN_ISOLATED_VALUE * isolated_pawns
         + PAWN_BACKWARD_VALUE * backward_pawns
+        + PAWN_CANDIDATE_PASSER_VALUE * candidate_passers
+        + PAWN_LEVER_VALUE * pawn_levers
         + PAWN_ATTACK_CENTER * center_attack_pawns
         + PAWN_MOBILITY * pawn_mobility;
 
@@ -947,10 +1599,18 @@ pub fn pawns(
     {
         trace.normal_coeffs[IDX_PAWN_DOUBLED] +=
             doubled_pawns as i8 * if side == WHITE { 1 } else { -1 };
+        trace.normal_coeffs[IDX_PAWN_DOUBLED_HALF_OPEN] +=
+            doubled_half_open as i8 * if side == WHITE { 1 } else { -1 };
+        trace.normal_coeffs[IDX_PAWN_TRIPLED] +=
+            tripled_pawns as i8 * if side == WHITE { 1 } else { -1 };
         trace.normal_coeffs[IDX_PAWN_ISOLATED] +=
             isolated_pawns as i8 * if side == WHITE { 1 } else { -1 };
         trace.normal_coeffs[IDX_PAWN_BACKWARD] +=
             backward_pawns as i8 * if side == WHITE { 1 } else { -1 };
+        trace.normal_coeffs[IDX_PAWN_CANDIDATE_PASSER] +=
+            candidate_passers as i8 * if side == WHITE { 1 } else { -1 };
+        trace.normal_coeffs[IDX_PAWN_LEVER] +=
+            pawn_levers as i8 * if side == WHITE { 1 } else { -1 };
         trace.normal_coeffs[IDX_PAWN_ATTACK_CENTER] +=
             center_attack_pawns as i8 * if side == WHITE { 1 } else { -1 };
         trace.normal_coeffs[IDX_PAWN_MOBILITY] +=
@@ -969,6 +1629,7 @@ pub fn pawns(
         (EvaluationScore::default(), 0, 0);
     let mut passer_dist = EvaluationScore::default();
     let mut weak_passers = 0;
+    let (mut escorted_passers, mut passers_in_square) = (0, 0);
     let behind_passers = if white {
         bitboards::b_front_span(passed_pawns)
     } else {
@@ -1030,6 +1691,18 @@ pub fn pawns(
         passer_dist += PASSED_KING_DISTANCE[d_myking - 1]
             + PASSED_ENEMY_KING_DISTANCE[d_enemyking - 1]
             + PASSED_SUBTRACT_DISTANCE[sub_dist];
+        //Our king escorts the passer from in front of it
+        let my_king = g.get_king_square(side);
+        if d_myking <= 2
+            && GameState::relative_rank(side, my_king) > GameState::relative_rank(side, idx)
+            && ((my_king % 8) as isize - (idx % 8) as isize).abs() <= 1
+        {
+            escorted_passers += 1;
+        }
+        //Without pieces only the enemy king can stop the passer
+        if !g.has_non_pawns(1 - side) && enemy_king_in_square(g, side, idx) {
+            passers_in_square += 1;
+        }
         #[cfg(feature = "texel-tuning")]
         {
             trace.normal_coeffs[IDX_PAWN_PASSED_KINGDISTANCE + d_myking - 1] +=
@@ -1045,8 +1718,16 @@ pub fn pawns(
     {
         trace.normal_coeffs[IDX_PAWN_PASSED_WEAK] +=
             weak_passers as i8 * if side == WHITE { 1 } else { -1 };
+        trace.normal_coeffs[IDX_PAWN_PASSED_ESCORTED] +=
+            escorted_passers as i8 * if side == WHITE { 1 } else { -1 };
+        trace.normal_coeffs[IDX_PAWN_PASSED_KING_IN_SQUARE] +=
+            passers_in_square as i8 * if side == WHITE { 1 } else { -1 };
     }
-    res += passer_score + PAWN_PASSED_WEAK * weak_passers + passer_dist;
+    res += passer_score
+        + PAWN_PASSED_WEAK * weak_passers
+        + PAWN_PASSED_ESCORTED * escorted_passers
+        + PAWN_PASSED_KING_IN_SQUARE * passers_in_square
+        + passer_dist;
     #[cfg(feature = "display-eval")]
     {
         println!("\nPawns for {}:", if white { "White" } else { "Black" });
@@ -1055,6 +1736,16 @@ pub fn pawns(
             doubled_pawns,
             PAWN_DOUBLED_VALUE * doubled_pawns
         );
+        println!(
+            "\tDoubled on half-open file: {} -> {}",
+            doubled_half_open,
+            PAWN_DOUBLED_HALF_OPEN_VALUE * doubled_half_open
+        );
+        println!(
+            "\tTripled: {} -> {}",
+            tripled_pawns,
+            PAWN_TRIPLED_VALUE * tripled_pawns
+        );
         println!(
             "\tIsolated: {} -> {}",
             isolated_pawns,
@@ -1065,6 +1756,16 @@ pub fn pawns(
             backward_pawns,
             PAWN_BACKWARD_VALUE * backward_pawns,
         );
+        println!(
+            "\tCandidate passers: {} -> {}",
+            candidate_passers,
+            PAWN_CANDIDATE_PASSER_VALUE * candidate_passers,
+        );
+        println!(
+            "\tLevers: {} -> {}",
+            pawn_levers,
+            PAWN_LEVER_VALUE * pawn_levers,
+        );
         println!("\tSupported: {} -> {}", _supported_amt, supp);
         println!(
             "\tAttack Center: {} -> {}",
@@ -1096,6 +1797,16 @@ pub fn pawns(
             PAWN_PASSED_WEAK * weak_passers,
         );
         println!("\tPassers distance to kings -> {}", passer_dist);
+        println!(
+            "\tEscorted passer: {} -> {}",
+            escorted_passers,
+            PAWN_PASSED_ESCORTED * escorted_passers,
+        );
+        println!(
+            "\tPasser in enemy king's square: {} -> {}",
+            passers_in_square,
+            PAWN_PASSED_KING_IN_SQUARE * passers_in_square,
+        );
         println!("Sum: {}", res);
     }
     res
@@ -1121,8 +1832,9 @@ pub fn piece_values(
     res += (KNIGHT_PIECE_VALUE + KNIGHT_VALUE_WITH_PAWNS[pawns_on_board]) * my_knights;
 
     res += BISHOP_PIECE_VALUE * my_bishops;
+    //The bishop pair gets stronger the more the position opens up
     if my_bishops > 1 {
-        res += BISHOP_PAIR_BONUS;
+        res += BISHOP_PAIR_BONUS + BISHOP_PAIR_WITH_PAWNS[pawns_on_board];
     }
 
     res += ROOK_PIECE_VALUE * my_rooks;
@@ -1141,6 +1853,8 @@ pub fn piece_values(
             my_bishops as i8 * if side == WHITE { 1 } else { -1 };
         if my_bishops > 1 {
             trace.normal_coeffs[IDX_BISHOP_PAIR] += if side == WHITE { 1 } else { -1 };
+            trace.normal_coeffs[IDX_BISHOP_PAIR_WITH_PAWNS + pawns_on_board] +=
+                if side == WHITE { 1 } else { -1 };
         }
         trace.normal_coeffs[IDX_ROOK_PIECE_VALUE] +=
             my_rooks as i8 * if side == WHITE { 1 } else { -1 };
@@ -1165,7 +1879,11 @@ pub fn piece_values(
             BISHOP_PIECE_VALUE * my_bishops,
         );
         if my_bishops > 1 {
-            println!("\tBishop-Pair: {} -> {}", 1, BISHOP_PAIR_BONUS);
+            println!(
+                "\tBishop-Pair: {} -> {}",
+                1,
+                BISHOP_PAIR_BONUS + BISHOP_PAIR_WITH_PAWNS[pawns_on_board]
+            );
         }
         println!("\tRooks: {} -> {}", my_rooks, ROOK_PIECE_VALUE * my_rooks,);
         println!(
@@ -26,13 +26,25 @@ pub mod normal_parameters {
         IDX_SHIELDING_PAWN_ONOPEN_MISSING + SIZE_SHIELDING_PAWN_ONOPEN_MISSING;
     pub const SIZE_PAWN_DOUBLED: usize = 1;
 
-    pub const IDX_PAWN_ISOLATED: usize = IDX_PAWN_DOUBLED + SIZE_PAWN_DOUBLED;
+    pub const IDX_PAWN_DOUBLED_HALF_OPEN: usize = IDX_PAWN_DOUBLED + SIZE_PAWN_DOUBLED;
+    pub const SIZE_PAWN_DOUBLED_HALF_OPEN: usize = 1;
+
+    pub const IDX_PAWN_TRIPLED: usize = IDX_PAWN_DOUBLED_HALF_OPEN + SIZE_PAWN_DOUBLED_HALF_OPEN;
+    pub const SIZE_PAWN_TRIPLED: usize = 1;
+
+    pub const IDX_PAWN_ISOLATED: usize = IDX_PAWN_TRIPLED + SIZE_PAWN_TRIPLED;
     pub const SIZE_PAWN_ISOLATED: usize = 1;
 
     pub const IDX_PAWN_BACKWARD: usize = IDX_PAWN_ISOLATED + SIZE_PAWN_ISOLATED;
     pub const SIZE_PAWN_BACKWARD: usize = 1;
 
-    pub const IDX_PAWN_SUPPORTED: usize = IDX_PAWN_BACKWARD + SIZE_PAWN_BACKWARD;
+    pub const IDX_PAWN_CANDIDATE_PASSER: usize = IDX_PAWN_BACKWARD + SIZE_PAWN_BACKWARD;
+    pub const SIZE_PAWN_CANDIDATE_PASSER: usize = 1;
+
+    pub const IDX_PAWN_LEVER: usize = IDX_PAWN_CANDIDATE_PASSER + SIZE_PAWN_CANDIDATE_PASSER;
+    pub const SIZE_PAWN_LEVER: usize = 1;
+
+    pub const IDX_PAWN_SUPPORTED: usize = IDX_PAWN_LEVER + SIZE_PAWN_LEVER;
     pub const SIZE_PAWN_SUPPORTED: usize = 64;
 
     pub const IDX_PAWN_ATTACK_CENTER: usize = IDX_PAWN_SUPPORTED + SIZE_PAWN_SUPPORTED;
@@ -71,7 +83,15 @@ pub mod normal_parameters {
         IDX_ROOK_BEHIND_ENEMY_PASSER + SIZE_ROOK_BEHIND_ENEMY_PASSER;
     pub const SIZE_PAWN_PASSED_WEAK: usize = 1;
 
-    pub const IDX_KNIGHT_SUPPORTED: usize = IDX_PAWN_PASSED_WEAK + SIZE_PAWN_PASSED_WEAK;
+    pub const IDX_PAWN_PASSED_ESCORTED: usize = IDX_PAWN_PASSED_WEAK + SIZE_PAWN_PASSED_WEAK;
+    pub const SIZE_PAWN_PASSED_ESCORTED: usize = 1;
+
+    pub const IDX_PAWN_PASSED_KING_IN_SQUARE: usize =
+        IDX_PAWN_PASSED_ESCORTED + SIZE_PAWN_PASSED_ESCORTED;
+    pub const SIZE_PAWN_PASSED_KING_IN_SQUARE: usize = 1;
+
+    pub const IDX_KNIGHT_SUPPORTED: usize =
+        IDX_PAWN_PASSED_KING_IN_SQUARE + SIZE_PAWN_PASSED_KING_IN_SQUARE;
     pub const SIZE_KNIGHT_SUPPORTED: usize = 1;
 
     pub const IDX_KNIGHT_OUTPOST_TABLE: usize = IDX_KNIGHT_SUPPORTED + SIZE_KNIGHT_SUPPORTED;
@@ -92,7 +112,32 @@ pub mod normal_parameters {
     pub const IDX_ROOK_ON_SEVENTH: usize = IDX_QUEEN_ON_SEMI_OPEN + SIZE_QUEEN_ON_SEMI_OPEN;
     pub const SIZE_ROOK_ON_SEVENTH: usize = 1;
 
-    pub const IDX_PAWN_PIECE_VALUE: usize = IDX_ROOK_ON_SEVENTH + SIZE_ROOK_ON_SEVENTH;
+    pub const IDX_ROOKS_CONNECTED: usize = IDX_ROOK_ON_SEVENTH + SIZE_ROOK_ON_SEVENTH;
+    pub const SIZE_ROOKS_CONNECTED: usize = 1;
+
+    pub const IDX_ROOKS_DOUBLED_ON_OPEN: usize = IDX_ROOKS_CONNECTED + SIZE_ROOKS_CONNECTED;
+    pub const SIZE_ROOKS_DOUBLED_ON_OPEN: usize = 1;
+
+    pub const IDX_BISHOP_TRAPPED: usize = IDX_ROOKS_DOUBLED_ON_OPEN + SIZE_ROOKS_DOUBLED_ON_OPEN;
+    pub const SIZE_BISHOP_TRAPPED: usize = 1;
+
+    pub const IDX_ROOK_TRAPPED_BY_KING: usize = IDX_BISHOP_TRAPPED + SIZE_BISHOP_TRAPPED;
+    pub const SIZE_ROOK_TRAPPED_BY_KING: usize = 1;
+
+    pub const IDX_KNIGHT_TRAPPED_ON_RIM: usize =
+        IDX_ROOK_TRAPPED_BY_KING + SIZE_ROOK_TRAPPED_BY_KING;
+    pub const SIZE_KNIGHT_TRAPPED_ON_RIM: usize = 1;
+
+    pub const IDX_KNIGHT_BEHIND_PAWN_CHAIN: usize =
+        IDX_KNIGHT_TRAPPED_ON_RIM + SIZE_KNIGHT_TRAPPED_ON_RIM;
+    pub const SIZE_KNIGHT_BEHIND_PAWN_CHAIN: usize = 1;
+
+    pub const IDX_BISHOP_BEHIND_PAWN_CHAIN: usize =
+        IDX_KNIGHT_BEHIND_PAWN_CHAIN + SIZE_KNIGHT_BEHIND_PAWN_CHAIN;
+    pub const SIZE_BISHOP_BEHIND_PAWN_CHAIN: usize = 1;
+
+    pub const IDX_PAWN_PIECE_VALUE: usize =
+        IDX_BISHOP_BEHIND_PAWN_CHAIN + SIZE_BISHOP_BEHIND_PAWN_CHAIN;
     pub const SIZE_PAWN_PIECE_VALUE: usize = 1;
 
     pub const IDX_KNIGHT_PIECE_VALUE: usize = IDX_PAWN_PIECE_VALUE + SIZE_PAWN_PIECE_VALUE;
@@ -104,7 +149,11 @@ pub mod normal_parameters {
     pub const IDX_BISHOP_PAIR: usize = IDX_BISHOP_PIECE_VALUE + SIZE_BISHOP_PIECE_VALUE;
     pub const SIZE_BISHOP_PAIR: usize = 1;
 
-    pub const IDX_ROOK_PIECE_VALUE: usize = IDX_BISHOP_PAIR + SIZE_BISHOP_PAIR;
+    pub const IDX_BISHOP_PAIR_WITH_PAWNS: usize = IDX_BISHOP_PAIR + SIZE_BISHOP_PAIR;
+    pub const SIZE_BISHOP_PAIR_WITH_PAWNS: usize = 17;
+
+    pub const IDX_ROOK_PIECE_VALUE: usize =
+        IDX_BISHOP_PAIR_WITH_PAWNS + SIZE_BISHOP_PAIR_WITH_PAWNS;
     pub const SIZE_ROOK_PIECE_VALUE: usize = 1;
 
     pub const IDX_QUEEN_PIECE_VALUE: usize = IDX_ROOK_PIECE_VALUE + SIZE_ROOK_PIECE_VALUE;
@@ -224,8 +273,22 @@ impl Parameters {
             true,
         );
         Parameters::init_constant(&mut params, PAWN_DOUBLED_VALUE, IDX_PAWN_DOUBLED, true);
+        Parameters::init_constant(
+            &mut params,
+            PAWN_DOUBLED_HALF_OPEN_VALUE,
+            IDX_PAWN_DOUBLED_HALF_OPEN,
+            true,
+        );
+        Parameters::init_constant(&mut params, PAWN_TRIPLED_VALUE, IDX_PAWN_TRIPLED, true);
         Parameters::init_constant(&mut params, PAWN_ISOLATED_VALUE, IDX_PAWN_ISOLATED, true);
         Parameters::init_constant(&mut params, PAWN_BACKWARD_VALUE, IDX_PAWN_BACKWARD, true);
+        Parameters::init_constant(
+            &mut params,
+            PAWN_CANDIDATE_PASSER_VALUE,
+            IDX_PAWN_CANDIDATE_PASSER,
+            true,
+        );
+        Parameters::init_constant(&mut params, PAWN_LEVER_VALUE, IDX_PAWN_LEVER, true);
         Parameters::init_psqt(&mut params, &PAWN_SUPPORTED_VALUE, IDX_PAWN_SUPPORTED);
         Parameters::init_constant(
             &mut params,
@@ -272,6 +335,18 @@ impl Parameters {
             true,
         );
         Parameters::init_constant(&mut params, PAWN_PASSED_WEAK, IDX_PAWN_PASSED_WEAK, true);
+        Parameters::init_constant(
+            &mut params,
+            PAWN_PASSED_ESCORTED,
+            IDX_PAWN_PASSED_ESCORTED,
+            true,
+        );
+        Parameters::init_constant(
+            &mut params,
+            PAWN_PASSED_KING_IN_SQUARE,
+            IDX_PAWN_PASSED_KING_IN_SQUARE,
+            true,
+        );
         Parameters::init_constant(
             &mut params,
             KNIGHT_SUPPORTED_BY_PAWN,
@@ -299,6 +374,38 @@ impl Parameters {
             true,
         );
         Parameters::init_constant(&mut params, ROOK_ON_SEVENTH, IDX_ROOK_ON_SEVENTH, true);
+        Parameters::init_constant(&mut params, ROOKS_CONNECTED, IDX_ROOKS_CONNECTED, true);
+        Parameters::init_constant(
+            &mut params,
+            ROOKS_DOUBLED_ON_OPEN_FILE,
+            IDX_ROOKS_DOUBLED_ON_OPEN,
+            true,
+        );
+        Parameters::init_constant(&mut params, BISHOP_TRAPPED, IDX_BISHOP_TRAPPED, true);
+        Parameters::init_constant(
+            &mut params,
+            ROOK_TRAPPED_BY_KING,
+            IDX_ROOK_TRAPPED_BY_KING,
+            true,
+        );
+        Parameters::init_constant(
+            &mut params,
+            KNIGHT_TRAPPED_ON_RIM,
+            IDX_KNIGHT_TRAPPED_ON_RIM,
+            true,
+        );
+        Parameters::init_constant(
+            &mut params,
+            KNIGHT_BEHIND_PAWN_CHAIN,
+            IDX_KNIGHT_BEHIND_PAWN_CHAIN,
+            true,
+        );
+        Parameters::init_constant(
+            &mut params,
+            BISHOP_BEHIND_PAWN_CHAIN,
+            IDX_BISHOP_BEHIND_PAWN_CHAIN,
+            true,
+        );
         Parameters::init_constant(&mut params, PAWN_PIECE_VALUE, IDX_PAWN_PIECE_VALUE, true);
         Parameters::init_constant(
             &mut params,
@@ -316,6 +423,12 @@ impl Parameters {
             true,
         );
         Parameters::init_constant(&mut params, BISHOP_PAIR_BONUS, IDX_BISHOP_PAIR, true);
+        Parameters::init_constants(
+            &mut params,
+            &BISHOP_PAIR_WITH_PAWNS,
+            IDX_BISHOP_PAIR_WITH_PAWNS,
+            true,
+        );
         Parameters::init_constant(&mut params, ROOK_PIECE_VALUE, IDX_ROOK_PIECE_VALUE, true);
         Parameters::init_constant(&mut params, QUEEN_PIECE_VALUE, IDX_QUEEN_PIECE_VALUE, true);
         Parameters::init_constants(
@@ -558,6 +671,14 @@ impl Display for Parameters {
             "pub const PAWN_DOUBLED_VALUE{}",
             self.format_constant(IDX_PAWN_DOUBLED, true),
         ));
+        res_str.push_str(&format!(
+            "pub const PAWN_DOUBLED_HALF_OPEN_VALUE{}",
+            self.format_constant(IDX_PAWN_DOUBLED_HALF_OPEN, true),
+        ));
+        res_str.push_str(&format!(
+            "pub const PAWN_TRIPLED_VALUE{}",
+            self.format_constant(IDX_PAWN_TRIPLED, true),
+        ));
         res_str.push_str(&format!(
             "pub const PAWN_ISOLATED_VALUE{}",
             self.format_constant(IDX_PAWN_ISOLATED, true),
@@ -566,6 +687,14 @@ impl Display for Parameters {
             "pub const PAWN_BACKWARD_VALUE{}",
             self.format_constant(IDX_PAWN_BACKWARD, true),
         ));
+        res_str.push_str(&format!(
+            "pub const PAWN_CANDIDATE_PASSER_VALUE{}",
+            self.format_constant(IDX_PAWN_CANDIDATE_PASSER, true),
+        ));
+        res_str.push_str(&format!(
+            "pub const PAWN_LEVER_VALUE{}",
+            self.format_constant(IDX_PAWN_LEVER, true),
+        ));
         res_str.push_str(&format!(
             "pub const PAWN_SUPPORTED_VALUE: [[EvaluationScore; 8];8] = {};\n",
             self.format_psqt(IDX_PAWN_SUPPORTED),
@@ -627,6 +756,14 @@ impl Display for Parameters {
             "pub const PAWN_PASSED_WEAK{}",
             self.format_constant(IDX_PAWN_PASSED_WEAK, true),
         ));
+        res_str.push_str(&format!(
+            "pub const PAWN_PASSED_ESCORTED{}",
+            self.format_constant(IDX_PAWN_PASSED_ESCORTED, true),
+        ));
+        res_str.push_str(&format!(
+            "pub const PAWN_PASSED_KING_IN_SQUARE{}",
+            self.format_constant(IDX_PAWN_PASSED_KING_IN_SQUARE, true),
+        ));
         res_str.push_str(&format!(
             "pub const KNIGHT_SUPPORTED_BY_PAWN{}",
             self.format_constant(IDX_KNIGHT_SUPPORTED, true),
@@ -655,6 +792,34 @@ impl Display for Parameters {
             "pub const ROOK_ON_SEVENTH{}",
             self.format_constant(IDX_ROOK_ON_SEVENTH, true),
         ));
+        res_str.push_str(&format!(
+            "pub const ROOKS_CONNECTED{}",
+            self.format_constant(IDX_ROOKS_CONNECTED, true),
+        ));
+        res_str.push_str(&format!(
+            "pub const ROOKS_DOUBLED_ON_OPEN_FILE{}",
+            self.format_constant(IDX_ROOKS_DOUBLED_ON_OPEN, true),
+        ));
+        res_str.push_str(&format!(
+            "pub const BISHOP_TRAPPED{}",
+            self.format_constant(IDX_BISHOP_TRAPPED, true),
+        ));
+        res_str.push_str(&format!(
+            "pub const ROOK_TRAPPED_BY_KING{}",
+            self.format_constant(IDX_ROOK_TRAPPED_BY_KING, true),
+        ));
+        res_str.push_str(&format!(
+            "pub const KNIGHT_TRAPPED_ON_RIM{}",
+            self.format_constant(IDX_KNIGHT_TRAPPED_ON_RIM, true),
+        ));
+        res_str.push_str(&format!(
+            "pub const KNIGHT_BEHIND_PAWN_CHAIN{}",
+            self.format_constant(IDX_KNIGHT_BEHIND_PAWN_CHAIN, true),
+        ));
+        res_str.push_str(&format!(
+            "pub const BISHOP_BEHIND_PAWN_CHAIN{}",
+            self.format_constant(IDX_BISHOP_BEHIND_PAWN_CHAIN, true),
+        ));
         res_str.push_str(&format!(
             "pub const PAWN_PIECE_VALUE{}",
             self.format_constant(IDX_PAWN_PIECE_VALUE, true),
@@ -679,6 +844,14 @@ impl Display for Parameters {
             "pub const BISHOP_PAIR_BONUS{}",
             self.format_constant(IDX_BISHOP_PAIR, true),
         ));
+        res_str.push_str(&format!(
+            "pub const BISHOP_PAIR_WITH_PAWNS{}",
+            self.format_constants(
+                IDX_BISHOP_PAIR_WITH_PAWNS,
+                SIZE_BISHOP_PAIR_WITH_PAWNS,
+                true
+            ),
+        ));
         res_str.push_str(&format!(
             "pub const ROOK_PIECE_VALUE{}",
             self.format_constant(IDX_ROOK_PIECE_VALUE, true),
@@ -15,8 +15,12 @@ pub const SHIELDING_PAWN_MISSING_ON_OPEN_FILE: [EvaluationScore; 4] = [
     EvaluationScore(-73, -23),
 ];
 pub const PAWN_DOUBLED_VALUE: EvaluationScore = EvaluationScore(-5, -14);
+pub const PAWN_DOUBLED_HALF_OPEN_VALUE: EvaluationScore = EvaluationScore(3, 6);
+pub const PAWN_TRIPLED_VALUE: EvaluationScore = EvaluationScore(-8, -15);
 pub const PAWN_ISOLATED_VALUE: EvaluationScore = EvaluationScore(-9, -21);
 pub const PAWN_BACKWARD_VALUE: EvaluationScore = EvaluationScore(-8, -16);
+pub const PAWN_CANDIDATE_PASSER_VALUE: EvaluationScore = EvaluationScore(6, 0);
+pub const PAWN_LEVER_VALUE: EvaluationScore = EvaluationScore(4, 0);
 pub const PAWN_SUPPORTED_VALUE: [[EvaluationScore; 8]; 8] = [
     [
         EvaluationScore(0, 0),
@@ -155,6 +159,8 @@ pub const PASSED_SUBTRACT_DISTANCE: [EvaluationScore; 13] = [
 pub const ROOK_BEHIND_SUPPORT_PASSER: EvaluationScore = EvaluationScore(7, 15);
 pub const ROOK_BEHIND_ENEMY_PASSER: EvaluationScore = EvaluationScore(21, -131);
 pub const PAWN_PASSED_WEAK: EvaluationScore = EvaluationScore(-11, 15);
+pub const PAWN_PASSED_ESCORTED: EvaluationScore = EvaluationScore(0, 20);
+pub const PAWN_PASSED_KING_IN_SQUARE: EvaluationScore = EvaluationScore(0, -30);
 pub const KNIGHT_SUPPORTED_BY_PAWN: EvaluationScore = EvaluationScore(-3, 0);
 pub const KNIGHT_OUTPOST_TABLE: [[EvaluationScore; 8]; 8] = [
     [
@@ -243,6 +249,13 @@ pub const ROOK_ON_SEMI_OPEN_FILE_BONUS: EvaluationScore = EvaluationScore(18, -7
 pub const QUEEN_ON_OPEN_FILE_BONUS: EvaluationScore = EvaluationScore(2, 5);
 pub const QUEEN_ON_SEMI_OPEN_FILE_BONUS: EvaluationScore = EvaluationScore(6, -1);
 pub const ROOK_ON_SEVENTH: EvaluationScore = EvaluationScore(28, 45);
+pub const ROOKS_CONNECTED: EvaluationScore = EvaluationScore(8, 4);
+pub const ROOKS_DOUBLED_ON_OPEN_FILE: EvaluationScore = EvaluationScore(18, 8);
+pub const BISHOP_TRAPPED: EvaluationScore = EvaluationScore(-95, -70);
+pub const ROOK_TRAPPED_BY_KING: EvaluationScore = EvaluationScore(-45, -10);
+pub const KNIGHT_TRAPPED_ON_RIM: EvaluationScore = EvaluationScore(-30, -20);
+pub const KNIGHT_BEHIND_PAWN_CHAIN: EvaluationScore = EvaluationScore(-12, -18);
+pub const BISHOP_BEHIND_PAWN_CHAIN: EvaluationScore = EvaluationScore(-20, -35);
 pub const PAWN_PIECE_VALUE: EvaluationScore = EvaluationScore(106, 178);
 pub const KNIGHT_PIECE_VALUE: EvaluationScore = EvaluationScore(449, 736);
 pub const KNIGHT_VALUE_WITH_PAWNS: [i16; 17] = [
@@ -250,6 +263,25 @@ pub const KNIGHT_VALUE_WITH_PAWNS: [i16; 17] = [
 ];
 pub const BISHOP_PIECE_VALUE: EvaluationScore = EvaluationScore(492, 711);
 pub const BISHOP_PAIR_BONUS: EvaluationScore = EvaluationScore(34, 109);
+pub const BISHOP_PAIR_WITH_PAWNS: [EvaluationScore; 17] = [
+    EvaluationScore(20, 30),
+    EvaluationScore(18, 27),
+    EvaluationScore(16, 24),
+    EvaluationScore(14, 21),
+    EvaluationScore(12, 18),
+    EvaluationScore(10, 15),
+    EvaluationScore(8, 12),
+    EvaluationScore(6, 9),
+    EvaluationScore(4, 6),
+    EvaluationScore(2, 3),
+    EvaluationScore(0, 0),
+    EvaluationScore(-2, -3),
+    EvaluationScore(-4, -6),
+    EvaluationScore(-6, -9),
+    EvaluationScore(-8, -12),
+    EvaluationScore(-10, -15),
+    EvaluationScore(-12, -18),
+];
 pub const ROOK_PIECE_VALUE: EvaluationScore = EvaluationScore(651, 1297);
 pub const QUEEN_PIECE_VALUE: EvaluationScore = EvaluationScore(1540, 2447);
 pub const DIAGONALLY_ADJACENT_SQUARES_WITH_OWN_PAWNS: [EvaluationScore; 5] = [
@@ -1,5 +1,6 @@
 use crate::board_representation::game_state::{BLACK, WHITE};
 use crate::evaluation::parameters::{normal_parameters::*, special_parameters::*, *};
+use crate::evaluation::{FORTRESS_DRAW_SCALE, UNSTOPPABLE_PASSER_SCORE};
 
 pub struct TraceEntry(pub u16, pub i8);
 pub struct CollapsedTrace {
@@ -16,9 +17,14 @@ pub struct CollapsedTrace {
     pub bishop_safe_check: [u8; 2],
     pub rook_safe_check: [u8; 2],
     pub queen_safe_check: [u8; 2],
+    pub unstoppable_passer: i8,
+    pub mating_corner: i16,
     pub is_guaranteed_draw: bool,
     pub slightly_winning_no_pawn: bool,
     pub slightly_winning_enemy_can_sac: bool,
+    pub fortress_draw: bool,
+    pub rook_endgame_scale: f32,
+    pub fifty_move_scale: f32,
 }
 impl CollapsedTrace {
     pub fn evaluate(&self, params: &Parameters) -> f32 {
@@ -122,6 +128,8 @@ impl CollapsedTrace {
             * f32::from(self.knights);
         res.1 += params.special[IDX_KNIGHT_VALUE_WITH_PAWN + self.pawns_on_board as usize]
             * f32::from(self.knights);
+        res.1 += f32::from(UNSTOPPABLE_PASSER_SCORE) * f32::from(self.unstoppable_passer);
+        res.1 += f32::from(self.mating_corner);
 
         if self.slightly_winning_no_pawn {
             res = (res.0, res.1 * params.special[IDX_SLIGHTLY_WINNING_NO_PAWN]);
@@ -131,6 +139,14 @@ impl CollapsedTrace {
                 res.1 * params.special[IDX_SLIGHTLY_WINNING_ENEMY_CAN_SAC],
             );
         }
+        if self.fortress_draw {
+            res = (res.0 * FORTRESS_DRAW_SCALE, res.1 * FORTRESS_DRAW_SCALE);
+        }
+        res = (
+            res.0 * self.rook_endgame_scale,
+            res.1 * self.rook_endgame_scale,
+        );
+        res = (res.0 * self.fifty_move_scale, res.1 * self.fifty_move_scale);
         (res.0 * self.phase + res.1 / 1.5 * (128.0 - self.phase)) / 128.0
     }
 }
@@ -148,9 +164,14 @@ pub struct LargeTrace {
     pub bishop_safe_check: [u8; 2],
     pub rook_safe_check: [u8; 2],
     pub queen_safe_check: [u8; 2],
+    pub unstoppable_passer: i8,
+    pub mating_corner: i16,
     pub is_guaranteed_draw: bool,
     pub slightly_winning_no_pawn: bool,
     pub slightly_winning_enemy_can_sac: bool,
+    pub fortress_draw: bool,
+    pub rook_endgame_scale: f32,
+    pub fifty_move_scale: f32,
 }
 
 impl LargeTrace {
@@ -169,9 +190,14 @@ impl LargeTrace {
             bishop_safe_check: [0; 2],
             rook_safe_check: [0; 2],
             queen_safe_check: [0; 2],
+            unstoppable_passer: 0,
+            mating_corner: 0,
             is_guaranteed_draw: false,
             slightly_winning_no_pawn: false,
             slightly_winning_enemy_can_sac: false,
+            fortress_draw: false,
+            rook_endgame_scale: 1.,
+            fifty_move_scale: 1.,
         }
     }
 
@@ -196,9 +222,14 @@ impl LargeTrace {
             bishop_safe_check: self.bishop_safe_check,
             rook_safe_check: self.rook_safe_check,
             queen_safe_check: self.queen_safe_check,
+            unstoppable_passer: self.unstoppable_passer,
+            mating_corner: self.mating_corner,
             is_guaranteed_draw: self.is_guaranteed_draw,
             slightly_winning_no_pawn: self.slightly_winning_no_pawn,
             slightly_winning_enemy_can_sac: self.slightly_winning_enemy_can_sac,
+            fortress_draw: self.fortress_draw,
+            rook_endgame_scale: self.rook_endgame_scale,
+            fifty_move_scale: self.fifty_move_scale,
         }
     }
 }
@@ -1,19 +1,29 @@
 pub mod bitboards;
 pub mod board_representation;
+pub mod compare;
+pub mod epd;
 pub mod evaluation;
 pub mod move_generation;
 pub mod search;
+pub mod sweep;
 
-use crate::board_representation::game_state::GameState;
+use crate::board_representation::game_state::{GameMove, GameState};
 use crate::move_generation::makemove::make_move;
 use crate::move_generation::movegen;
+use crate::move_generation::movegen::MoveList;
 use crate::search::cache::DEFAULT_HASH_SIZE;
 use crate::search::reserved_memory::ReservedMoveList;
 use crate::search::searcher::{
     InterThreadCommunicationSystem, DEFAULT_SKIP_RATIO, DEFAULT_THREADS,
 };
-use crate::search::timecontrol::DEFAULT_MOVE_OVERHEAD;
+use crate::search::strength::{DEFAULT_ELO, DEFAULT_EVAL_RANDOMNESS};
+use crate::search::timecontrol::{
+    DEFAULT_BOOK_EXIT_BOOST, DEFAULT_BOOK_EXIT_MOVES, DEFAULT_MIN_THINK_TIME, DEFAULT_MOVE_OVERHEAD,
+};
+use crate::search::{SearchParams, DEFAULT_CONTEMPT};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::thread;
 use std::time::Instant;
 
 #[derive(Copy, Clone)]
@@ -21,8 +31,23 @@ pub struct UCIOptions {
     pub hash_size: usize,
     pub threads: usize,
     pub move_overhead: u64,
+    pub min_think_time: u64,
+    pub book_exit_boost: u64, //Extra time in percent for the first book_exit_moves searches after ucinewgame
+    pub book_exit_moves: usize,
     pub debug_print: bool,
     pub skip_ratio: usize,
+    pub limit_strength: bool,
+    pub elo: usize,
+    pub ponder: bool,
+    pub contempt: i16,
+    pub eval_randomness: i16, //Maximum random offset of root move scores, for opening variety in selfplay
+    pub eval_random_seed: u64,
+    pub quiet: bool, //Suppresses all uci output of the search, e.g. when embedding the engine
+    pub verify_fail_high: bool, //Re-search a best move which took over by failing high before playing it
+    pub white_relative_score: bool, //Report scores from white's point of view instead of the side to move's
+    pub san_pv: bool, //Print the pv of info lines in SAN instead of long algebraic notation
+    pub report_branching_factor: bool, //Print the nodes and branching factor of every iteration
+    pub search_params: SearchParams,
 }
 impl Default for UCIOptions {
     fn default() -> Self {
@@ -30,8 +55,23 @@ impl Default for UCIOptions {
             hash_size: DEFAULT_HASH_SIZE,
             threads: DEFAULT_THREADS,
             move_overhead: DEFAULT_MOVE_OVERHEAD,
+            min_think_time: DEFAULT_MIN_THINK_TIME,
+            book_exit_boost: DEFAULT_BOOK_EXIT_BOOST,
+            book_exit_moves: DEFAULT_BOOK_EXIT_MOVES,
             debug_print: false,
             skip_ratio: DEFAULT_SKIP_RATIO,
+            limit_strength: false,
+            elo: DEFAULT_ELO,
+            ponder: false,
+            contempt: DEFAULT_CONTEMPT,
+            eval_randomness: DEFAULT_EVAL_RANDOMNESS,
+            eval_random_seed: 0,
+            quiet: false,
+            verify_fail_high: false,
+            white_relative_score: false,
+            san_pv: false,
+            report_branching_factor: false,
+            search_params: SearchParams::default(),
         }
     }
 }
@@ -51,12 +91,11 @@ pub fn perft_div(g: &GameState, depth: usize) -> u64 {
         count += res;
     }
     println!("{}", count);
-    let after = Instant::now();
-    let dur = after.duration_since(now);
-    let secs = dur.as_millis() as f64 / 1000.0;
+    let elapsed = now.elapsed();
     println!(
-        "{}",
-        &format!("Time {} ({} nps)", secs, count as f64 / secs)
+        "Time {} ({} nps)",
+        elapsed.as_millis() as f64 / 1000.0,
+        count as f64 * 1e9 / elapsed.as_nanos().max(1) as f64
     );
     count
 }
@@ -79,6 +118,34 @@ pub fn perft(g: &GameState, depth: usize, movelist: &mut ReservedMoveList) -> u6
         res
     }
 }
+//Root moves are handed out to the worker threads one at a time, every thread recurses with its own move lists
+pub fn perft_parallel(g: &GameState, depth: usize, threads: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let mut movelist = MoveList::default();
+    let _ = movegen::generate_moves(g, false, &mut movelist);
+    let root_moves: Vec<GameMove> = movelist.move_list.iter().map(|gmv| gmv.0).collect();
+    let next_root_move = AtomicUsize::new(0);
+    let count = AtomicU64::new(0);
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| {
+                let mut movelist = ReservedMoveList::default();
+                loop {
+                    let index = next_root_move.fetch_add(1, Ordering::Relaxed);
+                    if index >= root_moves.len() {
+                        break;
+                    }
+                    let res = perft(&make_move(g, root_moves[index]), depth - 1, &mut movelist);
+                    count.fetch_add(res, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+    count.load(Ordering::Relaxed)
+}
+
 const BENCHMARKING_POSITIONS: [&str; 50] = [
     "r3k2r/2pb1ppp/2pp1q2/p7/1nP1B3/1P2P3/P2N1PPP/R2QK2R w KQkq a6 0 14",
     "4rrk1/2p1b1p1/p1p3q1/4p3/2P2n1p/1P1NR2P/PB3PP1/3R1QK1 b - - 2 24",
@@ -1,10 +1,11 @@
 use crate::bitboards::bitboards::constants::{square, CASTLE_PERMISSION};
 use crate::bitboards::bitboards::square;
 use crate::board_representation::game_state::{
-    GameMove, GameMoveType, GameState, Irreversible, PieceType, WHITE,
+    GameMove, GameMoveType, GameState, Irreversible, PieceType, BLACK, WHITE,
 };
 use crate::board_representation::zobrist_hashing::ZOBRIST_KEYS;
 use crate::evaluation::psqt_evaluation::{psqt_add_piece, psqt_remove_piece};
+use crate::move_generation::movegen::pawn_targets;
 
 #[inline(always)]
 pub fn toggle_piece(
@@ -45,7 +46,7 @@ pub fn make_nullmove(g: &GameState) -> GameState {
     let half_moves = g.get_half_moves() + 1;
     let full_moves = g.get_full_moves() + g.get_color_to_move();
     let mut hash = g.get_hash() ^ ZOBRIST_KEYS.side_to_move;
-    enpassant_hash(g.get_en_passant(), en_passant, &mut hash);
+    enpassant_hash(g.capturable_en_passant(), en_passant, &mut hash);
     GameState::new(
         color_to_move,
         piece_bb,
@@ -57,6 +58,11 @@ pub fn make_nullmove(g: &GameState) -> GameState {
             g.castle_permissions(),
             g.get_phase().clone(),
             g.get_psqt(),
+            [
+                g.get_non_pawn_material(WHITE),
+                g.get_non_pawn_material(BLACK),
+            ],
+            g.get_piece_count() as u8,
         ),
         full_moves,
     )
@@ -87,6 +93,11 @@ pub fn make_move(g: &GameState, mv: GameMove) -> GameState {
     let mut hash = g.get_hash() ^ ZOBRIST_KEYS.side_to_move;
     let mut psqt = g.get_psqt();
     let mut phase = g.get_phase().clone();
+    let mut non_pawn_material = [
+        g.get_non_pawn_material(WHITE),
+        g.get_non_pawn_material(BLACK),
+    ];
+    let mut piece_count = g.get_piece_count() as u8;
     //Remove piece from original square
     toggle_piece(
         &mut piece_bb,
@@ -116,6 +127,8 @@ pub fn make_move(g: &GameState, mv: GameMove) -> GameState {
         toggle_hash(piece, square, color_to_move, &mut hash);
         psqt_remove_piece(piece, square as usize, color_to_move, &mut psqt);
         phase.delete_piece(piece);
+        non_pawn_material[color_to_move] -= piece.to_phase_score();
+        piece_count -= 1;
     }
     //Move rook for castling
     if let GameMoveType::Castle = mv.move_type {
@@ -189,6 +202,7 @@ pub fn make_move(g: &GameState, mv: GameMove) -> GameState {
             &mut psqt,
         );
         phase.add_piece(promo_piece);
+        non_pawn_material[g.get_color_to_move()] += promo_piece.to_phase_score();
     } else {
         //Add piece again at to
         toggle_piece(
@@ -224,14 +238,24 @@ pub fn make_move(g: &GameState, mv: GameMove) -> GameState {
     } else {
         0u64
     };
-    enpassant_hash(g.get_en_passant(), en_passant, &mut hash);
+    //The pawns of the side to move after a double push are untouched by it
+    let capturable_en_passant = if en_passant != 0u64 {
+        en_passant
+            & pawn_targets(
+                1 - g.get_color_to_move(),
+                g.get_piece(PieceType::Pawn, 1 - g.get_color_to_move()),
+            )
+    } else {
+        0u64
+    };
+    enpassant_hash(g.capturable_en_passant(), capturable_en_passant, &mut hash);
     //Step 5. Half moves
     let half_moves = if mv.move_type == GameMoveType::Quiet && mv.piece_type != PieceType::Pawn {
         g.get_half_moves() + 1
     } else {
         0
     };
-    GameState::new(
+    let res = GameState::new(
         color_to_move,
         piece_bb,
         color_bb,
@@ -242,7 +266,20 @@ pub fn make_move(g: &GameState, mv: GameMove) -> GameState {
             castle_permissions,
             phase,
             psqt,
+            non_pawn_material,
+            piece_count,
         ),
         full_moves,
-    )
+    );
+    debug_assert_eq!(
+        (
+            [
+                res.get_non_pawn_material(WHITE),
+                res.get_non_pawn_material(BLACK)
+            ],
+            res.get_piece_count() as u8
+        ),
+        res.recount_material()
+    );
+    res
 }
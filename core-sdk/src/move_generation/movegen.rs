@@ -90,15 +90,16 @@ impl GameState {
     //Occ: Blockers in the current position. Might be all_pieces or all_pieces without ctm king
     pub fn square_attackers(&self, sq: usize, occ: u64) -> u64 {
         let square = square(sq);
-        PieceType::King.attacks(sq, occ)
-            & self.get_piece(PieceType::King, 1 - self.get_color_to_move())
-            | PieceType::Knight.attacks(sq, occ)
-                & self.get_piece(PieceType::Knight, 1 - self.get_color_to_move())
-            | PieceType::Bishop.attacks(sq, occ)
-                & self.get_bishop_like_bb(1 - self.get_color_to_move())
-            | PieceType::Rook.attacks(sq, occ) & self.get_rook_like_bb(1 - self.get_color_to_move())
-            | pawn_targets(self.get_color_to_move(), square)
-                & self.get_piece(PieceType::Pawn, 1 - self.get_color_to_move())
+        (PieceType::King.attacks(sq, occ)
+            & self.get_piece(PieceType::King, 1 - self.get_color_to_move()))
+            | (PieceType::Knight.attacks(sq, occ)
+                & self.get_piece(PieceType::Knight, 1 - self.get_color_to_move()))
+            | (PieceType::Bishop.attacks(sq, occ)
+                & self.get_bishop_like_bb(1 - self.get_color_to_move()))
+            | (PieceType::Rook.attacks(sq, occ)
+                & self.get_rook_like_bb(1 - self.get_color_to_move()))
+            | (pawn_targets(self.get_color_to_move(), square)
+                & self.get_piece(PieceType::Pawn, 1 - self.get_color_to_move()))
     }
 
     pub fn get_checkers(&self) -> u64 {
@@ -1050,7 +1051,7 @@ pub fn generate_moves(
             }
             if g.castle_white_queenside()
                 && ((all_pieces | enemy_attacks) & (square(square::C1) | square(square::D1))
-                    | all_pieces & square(square::B1))
+                    | (all_pieces & square(square::B1)))
                     == 0u64
             {
                 movelist.add_move(GameMove {
@@ -1073,7 +1074,7 @@ pub fn generate_moves(
             }
             if g.castle_black_queenside()
                 && ((all_pieces | enemy_attacks) & (square(square::C8) | square(square::D8))
-                    | all_pieces & square(square::B8))
+                    | (all_pieces & square(square::B8)))
                     == 0u64
             {
                 movelist.add_move(GameMove {
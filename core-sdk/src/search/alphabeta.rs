@@ -2,12 +2,15 @@ use super::super::board_representation::game_state::*;
 use super::quiescence::q_search;
 use super::*;
 use super::{MATE_SCORE, MAX_SEARCH_DEPTH, STANDARD_SCORE};
-use crate::evaluation::eval_game_state;
+use crate::evaluation::{eval_game_state, FIFTY_MOVE_SCALE_START};
 use crate::move_generation::makemove::{make_move, make_nullmove};
 use crate::search::cache::{CacheEntry, INVALID_STATIC_EVALUATION};
+use crate::search::history_tables::HistoryUpdate;
 use crate::search::moveordering::{MoveOrderer, NORMAL_STAGES};
 use crate::search::quiescence::{piece_value, see};
 use crate::search::searcher::Thread;
+use crate::search::statistics::{ComponentTimer, SearchComponent};
+use crate::search::strength::{deviation_window, root_move_noise};
 
 pub const LMP_DEPTH: usize = 4;
 pub const FUTILITY_MARGIN: i16 = 90;
@@ -20,6 +23,15 @@ pub const HISTORY_PRUNING_THRESHOLD: isize = 0;
 pub const SEE_PRUNING_DEPTH: i16 = 6;
 pub const SEE_PRUNING_CAPTURE_MULT: f64 = -23.;
 pub const SEE_PRUNING_QUIET_MULT: f64 = -23.;
+pub const MAX_EXTENSION_DEPTH_FACTOR: i16 = 2;
+pub const LMR_MIN_DEPTH: i16 = 3;
+pub const LMR_MIN_MOVE_INDEX: usize = 2;
+//Above this static evaluation root moves are checked for stalemating the opponent
+pub const STALEMATE_WATCH_SCORE: i16 = 800;
+//Above this static evaluation a root move resetting the half move clock gets a bonus of one cp per
+//FIFTY_MOVE_BONUS_PLIES half moves played, up to FIFTY_MOVE_SCALE_START, so that a won position makes progress
+pub const FIFTY_MOVE_BONUS_SCORE: i16 = 300;
+pub const FIFTY_MOVE_BONUS_PLIES: usize = 2;
 
 pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut Thread) -> i16 {
     //Step 0. Prepare variables
@@ -31,6 +43,7 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
     if thread.search_statistics.nodes_searched % 4096 == 0 {
         checkup(thread)
     }
+    node_limit_checkup(thread);
     if thread.search_statistics.nodes_searched % 8192 == 0 {
         thread.itcs.update(
             thread.id,
@@ -49,8 +62,8 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
 
     //Step 3. Check for draw or mate distance pruning if not root (need best move at root)
     if !root {
-        if let SearchInstruction::StopSearching(r) = check_for_draw(p.game_state, &thread.history) {
-            return r;
+        if let SearchInstruction::StopSearching(_) = check_for_draw(p.game_state, &thread.history) {
+            return thread.draw_score(p.game_state, p.color);
         }
         //Mate distance pruning
         if let SearchInstruction::StopSearching(res) = mate_distance_pruning(&mut p) {
@@ -67,8 +80,8 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
     //Step 4. Attacks and in check  flag
     let incheck = p.game_state.in_check();
 
-    //Step 5. Check extensions if not at root
-    if incheck && !root {
+    //Step 5. Check extensions if not at root and the extension budget of the path isn't used up yet
+    if incheck && !root && extension_allowed(&p, thread) {
         p.depth_left += 1;
     }
 
@@ -85,7 +98,12 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
     //Step 8. TT Lookup
     //TODO Correctly insert and retrieve mates into the TT
     let mut tt_entry: Option<CacheEntry> = None;
-    if let SearchInstruction::StopSearching(res) = thread.itcs.cache().lookup(&p, &mut tt_entry) {
+    let timer = ComponentTimer::start();
+    let lookup = thread.itcs.cache().lookup(&p, &mut tt_entry);
+    thread
+        .search_statistics
+        .add_time(SearchComponent::Cache, timer);
+    if let SearchInstruction::StopSearching(res) = lookup {
         #[cfg(feature = "search-statistics")]
         {
             thread.search_statistics.add_cache_hit_aj_replace_ns();
@@ -98,11 +116,7 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
             thread.search_statistics.add_cache_hit_ns();
         }
     }
-    let mut tt_move = if let Some(ce) = tt_entry {
-        Some(CacheEntry::u16_to_mv(ce.mv, p.game_state))
-    } else {
-        None
-    };
+    let mut tt_move = tt_entry.and_then(|ce| thread.itcs.cache().tt_move(&ce, p.game_state));
     let mut static_evaluation = if let Some(ce) = tt_entry {
         if ce.static_evaluation != INVALID_STATIC_EVALUATION {
             Some(ce.static_evaluation)
@@ -118,7 +132,11 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
 
     //Step 9. Static Eval if needed
     let prunable = !is_pv_node && !incheck;
+    let timer = ComponentTimer::start();
     make_eval(&p, &mut static_evaluation, prunable);
+    thread
+        .search_statistics
+        .add_time(SearchComponent::Eval, timer);
 
     //Step 10. Prunings
     if prunable {
@@ -152,12 +170,27 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
     let mut current_max_score = STANDARD_SCORE;
     let mut index: usize = 0;
     let mut quiets_tried: usize = 0;
+    let mut captures_tried: usize = 0;
     let mut search_quiets = true;
     let mut move_orderer = MoveOrderer {
         stage: 0,
         stages: &NORMAL_STAGES,
         gen_only_captures: false,
     };
+    let root_evaluation = if root {
+        eval_game_state(p.game_state).final_eval * p.color
+    } else {
+        0
+    };
+    let stalemate_watch = root && root_evaluation >= STALEMATE_WATCH_SCORE;
+    let fifty_move_bonus = if root && root_evaluation >= FIFTY_MOVE_BONUS_SCORE {
+        (p.game_state.get_half_moves().min(FIFTY_MOVE_SCALE_START) / FIFTY_MOVE_BONUS_PLIES) as i16
+    } else {
+        0
+    };
+    if root {
+        thread.root_move_scores.clear();
+    }
     loop {
         let mv = move_orderer.next(thread, &p, pv_table_move, tt_move, search_quiets);
         if mv.is_none() {
@@ -184,19 +217,18 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
             && !gives_check
         {
             //Step 14.5. Futility Pruning. Skip quiet moves if futil_margin can't raise alpha
-            if futil_margin <= p.alpha {
-                #[cfg(feature = "search-statistics")]
-                {
-                    thread.search_statistics.add_futil_pruning();
-                }
+            if thread.search_params.futility_pruning && futil_margin <= p.alpha {
+                thread.search_statistics.add_futil_pruning();
                 index += 1;
                 search_quiets = false;
                 continue;
             }
             //Step 14.6. History Pruning. Skip quiet moves in low depths if they are below threshold
             if p.depth_left <= HISTORY_PRUNING_DEPTH
-                && thread.history_score[p.game_state.get_color_to_move()][mv.from as usize]
-                    [mv.to as usize]
+                && thread
+                    .history_tables
+                    .get(p.game_state.get_color_to_move(), mv)
+                    .history_score
                     < HISTORY_PRUNING_THRESHOLD
             {
                 #[cfg(feature = "search-statistics")]
@@ -219,7 +251,11 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
             let margin =
                 (SEE_PRUNING_QUIET_MULT * (p.depth_left as f64 * p.depth_left as f64)) as i16;
             if p.depth_left <= SEE_PRUNING_DEPTH && -piece_value(mv.piece_type) < margin {
+                let timer = ComponentTimer::start();
                 let see_value = see(p.game_state, mv, true, &mut thread.see_buffer);
+                thread
+                    .search_statistics
+                    .add_time(SearchComponent::See, timer);
                 if see_value < margin {
                     index += 1;
                     continue;
@@ -238,18 +274,49 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
         }
 
         //Step 14.7. Late move reductions. Compute reduction based on move type, node type and depth
-        let reduction =
-            if p.depth_left > 2 && (!isc || move_score < 0.) && index >= 2 && (!root || index >= 5)
-            {
-                compute_lmr_reduction(&p, thread, mv, index, isc || isp, gives_check, incheck)
-            } else {
-                0
-            };
+        let reduction = if thread.search_params.late_move_reductions
+            && p.depth_left >= thread.search_params.lmr_min_depth
+            && (!isc || move_score < 0.)
+            && index >= thread.search_params.lmr_min_move_index
+            && (!root || index >= 5)
+        {
+            compute_lmr_reduction(&p, thread, mv, index, isc || isp, gives_check, incheck)
+        } else {
+            0
+        };
+        if reduction > 0 {
+            thread.search_statistics.add_lmr_reduction();
+        }
 
         let next_state = make_move(p.game_state, mv);
-        //Step 14.8. Search the moves
+        let stalemates = stalemate_watch
+            && is_stalemate(
+                &next_state,
+                &mut thread.movelist.move_lists[p.current_depth + 1],
+            );
+        //Step 14.8. Search the moves. A random root offset and the fifty move bonus are applied by shifting the window,
+        // so the bounds stay valid
+        let root_offset = if root {
+            root_move_noise(
+                thread.eval_randomness,
+                thread.eval_random_seed,
+                p.game_state.get_hash(),
+                mv,
+            ) + if next_state.get_half_moves() == 0 {
+                fifty_move_bonus
+            } else {
+                0
+            }
+        } else {
+            0
+        };
+        p.alpha -= root_offset;
+        p.beta -= root_offset;
         let mut following_score: i16;
-        if p.depth_left <= 2 || !is_pv_node || index == 0 {
+        if stalemates {
+            //Throwing away the win, whatever the search below would make of it
+            following_score = thread.draw_score(p.game_state, p.color);
+        } else if p.depth_left <= 2 || !is_pv_node || index == 0 {
             //Step 14.8.1 Full move window. This is done in pv nodes when index == 0 or depth left <= 2, e.g. the first move. If we are in a pv node,
             // reduction is 0 and we really search the full window (without research). Else we are in a zero window, and the full window search is just
             // zero window again (with reduction). If the reduced zero window search raises alpha, research without reduction
@@ -307,18 +374,45 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
             }
         }
 
+        //Step 14.8.2 A fail low only gives an upper bound. Under limited strength any root move close to the best
+        // one may be played, so it is re-searched for its exact score down to the deviation window below alpha
+        if root
+            && index > 0
+            && !stalemates
+            && following_score <= p.alpha
+            && thread.itcs.uci_options().limit_strength
+        {
+            let window = deviation_window(thread.itcs.uci_options().elo);
+            if window > 0 {
+                following_score = -principal_variation_search(
+                    CombinedSearchParameters::from(
+                        -p.alpha - 1,
+                        -p.alpha + window + 1,
+                        p.depth_left - 1,
+                        &next_state,
+                        -p.color,
+                        p.current_depth + 1,
+                    ),
+                    thread,
+                );
+            }
+        }
+
+        following_score += root_offset;
+        p.alpha += root_offset;
+        p.beta += root_offset;
+
+        if root && !thread.self_stop {
+            thread.root_move_scores.push((mv, following_score));
+        }
+
         //Step 14.9. Update principal variation if move raised current best moves score (does not have to raise alpha)
         // Also update UCI pv
         if following_score > current_max_score && !thread.self_stop {
             thread.pv_table[p.current_depth].pv[0] = Some(mv);
             current_max_score = following_score;
             concatenate_pv(p.current_depth, thread);
-            uci_report_pv(
-                &p,
-                thread,
-                following_score,
-                following_score > original_alpha,
-            );
+            uci_report_pv(&p, thread, following_score, original_alpha);
         }
 
         //Step 14.10. Update alpha if score raises alpha
@@ -328,20 +422,32 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
 
         //Step 14.11. Beta cutoff: update several history statistics, and killer moves, then break
         if p.alpha >= p.beta {
-            #[cfg(feature = "search-statistics")]
-            {
-                thread.search_statistics.add_normal_node_beta_cutoff(index);
-            }
+            thread.search_statistics.add_normal_node_beta_cutoff(index);
             if !isc {
                 update_quiet_cutoff(&p, thread, mv, quiets_tried);
+            } else {
+                thread
+                    .history_tables
+                    .update_capture(mv, HistoryUpdate::Cutoff, p.depth_left);
             }
+            decrement_history_captures(thread, p.current_depth, captures_tried, p.depth_left);
             break;
-        } else if !isc {
+        } else if isc {
+            //Captures tried before a cutoff lose capture history
+            if captures_tried < thread.captures_tried[p.current_depth].len() {
+                thread.captures_tried[p.current_depth][captures_tried] = Some(mv);
+                captures_tried += 1;
+            }
+        } else {
             //Step 14.12 Move does not cause beta cutoff, add to quiet moves tried and update butterfly heuristic
             thread.quiets_tried[p.current_depth][quiets_tried] = Some(mv);
             quiets_tried += 1;
-            thread.bf_score[p.game_state.get_color_to_move()][mv.from as usize][mv.to as usize] +=
-                p.depth_left as usize * p.depth_left as usize;
+            thread.history_tables.update(
+                p.game_state.get_color_to_move(),
+                mv,
+                HistoryUpdate::Tried,
+                p.depth_left,
+            );
             //TODO: Update bf should maybe also be done in decrement history quiets
         }
 
@@ -355,7 +461,12 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
         check_end_condition(p.game_state, current_max_score > STANDARD_SCORE, incheck);
     if game_status != GameResult::Ingame {
         clear_pv(p.current_depth, thread);
-        return leaf_score(game_status, p.color, p.current_depth as i16);
+        return leaf_score(
+            game_status,
+            p.color,
+            p.current_depth as i16,
+            thread.white_contempt(p.game_state),
+        );
     }
     #[cfg(feature = "search-statistics")]
     {
@@ -366,6 +477,7 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
 
     //Step 16. Make TT Entry
     if !thread.self_stop {
+        let timer = ComponentTimer::start();
         thread.itcs.cache().insert(
             &p,
             thread.pv_table[p.current_depth].pv[0].expect("Can't unwrap move for TT"),
@@ -373,6 +485,9 @@ pub fn principal_variation_search(mut p: CombinedSearchParameters, thread: &mut
             original_alpha,
             static_evaluation,
         );
+        thread
+            .search_statistics
+            .add_time(SearchComponent::Cache, timer);
     }
 
     //Step 17. Return
@@ -386,7 +501,10 @@ pub fn uci_report_move(
     mv: GameMove,
     index: usize,
 ) {
-    if p.current_depth == 0 && thread.itcs.get_time_elapsed() > 1000 {
+    if p.current_depth == 0
+        && !thread.itcs.uci_options().quiet
+        && thread.itcs.get_time_elapsed() > 1000
+    {
         println!(
             "info depth {} currmove {:?} currmovenumber {}",
             p.depth_left,
@@ -396,6 +514,12 @@ pub fn uci_report_move(
     }
 }
 
+#[inline(always)]
+pub fn extension_allowed(p: &CombinedSearchParameters, thread: &Thread) -> bool {
+    //No path may end deeper than MAX_EXTENSION_DEPTH_FACTOR times the root depth
+    p.current_depth as i16 + p.depth_left < MAX_EXTENSION_DEPTH_FACTOR * thread.root_depth
+}
+
 #[inline(always)]
 pub fn mate_distance_pruning(p: &mut CombinedSearchParameters) -> SearchInstruction {
     //My score can at maximum be mate with this move
@@ -462,16 +586,14 @@ pub fn static_null_move_pruning(
     thread: &mut Thread,
     static_evaluation: Option<i16>,
 ) -> SearchInstruction {
-    if p.depth_left <= STATIC_NULL_MOVE_DEPTH
+    if thread.search_params.static_null_move_pruning
+        && p.depth_left <= STATIC_NULL_MOVE_DEPTH
         && static_evaluation.expect("Static null move") * p.color
             - STATIC_NULL_MOVE_MARGIN * p.depth_left
             >= p.beta
     {
         thread.history.pop();
-        #[cfg(feature = "search-statistics")]
-        {
-            thread.search_statistics.add_static_null_move_node();
-        }
+        thread.search_statistics.add_static_null_move_node();
         SearchInstruction::StopSearching(static_evaluation.expect("Static null move 2") * p.color)
     } else {
         SearchInstruction::ContinueSearching
@@ -485,7 +607,8 @@ pub fn null_move_pruning(
     static_evaluation: Option<i16>,
     tt_entry: &Option<CacheEntry>,
 ) -> SearchInstruction {
-    if p.depth_left >= NULL_MOVE_PRUNING_DEPTH
+    if thread.search_params.null_move_pruning
+        && p.depth_left >= NULL_MOVE_PRUNING_DEPTH
         && p.game_state.has_non_pawns(p.game_state.get_color_to_move())
         && static_evaluation.expect("null move static") * p.color >= p.beta
         && (tt_entry.is_none()
@@ -505,10 +628,7 @@ pub fn null_move_pruning(
             thread,
         );
         if rat >= p.beta {
-            #[cfg(feature = "search-statistics")]
-            {
-                thread.search_statistics.add_nm_pruning();
-            }
+            thread.search_statistics.add_nm_pruning();
             thread.history.pop();
             return SearchInstruction::StopSearching(rat);
         }
@@ -585,7 +705,11 @@ pub fn compute_lmr_reduction(
     if in_check {
         reduction -= 2;
     }
-    if thread.history_score[p.game_state.get_color_to_move()][mv.from as usize][mv.to as usize] > 0
+    if thread
+        .history_tables
+        .get(p.game_state.get_color_to_move(), mv)
+        .history_score
+        > 0
     {
         reduction -= 1;
     }
@@ -598,17 +722,23 @@ pub fn uci_report_pv(
     p: &CombinedSearchParameters,
     thread: &mut Thread,
     following_score: i16,
-    no_fail: bool,
+    original_alpha: i16,
 ) {
     if p.current_depth == 0 {
+        //Cutoffs in the tree can leave the pv shorter than the depth searched
+        let mut pv = thread.pv_table[0].clone();
+        thread
+            .itcs
+            .cache()
+            .reconstruct_pv(p.game_state, &mut pv, p.depth_left as usize);
         thread.replace_current_pv(
             p.game_state,
             ScoredPrincipalVariation {
-                pv: thread.pv_table[0].clone(),
+                pv,
                 score: following_score,
                 depth: p.depth_left as usize,
             },
-            no_fail,
+            ScoreBound::from_window(following_score, original_alpha, p.beta),
         );
     }
 }
@@ -620,15 +750,17 @@ pub fn update_quiet_cutoff(
     mv: GameMove,
     quiets_tried: usize,
 ) {
-    thread.hh_score[p.game_state.get_color_to_move()][mv.from as usize][mv.to as usize] +=
-        p.depth_left as usize * p.depth_left as usize;
-    thread.history_score[p.game_state.get_color_to_move()][mv.from as usize][mv.to as usize] +=
-        p.depth_left as isize * p.depth_left as isize;
+    thread.history_tables.update(
+        p.game_state.get_color_to_move(),
+        mv,
+        HistoryUpdate::Cutoff,
+        p.depth_left,
+    );
     decrement_history_quiets(
         thread,
         p.current_depth,
         quiets_tried,
-        p.depth_left as isize,
+        p.depth_left,
         p.game_state.get_color_to_move(),
     );
     if let Some(s) = thread.killer_moves[p.current_depth][0] {
@@ -651,12 +783,243 @@ pub fn decrement_history_quiets(
     thread: &mut Thread,
     current_depth: usize,
     quiets_tried: usize,
-    depth_left: isize,
+    depth_left: i16,
     side_to_move: usize,
 ) {
     for i in 0..quiets_tried {
-        let mv = thread.quiets_tried[current_depth][i].as_ref().unwrap();
-        thread.history_score[side_to_move][mv.from as usize][mv.to as usize] -=
-            depth_left * depth_left;
+        let mv = thread.quiets_tried[current_depth][i].unwrap();
+        thread
+            .history_tables
+            .update(side_to_move, mv, HistoryUpdate::Failed, depth_left);
+    }
+}
+
+//The captures tried before the cutoff didn't refute the move
+pub fn decrement_history_captures(
+    thread: &mut Thread,
+    current_depth: usize,
+    captures_tried: usize,
+    depth_left: i16,
+) {
+    for i in 0..captures_tried {
+        let mv = thread.captures_tried[current_depth][i].unwrap();
+        thread
+            .history_tables
+            .update_capture(mv, HistoryUpdate::Failed, depth_left);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::principal_variation_search;
+    use super::{LMR_MIN_DEPTH, LMR_MIN_MOVE_INDEX, MAX_EXTENSION_DEPTH_FACTOR};
+    use crate::board_representation::game_state::GameState;
+    use crate::search::searcher::test_thread;
+    use crate::search::{effective_contempt, CombinedSearchParameters, SearchParams, MATED_IN_MAX};
+
+    #[test]
+    fn check_extension_budget_test() {
+        let mut thread = test_thread();
+        //Both sides have plenty of checks available
+        let game_state = GameState::from_fen("3Q4/5pk1/6p1/8/8/8/5qPP/7K w - - 0 1");
+        for depth in 1..8 {
+            thread.root_depth = depth;
+            principal_variation_search(
+                CombinedSearchParameters::from(-16000, 16000, depth, &game_state, 1, 0),
+                &mut thread,
+            );
+            assert!(thread.search_statistics.depth as i16 <= MAX_EXTENSION_DEPTH_FACTOR * depth);
+        }
+        assert!(thread.search_statistics.nodes_searched < 1_000_000);
+    }
+
+    #[test]
+    fn stalemate_watch_test() {
+        //Qc7 takes the last square of the lone king without giving check, while Qc8 mates
+        for (fen, color, stalemate, mate) in [
+            ("k7/8/1K6/8/8/8/8/2Q5 w - - 0 1", 1, "c1c7", "c1c8"),
+            ("2q5/8/8/8/8/1k6/8/K7 b - - 0 1", -1, "c8c2", "c8c1"),
+        ]
+        .iter()
+        {
+            let mut thread = test_thread();
+            let game_state = GameState::from_fen(fen);
+            //At depth 1 the quiescence search would stand pat in the stalemate
+            thread.root_depth = 1;
+            principal_variation_search(
+                CombinedSearchParameters::from(-16000, 16000, 1, &game_state, *color, 0),
+                &mut thread,
+            );
+            let score = |uci: &str| {
+                thread
+                    .root_move_scores
+                    .iter()
+                    .find(|(mv, _)| format!("{:?}", mv) == uci)
+                    .unwrap()
+                    .1
+            };
+            assert_eq!(score(stalemate), 0);
+            assert!(score(mate) > -MATED_IN_MAX);
+            assert_eq!(format!("{:?}", thread.pv_table[0].pv[0].unwrap()), *mate);
+        }
+    }
+
+    #[test]
+    fn lmr_params_test() {
+        let reductions = |search_params: SearchParams| {
+            let mut thread = test_thread();
+            thread.search_params = search_params;
+            let game_state = GameState::from_fen(
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -",
+            );
+            for depth in 1..7 {
+                thread.root_depth = depth;
+                principal_variation_search(
+                    CombinedSearchParameters::from(-16000, 16000, depth, &game_state, 1, 0),
+                    &mut thread,
+                );
+            }
+            thread.search_statistics.lmr_reductions
+        };
+        let default = SearchParams::default();
+        assert_eq!(default.lmr_min_depth, LMR_MIN_DEPTH);
+        assert_eq!(default.lmr_min_move_index, LMR_MIN_MOVE_INDEX);
+        let default_reductions = reductions(default);
+        assert!(default_reductions > 0);
+        let later_moves = reductions(SearchParams {
+            lmr_min_move_index: 8,
+            ..default
+        });
+        assert!(later_moves < default_reductions);
+        let never = reductions(SearchParams {
+            lmr_min_move_index: 1000,
+            ..default
+        });
+        assert_eq!(never, 0);
+        let deeper = reductions(SearchParams {
+            lmr_min_depth: 100,
+            ..default
+        });
+        assert_eq!(deeper, 0);
+    }
+
+    #[test]
+    fn pruning_switches_test() {
+        let search = |search_params: SearchParams| {
+            let mut thread = test_thread();
+            thread.search_params = search_params;
+            let game_state = GameState::from_fen(
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -",
+            );
+            for depth in 1..7 {
+                thread.root_depth = depth;
+                principal_variation_search(
+                    CombinedSearchParameters::from(-16000, 16000, depth, &game_state, 1, 0),
+                    &mut thread,
+                );
+            }
+            thread.search_statistics
+        };
+        let default = SearchParams::default();
+        let statistics = search(default);
+        assert!(statistics.nm_pruned > 0);
+        assert!(statistics.snm_pruned > 0);
+        assert!(statistics.futil_nodes > 0);
+        assert!(statistics.lmr_reductions > 0);
+
+        let no_null_move = search(SearchParams {
+            null_move_pruning: false,
+            ..default
+        });
+        assert_eq!(no_null_move.nm_pruned, 0);
+        assert!(no_null_move.snm_pruned > 0);
+
+        let plain_pvs = search(SearchParams {
+            null_move_pruning: false,
+            static_null_move_pruning: false,
+            futility_pruning: false,
+            late_move_reductions: false,
+            ..default
+        });
+        assert_eq!(plain_pvs.nm_pruned, 0);
+        assert_eq!(plain_pvs.snm_pruned, 0);
+        assert_eq!(plain_pvs.futil_nodes, 0);
+        assert_eq!(plain_pvs.lmr_reductions, 0);
+        assert!(plain_pvs.nodes_searched > statistics.nodes_searched);
+    }
+
+    #[test]
+    fn beta_cutoff_histogram_test() {
+        let mut thread = test_thread();
+        let game_state =
+            GameState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -");
+        for depth in 1..7 {
+            thread.root_depth = depth;
+            principal_variation_search(
+                CombinedSearchParameters::from(-16000, 16000, depth, &game_state, 1, 0),
+                &mut thread,
+            );
+        }
+        let histogram = thread.search_statistics.beta_cutoff_histogram();
+        assert!(thread.search_statistics.normal_nodes_beta_cutoffs > 0);
+        assert_eq!(
+            histogram.iter().sum::<u64>(),
+            thread.search_statistics.normal_nodes_beta_cutoffs
+        );
+    }
+
+    #[test]
+    fn draw_leaf_contempt_test() {
+        let mut thread = test_thread();
+        thread.root_depth = 3;
+        thread.contempt = 40;
+        let draws = [
+            //Stalemate
+            ("7k/5Q2/6K1/8/8/8/8/RR6 b - - 0 1", false),
+            //Insufficient material
+            ("8/8/4k3/8/8/3BK3/8/8 b - - 0 1", false),
+            //Fifty-move rule
+            ("r3k3/8/8/8/8/8/8/R3K3 w - - 100 80", false),
+            //Repetition
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                true,
+            ),
+        ];
+        for &(fen, repeated) in draws.iter() {
+            let game_state = GameState::from_fen(fen);
+            thread.history.pointer = 0;
+            if repeated {
+                thread.history.push(game_state.get_hash(), false);
+            }
+            let contempt = effective_contempt(40, game_state.get_phase().phase);
+            let color = 1 - 2 * game_state.get_color_to_move() as i16;
+            for &root_plies_played in [0, 1].iter() {
+                thread.root_plies_played = root_plies_played;
+                //Plies from the root, so the root side is to move at even plies
+                let current_depth = if game_state.get_color_to_move() == root_plies_played {
+                    2
+                } else {
+                    1
+                };
+                let score = principal_variation_search(
+                    CombinedSearchParameters::from(
+                        -16000,
+                        16000,
+                        2,
+                        &game_state,
+                        color,
+                        current_depth,
+                    ),
+                    &mut thread,
+                );
+                let expected = if current_depth == 2 {
+                    -contempt
+                } else {
+                    contempt
+                };
+                assert_eq!(score, expected, "{}", fen);
+            }
+        }
     }
 }
@@ -1,8 +1,14 @@
 use crate::board_representation::game_state::{
     GameMove, GameMoveType, GameState, PieceType, PIECE_TYPES,
 };
-use crate::search::{CombinedSearchParameters, SearchInstruction, MATED_IN_MAX};
+use crate::move_generation::makemove::make_move;
+use crate::move_generation::movegen::{generate_moves, MoveList};
+use crate::search::{
+    CombinedSearchParameters, PrincipalVariation, SearchInstruction, MATED_IN_MAX,
+};
 use std::cell::UnsafeCell;
+#[cfg(debug_assertions)]
+use std::sync::atomic::{AtomicU64, Ordering};
 
 pub const INVALID_STATIC_EVALUATION: i16 = -32768;
 pub const DEFAULT_HASH_SIZE: usize = 256; //IN MB
@@ -14,6 +20,8 @@ pub struct Cache {
     pub buckets: usize,
     pub cache: UnsafeCell<Vec<CacheBucket>>,
     pub current_age: u8,
+    #[cfg(debug_assertions)]
+    pub collisions: AtomicU64, //Entries whose hash matched, but whose move doesn't fit the probed position
 }
 
 unsafe impl std::marker::Sync for Cache {}
@@ -47,8 +55,17 @@ impl Cache {
         }
     }
 
+    //The whole 64 bit hash takes part in the bucket index, independent of the table size and of the pointer
+    //width. The full hash is stored in the entries and checked by validate_hash, so index collisions are harmless
+    #[inline(always)]
+    pub fn bucket_index(hash: u64, buckets: usize) -> usize {
+        (hash % buckets as u64) as usize
+    }
+
+    //Buckets are padded to a cache line, so the table is sized by the padded bucket and not by its 3 entries.
+    //The table is the only allocation, which keeps the memory used within a bucket of the requested size
     pub fn with_size_threaded(mb_size: usize, num_threads: usize) -> Self {
-        let buckets = 1024 * 1024 * mb_size / 64;
+        let buckets = 1024 * 1024 * mb_size / std::mem::size_of::<CacheBucket>();
         let entries = buckets * 3;
         let cache = UnsafeCell::new(Cache::get_init_cache(buckets, num_threads));
         Cache {
@@ -56,10 +73,16 @@ impl Cache {
             buckets,
             cache,
             current_age: 0u8,
+            #[cfg(debug_assertions)]
+            collisions: AtomicU64::new(0),
         }
     }
 
     fn get_init_cache(buckets: usize, num_threads: usize) -> Vec<CacheBucket> {
+        // A hash size of 0 disables the cache, so there is nothing to allocate.
+        if buckets == 0 {
+            return Vec::new();
+        }
         // The cache may be large so initialize it using multiple threads, if possible.
         // This is relevant for events like TCEC, where huge memory is allocated (like 64GB),
         // and a startup time of >1min is unacceptable.
@@ -96,7 +119,14 @@ impl Cache {
         cache_vec
     }
 
+    pub fn allocated_bytes(&self) -> usize {
+        unsafe { (&*self.cache.get()).capacity() * std::mem::size_of::<CacheBucket>() }
+    }
+
     pub fn fill_status(&self) -> usize {
+        if self.entries == 0 {
+            return 0;
+        }
         if self.entries < 1000 {
             return 1000;
         }
@@ -135,13 +165,67 @@ impl Cache {
     pub fn age_entry(&self, hash: u64, new_age: u8) {
         unsafe {
             (&mut *self.cache.get())
-                .get_unchecked_mut(hash as usize % self.buckets)
+                .get_unchecked_mut(Cache::bucket_index(hash, self.buckets))
                 .age_entry(hash, new_age);
         }
     }
 
+    //The move of a validated entry always fits the position it was stored for, so an unfitting one means a
+    //genuine 64 bit collision (or a torn write in lazy smp). Those get counted in debug builds
+    #[inline(always)]
+    pub fn tt_move(&self, ce: &CacheEntry, game_state: &GameState) -> Option<GameMove> {
+        let mv = ce.get_tt_move(game_state);
+        #[cfg(debug_assertions)]
+        {
+            if mv.is_none() && ce.validate_hash(game_state.get_hash()) {
+                self.collisions.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        mv
+    }
+
     pub fn get(&self, hash: u64) -> CacheBucket {
-        unsafe { *(&*self.cache.get()).get_unchecked(hash as usize % self.buckets) }
+        unsafe { *(&*self.cache.get()).get_unchecked(Cache::bucket_index(hash, self.buckets)) }
+    }
+
+    //Extends the pv up to length moves by following the tt moves of exact entries. The walk stops at the
+    //first missing, inexact or illegal entry and on a repetition.
+    pub fn reconstruct_pv(&self, root: &GameState, pv: &mut PrincipalVariation, length: usize) {
+        if self.entries == 0 {
+            return;
+        }
+        let length = length.min(pv.pv.len() - 1);
+        let mut state = root.clone();
+        let mut visited = vec![state.get_hash()];
+        let mut index = 0;
+        while let Some(mv) = pv.pv[index] {
+            state = make_move(&state, mv);
+            visited.push(state.get_hash());
+            index += 1;
+        }
+        let mut movelist = MoveList::default();
+        while index < length {
+            let hash = state.get_hash();
+            let mv = match self.get(hash).probe(hash) {
+                Some(ce) if ce.is_exact() => self.tt_move(&ce, &state),
+                _ => None,
+            };
+            let mv = match mv {
+                Some(mv) => mv,
+                None => break,
+            };
+            generate_moves(&state, false, &mut movelist);
+            if !movelist.move_list.iter().any(|gmv| gmv.0 == mv) {
+                break;
+            }
+            state = make_move(&state, mv);
+            if visited.contains(&state.get_hash()) {
+                break;
+            }
+            visited.push(state.get_hash());
+            pv.pv[index] = Some(mv);
+            index += 1;
+        }
     }
 
     pub fn insert(
@@ -155,7 +239,7 @@ impl Cache {
         if self.entries == 0 {
             return;
         }
-        let index = p.game_state.get_hash() as usize % self.buckets;
+        let index = Cache::bucket_index(p.game_state.get_hash(), self.buckets);
         unsafe {
             (&mut *self.cache.get())
                 .get_unchecked_mut(index)
@@ -268,21 +352,24 @@ impl CacheBucket {
             self.0.swap(1, 2);
             return res;
         }
-        let mut min_score = self.0[2].get_score();
-        let mut min_entry = 2;
-
-        if self.0[1].get_score() < min_score {
-            min_score = self.0[1].get_score();
-            min_entry = 1;
-        }
-        if self.0[0].get_score() < min_score {
-            min_score = self.0[0].get_score();
-            min_entry = 0;
+        //Non PV nodes don't evict the PV entries of the current search
+        let mut min_entry = None;
+        for entry in (0..3).rev() {
+            if !pv_node && self.0[entry].is_protected(current_age) {
+                continue;
+            }
+            if min_entry
+                .is_none_or(|min: usize| self.0[entry].get_score() < self.0[min].get_score())
+            {
+                min_entry = Some(entry);
+            }
         }
 
         let new_score = p.depth_left as f64 * if pv_node { 1. } else { 0.7 };
-        if new_score >= min_score {
-            write_entry(&mut self.0[min_entry]);
+        if let Some(min_entry) = min_entry {
+            if new_score >= self.0[min_entry].get_score() {
+                write_entry(&mut self.0[min_entry]);
+            }
         }
         false
     }
@@ -381,6 +468,11 @@ impl CacheEntry {
         self.depth as f64 * if self.is_pv_node() { 1. } else { 0.7 }
     }
 
+    //PV entries written in the current search keep the PV reconstructable between iterations
+    pub fn is_protected(&self, current_age: u8) -> bool {
+        self.is_pv_node() && self.get_age() == current_age
+    }
+
     pub fn validate_hash(&self, hash: u64) -> bool {
         (self.upper_hash as u64) == (hash >> 32)
             && ((self.lower_hash ^ self.mv as u32) as u64) == (hash & 0xFFFF_FFFF)
@@ -451,6 +543,18 @@ impl CacheEntry {
         res as u16
     }
 
+    //The stored move could stem from a different position on a hash collision (or a racy write in lazy smp),
+    //so it is only returned if it is pseudo-legal in the given position
+    #[inline(always)]
+    pub fn get_tt_move(&self, game_state: &GameState) -> Option<GameMove> {
+        let mv = CacheEntry::u16_to_mv(self.mv, game_state);
+        if game_state.is_valid_tt_move(mv) {
+            Some(mv)
+        } else {
+            None
+        }
+    }
+
     #[inline(always)]
     pub fn u16_to_mv(mv: u16, game_state: &GameState) -> GameMove {
         let typ = mv & 15;
@@ -571,9 +675,11 @@ unsafe impl<T> Send for PtrWrapper<T> {}
 
 #[cfg(test)]
 mod tests {
-    use super::CacheEntry;
+    use super::{Cache, CacheBucket, CacheEntry, MAX_HASH_SIZE};
     use crate::board_representation::game_state::{GameMove, GameMoveType, GameState, PieceType};
     use crate::move_generation::makemove::make_move;
+    use crate::move_generation::movegen::{generate_moves, MoveList};
+    use crate::search::{CombinedSearchParameters, PrincipalVariation};
 
     #[test]
     fn mv_to_u16_test() {
@@ -780,4 +886,290 @@ mod tests {
             assert_eq!(d5d6res.piece_type, d5d6.piece_type);
         }
     }
+
+    #[test]
+    fn forged_tt_move_test() {
+        let game_state = GameState::standard();
+        let mut entry = CacheEntry::invalid();
+        //e7e5 is not pseudo-legal for white
+        let forged = GameMove {
+            from: 52,
+            to: 36,
+            piece_type: PieceType::Pawn,
+            move_type: GameMoveType::Quiet,
+        };
+        entry.write(
+            game_state.get_hash(),
+            5,
+            0,
+            None,
+            false,
+            false,
+            false,
+            forged,
+            0,
+        );
+        assert!(entry.get_tt_move(&game_state).is_none());
+        //Neither is moving a knight onto an own pawn
+        let forged = GameMove {
+            from: 1,
+            to: 11,
+            piece_type: PieceType::Knight,
+            move_type: GameMoveType::Quiet,
+        };
+        entry.write(
+            game_state.get_hash(),
+            5,
+            0,
+            None,
+            false,
+            false,
+            false,
+            forged,
+            0,
+        );
+        assert!(entry.get_tt_move(&game_state).is_none());
+
+        let e2e4 = GameMove {
+            from: 12,
+            to: 28,
+            piece_type: PieceType::Pawn,
+            move_type: GameMoveType::Quiet,
+        };
+        entry.write(
+            game_state.get_hash(),
+            5,
+            0,
+            None,
+            false,
+            false,
+            false,
+            e2e4,
+            0,
+        );
+        assert!(entry.get_tt_move(&game_state) == Some(e2e4));
+    }
+
+    #[test]
+    fn bucket_distribution_test() {
+        //Zobrist hashes of all positions up to 3 plies from two roots
+        let mut hashes = Vec::new();
+        let mut frontier = vec![
+            GameState::standard(),
+            GameState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -"),
+        ];
+        let mut movelist = MoveList::default();
+        for _ in 0..3 {
+            let mut next = Vec::new();
+            for state in frontier.iter() {
+                generate_moves(state, false, &mut movelist);
+                for gmv in movelist.move_list.iter() {
+                    next.push(make_move(state, gmv.0));
+                }
+            }
+            hashes.extend(next.iter().map(|state| state.get_hash()));
+            frontier = next;
+        }
+        hashes.sort_unstable();
+        hashes.dedup();
+        assert!(hashes.len() > 50_000);
+
+        //The largest configurable table and an odd sized one. Both the coarse position of the bucket and its
+        //lowest bits have to be spread evenly
+        const REGIONS: usize = 64;
+        for &buckets in [
+            1024 * 1024 * MAX_HASH_SIZE / 64,
+            3 * 1024 * 1024 * 1000 / 64 + 7,
+        ]
+        .iter()
+        {
+            let (mut coarse, mut fine) = ([0usize; REGIONS], [0usize; REGIONS]);
+            for &hash in hashes.iter() {
+                let index = Cache::bucket_index(hash, buckets);
+                assert!(index < buckets);
+                coarse[(index as u128 * REGIONS as u128 / buckets as u128) as usize] += 1;
+                fine[index % REGIONS] += 1;
+            }
+            let expected = hashes.len() / REGIONS;
+            for count in coarse.iter().chain(fine.iter()) {
+                assert!(*count > expected * 3 / 4 && *count < expected * 5 / 4);
+            }
+        }
+    }
+
+    #[test]
+    fn reconstruct_pv_test() {
+        let find_move = |state: &GameState, uci: &str| {
+            let mut movelist = MoveList::default();
+            generate_moves(state, false, &mut movelist);
+            movelist
+                .move_list
+                .iter()
+                .map(|gmv| gmv.0)
+                .find(|mv| format!("{:?}", mv) == uci)
+                .unwrap()
+        };
+        //Exact entries store a score within the window, bounds one outside of it
+        let store = |cache: &Cache, state: &GameState, mv: GameMove, exact: bool| {
+            let p = CombinedSearchParameters::from(-100, 100, 5, state, 1, 0);
+            cache.insert(&p, mv, if exact { 0 } else { 100 }, -100, None);
+        };
+        let pv_moves = |pv: &PrincipalVariation| -> Vec<String> {
+            pv.pv
+                .iter()
+                .take_while(|mv| mv.is_some())
+                .map(|mv| format!("{:?}", mv.unwrap()))
+                .collect()
+        };
+
+        let cache = Cache::with_size_threaded(1, 1);
+        let root = GameState::standard();
+        let mut state = root.clone();
+        for (uci, exact) in [
+            ("e2e4", true),
+            ("e7e5", true),
+            ("g1f3", true),
+            ("b8c6", false),
+            ("f1b5", true),
+        ]
+        .iter()
+        {
+            let mv = find_move(&state, uci);
+            store(&cache, &state, mv, *exact);
+            state = make_move(&state, mv);
+        }
+        //The pv already known is kept and the walk stops at the inexact entry after e2e4 e7e5 g1f3
+        let mut pv = PrincipalVariation::new(10);
+        pv.pv[0] = Some(find_move(&root, "e2e4"));
+        cache.reconstruct_pv(&root, &mut pv, 10);
+        assert_eq!(pv_moves(&pv), vec!["e2e4", "e7e5", "g1f3"]);
+        //The requested length is respected
+        let mut pv = PrincipalVariation::new(10);
+        cache.reconstruct_pv(&root, &mut pv, 2);
+        assert_eq!(pv_moves(&pv), vec!["e2e4", "e7e5"]);
+
+        //Knights going back and forth repeat the root, so the walk stops before reaching it again
+        let cache = Cache::with_size_threaded(1, 1);
+        let mut state = root.clone();
+        for uci in ["g1f3", "g8f6", "f3g1", "f6g8"].iter() {
+            let mv = find_move(&state, uci);
+            store(&cache, &state, mv, true);
+            state = make_move(&state, mv);
+        }
+        let mut pv = PrincipalVariation::new(10);
+        cache.reconstruct_pv(&root, &mut pv, 10);
+        assert_eq!(pv_moves(&pv), vec!["g1f3", "g8f6", "f3g1"]);
+
+        //Every reconstructed move is legal in the position it is played from
+        let mut state = root;
+        for mv in pv.pv.iter().take_while(|mv| mv.is_some()) {
+            let mv = mv.unwrap();
+            let mut movelist = MoveList::default();
+            generate_moves(&state, false, &mut movelist);
+            assert!(movelist.move_list.iter().any(|gmv| gmv.0 == mv));
+            state = make_move(&state, mv);
+        }
+    }
+
+    #[test]
+    fn pv_entry_protection_test() {
+        let root = GameState::standard();
+        let mut states = Vec::new();
+        let mut movelist = MoveList::default();
+        generate_moves(&root, false, &mut movelist);
+        for gmv in movelist.move_list.iter() {
+            let child = make_move(&root, gmv.0);
+            let mut child_movelist = MoveList::default();
+            generate_moves(&child, false, &mut child_movelist);
+            for child_gmv in child_movelist.move_list.iter() {
+                states.push((make_move(&child, child_gmv.0), child_gmv.0));
+            }
+        }
+        let e2e4 = GameMove {
+            from: 12,
+            to: 28,
+            piece_type: PieceType::Pawn,
+            move_type: GameMoveType::Quiet,
+        };
+        let insert_pv = |bucket: &mut CacheBucket, age: u8| {
+            let p = CombinedSearchParameters::from(-100, 100, 3, &root, 1, 0);
+            bucket.replace_entry(&p, e2e4, 0, -100, None, age);
+        };
+        //A burst of deeper non PV entries of other positions in the same bucket
+        let insert_burst = |bucket: &mut CacheBucket, age: u8| {
+            for (state, mv) in states.iter().skip(3) {
+                let p = CombinedSearchParameters::from(-1, 0, 10, state, 1, 2);
+                bucket.replace_entry(&p, *mv, 0, -1, None, age);
+            }
+        };
+
+        let mut bucket = CacheBucket::default();
+        insert_pv(&mut bucket, 0);
+        insert_burst(&mut bucket, 0);
+        let entry = bucket.probe(root.get_hash()).unwrap();
+        assert!(entry.is_pv_node() && entry.is_exact());
+        assert!(entry.get_tt_move(&root) == Some(e2e4));
+        assert_eq!(bucket.fill_status(), 3);
+
+        //PV entries of an earlier search are not protected
+        insert_burst(&mut bucket, 1);
+        assert!(bucket.probe(root.get_hash()).is_none());
+
+        //A bucket full of PV entries only takes further PV entries
+        let mut bucket = CacheBucket::default();
+        for (state, mv) in states.iter().take(3) {
+            let p = CombinedSearchParameters::from(-100, 100, 3, state, 1, 2);
+            bucket.replace_entry(&p, *mv, 0, -100, None, 0);
+        }
+        insert_burst(&mut bucket, 0);
+        assert!(bucket.probe(root.get_hash()).is_none());
+        insert_pv(&mut bucket, 0);
+        assert!(bucket.probe(root.get_hash()).is_some());
+    }
+
+    #[test]
+    fn allocated_size_test() {
+        assert_eq!(std::mem::size_of::<CacheBucket>(), 64);
+        for &mb_size in [0, 1, 3, 7, 16].iter() {
+            let cache = Cache::with_size_threaded(mb_size, 2);
+            let requested = mb_size * 1024 * 1024;
+            assert!(cache.allocated_bytes() <= requested);
+            assert!(requested - cache.allocated_bytes() < std::mem::size_of::<CacheBucket>());
+            assert_eq!(cache.entries, cache.buckets * 3);
+            cache.clear_threaded(3);
+            assert!(requested - cache.allocated_bytes() < std::mem::size_of::<CacheBucket>());
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn hash_collision_counter_test() {
+        use std::sync::atomic::Ordering;
+        let cache = Cache::with_size_threaded(1, 1);
+        let root = GameState::standard();
+        let p = CombinedSearchParameters::from(-100, 100, 5, &root, 1, 0);
+        //e7e5 is a move of another position sharing the key with the root, which has white to move
+        let foreign = GameMove {
+            from: 52,
+            to: 36,
+            move_type: GameMoveType::Quiet,
+            piece_type: PieceType::Pawn,
+        };
+        cache.insert(&p, foreign, 0, -100, None);
+        let entry = cache.get(root.get_hash()).probe(root.get_hash()).unwrap();
+        assert_eq!(cache.collisions.load(Ordering::Relaxed), 0);
+        assert!(cache.tt_move(&entry, &root).is_none());
+        assert_eq!(cache.collisions.load(Ordering::Relaxed), 1);
+
+        let legal = GameMove {
+            from: 12,
+            to: 28,
+            move_type: GameMoveType::Quiet,
+            piece_type: PieceType::Pawn,
+        };
+        cache.insert(&p, legal, 0, -100, None);
+        let entry = cache.get(root.get_hash()).probe(root.get_hash()).unwrap();
+        assert_eq!(cache.tt_move(&entry, &root), Some(legal));
+        assert_eq!(cache.collisions.load(Ordering::Relaxed), 1);
+    }
 }
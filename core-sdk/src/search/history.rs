@@ -6,6 +6,7 @@ pub struct History {
     pub hist: Vec<u64>,
     pub is_unique: Vec<bool>,
     pub pointer: usize,
+    pub root_pointer: usize, //Entries below the root pointer stem from the game history
 }
 
 impl Default for History {
@@ -14,6 +15,7 @@ impl Default for History {
             hist: vec![0u64; MAX_SEARCH_DEPTH + 100],
             is_unique: vec![false; MAX_SEARCH_DEPTH + 100],
             pointer: 0,
+            root_pointer: 0,
         }
     }
 }
@@ -29,18 +31,36 @@ impl History {
         self.pointer -= 1;
     }
 
-    pub fn get_occurences(&self, game_state: &GameState) -> usize {
-        let mut occurences = 0;
-        let mut index = self.pointer as isize - 1;
-        while index >= 0 {
-            if self.hist[index as usize] == game_state.get_hash() {
-                occurences += 1;
-            }
-            if self.is_unique[index as usize] {
-                break;
-            }
-            index -= 1;
-        }
-        occurences
+    //Marks every entry pushed so far as part of the game history
+    pub fn set_root(&mut self) {
+        self.root_pointer = self.pointer;
+    }
+
+    //Occurences of game_state after the root. Repeating a position of the search path is scored as a draw
+    //right away, since the side to move could just as well keep repeating it
+    pub fn search_path_occurrences(&self, game_state: &GameState) -> usize {
+        self.reversible_occurrences(game_state)
+            .filter(|&index| index >= self.root_pointer)
+            .count()
+    }
+
+    //Occurences of game_state in the moves played before the root. Only these count towards a
+    //claimable threefold repetition, so a single prior occurence is not a draw yet
+    pub fn game_occurrences(&self, game_state: &GameState) -> usize {
+        self.reversible_occurrences(game_state)
+            .filter(|&index| index < self.root_pointer)
+            .count()
+    }
+
+    //Indices of earlier entries matching game_state, up to the last irreversible move
+    fn reversible_occurrences<'a>(
+        &'a self,
+        game_state: &'a GameState,
+    ) -> impl Iterator<Item = usize> + 'a {
+        let first = (0..self.pointer)
+            .rev()
+            .find(|&index| self.is_unique[index])
+            .unwrap_or(0);
+        (first..self.pointer).filter(move |&index| self.hist[index] == game_state.get_hash())
     }
 }
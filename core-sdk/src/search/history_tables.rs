@@ -0,0 +1,172 @@
+use crate::board_representation::game_state::GameMove;
+
+//Capture history entries stay within +-CAPTURE_HISTORY_MAX, divided by CAPTURE_HISTORY_DIVISOR they can
+//reorder captures of the same victim, but never a capture of a more valuable victim behind one of a lesser
+pub const CAPTURE_HISTORY_MAX: isize = 5000;
+pub const CAPTURE_HISTORY_DIVISOR: f64 = 100.;
+pub const CAPTURE_HISTORY_MAX_BONUS: isize = 400;
+
+//All history statistics of one move, kept next to each other since they are read and written together
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    pub hh_score: usize,
+    pub bf_score: usize,
+    pub history_score: isize,
+}
+
+impl Default for HistoryEntry {
+    fn default() -> Self {
+        HistoryEntry {
+            hh_score: 0,
+            bf_score: 1,
+            history_score: 0,
+        }
+    }
+}
+
+impl HistoryEntry {
+    //Relative history: how often the move caused a cutoff compared to how often it was tried
+    pub fn ordering_score(&self) -> f64 {
+        self.hh_score as f64 / self.bf_score as f64 / 1000.0
+    }
+}
+
+pub enum HistoryUpdate {
+    Cutoff, //The move caused a beta cutoff
+    Tried,  //The move was searched without causing a cutoff
+    Failed, //The move was tried before another quiet move caused a cutoff
+}
+
+//Per thread history heuristics indexed by [color][from][to], aligned so that threads don't share cache lines.
+//Captures have their own history indexed by [moving piece][to][captured piece]
+#[repr(align(64))]
+pub struct HistoryTables {
+    entries: [[[HistoryEntry; 64]; 64]; 2],
+    captures: [[[isize; 5]; 64]; 6],
+}
+
+impl Default for HistoryTables {
+    fn default() -> Self {
+        HistoryTables {
+            entries: [[[HistoryEntry::default(); 64]; 64]; 2],
+            captures: [[[0; 5]; 64]; 6],
+        }
+    }
+}
+
+impl HistoryTables {
+    pub fn clear(&mut self) {
+        self.entries = [[[HistoryEntry::default(); 64]; 64]; 2];
+        self.captures = [[[0; 5]; 64]; 6];
+    }
+
+    //En passant is indexed by its target square, the captured piece is a pawn
+    #[inline(always)]
+    fn capture_entry(&mut self, mv: GameMove) -> &mut isize {
+        &mut self.captures[mv.piece_type as usize][mv.to as usize][mv.get_captured_piece() as usize]
+    }
+
+    #[inline(always)]
+    pub fn capture_score(&self, mv: GameMove) -> f64 {
+        self.captures[mv.piece_type as usize][mv.to as usize][mv.get_captured_piece() as usize]
+            as f64
+            / CAPTURE_HISTORY_DIVISOR
+    }
+
+    //Cutoffs raise the entry, failed captures lower it. The closer the entry is to the limit, the less it changes
+    #[inline(always)]
+    pub fn update_capture(&mut self, mv: GameMove, update: HistoryUpdate, depth_left: i16) {
+        let bonus = (depth_left as isize * depth_left as isize).min(CAPTURE_HISTORY_MAX_BONUS);
+        let bonus = match update {
+            HistoryUpdate::Cutoff => bonus,
+            HistoryUpdate::Failed => -bonus,
+            HistoryUpdate::Tried => return,
+        };
+        let entry = self.capture_entry(mv);
+        *entry += bonus - *entry * bonus.abs() / CAPTURE_HISTORY_MAX;
+    }
+
+    #[inline(always)]
+    pub fn get(&self, color: usize, mv: GameMove) -> &HistoryEntry {
+        &self.entries[color][mv.from as usize][mv.to as usize]
+    }
+
+    #[inline(always)]
+    pub fn update(&mut self, color: usize, mv: GameMove, update: HistoryUpdate, depth_left: i16) {
+        let entry = &mut self.entries[color][mv.from as usize][mv.to as usize];
+        match update {
+            HistoryUpdate::Cutoff => {
+                entry.hh_score += depth_left as usize * depth_left as usize;
+                entry.history_score += depth_left as isize * depth_left as isize;
+            }
+            HistoryUpdate::Tried => entry.bf_score += depth_left as usize * depth_left as usize,
+            HistoryUpdate::Failed => {
+                entry.history_score -= depth_left as isize * depth_left as isize
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HistoryTables, HistoryUpdate};
+    use crate::board_representation::game_state::{GameMove, GameMoveType, PieceType};
+
+    #[test]
+    fn history_tables_test() {
+        let mut hh_score = [[[0usize; 64]; 64]; 2];
+        let mut bf_score = [[[1usize; 64]; 64]; 2];
+        let mut history_score = [[[0isize; 64]; 64]; 2];
+        let mut tables = HistoryTables::default();
+        assert_eq!(std::mem::align_of::<HistoryTables>(), 64);
+
+        let quiet = |from: u8, to: u8| GameMove {
+            from,
+            to,
+            move_type: GameMoveType::Quiet,
+            piece_type: PieceType::Knight,
+        };
+        let moves = [quiet(1, 18), quiet(6, 21), quiet(57, 42), quiet(12, 28)];
+        //Pseudo random sequence of cutoffs, tried moves and failed moves at varying depths
+        let mut seed = 0x2545_f491u64;
+        for _ in 0..1000 {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            let color = (seed % 2) as usize;
+            let mv = moves[(seed >> 8) as usize % moves.len()];
+            let depth_left = ((seed >> 16) % 12) as i16 + 1;
+            let (from, to) = (mv.from as usize, mv.to as usize);
+            let update = match (seed >> 24) % 3 {
+                0 => {
+                    hh_score[color][from][to] += depth_left as usize * depth_left as usize;
+                    history_score[color][from][to] += depth_left as isize * depth_left as isize;
+                    HistoryUpdate::Cutoff
+                }
+                1 => {
+                    bf_score[color][from][to] += depth_left as usize * depth_left as usize;
+                    HistoryUpdate::Tried
+                }
+                _ => {
+                    history_score[color][from][to] -= depth_left as isize * depth_left as isize;
+                    HistoryUpdate::Failed
+                }
+            };
+            tables.update(color, mv, update, depth_left);
+        }
+        for color in 0..2 {
+            for &mv in moves.iter().chain([quiet(0, 63)].iter()) {
+                let (from, to) = (mv.from as usize, mv.to as usize);
+                let entry = tables.get(color, mv);
+                let old_ordering_score =
+                    hh_score[color][from][to] as f64 / bf_score[color][from][to] as f64 / 1000.0;
+                assert_eq!(entry.ordering_score(), old_ordering_score);
+                assert_eq!(entry.history_score, history_score[color][from][to]);
+            }
+        }
+
+        tables.clear();
+        assert_eq!(tables.get(0, moves[0]).bf_score, 1);
+        assert_eq!(tables.get(1, moves[1]).ordering_score(), 0.0);
+    }
+}
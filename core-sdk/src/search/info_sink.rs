@@ -0,0 +1,20 @@
+use crate::board_representation::game_state::GameMove;
+
+//Structured report of a completed iterative deepening iteration
+#[derive(Clone, Debug)]
+pub struct SearchInfo {
+    pub depth: usize,
+    pub seldepth: usize,
+    pub score: i16,
+    pub nodes: u64,
+    pub nps: u64,
+    pub time_elapsed: u64,
+    pub hashfull: usize,
+    pub pv: Vec<GameMove>,
+}
+
+//Lets an embedder (e.g. a GUI analysis panel) receive search progress without parsing info strings.
+//The sink is called from the search threads, once per completed depth.
+pub trait InfoSink: Send + Sync {
+    fn on_iteration(&self, info: &SearchInfo);
+}
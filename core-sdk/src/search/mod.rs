@@ -1,14 +1,19 @@
 pub mod alphabeta;
 pub mod cache;
 pub mod history;
+pub mod history_tables;
+pub mod info_sink;
 pub mod moveordering;
 pub mod quiescence;
 pub mod reserved_memory;
 pub mod searcher;
 pub mod statistics;
+pub mod strength;
 pub mod timecontrol;
 
 use crate::board_representation::game_state::*;
+use crate::move_generation::makemove::make_move;
+use crate::move_generation::movegen::{generate_moves, MoveList};
 use crate::search::searcher::Thread;
 use crate::search::timecontrol::TimeControlInformation;
 use history::History;
@@ -18,6 +23,66 @@ pub const MAX_SEARCH_DEPTH: usize = 100;
 pub const MATE_SCORE: i16 = 15000;
 pub const MATED_IN_MAX: i16 = -14000;
 pub const STANDARD_SCORE: i16 = -32767;
+pub const DEFAULT_CONTEMPT: i16 = 0;
+pub const MIN_CONTEMPT: i16 = -100;
+pub const MAX_CONTEMPT: i16 = 100;
+
+//Search parameters which can be changed at runtime, e.g. for tuning them
+#[derive(Copy, Clone)]
+pub struct SearchParams {
+    pub lmr_min_depth: i16,
+    pub lmr_min_move_index: usize,
+    pub aspiration_delta: i16,
+    pub aspiration_widening: f64,
+    pub max_qsearch_ply: i16,
+    pub stand_pat_margin: i16,
+    //Switches for single pruning techniques, turning all off leaves a plain PVS for debugging
+    pub null_move_pruning: bool,
+    pub static_null_move_pruning: bool,
+    pub futility_pruning: bool,
+    pub late_move_reductions: bool,
+}
+impl Default for SearchParams {
+    fn default() -> Self {
+        SearchParams {
+            lmr_min_depth: alphabeta::LMR_MIN_DEPTH,
+            lmr_min_move_index: alphabeta::LMR_MIN_MOVE_INDEX,
+            aspiration_delta: searcher::ASPIRATION_DELTA,
+            aspiration_widening: searcher::ASPIRATION_WIDENING,
+            max_qsearch_ply: quiescence::MAX_QSEARCH_PLY,
+            stand_pat_margin: quiescence::STAND_PAT_MARGIN,
+            null_move_pruning: true,
+            static_null_move_pruning: true,
+            futility_pruning: true,
+            late_move_reductions: true,
+        }
+    }
+}
+impl SearchParams {
+    //Takes the names of the hidden UCI options, case insensitive, and applies the same bounds
+    pub fn set(&mut self, name: &str, value: &str) -> std::result::Result<(), String> {
+        fn parse<T: std::str::FromStr>(name: &str, value: &str) -> std::result::Result<T, String> {
+            value
+                .trim()
+                .parse::<T>()
+                .map_err(|_| format!("Invalid {} value: {}", name, value))
+        }
+        match name.to_lowercase().as_str() {
+            "lmrmindepth" => self.lmr_min_depth = parse(name, value)?,
+            "lmrminmoveindex" => self.lmr_min_move_index = parse(name, value)?,
+            "aspirationdelta" => self.aspiration_delta = parse::<i16>(name, value)?.max(1),
+            "aspirationwidening" => self.aspiration_widening = parse::<f64>(name, value)?.max(1.1),
+            "maxqsearchply" => self.max_qsearch_ply = parse::<i16>(name, value)?.max(1),
+            "qsearchstandpatmargin" => self.stand_pat_margin = parse(name, value)?,
+            "nullmove" => self.null_move_pruning = parse(name, value)?,
+            "staticnullmove" => self.static_null_move_pruning = parse(name, value)?,
+            "futility" => self.futility_pruning = parse(name, value)?,
+            "lmr" => self.late_move_reductions = parse(name, value)?,
+            _ => return Err(format!("Unknown search parameter: {}", name)),
+        }
+        Ok(())
+    }
+}
 
 pub struct CombinedSearchParameters<'a> {
     pub alpha: i16,
@@ -78,6 +143,39 @@ impl PrincipalVariation {
             pv: vec![None; depth_left + 1],
         }
     }
+
+    //Cuts the pv off at the first move which is illegal in the position it would be played in.
+    //Returns the ply of that move and the position, which can only happen after a hash collision
+    pub fn truncate_illegal(&mut self, root: &GameState) -> Option<(usize, GameState)> {
+        let mut state = root.clone();
+        let mut movelist = MoveList::default();
+        for ply in 0..self.pv.len() {
+            let mv = match self.pv[ply] {
+                Some(mv) => mv,
+                None => break,
+            };
+            generate_moves(&state, false, &mut movelist);
+            if !movelist.move_list.iter().any(|gmv| gmv.0 == mv) {
+                for mv in self.pv[ply..].iter_mut() {
+                    *mv = None;
+                }
+                return Some((ply, state));
+            }
+            state = make_move(&state, mv);
+        }
+        None
+    }
+
+    //SAN depends on the position, e.g. for disambiguation and check marks, so the moves are played out from the root
+    pub fn to_san(&self, root: &GameState) -> String {
+        let mut state = root.clone();
+        let mut res = Vec::new();
+        for mv in self.pv.iter().map_while(|mv| *mv) {
+            res.push(mv.to_san(&state));
+            state = make_move(&state, mv);
+        }
+        res.join(" ")
+    }
 }
 
 impl Display for PrincipalVariation {
@@ -92,13 +190,33 @@ impl Display for PrincipalVariation {
     }
 }
 
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ScoreBound {
+    Exact,
+    LowerBound,
+    UpperBound,
+}
+impl ScoreBound {
+    //A score outside of the search window (a fail-high or fail-low) is only a bound on the real score
+    pub fn from_window(score: i16, alpha: i16, beta: i16) -> Self {
+        if score <= alpha {
+            ScoreBound::UpperBound
+        } else if score >= beta {
+            ScoreBound::LowerBound
+        } else {
+            ScoreBound::Exact
+        }
+    }
+}
+
 #[derive(Copy, Clone)]
 pub struct GradedMove(pub GameMove, pub Option<f64>);
 
 #[inline(always)]
-pub fn leaf_score(game_status: GameResult, color: i16, current_depth: i16) -> i16 {
+//Contempt is given from white's perspective, so draws get scored like any other evaluation
+pub fn leaf_score(game_status: GameResult, color: i16, current_depth: i16, contempt: i16) -> i16 {
     if game_status == GameResult::Draw {
-        return 0;
+        return -contempt * color;
     } else if game_status == GameResult::WhiteWin {
         return (MATE_SCORE - current_depth) * color;
     } else if game_status == GameResult::BlackWin {
@@ -107,13 +225,14 @@ pub fn leaf_score(game_status: GameResult, color: i16, current_depth: i16) -> i1
     panic!("Invalid Leaf");
 }
 
-//Doesn't actually check for stalemate
+//No side has more than a single minor piece left
 #[inline(always)]
-pub fn check_for_draw(game_state: &GameState, history: &History) -> SearchInstruction {
-    if game_state.get_piece_bb(PieceType::Pawn)
-        | game_state.get_piece_bb(PieceType::Rook)
-        | game_state.get_piece_bb(PieceType::Queen)
-        == 0u64
+pub fn is_dead_draw(game_state: &GameState) -> bool {
+    game_state.get_piece_count() <= 4
+        && game_state.get_piece_bb(PieceType::Pawn)
+            | game_state.get_piece_bb(PieceType::Rook)
+            | game_state.get_piece_bb(PieceType::Queen)
+            == 0u64
         && (game_state.get_piece(PieceType::Knight, WHITE)
             | game_state.get_piece(PieceType::Bishop, WHITE))
         .count_ones()
@@ -122,7 +241,18 @@ pub fn check_for_draw(game_state: &GameState, history: &History) -> SearchInstru
             | game_state.get_piece(PieceType::Bishop, BLACK))
         .count_ones()
             <= 1
-    {
+}
+
+//Contempt is only applied while there's enough material left to play for a win, tapering to zero in the endgame
+#[inline(always)]
+pub fn effective_contempt(contempt: i16, phase: f32) -> i16 {
+    (f32::from(contempt) * phase / 128.) as i16
+}
+
+//Doesn't actually check for stalemate
+#[inline(always)]
+pub fn check_for_draw(game_state: &GameState, history: &History) -> SearchInstruction {
+    if is_dead_draw(game_state) {
         return SearchInstruction::StopSearching(0);
     }
 
@@ -130,7 +260,10 @@ pub fn check_for_draw(game_state: &GameState, history: &History) -> SearchInstru
         return SearchInstruction::StopSearching(0);
     }
 
-    if history.get_occurences(game_state) >= 1 {
+    //A repetition in the search path is a draw already, but from the game history we need two prior
+    //occurences for a threefold repetition
+    if history.search_path_occurrences(game_state) >= 1 || history.game_occurrences(game_state) >= 2
+    {
         return SearchInstruction::StopSearching(0);
     }
     SearchInstruction::ContinueSearching
@@ -155,6 +288,16 @@ pub fn check_end_condition(
     GameResult::Ingame
 }
 
+//The side to move has no legal moves without being in check. The quiescence search stands pat instead of
+//seeing this, so a stalemating root move would look as good as the winning position before it
+pub fn is_stalemate(game_state: &GameState, movelist: &mut MoveList) -> bool {
+    if game_state.in_check() {
+        return false;
+    }
+    generate_moves(game_state, false, movelist);
+    movelist.move_list.is_empty()
+}
+
 #[inline(always)]
 pub fn clear_pv(at_depth: usize, thread: &mut Thread) {
     let mut index = 0;
@@ -177,14 +320,52 @@ pub fn concatenate_pv(at_depth: usize, thread: &mut Thread) {
     }
 }
 
+//How often a node limited search refreshes the node count of its thread, which bounds the overshoot per thread
+pub const NODE_LIMIT_GRANULARITY: u64 = 256;
+
+//Stops all threads once they searched the node limit together, but not before the first iteration completed, so
+//that there is a move to play
+#[inline(always)]
+pub fn node_limit_checkup(thread: &mut Thread) {
+    let limit = thread
+        .itcs
+        .node_limit
+        .load(std::sync::atomic::Ordering::Relaxed);
+    if limit == 0 || thread.search_statistics.nodes_searched < thread.next_node_limit_check {
+        return;
+    }
+    thread.next_node_limit_check = thread.search_statistics.nodes_searched + NODE_LIMIT_GRANULARITY;
+    thread.itcs.update(
+        thread.id,
+        thread.search_statistics.nodes_searched,
+        thread.search_statistics.seldepth,
+    );
+    if thread.itcs.get_nodes_sum() >= limit
+        && thread.itcs.completed_pv.lock().unwrap().pv.pv[0].is_some()
+    {
+        *thread
+            .itcs
+            .timeout_flag
+            .write()
+            .expect("Writing poisoned timeoutflag") = true;
+        thread.self_stop = true;
+    }
+}
+
 #[inline(always)]
 pub fn checkup(thread: &mut Thread) {
     if (thread.id == 0
+        && !thread
+            .itcs
+            .pondering
+            .load(std::sync::atomic::Ordering::SeqCst)
         && thread.tc.time_over(
             thread.itcs.get_time_elapsed(),
             &TimeControlInformation {
                 high_score_diff: false,
+                min_think_time: thread.min_think_time,
                 time_saved: thread.time_saved,
+                time_boost: thread.time_boost,
                 stable_pv: thread
                     .itcs
                     .stable_pv
@@ -192,6 +373,9 @@ pub fn checkup(thread: &mut Thread) {
             },
             thread.itcs.uci_options().move_overhead,
         ))
+        || thread
+            .verification_deadline
+            .is_some_and(|deadline| thread.itcs.get_time_elapsed() > deadline)
         || *thread
             .itcs
             .timeout_flag
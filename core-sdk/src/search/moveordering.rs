@@ -1,4 +1,4 @@
-use crate::board_representation::game_state::{GameMove, PieceType};
+use crate::board_representation::game_state::{GameMove, GameMoveType, PieceType};
 use crate::move_generation::movegen;
 use crate::search::moveordering::MoveOrderingStage::{
     BadCapture, GoodCapture, GoodCaptureInitialization, Killer, PVMove, Quiet, QuietInitialization,
@@ -6,6 +6,7 @@ use crate::search::moveordering::MoveOrderingStage::{
 };
 use crate::search::quiescence::{see, PIECE_VALUES};
 use crate::search::searcher::Thread;
+use crate::search::statistics::{ComponentTimer, SearchComponent};
 use crate::search::{CombinedSearchParameters, GradedMove};
 
 //For MVV-LVA calculation
@@ -17,6 +18,18 @@ pub fn mvvlva(mv: GameMove) -> i16 {
     TARGET_VALUE[mv.get_captured_piece() as usize] - ATTACKER_VALUE[mv.piece_type as usize]
 }
 
+//Queen promotions come before all captures. Rook and bishop promotions are hardly ever better than a queen
+//promotion, so they are tried after the bad captures. Knight promotions keep their usual place, as they can be tactics
+pub const QUEEN_PROMOTION_SCORE: i16 = TARGET_VALUE[4] - TARGET_VALUE[0];
+pub const UNDERPROMOTION_SCORE: f64 = -10_000.;
+
+pub fn is_useless_underpromotion(mv: GameMove) -> bool {
+    matches!(
+        mv.move_type,
+        GameMoveType::Promotion(PieceType::Rook, _) | GameMoveType::Promotion(PieceType::Bishop, _)
+    )
+}
+
 pub const NORMAL_STAGES: [MoveOrderingStage; 8] = [
     PVMove,
     TTMove,
@@ -29,6 +42,15 @@ pub const NORMAL_STAGES: [MoveOrderingStage; 8] = [
 ];
 pub const QUIESCENCE_STAGES: [MoveOrderingStage; 3] =
     [TTMove, GoodCaptureInitialization, GoodCapture];
+//In check the quiescence search has to try every evasion, not only the captures
+pub const QUIESCENCE_EVASION_STAGES: [MoveOrderingStage; 6] = [
+    TTMove,
+    GoodCaptureInitialization,
+    GoodCapture,
+    QuietInitialization,
+    Quiet,
+    BadCapture,
+];
 pub enum MoveOrderingStage {
     PVMove,
     TTMove,
@@ -68,10 +90,8 @@ impl MoveOrderer {
             }
             MoveOrderingStage::TTMove => {
                 self.stage += 1;
-                if tt_move.is_some()
-                    && tt_move != pv_table_move
-                    && p.game_state.is_valid_tt_move(tt_move.unwrap())
-                {
+                //The tt move has already been checked for pseudo-legality when probing
+                if tt_move.is_some() && tt_move != pv_table_move {
                     Some((tt_move.unwrap(), 0.))
                 } else {
                     self.next(thread, p, pv_table_move, tt_move, search_quiets)
@@ -79,11 +99,15 @@ impl MoveOrderer {
             }
             MoveOrderingStage::GoodCaptureInitialization => {
                 //Generate moves first!
+                let timer = ComponentTimer::start();
                 movegen::generate_moves(
                     &p.game_state,
                     self.gen_only_captures,
                     &mut thread.movelist.move_lists[p.current_depth],
                 );
+                thread
+                    .search_statistics
+                    .add_time(SearchComponent::MoveGen, timer);
                 let our_mvlist = &mut thread.movelist.move_lists[p.current_depth];
 
                 if let Some(pv_move) = pv_table_move {
@@ -99,10 +123,26 @@ impl MoveOrderer {
                     }
                 }
 
-                //Give any capture move in movelist its MVV-LVA score
+                //Give any capture move in movelist its MVV-LVA score, adjusted by the capture history. Quiet queen
+                //promotions are only tried outside of the quiescence search
                 for mv in our_mvlist.move_list.iter_mut() {
-                    if mv.0.is_capture() {
-                        mv.1 = Some(f64::from(mvvlva(mv.0)));
+                    if is_useless_underpromotion(mv.0) {
+                        mv.1 = Some(UNDERPROMOTION_SCORE);
+                    } else if let GameMoveType::Promotion(PieceType::Queen, captured) =
+                        mv.0.move_type
+                    {
+                        if captured.is_some() {
+                            mv.1 = Some(
+                                f64::from(QUEEN_PROMOTION_SCORE + mvvlva(mv.0))
+                                    + thread.history_tables.capture_score(mv.0),
+                            );
+                        } else if !self.gen_only_captures {
+                            mv.1 = Some(f64::from(QUEEN_PROMOTION_SCORE));
+                        }
+                    } else if mv.0.is_capture() {
+                        mv.1 = Some(
+                            f64::from(mvvlva(mv.0)) + thread.history_tables.capture_score(mv.0),
+                        );
                     }
                 }
 
@@ -119,19 +159,24 @@ impl MoveOrderer {
                 } else {
                     let (gm_index, graded_move) = highest_mvv_lva.unwrap();
                     our_list.move_list.remove(gm_index);
-                    if PIECE_VALUES[graded_move.0.get_captured_piece() as usize]
-                        - PIECE_VALUES[graded_move.0.piece_type as usize]
-                        >= 0
+                    if !graded_move.0.is_capture()
+                        || PIECE_VALUES[graded_move.0.get_captured_piece() as usize]
+                            - PIECE_VALUES[graded_move.0.piece_type as usize]
+                            >= 0
                         || graded_move.0.piece_type == PieceType::King
                     {
                         Some((graded_move.0, 0.))
                     } else {
+                        let timer = ComponentTimer::start();
                         let see_value = see(
                             p.game_state,
                             graded_move.0,
                             self.stages.len() == NORMAL_STAGES.len(),
                             &mut thread.see_buffer,
                         );
+                        thread
+                            .search_statistics
+                            .add_time(SearchComponent::See, timer);
                         if see_value >= 0 {
                             Some((graded_move.0, 0.))
                         } else {
@@ -185,14 +230,10 @@ impl MoveOrderer {
                         if mv.1.is_none() {
                             debug_assert!(!mv.0.is_capture());
                             mv.1 = Some(
-                                thread.hh_score[p.game_state.get_color_to_move()]
-                                    [mv.0.from as usize][mv.0.to as usize]
-                                    as f64
-                                    / thread.bf_score[p.game_state.get_color_to_move()]
-                                        [mv.0.from as usize]
-                                        [mv.0.to as usize]
-                                        as f64
-                                    / 1000.0,
+                                thread
+                                    .history_tables
+                                    .get(p.game_state.get_color_to_move(), mv.0)
+                                    .ordering_score(),
                             );
                         }
                     }
@@ -227,7 +268,7 @@ impl MoveOrderer {
                 let our_list = &mut thread.movelist.move_lists[p.current_depth];
                 let highest = our_list.highest_score();
                 if let Some((index, gmv)) = highest {
-                    debug_assert!(gmv.0.is_capture());
+                    debug_assert!(gmv.0.is_capture() || is_useless_underpromotion(gmv.0));
                     debug_assert!(gmv.1.unwrap() < 0.);
                     our_list.move_list.remove(index);
                     Some((gmv.0, gmv.1.unwrap()))
@@ -239,3 +280,92 @@ impl MoveOrderer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{MoveOrderer, NORMAL_STAGES};
+    use crate::board_representation::game_state::{GameMove, GameState};
+    use crate::search::history_tables::HistoryUpdate;
+    use crate::search::searcher::{search_move, test_thread, Thread};
+    use crate::search::timecontrol::TimeControl;
+    use crate::search::{CombinedSearchParameters, MATE_SCORE, MAX_SEARCH_DEPTH};
+    use std::sync::Arc;
+
+    #[test]
+    fn underpromotion_ordering_test() {
+        //f8=N is mate, the bishop on g8 can also be taken with promotion
+        let game_state = GameState::from_fen("6br/5Ppk/7p/5P2/8/8/8/K7 w - - 0 1");
+        let mut thread = test_thread();
+        let itcs = Arc::clone(&thread.itcs);
+        let mut move_orderer = MoveOrderer {
+            stage: 0,
+            stages: &NORMAL_STAGES,
+            gen_only_captures: false,
+        };
+        let p = CombinedSearchParameters::from(-16000, 16000, 3, &game_state, 1, 0);
+        let mut order = Vec::new();
+        while let Some((mv, _)) = move_orderer.next(&mut thread, &p, None, None, true) {
+            order.push(format!("{:?}", mv));
+        }
+        assert_eq!(order[..2], ["f7g8q", "f7f8q"]);
+        let (useful, useless) = order.split_at(order.len() - 4);
+        assert!(useful.contains(&"f7f8n".to_owned()));
+        assert!(useful.contains(&"f7g8n".to_owned()));
+        let mut useless = useless.to_vec();
+        useless.sort();
+        assert_eq!(useless, ["f7f8b", "f7f8r", "f7g8b", "f7g8r"]);
+
+        //The knight promotion is still found
+        let score = search_move(
+            Arc::clone(&itcs),
+            3,
+            game_state,
+            Vec::new(),
+            TimeControl::Infinite,
+        );
+        assert_eq!(format!("{:?}", itcs.played_pv().pv.pv[0].unwrap()), "f7f8n");
+        assert!(score >= MATE_SCORE - MAX_SEARCH_DEPTH as i16);
+    }
+
+    #[test]
+    fn capture_history_ordering_test() {
+        //Both pawns are hanging, the knight on b4 is worth more
+        let game_state = GameState::from_fen("4k3/8/8/2p1p3/1n6/3N4/8/4K3 w - - 0 1");
+        let mut thread = test_thread();
+        let p = CombinedSearchParameters::from(-16000, 16000, 3, &game_state, 1, 0);
+        let captures = |thread: &mut Thread| {
+            let mut move_orderer = MoveOrderer {
+                stage: 0,
+                stages: &NORMAL_STAGES,
+                gen_only_captures: false,
+            };
+            let mut order = Vec::new();
+            while let Some((mv, _)) = move_orderer.next(thread, &p, None, None, true) {
+                if mv.is_capture() {
+                    order.push(mv);
+                }
+            }
+            order
+        };
+        let default_order = captures(&mut thread);
+        assert_eq!(default_order.len(), 3);
+        assert_eq!(format!("{:?}", default_order[0]), "d3b4");
+        let sibling: GameMove = default_order[2];
+        for _ in 0..10 {
+            thread
+                .history_tables
+                .update_capture(sibling, HistoryUpdate::Cutoff, 4);
+        }
+        let order = captures(&mut thread);
+        assert_eq!(order[..2], [default_order[0], sibling]);
+        assert_eq!(order[2], default_order[1]);
+
+        //Failed captures fall behind their sibling again
+        for _ in 0..20 {
+            thread
+                .history_tables
+                .update_capture(sibling, HistoryUpdate::Failed, 4);
+        }
+        assert_eq!(captures(&mut thread), default_order);
+    }
+}
@@ -7,10 +7,15 @@ use super::alphabeta::*;
 use super::*;
 use crate::bitboards::bitboards::constants::{KING_ATTACKS, KNIGHT_ATTACKS, RANKS};
 use crate::move_generation::makemove::make_move;
-use crate::search::cache::CacheEntry;
-use crate::search::moveordering::{MoveOrderer, QUIESCENCE_STAGES};
+use crate::search::cache::INVALID_STATIC_EVALUATION;
+use crate::search::moveordering::{MoveOrderer, QUIESCENCE_EVASION_STAGES, QUIESCENCE_STAGES};
+use crate::search::statistics::{ComponentTimer, SearchComponent};
 
 pub const DELTA_PRUNING: i16 = 100;
+//Plies into the quiescence search after which the stand pat is returned, only reached in long capture chains
+pub const MAX_QSEARCH_PLY: i16 = 32;
+//The side to move is assumed to get at least its static evaluation minus this margin by not capturing
+pub const STAND_PAT_MARGIN: i16 = 0;
 pub const PIECE_VALUES: [i16; 6] = [100, 400, 400, 650, 1100, 30000];
 
 pub fn q_search(mut p: CombinedSearchParameters, thread: &mut Thread) -> i16 {
@@ -29,31 +34,24 @@ pub fn q_search(mut p: CombinedSearchParameters, thread: &mut Thread) -> i16 {
     }
 
     //Step 3. Check for draw
-    if let SearchInstruction::StopSearching(res) = check_for_draw(p.game_state, &thread.history) {
-        return res;
+    if let SearchInstruction::StopSearching(_) = check_for_draw(p.game_state, &thread.history) {
+        return thread.draw_score(p.game_state, p.color);
     }
 
-    //Step 5. Get standing pat when not in check
-    let stand_pat = eval_game_state(&p.game_state).final_eval * p.color;
-
-    //Step 6. Preliminary pruning
-    if let SearchInstruction::StopSearching(res) = adjust_standpat(&mut p, stand_pat) {
-        return res;
-    } else if let SearchInstruction::StopSearching(res) = delta_pruning(&p, stand_pat) {
-        return res;
-    }
-
-    //Step 7. TT Lookup
+    //Step 4. TT Lookup
+    let original_alpha = p.alpha;
     let mut tt_entry = None;
-    if p.depth_left == 0 {
-        if let SearchInstruction::StopSearching(res) = thread.itcs.cache().lookup(&p, &mut tt_entry)
+    let timer = ComponentTimer::start();
+    let lookup = thread.itcs.cache().lookup(&p, &mut tt_entry);
+    thread
+        .search_statistics
+        .add_time(SearchComponent::Cache, timer);
+    if let SearchInstruction::StopSearching(res) = lookup {
+        #[cfg(feature = "search-statistics")]
         {
-            #[cfg(feature = "search-statistics")]
-            {
-                thread.search_statistics.add_cache_hit_aj_replace_ns();
-            }
-            return res;
+            thread.search_statistics.add_cache_hit_aj_replace_ns();
         }
+        return res;
     }
     #[cfg(feature = "search-statistics")]
     {
@@ -61,13 +59,44 @@ pub fn q_search(mut p: CombinedSearchParameters, thread: &mut Thread) -> i16 {
             thread.search_statistics.add_cache_hit_ns();
         }
     }
-    let mut tt_move = if let Some(ce) = tt_entry {
-        Some(CacheEntry::u16_to_mv(ce.mv, p.game_state))
+
+    //Step 5. Get standing pat. Reuse the static evaluation of the TT entry if there is one
+    let incheck = p.game_state.in_check();
+    let static_evaluation = match tt_entry {
+        Some(ce) if ce.static_evaluation != INVALID_STATIC_EVALUATION => {
+            ce.static_evaluation * p.color
+        }
+        _ => {
+            let timer = ComponentTimer::start();
+            let eval = eval_game_state(p.game_state).final_eval * p.color;
+            thread
+                .search_statistics
+                .add_time(SearchComponent::Eval, timer);
+            eval
+        }
+    };
+    if -p.depth_left >= thread.search_params.max_qsearch_ply {
+        return static_evaluation;
+    }
+    //In check there is no standing pat, all evasions are searched instead and none means we are mated
+    let stand_pat = if incheck {
+        -MATE_SCORE + p.current_depth as i16
     } else {
-        None
+        static_evaluation - thread.search_params.stand_pat_margin
     };
-    //Only captures are valid tt moves (if not in check)
-    if tt_move.is_some() && !tt_move.as_ref().unwrap().is_capture() {
+
+    //Step 6. Preliminary pruning
+    if !incheck {
+        if let SearchInstruction::StopSearching(res) = adjust_standpat(&mut p, stand_pat) {
+            return res;
+        } else if let SearchInstruction::StopSearching(res) = delta_pruning(&p, stand_pat) {
+            return res;
+        }
+    }
+
+    let mut tt_move = tt_entry.and_then(|ce| thread.itcs.cache().tt_move(&ce, p.game_state));
+    //Only captures are valid tt moves if not in check
+    if !incheck && tt_move.is_some() && !tt_move.as_ref().unwrap().is_capture() {
         tt_move = None;
     }
 
@@ -82,25 +111,31 @@ pub fn q_search(mut p: CombinedSearchParameters, thread: &mut Thread) -> i16 {
     let mut has_pv = false;
     let mut move_orderer = MoveOrderer {
         stage: 0,
-        stages: &QUIESCENCE_STAGES,
-        gen_only_captures: true,
+        stages: if incheck {
+            &QUIESCENCE_EVASION_STAGES
+        } else {
+            &QUIESCENCE_STAGES
+        },
+        gen_only_captures: !incheck,
     };
 
     loop {
-        let mv = move_orderer.next(thread, &p, None, tt_move, false);
+        let mv = move_orderer.next(thread, &p, None, tt_move, incheck);
         if mv.is_none() {
             break;
         }
         let (capture_move, _) = mv.unwrap();
-        if !passes_delta_pruning(
-            capture_move,
-            p.game_state.get_phase().phase,
-            stand_pat,
-            p.alpha,
-        ) {
+        if !incheck
+            && !passes_delta_pruning(
+                capture_move,
+                p.game_state.get_phase().phase,
+                stand_pat,
+                p.alpha,
+            )
+        {
             continue;
         }
-        debug_assert!(capture_move.is_capture());
+        debug_assert!(incheck || capture_move.is_capture());
         let next_g = make_move(p.game_state, capture_move);
         //Step 8.4. Search move
         let score = -q_search(
@@ -144,15 +179,19 @@ pub fn q_search(mut p: CombinedSearchParameters, thread: &mut Thread) -> i16 {
             thread.search_statistics.add_q_beta_noncutoff();
         }
     }
-    //Step 10. Make TT entry
-    if has_pv && p.depth_left == 0 && !thread.self_stop {
+    //Step 10. Make TT entry. The bound has to be determined from the alpha before stand pat raised it
+    if has_pv && !thread.self_stop {
+        let timer = ComponentTimer::start();
         thread.itcs.cache().insert(
             &p,
             thread.pv_table[p.current_depth].pv[0].expect("Can't unwrap move for TT in qsearch!"),
             current_max_score,
-            p.alpha,
-            Some(stand_pat * p.color),
+            original_alpha,
+            Some(static_evaluation * p.color),
         );
+        thread
+            .search_statistics
+            .add_time(SearchComponent::Cache, timer);
     }
 
     //Step 11. Return
@@ -250,9 +289,8 @@ pub fn see(game_state: &GameState, mv: GameMove, exact: bool, gain: &mut Vec<i16
         attadef ^= from_set;
         occ ^= from_set;
         if from_set & may_xray != 0u64 {
-            //Recalculate rays
-            attadef |= recalculate_sliders(&game_state, color_to_move, mv.to as usize, occ)
-                & (!deleted_pieces);
+            //Recalculate rays. The slider revealed behind the moved piece may belong to either side
+            attadef |= recalculate_sliders(game_state, mv.to as usize, occ) & (!deleted_pieces);
         }
         color_to_move = 1 - color_to_move;
         let res = least_valuable_piece(attadef, color_to_move, &game_state);
@@ -272,15 +310,11 @@ pub fn see(game_state: &GameState, mv: GameMove, exact: bool, gain: &mut Vec<i16
 }
 
 #[inline(always)]
-pub fn recalculate_sliders(
-    game_state: &GameState,
-    color_to_move: usize,
-    square: usize,
-    occ: u64,
-) -> u64 {
-    //Bishops
-    movegen::bishop_attack(square, occ) & game_state.get_bishop_like_bb(color_to_move)
-        | movegen::rook_attack(square, occ) & game_state.get_rook_like_bb(color_to_move)
+pub fn recalculate_sliders(game_state: &GameState, square: usize, occ: u64) -> u64 {
+    movegen::bishop_attack(square, occ)
+        & (game_state.get_bishop_like_bb(WHITE) | game_state.get_bishop_like_bb(BLACK))
+        | movegen::rook_attack(square, occ)
+            & (game_state.get_rook_like_bb(WHITE) | game_state.get_rook_like_bb(BLACK))
 }
 
 #[inline(always)]
@@ -292,9 +326,9 @@ pub fn attacks_to(game_state: &GameState, square: usize, occ: u64) -> u64 {
         game_state.get_piece_bb(PieceType::Bishop) | game_state.get_piece_bb(PieceType::Queen);
     let rooks =
         game_state.get_piece_bb(PieceType::Rook) | game_state.get_piece_bb(PieceType::Queen);
-    attacks |= KNIGHT_ATTACKS[square] & knights
-        | movegen::bishop_attack(square, occ) & bishops
-        | movegen::rook_attack(square, occ) & rooks;
+    attacks |= (KNIGHT_ATTACKS[square] & knights)
+        | (movegen::bishop_attack(square, occ) & bishops)
+        | (movegen::rook_attack(square, occ) & rooks);
     attacks |= (movegen::w_pawn_west_targets(square_board)
         | movegen::w_pawn_east_targets(square_board))
         & game_state.get_piece(PieceType::Pawn, BLACK);
@@ -344,11 +378,17 @@ pub fn least_valuable_piece(
 
 #[cfg(test)]
 mod tests {
+    use super::q_search;
     use super::see;
     use super::GameMove;
     use super::GameMoveType;
     use super::GameState;
     use super::PieceType;
+    use super::{CombinedSearchParameters, WHITE};
+    use super::{MATED_IN_MAX, MATE_SCORE, MAX_QSEARCH_PLY};
+    use crate::search::cache::CacheEntry;
+    use crate::search::moveordering::{MoveOrderer, NORMAL_STAGES};
+    use crate::search::searcher::{test_thread, Thread};
 
     #[test]
     fn see_test() {
@@ -494,4 +534,184 @@ mod tests {
             100
         );
     }
+
+    #[test]
+    fn see_xray_test() {
+        let mut see_buffer = vec![0i16; 128];
+        let rook_takes_e5 = GameMove {
+            from: 12,
+            to: 36,
+            move_type: GameMoveType::Capture(PieceType::Pawn),
+            piece_type: PieceType::Rook,
+        };
+        //The black rook behind the capturing rook joins the exchange
+        assert_eq!(
+            see(
+                &GameState::from_fen("4k3/8/8/4p3/8/8/4R2K/4r3 w - -"),
+                rook_takes_e5,
+                true,
+                &mut see_buffer,
+            ),
+            -550
+        );
+        //Doubled rooks on both sides
+        assert_eq!(
+            see(
+                &GameState::from_fen("4r1k1/4r3/8/4p3/8/8/4R3/4R1K1 w - -"),
+                rook_takes_e5,
+                true,
+                &mut see_buffer,
+            ),
+            -550
+        );
+        assert_eq!(
+            see(
+                &GameState::from_fen("4r1k1/8/8/4p3/8/8/4R3/4R1K1 w - -"),
+                rook_takes_e5,
+                true,
+                &mut see_buffer,
+            ),
+            100
+        );
+        let bishop_takes_e5 = GameMove {
+            from: 27,
+            to: 36,
+            move_type: GameMoveType::Capture(PieceType::Pawn),
+            piece_type: PieceType::Bishop,
+        };
+        //Queen behind bishop against a single defender
+        assert_eq!(
+            see(
+                &GameState::from_fen("6k1/8/5b2/4p3/3B4/2Q5/8/6K1 w - -"),
+                bishop_takes_e5,
+                true,
+                &mut see_buffer,
+            ),
+            100
+        );
+        //The black queen behind the defending bishop wins the exchange for black
+        assert_eq!(
+            see(
+                &GameState::from_fen("6k1/6q1/5b2/4p3/3B4/2Q5/8/6K1 w - -"),
+                bishop_takes_e5,
+                true,
+                &mut see_buffer,
+            ),
+            -300
+        );
+    }
+
+    #[test]
+    fn see_xray_ordering_test() {
+        let mut thread = test_thread();
+        let game_state = GameState::from_fen("4k3/8/8/4p3/8/8/4R2K/4r3 w - -");
+        let p = CombinedSearchParameters::from(-16000, 16000, 3, &game_state, 1, 0);
+        let mut move_orderer = MoveOrderer {
+            stage: 0,
+            stages: &NORMAL_STAGES,
+            gen_only_captures: false,
+        };
+        let mut moves = Vec::new();
+        while let Some((mv, _)) = move_orderer.next(&mut thread, &p, None, None, true) {
+            moves.push(mv);
+        }
+        //Rxe5 loses the rook to the x-ray, so it is tried after all quiet moves
+        assert!(moves.len() > 1);
+        assert_eq!(moves.last().unwrap().to, 36);
+    }
+
+    #[test]
+    fn q_search_tt_test() {
+        let mut thread = test_thread();
+        let game_state =
+            GameState::from_fen("r1b1kbnr/pppp1ppp/2n5/4p3/3PP2q/5N2/PPP2PPP/RNBQKB1R w KQkq -");
+        let color = if game_state.get_color_to_move() == WHITE {
+            1
+        } else {
+            -1
+        };
+        let search = |thread: &mut Thread| {
+            thread.search_statistics.q_nodes_searched = 0;
+            let score = q_search(
+                CombinedSearchParameters::from(-16000, 16000, 0, &game_state, color, 0),
+                thread,
+            );
+            (score, thread.search_statistics.q_nodes_searched)
+        };
+
+        let (cold_score, cold_nodes) = search(&mut thread);
+        let best_move = thread.pv_table[0].pv[0];
+        assert!(best_move.is_some());
+        let (warm_score, warm_nodes) = search(&mut thread);
+        assert_eq!(cold_score, warm_score);
+        assert!(warm_nodes < cold_nodes);
+        let tt_move = thread
+            .itcs
+            .cache()
+            .get(game_state.get_hash())
+            .probe(game_state.get_hash())
+            .map(|ce| CacheEntry::u16_to_mv(ce.mv, &game_state));
+        assert!(tt_move == best_move);
+    }
+
+    #[test]
+    fn q_search_ply_cap_test() {
+        //Every piece can be captured and recaptured, so the capture chains run long
+        let game_state =
+            GameState::from_fen("r1bqk2r/ppp2ppp/2n2n2/2bpp3/2BPP3/2N2N2/PPP2PPP/R1BQK2R w KQkq -");
+        let search = |max_qsearch_ply: i16| {
+            let mut thread = test_thread();
+            thread.search_params.max_qsearch_ply = max_qsearch_ply;
+            let score = q_search(
+                CombinedSearchParameters::from(-16000, 16000, 0, &game_state, 1, 0),
+                &mut thread,
+            );
+            (
+                score,
+                thread.search_statistics.seldepth,
+                thread.search_statistics.q_nodes_searched,
+            )
+        };
+        let (score, seldepth, nodes) = search(MAX_QSEARCH_PLY);
+        assert!(seldepth > 4 && (seldepth as i16) < MAX_QSEARCH_PLY);
+        //A cap beyond the longest chain doesn't change anything
+        assert_eq!(search(seldepth as i16 + 1), (score, seldepth, nodes));
+        for cap in 1..seldepth {
+            let (_, capped_seldepth, capped_nodes) = search(cap as i16);
+            assert!(capped_seldepth <= cap);
+            assert!(capped_nodes < nodes);
+        }
+    }
+
+    #[test]
+    fn q_search_in_check_test() {
+        let search = |fen: &str, stand_pat_margin: i16| {
+            let game_state = GameState::from_fen(fen);
+            let mut thread = test_thread();
+            thread.search_params.stand_pat_margin = stand_pat_margin;
+            let score = q_search(
+                CombinedSearchParameters::from(-16000, 16000, 0, &game_state, 1, 0),
+                &mut thread,
+            );
+            (score, thread.pv_table[0].pv[0])
+        };
+        //Only a quiet king move escapes the check of the rook
+        let (score, best_move) = search("k3r3/8/8/8/8/8/8/4K3 w - - 0 1", 0);
+        assert!(score > MATED_IN_MAX);
+        assert_eq!(best_move.unwrap().piece_type, PieceType::King);
+        assert!(!best_move.unwrap().is_capture());
+        //Back rank mate, no evasion exists
+        let (score, best_move) = search("6k1/5ppp/8/8/8/8/5PPP/3r2K1 w - - 0 1", 0);
+        assert_eq!(score, -MATE_SCORE);
+        assert!(best_move.is_none());
+        //Without captures the stand pat is returned, lowered by the margin
+        let quiet = "4k3/4p3/8/8/8/8/4P3/4K3 w - - 0 1";
+        let (score, _) = search(quiet, 0);
+        assert_eq!(search(quiet, 50).0, score - 50);
+        //The margin does not apply in check
+        assert_eq!(
+            search("6k1/5ppp/8/8/8/8/5PPP/3r2K1 w - - 0 1", 50).0,
+            -MATE_SCORE
+        );
+    }
 }
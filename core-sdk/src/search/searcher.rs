@@ -1,19 +1,31 @@
 use super::alphabeta::principal_variation_search;
 use super::cache::Cache;
 use super::history::History;
-use super::statistics::SearchStatistics;
-use super::timecontrol::TimeControl;
+use super::history_tables::HistoryTables;
+use super::info_sink::{InfoSink, SearchInfo};
+#[cfg(feature = "search-timing")]
+use super::statistics::SEARCH_COMPONENT_NAMES;
+use super::statistics::{SearchStatistics, BETA_CUTOFF_BUCKETS, SEARCH_COMPONENTS};
+use super::timecontrol::{
+    RootStability, TimeControl, DEFAULT_MIN_THINK_TIME, VERIFICATION_MARGIN, VERIFICATION_TIME,
+};
 use super::GameMove;
 use super::PrincipalVariation;
 use super::MATED_IN_MAX;
 use super::MAX_SEARCH_DEPTH;
-use crate::board_representation::game_state::{GameState, WHITE};
+use crate::board_representation::game_state::{GameState, BLACK, WHITE};
 //use crate::logging::log;
 use crate::move_generation::makemove::make_move;
 use crate::move_generation::movegen::{generate_moves, MoveList};
 use crate::search::reserved_memory::ReservedMoveList;
-use crate::search::{CombinedSearchParameters, ScoredPrincipalVariation, MATE_SCORE};
+use crate::search::strength::{self, RootMoveScores, DEFAULT_EVAL_RANDOMNESS};
+use crate::search::{
+    effective_contempt, is_dead_draw, leaf_score, CombinedSearchParameters, GameResult, ScoreBound,
+    ScoredPrincipalVariation, SearchParams, DEFAULT_CONTEMPT, MATE_SCORE,
+};
 use crate::UCIOptions;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use std::cell::UnsafeCell;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicU64;
@@ -32,6 +44,13 @@ pub const DEFAULT_THREADS: usize = 1;
 pub const MAX_THREADS: usize = 65536;
 pub const MIN_THREADS: usize = 1;
 
+//Initial half width of the aspiration window and the factor it grows by after every re-search
+pub const ASPIRATION_DELTA: i16 = 14;
+pub const ASPIRATION_WIDENING: f64 = 1.5;
+
+//Depth of the verification search when there is only one legal move, it just gets a score to report
+pub const ONLY_MOVE_DEPTH: i16 = 4;
+
 #[derive(Copy, Clone)]
 pub enum DepthInformation {
     FullySearched,
@@ -41,6 +60,7 @@ pub enum DepthInformation {
 pub struct InterThreadCommunicationSystem {
     pub uci_options: UnsafeCell<UCIOptions>,
     pub best_pv: Mutex<ScoredPrincipalVariation>,
+    pub completed_pv: Mutex<ScoredPrincipalVariation>, //Best pv of the deepest fully searched iteration
     pub stable_pv: AtomicBool,
     pub depth_info: Mutex<[DepthInformation; MAX_SEARCH_DEPTH]>,
     pub start_time: RwLock<Instant>, //Only used for reporting
@@ -50,8 +70,19 @@ pub struct InterThreadCommunicationSystem {
     pub cache_status: AtomicUsize,
     pub last_cache_status: Mutex<Option<Instant>>,
     pub timeout_flag: RwLock<bool>,
+    pub node_limit: AtomicU64, //Nodes of all threads after which the search stops, 0 for no limit
+    pub pondering: AtomicBool, //The time control only applies after ponderhit
     pub saved_time: AtomicU64,
+    pub searches_since_newgame: AtomicUsize, //The first ones are the first moves out of book
     pub tx: RwLock<Vec<Sender<ThreadInstruction>>>,
+    pub root_move_scores: Mutex<RootMoveScores>, //Only used when strength is limited
+    pub strength_rng: Mutex<StdRng>,
+    pub info_sink: RwLock<Option<Arc<dyn InfoSink>>>,
+    pub last_reported_depth: AtomicUsize,
+    pub last_info_line: Mutex<Option<(usize, String)>>, //Depth, score and pv of the last info line
+    pub root_state: Mutex<GameState>,                   //Only used for reporting
+    pub beta_cutoffs: [AtomicU64; BETA_CUTOFF_BUCKETS], //Only used for reporting
+    pub component_times: [AtomicU64; SEARCH_COMPONENTS], //Only used for reporting
     rx_f: Receiver<()>,
     tx_f: Sender<()>,
 }
@@ -61,6 +92,7 @@ impl Default for InterThreadCommunicationSystem {
         InterThreadCommunicationSystem {
             uci_options: UnsafeCell::new(UCIOptions::default()),
             best_pv: Mutex::new(ScoredPrincipalVariation::default()),
+            completed_pv: Mutex::new(ScoredPrincipalVariation::default()),
             stable_pv: AtomicBool::new(false),
             depth_info: Mutex::new([DepthInformation::UnSearched; MAX_SEARCH_DEPTH]),
             nodes_searched: UnsafeCell::new(Vec::new()),
@@ -70,8 +102,19 @@ impl Default for InterThreadCommunicationSystem {
             cache_status: AtomicUsize::new(0),
             cache: UnsafeCell::new(Cache::with_size_threaded(0, 1)),
             timeout_flag: RwLock::new(false),
+            node_limit: AtomicU64::new(0),
+            pondering: AtomicBool::new(false),
             saved_time: AtomicU64::new(0u64),
+            searches_since_newgame: AtomicUsize::new(0),
             tx: RwLock::new(Vec::new()),
+            root_move_scores: Mutex::new(RootMoveScores::default()),
+            strength_rng: Mutex::new(StdRng::from_entropy()),
+            info_sink: RwLock::new(None),
+            last_reported_depth: AtomicUsize::new(0),
+            last_info_line: Mutex::new(None),
+            root_state: Mutex::new(GameState::standard()),
+            beta_cutoffs: Default::default(),
+            component_times: Default::default(),
             rx_f,
             tx_f,
         }
@@ -88,6 +131,10 @@ impl InterThreadCommunicationSystem {
         unsafe { self.nodes_searched.get().as_mut().unwrap() }
     }
 
+    pub fn seed_strength_rng(&self, seed: u64) {
+        *self.strength_rng.lock().unwrap() = StdRng::seed_from_u64(seed);
+    }
+
     pub fn update_thread_count(
         itcs: &Arc<InterThreadCommunicationSystem>,
         new_thread_count: usize,
@@ -126,6 +173,16 @@ impl InterThreadCommunicationSystem {
         dur.as_millis() as u64
     }
 
+    //The time boost in percent for the next search, counting it as one more move out of book
+    pub fn time_boost(&self) -> u64 {
+        let searches = self.searches_since_newgame.fetch_add(1, Ordering::Relaxed);
+        if searches < self.uci_options().book_exit_moves {
+            self.uci_options().book_exit_boost
+        } else {
+            0
+        }
+    }
+
     pub fn update(&self, thread_id: usize, nodes_searched: u64, seldepth: usize) {
         let curr_seldepth = self.seldepth.load(Ordering::Relaxed);
         self.seldepth
@@ -140,7 +197,8 @@ impl InterThreadCommunicationSystem {
             .sum()
     }
 
-    pub fn register_pv(&self, scored_pv: &ScoredPrincipalVariation, no_fail: bool) {
+    pub fn register_pv(&self, scored_pv: &ScoredPrincipalVariation, bound: ScoreBound) {
+        let no_fail = bound != ScoreBound::UpperBound;
         let mut curr_best = self.best_pv.lock().unwrap();
         self.stable_pv.store(false, Ordering::Relaxed);
         //Update pv stability
@@ -172,41 +230,300 @@ impl InterThreadCommunicationSystem {
             } else {
                 self.cache_status.load(Ordering::Relaxed)
             };
-            let score_string = if cfg!(feature = "avoid-adj") {
-                let score = scored_pv.score.min(200).max(-200);
-                let score = if score.abs() < 10 { 25 } else { score };
-                format!("score cp {}", score)
-            } else if scored_pv.score.abs() > MATE_SCORE - 200 {
-                let dtm = if scored_pv.score > 0 {
-                    (MATE_SCORE - scored_pv.score) / 2 + 1
-                } else {
-                    (-MATE_SCORE - scored_pv.score) / 2
-                };
-                format!("score mate {}", dtm)
-            } else {
-                format!("score cp {}", scored_pv.score)
+            *self.last_info_line.lock().unwrap() = Some(self.info_line_key(scored_pv, bound));
+            if self.uci_options().quiet {
+                return;
+            }
+            self.print_info_line(scored_pv, bound, searched_nodes, elapsed_time, fill_status);
+        }
+    }
+
+    fn print_info_line(
+        &self,
+        scored_pv: &ScoredPrincipalVariation,
+        bound: ScoreBound,
+        searched_nodes: u64,
+        elapsed_time: u64,
+        fill_status: usize,
+    ) {
+        println!(
+            "info depth {} seldepth {} nodes {} nps {} hashfull {:.0} time {} {} pv {}",
+            scored_pv.depth,
+            self.seldepth.load(Ordering::Relaxed),
+            searched_nodes,
+            (searched_nodes as f64 / (elapsed_time.max(1) as f64 / 1000.0)) as u64,
+            fill_status,
+            elapsed_time,
+            self.score_string(scored_pv.score, bound),
+            self.pv_string(&scored_pv.pv)
+        );
+    }
+
+    //UCI scores are from the point of view of the side to move, unless white relative scores are asked for
+    pub fn score_string(&self, score: i16, bound: ScoreBound) -> String {
+        if self.uci_options().white_relative_score
+            && self.root_state.lock().unwrap().get_color_to_move() == BLACK
+        {
+            let bound = match bound {
+                ScoreBound::LowerBound => ScoreBound::UpperBound,
+                ScoreBound::UpperBound => ScoreBound::LowerBound,
+                ScoreBound::Exact => ScoreBound::Exact,
             };
+            uci_score_string(-score, bound)
+        } else {
+            uci_score_string(score, bound)
+        }
+    }
+
+    //GUIs expect long algebraic notation, SAN is only meant for reading the analysis in a console
+    pub fn pv_string(&self, pv: &PrincipalVariation) -> String {
+        if self.uci_options().san_pv {
+            pv.to_san(&self.root_state.lock().unwrap())
+        } else {
+            pv.to_string()
+        }
+    }
+
+    fn info_line_key(
+        &self,
+        scored_pv: &ScoredPrincipalVariation,
+        bound: ScoreBound,
+    ) -> (usize, String) {
+        (
+            scored_pv.depth,
+            format!(
+                "{} pv {}",
+                self.score_string(scored_pv.score, bound),
+                self.pv_string(&scored_pv.pv)
+            ),
+        )
+    }
+
+    //The last info line can be a bound or belong to an aborted iteration. Then the line of the pv we
+    //actually play has to be repeated, so that it is the last one before bestmove
+    pub fn final_info_line_missing(&self) -> bool {
+        let played_pv = self.played_pv();
+        played_pv.pv.pv[0].is_some()
+            && self.last_info_line.lock().unwrap().as_ref()
+                != Some(&self.info_line_key(&played_pv, ScoreBound::Exact))
+    }
+
+    pub fn report_final_pv(&self) {
+        if !self.final_info_line_missing() {
+            return;
+        }
+        let played_pv = self.played_pv();
+        *self.last_info_line.lock().unwrap() =
+            Some(self.info_line_key(&played_pv, ScoreBound::Exact));
+        if self.uci_options().quiet {
+            return;
+        }
+        self.print_info_line(
+            &played_pv,
+            ScoreBound::Exact,
+            self.get_nodes_sum(),
+            self.get_time_elapsed(),
+            self.cache().fill_status(),
+        );
+    }
+
+    pub fn add_beta_cutoffs(&self, histogram: &[u64; BETA_CUTOFF_BUCKETS]) {
+        for (total, cutoffs) in self.beta_cutoffs.iter().zip(histogram.iter()) {
+            total.fetch_add(*cutoffs, Ordering::Relaxed);
+        }
+    }
+
+    //Beta cutoffs of all threads in the last search, bucketed by the index of the cutting move
+    pub fn beta_cutoff_histogram(&self) -> [u64; BETA_CUTOFF_BUCKETS] {
+        let mut histogram = [0u64; BETA_CUTOFF_BUCKETS];
+        for (bucket, total) in histogram.iter_mut().zip(self.beta_cutoffs.iter()) {
+            *bucket = total.load(Ordering::Relaxed);
+        }
+        histogram
+    }
+
+    pub fn report_beta_cutoffs(&self) {
+        if self.uci_options().quiet {
+            return;
+        }
+        let histogram = self.beta_cutoff_histogram();
+        let total = histogram.iter().sum::<u64>().max(1) as f64;
+        let share = |cutoffs: u64| cutoffs as f64 / total * 100.;
+        println!(
+            "info string beta cutoffs 1st {:.1}% 2nd {:.1}% 3rd {:.1}% later {:.1}%",
+            share(histogram[0]),
+            share(histogram[1]),
+            share(histogram[2]),
+            share(histogram[3])
+        );
+    }
+
+    pub fn add_component_times(&self, times: &[u64; SEARCH_COMPONENTS]) {
+        for (total, time) in self.component_times.iter().zip(times.iter()) {
+            total.fetch_add(*time, Ordering::Relaxed);
+        }
+    }
+
+    //Time of all threads in the last search spent in the parts of the search, in nanoseconds
+    pub fn component_times(&self) -> [u64; SEARCH_COMPONENTS] {
+        let mut times = [0u64; SEARCH_COMPONENTS];
+        for (time, total) in times.iter_mut().zip(self.component_times.iter()) {
+            *time = total.load(Ordering::Relaxed);
+        }
+        times
+    }
+
+    #[cfg(feature = "search-timing")]
+    pub fn report_component_times(&self) {
+        if self.uci_options().quiet {
+            return;
+        }
+        let total = (self.get_time_elapsed() * self.uci_options().threads as u64).max(1) as f64;
+        let components: Vec<String> = self
+            .component_times()
+            .iter()
+            .zip(SEARCH_COMPONENT_NAMES.iter())
+            .map(|(time, name)| {
+                let ms = *time as f64 / 1_000_000.;
+                format!("{} {:.0}ms ({:.1}%)", name, ms, ms / total * 100.)
+            })
+            .collect();
+        println!("info string time {}", components.join(" "));
+    }
+
+    #[cfg(debug_assertions)]
+    pub fn report_hash_collisions(&self) {
+        if !self.uci_options().quiet {
             println!(
-                "info depth {} seldepth {} nodes {} nps {} hashfull {:.0} time {} {} pv {}",
-                scored_pv.depth,
-                self.seldepth.load(Ordering::Relaxed),
-                searched_nodes,
-                (searched_nodes as f64 / (elapsed_time.max(1) as f64 / 1000.0)) as u64,
-                fill_status,
-                self.get_time_elapsed(),
-                score_string,
-                scored_pv.pv
+                "info string hash collisions {}",
+                self.cache().collisions.load(Ordering::Relaxed)
             );
         }
     }
 
+    //The opponent played the expected move. The search goes on as it is and the time control applies from
+    //now on, counting the time since go ponder as spent, so the reported time and nps stay consistent as well
+    pub fn ponderhit(&self) {
+        self.pondering.store(false, Ordering::SeqCst);
+    }
+
+    //A finished ponder search must not send its bestmove before ponderhit or stop
+    pub fn wait_for_ponderhit(&self) {
+        while self.pondering.load(Ordering::SeqCst) && !*self.timeout_flag.read().unwrap() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+
+    pub fn set_info_sink(&self, info_sink: Option<Arc<dyn InfoSink>>) {
+        *self.info_sink.write().unwrap() = info_sink;
+    }
+
+    //Only the first thread to complete a depth reports it
+    pub fn report_iteration(&self, scored_pv: &ScoredPrincipalVariation) {
+        let info_sink = self.info_sink.read().unwrap();
+        if info_sink.is_none()
+            || self
+                .last_reported_depth
+                .fetch_max(scored_pv.depth, Ordering::Relaxed)
+                >= scored_pv.depth
+        {
+            return;
+        }
+        let nodes = self.get_nodes_sum();
+        let time_elapsed = self.get_time_elapsed();
+        info_sink.as_ref().unwrap().on_iteration(&SearchInfo {
+            depth: scored_pv.depth,
+            seldepth: self.seldepth.load(Ordering::Relaxed),
+            score: scored_pv.score,
+            nodes,
+            nps: (nodes as f64 / (time_elapsed.max(1) as f64 / 1000.0)) as u64,
+            time_elapsed,
+            hashfull: self.cache().fill_status(),
+            pv: scored_pv.pv.pv.iter().map_while(|mv| *mv).collect(),
+        });
+    }
+
+    pub fn register_root_move_scores(&self, depth: usize, scores: &[(GameMove, i16)]) {
+        let mut root_move_scores = self.root_move_scores.lock().unwrap();
+        if depth > root_move_scores.depth {
+            root_move_scores.depth = depth;
+            root_move_scores.scores = scores.to_vec();
+        }
+    }
+
+    pub fn register_completed_pv(&self, scored_pv: &ScoredPrincipalVariation) {
+        let mut completed_pv = self.completed_pv.lock().unwrap();
+        if scored_pv.depth > completed_pv.depth && scored_pv.pv.pv[0].is_some() {
+            *completed_pv = scored_pv.clone();
+        }
+    }
+
+    //A best move which failed its verification is replaced by the one of the previous iteration
+    pub fn revert_completed_pv(&self, scored_pv: &ScoredPrincipalVariation) {
+        *self.completed_pv.lock().unwrap() = scored_pv.clone();
+    }
+
+    //The pv we actually play. Moves of an aborted iteration might not have been compared against all
+    //other root moves yet, so they only count if not even the first iteration got completed
+    pub fn played_pv(&self) -> ScoredPrincipalVariation {
+        let completed_pv = self.completed_pv.lock().unwrap();
+        if completed_pv.pv.pv[0].is_some() {
+            completed_pv.clone()
+        } else {
+            self.best_pv.lock().unwrap().clone()
+        }
+    }
+
+    //Makes sure no corrupt line gets reported as the final result of the search, returns false if one was cut
+    pub fn validate_played_pv(&self, root: &GameState) -> bool {
+        let mut valid = true;
+        for scored_pv in [&self.completed_pv, &self.best_pv].iter() {
+            let illegal = scored_pv.lock().unwrap().pv.truncate_illegal(root);
+            if let Some((ply, state)) = illegal {
+                if !self.uci_options().quiet {
+                    println!(
+                        "info string illegal pv move at ply {} in position {}",
+                        ply,
+                        state.to_fen()
+                    );
+                }
+                valid = false;
+            }
+        }
+        valid
+    }
+
+    pub fn bestmove_string(&self) -> String {
+        let best_pv = self.played_pv();
+        let best_move = best_pv.pv.pv[0].expect("Could not unwrap pv for bestmove!");
+        let played_move = if self.uci_options().limit_strength {
+            strength::pick_root_move(
+                best_move,
+                &self.root_move_scores.lock().unwrap().scores,
+                self.uci_options().elo,
+                &mut self.strength_rng.lock().unwrap(),
+            )
+        } else {
+            best_move
+        };
+        //The predicted reply is only valid if we actually play the pv move
+        let ponder_move = if self.uci_options().ponder && played_move == best_move {
+            best_pv.pv.pv.get(1).cloned().flatten()
+        } else {
+            None
+        };
+        if let Some(ponder_move) = ponder_move {
+            format!("bestmove {:?} ponder {:?}", played_move, ponder_move)
+        } else {
+            format!("bestmove {:?}", played_move)
+        }
+    }
+
     pub fn report_bestmove(&self) {
-        println!(
-            "bestmove {:?}",
-            self.best_pv.lock().unwrap().pv.pv[0]
-                .as_ref()
-                .expect("Could not unwrap pv for bestmove!")
-        );
+        if self.uci_options().quiet {
+            return;
+        }
+        println!("{}", self.bestmove_string());
     }
 
     pub fn get_next_depth(&self, mut from_depth: usize) -> (usize, bool) {
@@ -248,41 +565,88 @@ impl InterThreadCommunicationSystem {
 unsafe impl std::marker::Sync for InterThreadCommunicationSystem {}
 pub enum ThreadInstruction {
     Exit,
-    StartSearch(i16, GameState, TimeControl, History, u64),
+    StartSearch(i16, GameState, TimeControl, History, u64, u64),
+}
+
+//Only scores outside of the aspiration window are reported as bounds, a resolved pv score is exact
+pub fn uci_score_string(score: i16, bound: ScoreBound) -> String {
+    let score_string = if cfg!(feature = "avoid-adj") {
+        let score = score.clamp(-200, 200);
+        let score = if score.abs() < 10 { 25 } else { score };
+        format!("score cp {}", score)
+    } else if score.abs() > MATE_SCORE - 200 {
+        let dtm = if score > 0 {
+            (MATE_SCORE - score) / 2 + 1
+        } else {
+            (-MATE_SCORE - score) / 2
+        };
+        format!("score mate {}", dtm)
+    } else {
+        format!("score cp {}", score)
+    };
+    match bound {
+        ScoreBound::Exact => score_string,
+        ScoreBound::LowerBound => format!("{} lowerbound", score_string),
+        ScoreBound::UpperBound => format!("{} upperbound", score_string),
+    }
 }
 
 pub struct Thread {
     pub id: usize,
     pub itcs: Arc<InterThreadCommunicationSystem>,
     pub root_plies_played: usize,
+    pub root_depth: i16,
+    pub contempt: i16,
+    pub eval_randomness: i16,
+    pub eval_random_seed: u64,
+    pub search_params: SearchParams,
     pub history: History,
     pub movelist: ReservedMoveList,
     pub pv_table: Vec<PrincipalVariation>,
     pub killer_moves: [[Option<GameMove>; 2]; MAX_SEARCH_DEPTH],
     pub quiets_tried: [[Option<GameMove>; 128]; MAX_SEARCH_DEPTH],
-    pub hh_score: [[[usize; 64]; 64]; 2],
-    pub bf_score: [[[usize; 64]; 64]; 2],
-    pub history_score: [[[isize; 64]; 64]; 2],
+    pub captures_tried: [[Option<GameMove>; 128]; MAX_SEARCH_DEPTH],
+    pub history_tables: Box<HistoryTables>,
     pub see_buffer: Vec<i16>,
     pub search_statistics: SearchStatistics,
-    pub tc: TimeControl, //Only thread 0 takes care of Timecontrol though
+    pub tc: TimeControl,     //Only thread 0 takes care of Timecontrol though
+    pub min_think_time: u64, //Dropped once a mate is found
+    pub time_boost: u64,
     pub time_saved: u64,
     pub self_stop: bool, //This is set when timeout_stop is set(timeout_stop isn't always polled)
     pub current_pv: ScoredPrincipalVariation,
     pub pv_applicable: Vec<u64>, //Hashes of gamestates the pv plays along
     pub main_thread_in_depth: bool,
+    pub root_move_scores: Vec<(GameMove, i16)>,
+    pub root_stability: RootStability, //Only thread 0 takes care of Timecontrol though
+    pub verification_deadline: Option<u64>, //Elapsed ms at which a verification search is stopped
+    pub next_node_limit_check: u64,
     rx: Receiver<ThreadInstruction>,
     tx: Sender<()>,
 }
 
 impl Thread {
+    //The side to move at the root wants to avoid draws, converted to white's perspective for leaf_score
+    pub fn white_contempt(&self, game_state: &GameState) -> i16 {
+        let contempt = effective_contempt(self.contempt, game_state.get_phase().phase);
+        if self.root_plies_played & 1 == WHITE {
+            contempt
+        } else {
+            -contempt
+        }
+    }
+
+    pub fn draw_score(&self, game_state: &GameState, color: i16) -> i16 {
+        leaf_score(GameResult::Draw, color, 0, self.white_contempt(game_state))
+    }
+
     pub fn replace_current_pv(
         &mut self,
         root: &GameState,
         scored_pv: ScoredPrincipalVariation,
-        no_fail: bool,
+        bound: ScoreBound,
     ) {
-        self.itcs.register_pv(&scored_pv, no_fail);
+        self.itcs.register_pv(&scored_pv, bound);
         self.current_pv = scored_pv;
         self.pv_applicable.clear();
         self.pv_applicable.push(root.get_hash());
@@ -301,7 +665,7 @@ impl Thread {
             }
         }
     }
-    fn new(
+    pub(crate) fn new(
         id: usize,
         itcs: Arc<InterThreadCommunicationSystem>,
         rx: Receiver<ThreadInstruction>,
@@ -315,22 +679,32 @@ impl Thread {
             id,
             itcs,
             root_plies_played: 0,
+            root_depth: 0,
+            contempt: DEFAULT_CONTEMPT,
+            eval_randomness: DEFAULT_EVAL_RANDOMNESS,
+            eval_random_seed: 0,
+            search_params: SearchParams::default(),
             history: History::default(),
             movelist: ReservedMoveList::default(),
             pv_table,
             killer_moves: [[None; 2]; MAX_SEARCH_DEPTH],
             quiets_tried: [[None; 128]; MAX_SEARCH_DEPTH],
-            hh_score: [[[0; 64]; 64]; 2],
-            bf_score: [[[1; 64]; 64]; 2],
-            history_score: [[[0; 64]; 64]; 2],
+            captures_tried: [[None; 128]; MAX_SEARCH_DEPTH],
+            history_tables: Box::new(HistoryTables::default()),
             see_buffer: vec![0i16; MAX_SEARCH_DEPTH],
             search_statistics: SearchStatistics::default(),
             tc: TimeControl::MoveTime(0u64),
+            min_think_time: DEFAULT_MIN_THINK_TIME,
+            time_boost: 0,
             time_saved: 0u64,
             self_stop: false,
             current_pv: ScoredPrincipalVariation::default(),
             pv_applicable: Vec::with_capacity(MAX_SEARCH_DEPTH),
             main_thread_in_depth: false,
+            root_move_scores: Vec::with_capacity(MAX_SEARCH_DEPTH),
+            root_stability: RootStability::default(),
+            verification_deadline: None,
+            next_node_limit_check: 0,
             rx,
             tx,
         }
@@ -344,21 +718,33 @@ impl Thread {
                     self.tx.send(()).expect("Error sending exit flag!");
                     break;
                 }
-                ThreadInstruction::StartSearch(max_depth, state, tc, history, time_saved) => {
+                ThreadInstruction::StartSearch(
+                    max_depth,
+                    state,
+                    tc,
+                    history,
+                    time_saved,
+                    time_boost,
+                ) => {
                     self.root_plies_played =
                         (state.get_full_moves() - 1) * 2 + state.get_color_to_move();
                     self.history = history;
+                    self.contempt = self.itcs.uci_options().contempt;
+                    self.eval_randomness = self.itcs.uci_options().eval_randomness;
+                    self.eval_random_seed = self.itcs.uci_options().eval_random_seed;
+                    self.search_params = self.itcs.uci_options().search_params;
                     self.time_saved = time_saved;
+                    self.time_boost = time_boost;
                     self.pv_applicable.clear();
                     self.current_pv = ScoredPrincipalVariation::default();
                     self.main_thread_in_depth = false;
-                    self.killer_moves = [[None; 2]; MAX_SEARCH_DEPTH];
-                    self.hh_score = [[[0; 64]; 64]; 2];
-                    self.bf_score = [[[1; 64]; 64]; 2];
-                    self.history_score = [[[0; 64]; 64]; 2];
+                    self.clear_heuristics();
                     self.search_statistics = SearchStatistics::default();
+                    self.next_node_limit_check = 0;
                     self.tc = tc;
+                    self.min_think_time = self.itcs.uci_options().min_think_time;
                     self.self_stop = false;
+                    self.root_stability = RootStability::default();
                     self.search(max_depth, state);
                     self.tx.send(()).expect("Error sending finish flag!");
                 }
@@ -366,6 +752,28 @@ impl Thread {
         }
     }
 
+    //Learned move ordering never carries over from one search to the next, unlike the shared cache
+    pub fn clear_heuristics(&mut self) {
+        self.killer_moves = [[None; 2]; MAX_SEARCH_DEPTH];
+        self.history_tables.clear();
+    }
+
+    fn report_branching_factor(&self, depth: usize) {
+        let statistics = &self.search_statistics;
+        let nodes = statistics.depth_nodes.last().map_or(0, |&(_, nodes)| nodes);
+        let iteration = statistics.iteration_nodes().last().copied().unwrap_or(0);
+        match statistics.branching_factors().last() {
+            Some(branching_factor) => println!(
+                "info string depth {} nodes {} iteration {} branching factor {:.2}",
+                depth, nodes, iteration, branching_factor
+            ),
+            None => println!(
+                "info string depth {} nodes {} iteration {}",
+                depth, nodes, iteration
+            ),
+        }
+    }
+
     fn search(&mut self, max_depth: i16, state: GameState) {
         if self.itcs.uci_options().debug_print {
             println!(
@@ -375,6 +783,7 @@ impl Thread {
         }
         let mut curr_depth = 0;
         let mut previous_score: Option<i16> = None;
+        let mut previous_pv = ScoredPrincipalVariation::default();
         loop {
             let temp = self.itcs.get_next_depth(curr_depth);
             curr_depth = temp.0;
@@ -389,11 +798,12 @@ impl Thread {
                     self.id, curr_depth
                 );
             }
+            self.root_depth = curr_depth as i16;
             let mut delta = if let Some(ps) = previous_score {
                 ps.abs() / 50
             } else {
                 0
-            } + 14;
+            } + self.search_params.aspiration_delta;
             let mut alpha = if curr_depth == 1 {
                 -16000
             } else {
@@ -404,6 +814,8 @@ impl Thread {
             } else {
                 self.current_pv.score + delta
             };
+            let mut failed_high = false;
+            let mut researches = 0;
             loop {
                 principal_variation_search(
                     CombinedSearchParameters::from(
@@ -427,7 +839,9 @@ impl Thread {
                     break;
                 }
 
+                researches += 1;
                 if self.current_pv.score <= alpha {
+                    self.search_statistics.add_aspiration_fail_low();
                     if alpha < -10000 || self.current_pv.score < MATED_IN_MAX {
                         alpha = -16000;
                         beta = 16000;
@@ -437,6 +851,8 @@ impl Thread {
                     }
                 }
                 if self.current_pv.score >= beta {
+                    failed_high = true;
+                    self.search_statistics.add_aspiration_fail_high();
                     if beta > 10000 || self.current_pv.score > -MATED_IN_MAX {
                         beta = 16000;
                         alpha = -16000;
@@ -444,12 +860,76 @@ impl Thread {
                         beta += delta;
                     }
                 }
-                delta = (f64::from(delta) * 1.5) as i16;
+                //Truncating would stall a small delta with a widening factor close to 1
+                delta = ((f64::from(delta) * self.search_params.aspiration_widening) as i16)
+                    .max(delta.saturating_add(1));
             }
             previous_score = Some(self.current_pv.score);
             if self.self_stop {
                 break;
             }
+            self.itcs.update(
+                self.id,
+                self.search_statistics.nodes_searched,
+                self.search_statistics.seldepth,
+            );
+            self.itcs.register_completed_pv(&self.current_pv);
+            self.itcs.report_iteration(&self.current_pv);
+            self.search_statistics.snapshot_depth(curr_depth);
+            if self.itcs.uci_options().limit_strength {
+                self.itcs
+                    .register_root_move_scores(curr_depth, &self.root_move_scores);
+            }
+            if self.id == 0 {
+                if researches > 0 && !self.itcs.uci_options().quiet {
+                    println!(
+                        "info string depth {} aspiration re-searches {}",
+                        curr_depth, researches
+                    );
+                }
+                if self.itcs.uci_options().report_branching_factor && !self.itcs.uci_options().quiet
+                {
+                    self.report_branching_factor(curr_depth);
+                }
+                if let Some(best_move) = self.current_pv.pv.pv[0] {
+                    self.root_stability
+                        .register_iteration(best_move, failed_high);
+                }
+                if self.current_pv.score.abs() >= -MATED_IN_MAX {
+                    self.min_think_time = 0;
+                }
+                let time_elapsed = self.itcs.get_time_elapsed();
+                let move_overhead = self.itcs.uci_options().move_overhead;
+                if !self.itcs.pondering.load(Ordering::SeqCst)
+                    && time_elapsed >= self.tc.min_think_time(self.min_think_time, move_overhead)
+                    && self.tc.soft_time_over(
+                        time_elapsed,
+                        self.time_saved,
+                        move_overhead,
+                        self.time_boost,
+                        &self.root_stability,
+                    )
+                {
+                    if self.itcs.uci_options().verify_fail_high
+                        && self.root_stability.switched_on_fail_high()
+                        && previous_pv.pv.pv[0].is_some()
+                        && !self.verify_best_move(&state, curr_depth as i16, previous_pv.score)
+                    {
+                        if !self.itcs.uci_options().quiet {
+                            println!(
+                                "info string depth {} fail high of {:?} not verified, keeping {:?}",
+                                curr_depth,
+                                self.current_pv.pv.pv[0].unwrap(),
+                                previous_pv.pv.pv[0].unwrap()
+                            );
+                        }
+                        self.itcs.revert_completed_pv(&previous_pv);
+                        self.current_pv = previous_pv;
+                    }
+                    break;
+                }
+                previous_pv = self.current_pv.clone();
+            }
         }
         if self.itcs.uci_options().debug_print {
             println!(
@@ -463,7 +943,12 @@ impl Thread {
             self.search_statistics.nodes_searched,
             self.search_statistics.seldepth,
         );
+        self.itcs
+            .add_beta_cutoffs(&self.search_statistics.beta_cutoff_histogram());
+        self.itcs
+            .add_component_times(&self.search_statistics.component_times);
         if self.id == 0 {
+            self.itcs.wait_for_ponderhit();
             *self
                 .itcs
                 .timeout_flag
@@ -471,6 +956,59 @@ impl Thread {
                 .expect("Couldn't write to timeout flag") = true;
         }
     }
+
+    //Re-searches the best move at the same depth, with a null window at the lowest score it may have compared to
+    //the previous best move. A verification which runs out of time keeps the move
+    fn verify_best_move(&mut self, state: &GameState, depth: i16, previous_score: i16) -> bool {
+        let best_move = self.current_pv.pv.pv[0].expect("Verifying an empty pv");
+        let threshold = previous_score - VERIFICATION_MARGIN;
+        let color = if state.get_color_to_move() == WHITE {
+            1
+        } else {
+            -1
+        };
+        let next_state = make_move(state, best_move);
+        self.verification_deadline = Some(self.itcs.get_time_elapsed() + VERIFICATION_TIME);
+        self.history
+            .push(state.get_hash(), state.get_half_moves() == 0);
+        let score = -principal_variation_search(
+            CombinedSearchParameters::from(
+                -threshold,
+                -threshold + 1,
+                depth - 1,
+                &next_state,
+                -color,
+                1,
+            ),
+            self,
+        );
+        self.history.pop();
+        self.verification_deadline = None;
+        self.self_stop || !RootStability::verification_fails(previous_score, score)
+    }
+}
+
+//Prefers a move which neither stalemates the opponent nor repeats a position of the game
+pub fn dead_draw_move(
+    game_state: &GameState,
+    movelist: &MoveList,
+    history: &[GameState],
+) -> GameMove {
+    let mut replies = MoveList::default();
+    let is_safe = |mv: GameMove, replies: &mut MoveList| {
+        let next_state = make_move(game_state, mv);
+        generate_moves(&next_state, false, replies);
+        !replies.move_list.is_empty()
+            && history
+                .iter()
+                .all(|state| state.get_hash() != next_state.get_hash())
+    };
+    movelist
+        .move_list
+        .iter()
+        .map(|gmv| gmv.0)
+        .find(|mv| is_safe(*mv, &mut replies))
+        .unwrap_or(movelist.move_list[0].0)
 }
 
 pub fn search_move(
@@ -479,9 +1017,10 @@ pub fn search_move(
     game_state: GameState,
     history: Vec<GameState>,
     tc: TimeControl,
-) -> Option<i16> {
+) -> i16 {
     //1. Prepare itcs (reset things from previous search)
     *itcs.best_pv.lock().unwrap() = ScoredPrincipalVariation::default();
+    *itcs.completed_pv.lock().unwrap() = ScoredPrincipalVariation::default();
     itcs.stable_pv.store(false, Ordering::Relaxed);
     *itcs.depth_info.lock().unwrap() = [DepthInformation::UnSearched; MAX_SEARCH_DEPTH];
     itcs.nodes_searched()
@@ -493,24 +1032,60 @@ pub fn search_move(
     itcs.cache_status.store(0, Ordering::Relaxed);
     itcs.cache().increase_age();
     *itcs.timeout_flag.write().unwrap() = false;
+    *itcs.root_move_scores.lock().unwrap() = RootMoveScores::default();
+    itcs.last_reported_depth.store(0, Ordering::Relaxed);
+    *itcs.last_info_line.lock().unwrap() = None;
+    *itcs.root_state.lock().unwrap() = game_state.clone();
+    itcs.beta_cutoffs
+        .iter()
+        .for_each(|x| x.store(0u64, Ordering::Relaxed));
+    itcs.component_times
+        .iter()
+        .for_each(|x| x.store(0u64, Ordering::Relaxed));
+    #[cfg(debug_assertions)]
+    itcs.cache().collisions.store(0, Ordering::Relaxed);
+    let max_depth = if itcs.uci_options().limit_strength {
+        max_depth.min(strength::max_depth(itcs.uci_options().elo))
+    } else {
+        max_depth
+    };
 
     let time_saved_before = itcs.saved_time.load(Ordering::Relaxed);
+    let time_boost = itcs.time_boost();
     //Step 1. Check how many legal moves there are
     let mut movelist = MoveList::default();
     generate_moves(&game_state, false, &mut movelist);
 
     //Step2. Check legal moves
-    if movelist.move_list.is_empty() {
+    let max_depth = if movelist.move_list.is_empty() {
         panic!("The root position given does not have any legal move!");
     } else if movelist.move_list.len() == 1 {
-        println!("bestmove {:?}", movelist.move_list[0].0);
+        //The move is forced, so don't waste time on it
+        max_depth.min(ONLY_MOVE_DEPTH)
+    } else if is_dead_draw(&game_state) {
+        //Nothing to search for, but the game may still go on if the opponent doesn't claim the draw
+        let mv = dead_draw_move(&game_state, &movelist, &history);
+        let mut pv = PrincipalVariation::new(1);
+        pv.pv[0] = Some(mv);
+        *itcs.best_pv.lock().unwrap() = ScoredPrincipalVariation {
+            score: 0,
+            pv,
+            depth: 1,
+        };
+        if !itcs.uci_options().quiet {
+            println!("info depth 1 score cp 0 pv {:?}", mv);
+        }
+        itcs.wait_for_ponderhit();
+        itcs.report_bestmove();
 
         let new_timesaved: u64 = (time_saved_before as i64
             + tc.time_saved(0, time_saved_before, itcs.uci_options().move_overhead))
         .max(0) as u64;
         itcs.saved_time.store(new_timesaved, Ordering::Relaxed);
-        return None;
-    }
+        return 0;
+    } else {
+        max_depth
+    };
 
     //Step3. Prepare history
     let mut hist: History = History::default();
@@ -524,6 +1099,7 @@ pub fn search_move(
     for hashes in relevant_hashes.iter().rev() {
         hist.push(*hashes, false);
     }
+    hist.set_root();
 
     //Step 4. Send search command
     for tx in itcs.tx.read().unwrap().iter() {
@@ -533,6 +1109,7 @@ pub fn search_move(
             tc,
             hist.clone(),
             time_saved_before,
+            time_boost,
         ))
         .expect("Couldn't send search command!");
     }
@@ -545,6 +1122,18 @@ pub fn search_move(
     }
 
     //Step 6. Report to UCI
+    let valid_pv = itcs.validate_played_pv(&game_state);
+    debug_assert!(
+        valid_pv,
+        "Illegal move in the pv of {}",
+        game_state.to_fen()
+    );
+    itcs.report_beta_cutoffs();
+    #[cfg(feature = "search-timing")]
+    itcs.report_component_times();
+    #[cfg(debug_assertions)]
+    itcs.report_hash_collisions();
+    itcs.report_final_pv();
     itcs.report_bestmove();
     //Store new saved time
     let elapsed_time = itcs.get_time_elapsed();
@@ -557,6 +1146,472 @@ pub fn search_move(
     .max(0) as u64;
     itcs.saved_time.store(new_timesaved, Ordering::Relaxed);
     //And return
-    let best_score = itcs.best_pv.lock().unwrap().score;
-    Some(best_score)
+    itcs.played_pv().score
+}
+
+//One quiet search thread with a small cache, as used by the search tests
+#[cfg(test)]
+pub(crate) fn test_itcs() -> Arc<InterThreadCommunicationSystem> {
+    let itcs = Arc::new(InterThreadCommunicationSystem::default());
+    InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+    *itcs.cache() = Cache::with_size_threaded(1, 1);
+    itcs.uci_options().quiet = true;
+    itcs
+}
+
+//A single search thread of test_itcs() with no time limit, driven by the test itself instead of search_move
+#[cfg(test)]
+pub(crate) fn test_thread() -> Thread {
+    let itcs = test_itcs();
+    let (_, rx) = channel();
+    let (tx, _) = channel();
+    let mut thread = Thread::new(0, itcs, rx, tx);
+    thread.tc = TimeControl::Infinite;
+    thread
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        search_move, test_itcs, test_thread, InterThreadCommunicationSystem, Thread,
+        ONLY_MOVE_DEPTH,
+    };
+    use crate::board_representation::game_state::{GameMove, GameMoveType, GameState, PieceType};
+    use crate::move_generation::makemove::make_move;
+    use crate::move_generation::movegen::{generate_moves, MoveList};
+    use crate::search::info_sink::{InfoSink, SearchInfo};
+    use crate::search::statistics::SearchStatistics;
+    use crate::search::timecontrol::{
+        RootStability, TimeControl, TimeControlInformation, MAX_BOOK_EXIT_BOOST,
+    };
+    use crate::search::{CombinedSearchParameters, SearchParams, MAX_SEARCH_DEPTH};
+    use std::sync::atomic::Ordering;
+    use std::sync::{Arc, Mutex, Weak};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn branching_factor_test() {
+        let mut thread = test_thread();
+        thread.search(
+            7,
+            GameState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -"),
+        );
+        let statistics = &thread.search_statistics;
+        let depths: Vec<usize> = statistics
+            .depth_nodes
+            .iter()
+            .map(|&(depth, _)| depth)
+            .collect();
+        assert_eq!(depths, [1, 2, 3, 4, 5, 6, 7]);
+        assert!(statistics
+            .depth_nodes
+            .windows(2)
+            .all(|pair| pair[0].1 <= pair[1].1));
+        assert_eq!(
+            statistics.depth_nodes.last().unwrap().1,
+            statistics.nodes_searched
+        );
+        let iterations = statistics.iteration_nodes();
+        assert_eq!(iterations.iter().sum::<u64>(), statistics.nodes_searched);
+        let factors = statistics.branching_factors();
+        assert_eq!(factors.len(), 6);
+        for (factor, pair) in factors.iter().zip(statistics.depth_nodes.windows(2)) {
+            assert!((factor - pair[1].1 as f64 / pair[0].1 as f64).abs() < 1e-9);
+            assert!(*factor >= 1.);
+        }
+
+        //Skipped depths share the growth
+        let skipped = SearchStatistics {
+            depth_nodes: vec![(1, 10), (3, 90), (4, 270)],
+            ..SearchStatistics::default()
+        };
+        assert_eq!(skipped.iteration_nodes(), [10, 80, 180]);
+        let factors = skipped.branching_factors();
+        assert!((factors[0] - 3.).abs() < 1e-9);
+        assert!((factors[1] - 3.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn aspiration_researches_test() {
+        let search = |search_params: SearchParams| {
+            let mut thread = test_thread();
+            thread.search_params = search_params;
+            //Tactically rich, so the score of the one unit window keeps falling out of it
+            let game_state = GameState::from_fen(
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -",
+            );
+            thread.search(7, game_state);
+            (
+                thread.search_statistics.aspiration_researches(),
+                thread.current_pv.pv.pv[0].unwrap(),
+            )
+        };
+        let default = SearchParams::default();
+        let (narrow_researches, narrow_move) = search(SearchParams {
+            aspiration_delta: 1,
+            aspiration_widening: 1.1,
+            ..default
+        });
+        let (wide_researches, wide_move) = search(SearchParams {
+            aspiration_delta: 1000,
+            ..default
+        });
+        assert!(narrow_researches > 0);
+        //The window has to keep widening, even if the factor truncates to the old delta
+        assert!(narrow_researches < 100);
+        assert_eq!(wide_researches, 0);
+        assert_eq!(narrow_move, wide_move);
+    }
+
+    #[test]
+    fn pv_legality_test() {
+        let mut thread = test_thread();
+        let itcs = Arc::clone(&thread.itcs);
+        let game_state = GameState::standard();
+        thread.search(6, game_state.clone());
+        assert!(itcs.validate_played_pv(&game_state));
+        let pv = itcs.played_pv().pv;
+        assert!(pv.pv[3].is_some());
+
+        //A white move where black is to move, as a hash collision could produce it
+        let illegal = GameMove {
+            from: 12,
+            to: 28,
+            move_type: GameMoveType::Quiet,
+            piece_type: PieceType::Pawn,
+        };
+        let mut corrupted = pv.clone();
+        corrupted.pv[3] = Some(illegal);
+        corrupted.pv[4] = pv.pv[0];
+        let mut truncated = corrupted.clone();
+        let (ply, state) = truncated.truncate_illegal(&game_state).unwrap();
+        assert_eq!(ply, 3);
+        assert_eq!(state.get_color_to_move(), 1);
+        assert_eq!(truncated.pv[..3], pv.pv[..3]);
+        assert!(truncated.pv[3..].iter().all(|mv| mv.is_none()));
+        assert!(truncated.truncate_illegal(&game_state).is_none());
+
+        itcs.completed_pv.lock().unwrap().pv = corrupted;
+        assert!(!itcs.validate_played_pv(&game_state));
+        assert_eq!(itcs.played_pv().pv.pv, truncated.pv);
+    }
+
+    #[test]
+    fn go_depth_test() {
+        struct DepthSink(Mutex<Vec<usize>>);
+        impl InfoSink for DepthSink {
+            fn on_iteration(&self, info: &SearchInfo) {
+                self.0.lock().unwrap().push(info.depth);
+            }
+        }
+        let itcs = test_itcs();
+        let sink = Arc::new(DepthSink(Mutex::new(Vec::new())));
+        itcs.set_info_sink(Some(sink.clone()));
+        let game_state =
+            GameState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -");
+        let score = search_move(
+            Arc::clone(&itcs),
+            8,
+            game_state,
+            Vec::new(),
+            TimeControl::Infinite,
+        );
+        //Every depth up to 8 is completed and reported once, nothing deeper is started
+        assert_eq!(*sink.0.lock().unwrap(), (1..=8).collect::<Vec<usize>>());
+        let played = itcs.played_pv();
+        assert_eq!(played.depth, 8);
+        assert_eq!(score, played.score);
+        //The depth 8 line of the played pv is the last info line before bestmove
+        assert_eq!(itcs.last_info_line.lock().unwrap().as_ref().unwrap().0, 8);
+        assert!(!itcs.final_info_line_missing());
+        assert!(itcs
+            .bestmove_string()
+            .starts_with(&format!("bestmove {:?}", played.pv.pv[0].unwrap())));
+
+        //Otherwise, e.g. after a bound as last line, the played pv is repeated
+        *itcs.last_info_line.lock().unwrap() = None;
+        assert!(itcs.final_info_line_missing());
+        itcs.report_final_pv();
+        assert!(!itcs.final_info_line_missing());
+    }
+
+    #[test]
+    fn clear_heuristics_test() {
+        let mut thread = test_thread();
+        let itcs = Arc::clone(&thread.itcs);
+        let game_state =
+            GameState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -");
+        thread.search(6, game_state.clone());
+
+        let mut movelist = MoveList::default();
+        generate_moves(&game_state, false, &mut movelist);
+        let learned = |thread: &Thread| {
+            let mut learned = thread.killer_moves.iter().flatten().any(|mv| mv.is_some());
+            for color in 0..2 {
+                for gmv in movelist.move_list.iter() {
+                    let entry = thread.history_tables.get(color, gmv.0);
+                    learned |= entry.ordering_score() != 0.
+                        || entry.history_score != 0
+                        || entry.bf_score != 1;
+                    if gmv.0.is_capture() {
+                        learned |= thread.history_tables.capture_score(gmv.0) != 0.;
+                    }
+                }
+            }
+            learned
+        };
+        assert!(learned(&thread));
+        thread.clear_heuristics();
+        assert!(!learned(&thread));
+        //The cache stays warm
+        let hash = game_state.get_hash();
+        assert!(itcs.cache().get(hash).probe(hash).is_some());
+    }
+
+    #[test]
+    fn only_move_test() {
+        let itcs = test_itcs();
+        //The king has to take the checking queen
+        let game_state = GameState::from_fen("4k3/pp6/8/8/8/8/3q2PP/3K4 w - - 0 1");
+        let start = std::time::Instant::now();
+        let score = search_move(
+            Arc::clone(&itcs),
+            100,
+            game_state,
+            Vec::new(),
+            TimeControl::Infinite,
+        );
+        assert!(start.elapsed().as_millis() < 1000);
+        let played = itcs.played_pv();
+        assert_eq!(format!("{:?}", played.pv.pv[0].unwrap()), "d1d2");
+        assert_eq!(played.depth, ONLY_MOVE_DEPTH as usize);
+        assert_eq!(score, played.score);
+        assert!(score.abs() < 300);
+    }
+
+    #[test]
+    fn min_think_time_test() {
+        let itcs = test_itcs();
+        itcs.uci_options().min_think_time = 700;
+        let think_time = |fen: &str, tc: TimeControl| {
+            let start = std::time::Instant::now();
+            search_move(
+                Arc::clone(&itcs),
+                100,
+                GameState::from_fen(fen),
+                Vec::new(),
+                tc,
+            );
+            start.elapsed().as_millis()
+        };
+        let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -";
+        //The soft limit would end the search after about 400ms, the budget is 775ms
+        let elapsed = think_time(kiwipete, TimeControl::Incremental(24000, 0));
+        assert!((700..1300).contains(&elapsed));
+        //With a budget of only 75ms the minimum doesn't apply
+        assert!(think_time(kiwipete, TimeControl::Incremental(3000, 0)) < 400);
+        //The hard limit is still respected
+        assert!(think_time(kiwipete, TimeControl::MoveTime(150)) < 300);
+        //Forced moves and mates are played right away
+        itcs.uci_options().min_think_time = 2000;
+        let forced_move = "4k3/pp6/8/8/8/8/3q2PP/3K4 w - - 0 1";
+        assert!(think_time(forced_move, TimeControl::Incremental(3000, 0)) < 1000);
+        let back_rank_mate = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+        assert!(think_time(back_rank_mate, TimeControl::Incremental(3000, 0)) < 1000);
+    }
+
+    #[cfg(feature = "search-timing")]
+    #[test]
+    fn component_times_test() {
+        let itcs = test_itcs();
+        let kiwipete =
+            GameState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -");
+        let timed_search = |depth: i16| {
+            let start = std::time::Instant::now();
+            search_move(
+                Arc::clone(&itcs),
+                depth,
+                kiwipete.clone(),
+                Vec::new(),
+                TimeControl::Infinite,
+            );
+            let elapsed = start.elapsed().as_nanos() as u64;
+            let times = itcs.component_times();
+            //The parts never overlap, so together they can't take longer than the search
+            assert!(times.iter().sum::<u64>() <= elapsed);
+            times
+        };
+        let deep = timed_search(8);
+        assert!(deep.iter().all(|time| *time > 0));
+        //The next go starts counting from zero
+        itcs.cache().clear_threaded(1);
+        let shallow = timed_search(1);
+        assert!(shallow.iter().sum::<u64>() < deep.iter().sum::<u64>());
+    }
+
+    #[test]
+    fn book_exit_boost_test() {
+        let itcs = InterThreadCommunicationSystem::default();
+        itcs.uci_options().book_exit_boost = 50;
+        itcs.uci_options().book_exit_moves = 2;
+        let stop = |tc: TimeControl, move_overhead: u64| {
+            let tc_information = TimeControlInformation {
+                time_boost: itcs.time_boost(),
+                ..TimeControlInformation::new(0)
+            };
+            (0..=tc.time_left())
+                .find(|&time_spent| tc.time_over(time_spent, &tc_information, move_overhead))
+                .unwrap()
+        };
+        //A normal time of 100ms, boosted to 150ms and extended by 15% for a changing pv
+        let tc = TimeControl::Incremental(3000, 0);
+        assert_eq!(stop(tc, 0), 173);
+        assert_eq!(stop(tc, 0), 173);
+        //Later moves only get the normal budget
+        assert_eq!(stop(tc, 0), 115);
+        assert_eq!(stop(tc, 0), 115);
+        assert!(!tc.soft_time_over(60, 0, 0, 50, &RootStability::default()));
+        assert!(tc.soft_time_over(60, 0, 0, 0, &RootStability::default()));
+
+        //Until the next game
+        itcs.searches_since_newgame.store(0, Ordering::Relaxed);
+        assert_eq!(stop(tc, 0), 173);
+        //The hard limit is never exceeded
+        itcs.uci_options().book_exit_boost = MAX_BOOK_EXIT_BOOST;
+        itcs.searches_since_newgame.store(0, Ordering::Relaxed);
+        assert_eq!(stop(TimeControl::Incremental(300, 1000), 25), 201);
+    }
+
+    #[test]
+    fn eval_randomness_test() {
+        let itcs = test_itcs();
+        //Plenty of near-equal first moves
+        let game_state = GameState::standard();
+        let mut movelist = MoveList::default();
+        generate_moves(&game_state, false, &mut movelist);
+        let played = |randomness: i16, seed: u64| {
+            itcs.uci_options().eval_randomness = randomness;
+            itcs.uci_options().eval_random_seed = seed;
+            itcs.cache().clear_threaded(1);
+            search_move(
+                Arc::clone(&itcs),
+                5,
+                game_state.clone(),
+                Vec::new(),
+                TimeControl::Infinite,
+            );
+            itcs.played_pv().pv.pv[0].unwrap()
+        };
+        let unrandomized = played(0, 0);
+        for seed in 1..4 {
+            assert_eq!(played(0, seed), unrandomized);
+        }
+        let mut randomized = Vec::new();
+        for seed in 0..8 {
+            let mv = played(30, seed);
+            assert!(movelist.move_list.iter().any(|gmv| gmv.0 == mv));
+            if !randomized.contains(&mv) {
+                randomized.push(mv);
+            }
+        }
+        assert!(randomized.len() > 1);
+    }
+
+    #[test]
+    fn fifty_move_progress_test() {
+        let search = |fen: &str, half_moves: usize| {
+            let itcs = test_itcs();
+            let score = search_move(
+                Arc::clone(&itcs),
+                8,
+                GameState::from_fen(&format!("{} {} 60", fen, half_moves)),
+                Vec::new(),
+                TimeControl::Infinite,
+            );
+            (score, format!("{:?}", itcs.played_pv().pv.pv[0].unwrap()))
+        };
+        //The blocked a-pawns leave a3a4 as the only move resetting the half move clock
+        let won = "4k3/8/8/p7/8/P7/8/4KBN1 w - -";
+        let (fresh_score, fresh_move) = search(won, 0);
+        assert!(fresh_score > 500);
+        assert_ne!(fresh_move, "a3a4");
+        for half_moves in [70, 90].iter() {
+            let (score, mv) = search(won, *half_moves);
+            assert_eq!(mv, "a3a4");
+            //Not damped below the fresh position, the score includes the bonus of the push
+            assert!(score > fresh_score - 30);
+        }
+        //Without a winning advantage the clock doesn't matter before the fifty move scale starts
+        let balanced = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -";
+        assert_eq!(search(balanced, 70), search(balanced, 0));
+    }
+
+    #[test]
+    fn verify_fail_high_search_test() {
+        //Records the best move of every depth. At stop_depth the clock is moved past the soft limit, so the
+        //search stops there, and with refute set the hash table claims that the new best move loses
+        struct StopSink {
+            itcs: Weak<InterThreadCommunicationSystem>,
+            root: GameState,
+            stop_depth: usize,
+            refute: bool,
+            best_moves: Mutex<Vec<GameMove>>,
+        }
+        impl InfoSink for StopSink {
+            fn on_iteration(&self, info: &SearchInfo) {
+                self.best_moves.lock().unwrap().push(info.pv[0]);
+                if info.depth != self.stop_depth {
+                    return;
+                }
+                let itcs = self.itcs.upgrade().unwrap();
+                if self.refute {
+                    let next_state = make_move(&self.root, info.pv[0]);
+                    let mut movelist = MoveList::default();
+                    generate_moves(&next_state, false, &mut movelist);
+                    itcs.cache().insert(
+                        &CombinedSearchParameters::from(-16000, 16000, 60, &next_state, 1, 1),
+                        movelist.move_list[0].0,
+                        900,
+                        -16000,
+                        None,
+                    );
+                }
+                *itcs.start_time.write().unwrap() = Instant::now() - Duration::from_millis(1500);
+            }
+        }
+        //Kiwipete switches to a new best move by failing high at depth 8
+        let root =
+            GameState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -");
+        let search = |refute: bool| {
+            let mut thread = test_thread();
+            let itcs = Arc::clone(&thread.itcs);
+            itcs.uci_options().verify_fail_high = true;
+            let sink = Arc::new(StopSink {
+                itcs: Arc::downgrade(&itcs),
+                root: root.clone(),
+                stop_depth: 8,
+                refute,
+                best_moves: Mutex::new(Vec::new()),
+            });
+            itcs.set_info_sink(Some(sink.clone()));
+            //A budget of 1975ms, so the soft limit is only reached once the clock is moved
+            thread.tc = TimeControl::Incremental(60000, 0);
+            thread.search(MAX_SEARCH_DEPTH as i16, root.clone());
+            assert!(thread.root_stability.switched_on_fail_high());
+            let best_moves = sink.best_moves.lock().unwrap().clone();
+            assert_eq!(best_moves.len(), 8);
+            assert_ne!(best_moves[6], best_moves[7]);
+            (best_moves, itcs.played_pv())
+        };
+
+        //The new best move holds up in the verification and is played
+        let (best_moves, played) = search(false);
+        assert_eq!(played.depth, 8);
+        assert_eq!(played.pv.pv[0], Some(best_moves[7]));
+
+        //It collapses in the verification, so the pv of depth 7 is played instead
+        let (best_moves, played) = search(true);
+        assert_eq!(played.depth, 7);
+        assert_eq!(played.pv.pv[0], Some(best_moves[6]));
+    }
 }
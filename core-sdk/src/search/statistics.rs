@@ -1,4 +1,38 @@
 use std::fmt::{Display, Formatter, Result};
+#[cfg(feature = "search-timing")]
+use std::time::Instant;
+
+//Beta cutoffs on the first, second, third and any later move
+pub const BETA_CUTOFF_BUCKETS: usize = 4;
+
+//Parts of the search whose cumulative time is measured with the search-timing feature
+#[derive(Copy, Clone)]
+pub enum SearchComponent {
+    MoveGen,
+    Eval,
+    See,
+    Cache,
+}
+pub const SEARCH_COMPONENTS: usize = 4;
+pub const SEARCH_COMPONENT_NAMES: [&str; SEARCH_COMPONENTS] = ["movegen", "eval", "see", "tt"];
+
+//Started before a timed part of the search and handed to SearchStatistics::add_time after it. Without the
+//search-timing feature it is empty and measures nothing
+#[derive(Copy, Clone)]
+pub struct ComponentTimer {
+    #[cfg(feature = "search-timing")]
+    start: Instant,
+}
+
+impl ComponentTimer {
+    #[inline(always)]
+    pub fn start() -> Self {
+        ComponentTimer {
+            #[cfg(feature = "search-timing")]
+            start: Instant::now(),
+        }
+    }
+}
 
 pub struct SearchStatistics {
     pub depth: usize,
@@ -23,6 +57,11 @@ pub struct SearchStatistics {
     pub iid_nodes: u64,
     pub futil_nodes: u64,
     pub history_pruned: u64,
+    pub lmr_reductions: u64,
+    pub aspiration_fail_highs: u64,
+    pub aspiration_fail_lows: u64,
+    pub component_times: [u64; SEARCH_COMPONENTS], //In nanoseconds
+    pub depth_nodes: Vec<(usize, u64)>, //Nodes searched so far after every completed iteration
 }
 
 impl Default for SearchStatistics {
@@ -50,6 +89,11 @@ impl Default for SearchStatistics {
             iid_nodes: 0,
             futil_nodes: 0,
             history_pruned: 0,
+            lmr_reductions: 0,
+            aspiration_fail_highs: 0,
+            aspiration_fail_lows: 0,
+            component_times: [0; SEARCH_COMPONENTS],
+            depth_nodes: Vec::new(),
         }
     }
 }
@@ -68,6 +112,58 @@ impl SearchStatistics {
         self.history_pruned += 1;
     }
     #[inline(always)]
+    pub fn add_lmr_reduction(&mut self) {
+        self.lmr_reductions += 1;
+    }
+    pub fn add_aspiration_fail_high(&mut self) {
+        self.aspiration_fail_highs += 1;
+    }
+    pub fn add_aspiration_fail_low(&mut self) {
+        self.aspiration_fail_lows += 1;
+    }
+    #[inline(always)]
+    pub fn add_time(&mut self, component: SearchComponent, timer: ComponentTimer) {
+        #[cfg(feature = "search-timing")]
+        {
+            self.component_times[component as usize] += timer.start.elapsed().as_nanos() as u64;
+        }
+        #[cfg(not(feature = "search-timing"))]
+        {
+            let _ = (component, timer);
+        }
+    }
+    pub fn snapshot_depth(&mut self, depth: usize) {
+        self.depth_nodes.push((depth, self.nodes_searched));
+    }
+    //Nodes spent on every completed iteration alone
+    pub fn iteration_nodes(&self) -> Vec<u64> {
+        let mut previous = 0;
+        self.depth_nodes
+            .iter()
+            .map(|&(_, nodes)| {
+                let iteration = nodes - previous;
+                previous = nodes;
+                iteration
+            })
+            .collect()
+    }
+    //Effective branching factor between successive completed iterations. A helper thread may skip depths, then
+    //the growth is spread evenly over the skipped ones
+    pub fn branching_factors(&self) -> Vec<f64> {
+        self.depth_nodes
+            .windows(2)
+            .map(|pair| {
+                let ((from_depth, from_nodes), (to_depth, to_nodes)) = (pair[0], pair[1]);
+                (to_nodes as f64 / from_nodes.max(1) as f64)
+                    .powf(1. / (to_depth - from_depth) as f64)
+            })
+            .collect()
+    }
+    //Searches of the root which had to be repeated with a wider aspiration window
+    pub fn aspiration_researches(&self) -> u64 {
+        self.aspiration_fail_highs + self.aspiration_fail_lows
+    }
+    #[inline(always)]
     pub fn add_q_node(&mut self, seldepth: usize) {
         self.nodes_searched += 1;
         self.q_nodes_searched += 1;
@@ -126,6 +222,13 @@ impl SearchStatistics {
             self.normal_nodes_beta_cutoffs_index[index] += 1;
         }
     }
+    pub fn beta_cutoff_histogram(&self) -> [u64; BETA_CUTOFF_BUCKETS] {
+        let mut histogram = [0u64; BETA_CUTOFF_BUCKETS];
+        for (index, cutoffs) in self.normal_nodes_beta_cutoffs_index.iter().enumerate() {
+            histogram[index.min(BETA_CUTOFF_BUCKETS - 1)] += *cutoffs as u64;
+        }
+        histogram
+    }
     #[inline(always)]
     pub fn add_normal_node_non_beta_cutoff(&mut self) {
         self.normal_nodes_non_beta_cutoffs += 1;
@@ -218,6 +321,17 @@ impl Display for SearchStatistics {
             self.history_pruned,
             (self.history_pruned as f64 / self.normal_nodes_searched as f64 * 100.0)
         ));
+        res_str.push_str(&format!(
+            "Normal-Search LMR-Reduced : {} ({}%)\n",
+            self.lmr_reductions,
+            (self.lmr_reductions as f64 / self.normal_nodes_searched as f64 * 100.0)
+        ));
+        res_str.push_str(&format!(
+            "Aspiration re-searches: {} (fail high {}, fail low {})\n",
+            self.aspiration_researches(),
+            self.aspiration_fail_highs,
+            self.aspiration_fail_lows
+        ));
 
         res_str.push_str("\n");
         res_str.push_str(&format!(
@@ -0,0 +1,135 @@
+use super::MAX_SEARCH_DEPTH;
+use crate::board_representation::game_state::GameMove;
+use rand::rngs::StdRng;
+use rand::Rng;
+
+pub const DEFAULT_ELO: usize = 1500;
+pub const MIN_ELO: usize = 800;
+pub const MAX_ELO: usize = 2800;
+
+//Probability of deviating from the best move at MIN_ELO. Scales linearly to 0 at MAX_ELO
+pub const MAX_DEVIATION_PROBABILITY: f64 = 0.5;
+//Moves which are worse than the best move by more than this window (at MIN_ELO) are never picked
+pub const MAX_DEVIATION_WINDOW: i16 = 400;
+
+//Random offsets of root move scores, only meant for opening variety in selfplay
+pub const DEFAULT_EVAL_RANDOMNESS: i16 = 0;
+pub const MAX_EVAL_RANDOMNESS: i16 = 50;
+
+#[derive(Default)]
+pub struct RootMoveScores {
+    pub depth: usize,
+    pub scores: Vec<(GameMove, i16)>,
+}
+
+#[inline(always)]
+fn weakness(elo: usize) -> f64 {
+    let elo = elo.clamp(MIN_ELO, MAX_ELO);
+    (MAX_ELO - elo) as f64 / (MAX_ELO - MIN_ELO) as f64
+}
+
+pub fn max_depth(elo: usize) -> i16 {
+    if elo >= MAX_ELO {
+        MAX_SEARCH_DEPTH as i16
+    } else {
+        (1 + (elo.max(MIN_ELO) - MIN_ELO) / 100) as i16
+    }
+}
+
+pub fn deviation_probability(elo: usize) -> f64 {
+    weakness(elo) * MAX_DEVIATION_PROBABILITY
+}
+
+pub fn deviation_window(elo: usize) -> i16 {
+    (weakness(elo) * f64::from(MAX_DEVIATION_WINDOW)) as i16
+}
+
+//Picks the move to play at the root under limited strength. With an elo dependent probability, a move
+//which is not the best one is picked, weighted by how close its score is to the best score. Scores have to be exact
+//within the deviation window below the best score, scores further below are never picked.
+pub fn pick_root_move(
+    best_move: GameMove,
+    root_scores: &[(GameMove, i16)],
+    elo: usize,
+    rng: &mut StdRng,
+) -> GameMove {
+    let best_score = root_scores
+        .iter()
+        .find(|(mv, _)| *mv == best_move)
+        .map(|(_, score)| *score)
+        .or_else(|| root_scores.iter().map(|(_, score)| *score).max());
+    if best_score.is_none() || rng.gen::<f64>() >= deviation_probability(elo) {
+        return best_move;
+    }
+    let best_score = best_score.unwrap();
+    let window = deviation_window(elo);
+    let weight = |score: i16| i32::from(window) - (i32::from(best_score) - i32::from(score));
+    let total_weight: i32 = root_scores
+        .iter()
+        .filter(|(mv, _)| *mv != best_move)
+        .map(|(_, score)| weight(*score).max(0))
+        .sum();
+    if total_weight <= 0 {
+        return best_move;
+    }
+    let mut pick = rng.gen_range(0, total_weight);
+    for (mv, score) in root_scores.iter().filter(|(mv, _)| *mv != best_move) {
+        let w = weight(*score).max(0);
+        if pick < w {
+            return *mv;
+        }
+        pick -= w;
+    }
+    best_move
+}
+
+//An offset in [-amount, amount] which is the same for a root move in every iteration, so the search stays
+//consistent, but differs between seeds
+pub fn root_move_noise(amount: i16, seed: u64, root_hash: u64, mv: GameMove) -> i16 {
+    if amount <= 0 {
+        return 0;
+    }
+    let move_key = (u64::from(mv.from) << 6 | u64::from(mv.to)) << 3 | mv.piece_type as u64;
+    //splitmix64 finalizer
+    let mut x = seed ^ root_hash ^ move_key.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^= x >> 31;
+    (x % (2 * amount as u64 + 1)) as i16 - amount
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pick_root_move, MAX_ELO, MIN_ELO};
+    use crate::board_representation::game_state::GameState;
+    use crate::move_generation::movegen::{generate_moves, MoveList};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn limit_strength_test() {
+        let game_state = GameState::standard();
+        let mut movelist = MoveList::default();
+        generate_moves(&game_state, false, &mut movelist);
+        let root_scores: Vec<_> = movelist
+            .move_list
+            .iter()
+            .enumerate()
+            .map(|(i, gmv)| (gmv.0, 50 - 5 * i as i16))
+            .collect();
+        let best_move = root_scores[0].0;
+        let mut rng = StdRng::seed_from_u64(0xFAB);
+
+        let mut deviated = false;
+        for _ in 0..1000 {
+            let mv = pick_root_move(best_move, &root_scores, MIN_ELO, &mut rng);
+            assert!(root_scores.iter().any(|(other, _)| *other == mv));
+            deviated |= mv != best_move;
+        }
+        assert!(deviated);
+
+        for _ in 0..1000 {
+            assert!(pick_root_move(best_move, &root_scores, MAX_ELO, &mut rng) == best_move);
+        }
+    }
+}
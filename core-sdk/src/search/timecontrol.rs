@@ -1,11 +1,34 @@
+use crate::board_representation::game_state::GameMove;
+
 pub const DEFAULT_MOVE_OVERHEAD: u64 = 25;
 pub const MIN_MOVE_OVERHEAD: u64 = 0;
 pub const MAX_MOVE_OVERHEAD: u64 = 20000;
+//The search doesn't stop before this many ms, unless the hard limit would be exceeded
+pub const DEFAULT_MIN_THINK_TIME: u64 = 0;
+pub const MAX_MIN_THINK_TIME: u64 = 60000;
+//Extra time in percent of the normal time for the first moves out of book, where neither the cache nor a pv
+//of the previous search help the search yet
+pub const DEFAULT_BOOK_EXIT_BOOST: u64 = 0;
+pub const MAX_BOOK_EXIT_BOOST: u64 = 200;
+pub const DEFAULT_BOOK_EXIT_MOVES: usize = 1;
+pub const MAX_BOOK_EXIT_MOVES: usize = 20;
+
+//A new iteration takes longer than all previous ones, so it is only started within this share of the time
+pub const SOFT_LIMIT_SHARE: f64 = 0.5;
+pub const FAIL_HIGH_TIME_FACTOR: f64 = 1.4;
+pub const STABLE_TIME_FACTOR: f64 = 0.6;
+pub const STABLE_ITERATIONS: usize = 4;
+//A best move which took over by failing high when the soft limit ends the search is re-searched within this
+//many ms, and dropped again if it scores more than VERIFICATION_MARGIN below the previous best move
+pub const VERIFICATION_TIME: u64 = 50;
+pub const VERIFICATION_MARGIN: i16 = 25;
 
 pub struct TimeControlInformation {
     pub time_saved: u64,
     pub stable_pv: bool,
     pub high_score_diff: bool,
+    pub min_think_time: u64,
+    pub time_boost: u64, //In percent of the normal time
 }
 
 impl TimeControlInformation {
@@ -14,6 +37,51 @@ impl TimeControlInformation {
             time_saved,
             stable_pv: false,
             high_score_diff: false,
+            min_think_time: DEFAULT_MIN_THINK_TIME,
+            time_boost: 0,
+        }
+    }
+}
+
+//How the root behaved over the last iterations, used to scale the soft time limit
+#[derive(Default)]
+pub struct RootStability {
+    pub best_move: Option<GameMove>,
+    pub previous_best_move: Option<GameMove>, //The best move before the last change
+    pub stable_iterations: usize,             //Iterations the best move stayed the same
+    pub failed_high: bool, //The last iteration failed high in the aspiration window
+}
+
+impl RootStability {
+    pub fn register_iteration(&mut self, best_move: GameMove, failed_high: bool) {
+        if self.best_move == Some(best_move) {
+            self.stable_iterations += 1;
+        } else {
+            self.previous_best_move = self.best_move;
+            self.best_move = Some(best_move);
+            self.stable_iterations = 0;
+        }
+        self.failed_high = failed_high;
+    }
+
+    //The last iteration replaced the best move by failing high
+    pub fn switched_on_fail_high(&self) -> bool {
+        self.failed_high && self.stable_iterations == 0 && self.previous_best_move.is_some()
+    }
+
+    //Scores are from the point of view of the side to move, verified_score is the new best move's
+    pub fn verification_fails(previous_score: i16, verified_score: i16) -> bool {
+        verified_score < previous_score - VERIFICATION_MARGIN
+    }
+
+    pub fn time_factor(&self) -> f64 {
+        if self.failed_high {
+            //The score jumped, give the search some time to resolve what it found
+            FAIL_HIGH_TIME_FACTOR
+        } else if self.stable_iterations >= STABLE_ITERATIONS {
+            STABLE_TIME_FACTOR
+        } else {
+            1.0
         }
     }
 }
@@ -82,13 +150,18 @@ impl TimeControl {
         tc_information: &TimeControlInformation,
         move_overhead: u64,
     ) -> bool {
+        if time_spent < self.min_think_time(tc_information.min_think_time, move_overhead) {
+            return false;
+        }
         if let TimeControl::Incremental(mytime, myinc) = self {
             if time_spent as isize > *mytime as isize - 4 * move_overhead as isize {
                 return true;
             }
-            let normal_time = ((*mytime as f64 - tc_information.time_saved as f64) / 30.0) as u64
-                + myinc
-                - move_overhead;
+            let normal_time = self.boosted_time(
+                tc_information.time_saved,
+                move_overhead,
+                tc_information.time_boost,
+            );
             let time_aspired = if tc_information.time_saved < normal_time {
                 ((normal_time as f64 * 0.85) as u64).max(*myinc)
             } else {
@@ -106,17 +179,18 @@ impl TimeControl {
             //Non stable pv so we increase time
             return time_spent as f64 > 1.15 * (normal_time + tc_information.time_saved) as f64;
         } else if let TimeControl::MoveTime(move_time) = self {
-            return time_spent > move_time - move_overhead || *move_time < move_overhead;
+            return time_spent > move_time.saturating_sub(move_overhead);
         } else if let TimeControl::Infinite = self {
             return false;
-        } else if let TimeControl::Tournament(mytime, myinc, movestogo) = self {
+        } else if let TimeControl::Tournament(mytime, _, _) = self {
             if time_spent as isize > *mytime as isize - 4 * move_overhead as isize {
                 return true;
             }
-            let normal_time = ((*mytime as f64 - tc_information.time_saved as f64)
-                / *movestogo as f64) as u64
-                + myinc
-                - move_overhead;
+            let normal_time = self.boosted_time(
+                tc_information.time_saved,
+                move_overhead,
+                tc_information.time_boost,
+            );
             let time_aspired = if tc_information.time_saved < normal_time {
                 (normal_time as f64 * 0.85) as u64
             } else {
@@ -134,18 +208,74 @@ impl TimeControl {
         panic!("Invalid Timecontrol");
     }
 
+    //Checked between iterations, the hard limit during an iteration is time_over
+    pub fn soft_time_over(
+        &self,
+        time_spent: u64,
+        time_saved: u64,
+        move_overhead: u64,
+        time_boost: u64,
+        stability: &RootStability,
+    ) -> bool {
+        match self {
+            TimeControl::Incremental(_, _) | TimeControl::Tournament(_, _, _) => {
+                let budget = self.boosted_time(time_saved, move_overhead, time_boost) + time_saved;
+                time_spent as f64 > SOFT_LIMIT_SHARE * budget as f64 * stability.time_factor()
+            }
+            TimeControl::MoveTime(_) | TimeControl::Infinite => false,
+        }
+    }
+
+    //The minimum think time, capped by the time normally spent on a move and the hard limit of time_over, so that
+    //it can't burn the clock
+    pub fn min_think_time(&self, min_think_time: u64, move_overhead: u64) -> u64 {
+        match self {
+            TimeControl::Incremental(mytime, _) | TimeControl::Tournament(mytime, _, _) => {
+                min_think_time
+                    .min(self.normal_time(0, move_overhead))
+                    .min(mytime.saturating_sub(4 * move_overhead))
+            }
+            TimeControl::MoveTime(move_time) => {
+                min_think_time.min(move_time.saturating_sub(move_overhead))
+            }
+            TimeControl::Infinite => min_think_time,
+        }
+    }
+
+    //The time we would normally spend on this move, before adjusting for pv stability
+    pub fn normal_time(&self, time_saved: u64, move_overhead: u64) -> u64 {
+        match self {
+            TimeControl::Incremental(mytime, myinc) => {
+                (((*mytime as f64 - time_saved as f64) / 30.0) as u64 + myinc)
+                    .saturating_sub(move_overhead)
+            }
+            TimeControl::Tournament(mytime, myinc, movestogo) => {
+                (((*mytime as f64 - time_saved as f64) / *movestogo as f64) as u64 + myinc)
+                    .saturating_sub(move_overhead)
+            }
+            TimeControl::MoveTime(move_time) => move_time.saturating_sub(move_overhead),
+            TimeControl::Infinite => u64::MAX,
+        }
+    }
+
+    //The normal time raised by time_boost percent. The extra time isn't part of normal_time, so time_saved
+    //charges it to the time saved on earlier moves
+    pub fn boosted_time(&self, time_saved: u64, move_overhead: u64, time_boost: u64) -> u64 {
+        let normal_time = self.normal_time(time_saved, move_overhead);
+        match self {
+            TimeControl::Incremental(_, _) | TimeControl::Tournament(_, _, _) => {
+                normal_time + normal_time * time_boost / 100
+            }
+            TimeControl::MoveTime(_) | TimeControl::Infinite => normal_time,
+        }
+    }
+
     pub fn time_saved(&self, time_spent: u64, saved: u64, move_overhead: u64) -> i64 {
-        if let TimeControl::Incremental(mytime, myinc) = self {
-            let normal_timecontrol =
-                ((*mytime as f64 - saved as f64) / 30.0) as u64 + myinc - move_overhead;
-            normal_timecontrol as i64 - time_spent as i64
-        } else if let TimeControl::Tournament(mytime, myinc, movestogo) = self {
-            let normal_timecontrol = ((*mytime as f64 - saved as f64) / *movestogo as f64) as u64
-                + myinc
-                - move_overhead;
-            normal_timecontrol as i64 - time_spent as i64
-        } else {
-            0
+        match self {
+            TimeControl::Incremental(_, _) | TimeControl::Tournament(_, _, _) => {
+                self.normal_time(saved, move_overhead) as i64 - time_spent as i64
+            }
+            _ => 0,
         }
     }
 
@@ -154,9 +284,7 @@ impl TimeControl {
         if let TimeControl::Incremental(mytime, myinc) = self {
             res_str.push_str(&format!("My Time: {}\n", mytime));
             res_str.push_str(&format!("My Inc: {}\n", myinc));
-            let normal_time = ((*mytime as f64 - tc_information.time_saved as f64) / 30.0) as u64
-                + myinc
-                - move_overhead;
+            let normal_time = self.normal_time(tc_information.time_saved, move_overhead);
             let time_aspired = if tc_information.time_saved < normal_time {
                 ((normal_time as f64 * 0.85) as u64).max(*myinc)
             } else {
@@ -175,10 +303,7 @@ impl TimeControl {
             res_str.push_str(&format!("My Time: {}\n", mytime));
             res_str.push_str(&format!("My Inc: {}\n", myinc));
             res_str.push_str(&format!("Moves to go : {}\n", movestogo));
-            let normal_time = ((*mytime as f64 - tc_information.time_saved as f64)
-                / *movestogo as f64) as u64
-                + myinc
-                - move_overhead;
+            let normal_time = self.normal_time(tc_information.time_saved, move_overhead);
             let time_aspired = if tc_information.time_saved < normal_time {
                 (normal_time as f64 * 0.85) as u64
             } else {
@@ -194,3 +319,160 @@ impl TimeControl {
         res_str
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        RootStability, TimeControl, TimeControlInformation, STABLE_ITERATIONS, VERIFICATION_MARGIN,
+    };
+    use crate::board_representation::game_state::{GameMove, GameMoveType, PieceType};
+
+    fn stop_time(tc: TimeControl, move_overhead: u64) -> u64 {
+        let tc_information = TimeControlInformation::new(0);
+        (0..=tc.time_left())
+            .find(|&time_spent| tc.time_over(time_spent, &tc_information, move_overhead))
+            .unwrap_or_else(|| tc.time_left())
+    }
+
+    #[test]
+    fn move_overhead_test() {
+        for tc in [
+            TimeControl::Incremental(3000, 0),
+            TimeControl::Tournament(3000, 0, 5),
+            TimeControl::MoveTime(300),
+        ]
+        .iter()
+        {
+            let stop = stop_time(*tc, 0);
+            let stop_with_overhead = stop_time(*tc, 200);
+            assert!(stop_with_overhead < stop);
+            //The last millisecond searched still leaves the overhead on the clock
+            assert!(stop_with_overhead - 1 + 200 <= tc.time_left());
+        }
+        //An overhead larger than the whole budget stops at once instead of underflowing
+        for tc in [
+            TimeControl::Incremental(50, 10),
+            TimeControl::Tournament(50, 10, 2),
+            TimeControl::MoveTime(50),
+        ]
+        .iter()
+        {
+            assert!(stop_time(*tc, 1000) <= 1);
+            tc.time_saved(0, 0, 1000);
+        }
+    }
+
+    #[test]
+    fn min_think_time_test() {
+        let stop = |tc: TimeControl, min_think_time: u64| {
+            let tc_information = TimeControlInformation {
+                min_think_time,
+                stable_pv: true,
+                ..TimeControlInformation::new(0)
+            };
+            (0..=tc.time_left())
+                .find(|&time_spent| tc.time_over(time_spent, &tc_information, 25))
+                .unwrap_or_else(|| tc.time_left())
+        };
+        //A budget of 1975ms, a stable pv would stop after 85% of it
+        let tc = TimeControl::Incremental(60000, 0);
+        assert_eq!(stop(tc, 0), 1678);
+        assert_eq!(stop(tc, 1900), 1900);
+        assert_eq!(stop(TimeControl::Tournament(60000, 0, 20), 2900), 2900);
+        //Never beyond the time normally spent on the move, so a short clock isn't burnt
+        let short = TimeControl::Incremental(3000, 0);
+        assert_eq!(short.min_think_time(1900, 25), 75);
+        assert_eq!(stop(short, 1900), 75);
+        assert_eq!(stop(TimeControl::Tournament(3000, 0, 5), 1900), 575);
+        //Nor beyond the hard limit
+        assert_eq!(stop(TimeControl::MoveTime(300), 1000), 276);
+        assert_eq!(
+            TimeControl::Incremental(600, 1000).min_think_time(1900, 25),
+            500
+        );
+    }
+
+    #[test]
+    fn root_stability_test() {
+        let quiet = |from: u8, to: u8| GameMove {
+            from,
+            to,
+            move_type: GameMoveType::Quiet,
+            piece_type: PieceType::Knight,
+        };
+        let (nf3, nc3) = (quiet(6, 21), quiet(1, 18));
+        //A budget of 2000ms, so new iterations are started within the first 1000ms
+        let tc = TimeControl::Incremental(60000, 0);
+        let soft_over = |stability: &RootStability, time_spent: u64| {
+            tc.soft_time_over(time_spent, 0, 0, 0, stability)
+        };
+        let mut stability = RootStability::default();
+        stability.register_iteration(nf3, false);
+        assert!(!soft_over(&stability, 900));
+        assert!(soft_over(&stability, 1100));
+
+        //A fail high extends the soft limit
+        stability.register_iteration(nf3, true);
+        assert!(!soft_over(&stability, 1300));
+        assert!(soft_over(&stability, 1500));
+
+        //A best move which stays the same over several iterations shortens it
+        while stability.stable_iterations < STABLE_ITERATIONS - 1 {
+            stability.register_iteration(nf3, false);
+            assert!(!soft_over(&stability, 700));
+        }
+        stability.register_iteration(nf3, false);
+        assert_eq!(stability.stable_iterations, STABLE_ITERATIONS);
+        assert!(soft_over(&stability, 700));
+        assert!(!soft_over(&stability, 500));
+
+        //Until the best move changes
+        stability.register_iteration(nc3, false);
+        assert_eq!(stability.stable_iterations, 0);
+        assert!(!soft_over(&stability, 700));
+
+        //Fixed time and infinite searches are never cut short
+        stability.register_iteration(nc3, false);
+        assert!(!TimeControl::MoveTime(1000).soft_time_over(900, 0, 0, 0, &stability));
+        assert!(!TimeControl::Infinite.soft_time_over(u64::MAX / 2, 0, 0, 0, &stability));
+    }
+
+    #[test]
+    fn fail_high_verification_test() {
+        let quiet = |from: u8, to: u8| GameMove {
+            from,
+            to,
+            move_type: GameMoveType::Quiet,
+            piece_type: PieceType::Knight,
+        };
+        let (nf3, nc3) = (quiet(6, 21), quiet(1, 18));
+        let mut stability = RootStability::default();
+        stability.register_iteration(nf3, true);
+        assert!(!stability.switched_on_fail_high());
+        stability.register_iteration(nf3, false);
+        assert!(!stability.switched_on_fail_high());
+        //Nf3 scored 30, then Nc3 took over by failing high to 120
+        let previous_score = 30;
+        stability.register_iteration(nc3, true);
+        assert!(stability.switched_on_fail_high());
+        assert_eq!(stability.previous_best_move, Some(nf3));
+
+        //A spurious fail high collapses in the verification and Nf3 is played
+        assert!(RootStability::verification_fails(previous_score, -40));
+        assert!(RootStability::verification_fails(
+            previous_score,
+            previous_score - VERIFICATION_MARGIN - 1
+        ));
+        //A verified score close to the old one is good enough to keep Nc3
+        assert!(!RootStability::verification_fails(
+            previous_score,
+            previous_score - VERIFICATION_MARGIN
+        ));
+        assert!(!RootStability::verification_fails(previous_score, 120));
+
+        //A change of the best move without a fail high isn't verified
+        stability.register_iteration(nf3, false);
+        assert!(!stability.switched_on_fail_high());
+        assert_eq!(stability.previous_best_move, Some(nc3));
+    }
+}
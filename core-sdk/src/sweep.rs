@@ -0,0 +1,167 @@
+use crate::epd::{analyse_epd, EPDEntry};
+use crate::search::cache::Cache;
+use crate::search::searcher::InterThreadCommunicationSystem;
+use crate::search::SearchParams;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+
+//Same hash size as the EPD runner, the table is cleared before every position
+pub const SWEEP_HASH_SIZE: usize = 8;
+
+pub struct SweepResult {
+    pub value: String,
+    pub nodes: u64,
+    pub positions: usize,
+    pub solved: usize,
+    pub judged: usize, //Positions with a bm or am operation
+}
+
+//Either a comma separated list like `true,false` or an inclusive integer range `from..to` with an optional
+//`:step`, e.g. `1..7:2`
+pub fn parse_sweep_values(spec: &str) -> Result<Vec<String>, String> {
+    if let Some(separator) = spec.find("..") {
+        let (range, step) = match spec[separator + 2..].find(':') {
+            Some(colon) => (
+                &spec[..separator + 2 + colon],
+                &spec[separator + 3 + colon..],
+            ),
+            None => (spec, "1"),
+        };
+        let parse = |value: &str| {
+            value
+                .trim()
+                .parse::<i64>()
+                .map_err(|_| format!("Invalid sweep range: {}", spec))
+        };
+        let (from, to, step) = (
+            parse(&range[..separator])?,
+            parse(&range[separator + 2..])?,
+            parse(step)?,
+        );
+        if step <= 0 || from > to {
+            return Err(format!("Invalid sweep range: {}", spec));
+        }
+        return Ok((from..=to)
+            .step_by(step as usize)
+            .map(|value| value.to_string())
+            .collect());
+    }
+    let values: Vec<String> = spec
+        .split(',')
+        .map(|value| value.trim().to_owned())
+        .filter(|value| !value.is_empty())
+        .collect();
+    if values.is_empty() {
+        return Err(format!("No sweep values given: {}", spec));
+    }
+    Ok(values)
+}
+
+//Searches every position with every value of the parameter, all other search parameters keep their defaults
+pub fn sweep(
+    param: &str,
+    values: &[String],
+    entries: &[EPDEntry],
+    default_depth: usize,
+) -> Result<Vec<SweepResult>, String> {
+    let itcs = Arc::new(InterThreadCommunicationSystem::default());
+    InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+    *itcs.cache() = Cache::with_size_threaded(SWEEP_HASH_SIZE, 1);
+    itcs.uci_options().quiet = true;
+    let mut res = Vec::with_capacity(values.len());
+    for value in values.iter() {
+        let mut search_params = SearchParams::default();
+        search_params.set(param, value)?;
+        itcs.uci_options().search_params = search_params;
+        let mut result = SweepResult {
+            value: value.clone(),
+            nodes: 0,
+            positions: 0,
+            solved: 0,
+            judged: 0,
+        };
+        for entry in entries.iter() {
+            itcs.cache().clear_threaded(1);
+            analyse_epd(&itcs, entry, default_depth);
+            result.nodes += itcs.get_nodes_sum();
+            result.positions += 1;
+            let bestmove = itcs.played_pv().pv.pv[0].expect("No bestmove");
+            if let Some(solved) = entry.solved_by(bestmove) {
+                result.judged += 1;
+                result.solved += solved as usize;
+            }
+        }
+        res.push(result);
+    }
+    Ok(res)
+}
+
+pub fn run_sweep(param: &str, values: &str, path: &str, default_depth: usize) {
+    let values = parse_sweep_values(values).unwrap_or_else(|e| panic!("{}", e));
+    let reader = BufReader::new(File::open(path).expect("Unable to open EPD file"));
+    let entries: Vec<EPDEntry> = reader
+        .lines()
+        .map(|line| line.expect("Unable to read EPD file"))
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| EPDEntry::parse(&line).unwrap_or_else(|e| panic!("{}", e)))
+        .collect();
+    let results =
+        sweep(param, &values, &entries, default_depth).unwrap_or_else(|e| panic!("{}", e));
+    for result in results.iter() {
+        println!(
+            "{} {}: nodes {} solved {}/{} positions {}",
+            param, result.value, result.nodes, result.solved, result.judged, result.positions
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_sweep_values, sweep};
+    use crate::epd::EPDEntry;
+    use crate::search::SearchParams;
+
+    #[test]
+    fn sweep_test() {
+        assert_eq!(
+            parse_sweep_values("true, false").unwrap(),
+            ["true", "false"]
+        );
+        assert_eq!(parse_sweep_values("1..7:2").unwrap(), ["1", "3", "5", "7"]);
+        assert_eq!(parse_sweep_values("-1..1").unwrap(), ["-1", "0", "1"]);
+        assert!(parse_sweep_values("3..1").is_err());
+        assert!(parse_sweep_values("1..3:0").is_err());
+        assert!(parse_sweep_values(",").is_err());
+
+        let mut search_params = SearchParams::default();
+        assert!(search_params.set("LMR", "false").is_ok());
+        assert!(!search_params.late_move_reductions);
+        assert!(search_params.set("AspirationDelta", "0").is_ok());
+        assert_eq!(search_params.aspiration_delta, 1);
+        assert!(search_params.set("LMR", "7").is_err());
+        assert!(search_params.set("NoSuchParam", "1").is_err());
+
+        let entries: Vec<EPDEntry> = [
+            "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - bm Qxf7#; id \"mate\";",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - am a4; id \"start\";",
+            "8/5pk1/6p1/8/3R4/6P1/5PK1/8 b - - id \"endgame\";",
+        ]
+        .iter()
+        .map(|line| EPDEntry::parse(line).unwrap())
+        .collect();
+        let values = parse_sweep_values("true,false").unwrap();
+        let results = sweep("LMR", &values, &entries, 4).unwrap();
+        assert_eq!(results.len(), 2);
+        for (result, value) in results.iter().zip(values.iter()) {
+            assert_eq!(&result.value, value);
+            assert_eq!(result.positions, 3);
+            assert_eq!(result.judged, 2);
+            assert_eq!(result.solved, 2);
+            assert!(result.nodes > 0);
+        }
+        //Without reductions the same depth needs more nodes
+        assert!(results[1].nodes > results[0].nodes);
+        assert!(sweep("NoSuchParam", &values, &entries, 4).is_err());
+    }
+}
@@ -1,8 +1,16 @@
 use crate::pgn::pgn_reader::{GameParser, PGNParser};
 use core_sdk::board_representation::game_state::{GameMove, GameState};
+use core_sdk::move_generation::makemove::make_move;
 use core_sdk::move_generation::movegen;
+use core_sdk::search::cache::CacheEntry;
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{BufReader, Error, ErrorKind, Read, Write};
+
+//Binary opening database: magic, version, number of games, then per game the number of plies followed by
+//its moves from the standard position in the 16 bit encoding of the transposition table, and a checksum
+//over everything before it. All numbers are little endian
+pub const OPENING_DB_MAGIC: [u8; 4] = *b"FCOB";
+pub const OPENING_DB_VERSION: u32 = 1;
 
 pub fn load_db_until(db: &str, until: usize) -> (Vec<GameState>, Vec<Vec<GameMove>>) {
     let movelist = movegen::MoveList::default();
@@ -25,3 +33,72 @@ pub fn load_db_until(db: &str, until: usize) -> (Vec<GameState>, Vec<Vec<GameMov
     }
     (res, res_mvs)
 }
+
+fn checksum(bytes: &[u8]) -> u64 {
+    //FNV-1a
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+pub fn save_opening_db(path: &str, sequences: &[Vec<GameMove>]) -> std::io::Result<()> {
+    let mut bytes = Vec::with_capacity(16 + sequences.len() * 20);
+    bytes.extend_from_slice(&OPENING_DB_MAGIC);
+    bytes.extend_from_slice(&OPENING_DB_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&(sequences.len() as u32).to_le_bytes());
+    for sequence in sequences.iter() {
+        bytes.extend_from_slice(&(sequence.len() as u16).to_le_bytes());
+        for &mv in sequence.iter() {
+            bytes.extend_from_slice(&CacheEntry::mv_to_u16(mv).to_le_bytes());
+        }
+    }
+    let checksum = checksum(&bytes);
+    bytes.extend_from_slice(&checksum.to_le_bytes());
+    File::create(path)?.write_all(&bytes)
+}
+
+//Returns the same positions and move sequences as load_db_until did for the games that were saved
+pub fn load_opening_db(path: &str) -> std::io::Result<(Vec<GameState>, Vec<Vec<GameMove>>)> {
+    let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, format!("{}: {}", path, msg));
+    let mut bytes = Vec::new();
+    File::open(path)?.read_to_end(&mut bytes)?;
+    if bytes.len() < 20 || bytes[..4] != OPENING_DB_MAGIC {
+        return Err(invalid("not an opening database"));
+    }
+    let (payload, stored_checksum) = bytes.split_at(bytes.len() - 8);
+    let mut stored = [0u8; 8];
+    stored.copy_from_slice(stored_checksum);
+    if checksum(payload) != u64::from_le_bytes(stored) {
+        return Err(invalid("checksum mismatch"));
+    }
+    let mut offset = 4;
+    let mut next = |len: usize| {
+        let mut value = 0u32;
+        for i in 0..len {
+            value |= u32::from(*payload.get(offset + i)?) << (8 * i);
+        }
+        offset += len;
+        Some(value)
+    };
+    let version = next(4).unwrap();
+    if version != OPENING_DB_VERSION {
+        return Err(invalid(&format!("unsupported version {}", version)));
+    }
+    let games = next(4).ok_or_else(|| invalid("truncated"))? as usize;
+    let mut res: Vec<GameState> = Vec::with_capacity(games);
+    let mut res_mvs = Vec::with_capacity(games);
+    for _ in 0..games {
+        let plies = next(2).ok_or_else(|| invalid("truncated"))? as usize;
+        let mut state = GameState::standard();
+        let mut sequence = Vec::with_capacity(plies);
+        for _ in 0..plies {
+            let mv =
+                CacheEntry::u16_to_mv(next(2).ok_or_else(|| invalid("truncated"))? as u16, &state);
+            state = make_move(&state, mv);
+            sequence.push(mv);
+        }
+        res.push(state);
+        res_mvs.push(sequence);
+    }
+    Ok((res, res_mvs))
+}
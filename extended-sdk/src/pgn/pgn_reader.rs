@@ -23,31 +23,21 @@ impl Iterator for GameParser {
         match next {
             None => None,
             Some(res) => {
-                let game = res.replace("\r", "").replace("\n", " ");
-                if game.contains("--") || game.contains('*') || game.contains("..") {
+                let tokens = movetext_tokens(&res);
+                if tokens.is_empty() || tokens.iter().any(|token| token == "--" || token == "*") {
                     //Invalid state
                     return Some((vec_res, vec_gs, -2));
                 }
-                //log(&format!("{}\n", game));
-                let moves = game.split(' ').collect::<Vec<&str>>();
-                for move_str in moves.iter().take(moves.len() - 2) {
-                    let mut move_str = (*move_str).to_string();
-                    if move_str.contains('.') {
-                        move_str = move_str.rsplit('.').collect::<Vec<&str>>()[0].to_string();
-                    }
-                    if move_str.is_empty() {
-                        continue;
-                    }
-                    //println!("{} || len: {}", move_str, move_str.len());
+                let (last_elem, moves) = tokens.split_last().unwrap();
+                for move_str in moves.iter() {
                     let last_state = &vec_gs[vec_gs.len() - 1];
-                    let parsed_move = parse_move(last_state, &move_str, &mut self.move_list);
+                    let parsed_move = parse_move(last_state, move_str, &mut self.move_list);
                     vec_gs.push(parsed_move.1);
                     vec_res.push(parsed_move.0);
                     if self.is_opening && vec_res.len() == self.opening_load_untilply {
                         break;
                     }
                 }
-                let last_elem = moves[moves.len() - 2];
                 let mut score = 0;
                 if last_elem == "1-0" {
                     score = 1;
@@ -62,6 +52,46 @@ impl Iterator for GameParser {
     }
 }
 
+//Splits movetext into its SAN moves followed by the result. Comments, variations, NAGs,
+//move numbers and annotation symbols like `!?` are skipped
+pub fn movetext_tokens(movetext: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut variation_depth = 0;
+    let mut chars = movetext.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                chars.by_ref().find(|&c| c == '}');
+            }
+            ';' => {
+                chars.by_ref().find(|&c| c == '\n');
+            }
+            '(' => variation_depth += 1,
+            ')' => variation_depth -= 1,
+            _ if variation_depth > 0 => continue,
+            _ if c.is_whitespace() => {}
+            _ => {
+                current.push(c);
+                continue;
+            }
+        }
+        push_token(&mut tokens, &mut current);
+    }
+    push_token(&mut tokens, &mut current);
+    tokens
+}
+
+fn push_token(tokens: &mut Vec<String>, current: &mut String) {
+    //`12.e4` and `12...e5` carry the move number in front of the move
+    let token = current.rsplit('.').next().unwrap_or("");
+    let token = token.trim_end_matches(['!', '?']);
+    if !token.is_empty() && !token.starts_with('$') {
+        tokens.push(token.to_owned());
+    }
+    current.clear();
+}
+
 pub fn find_castle(
     movelist: &movegen::MoveList,
     g: &GameState,
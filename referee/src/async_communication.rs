@@ -1,17 +1,28 @@
 use log::{info, warn};
+use std::collections::VecDeque;
 use std::time::{Duration, Instant};
 use tokio::prelude::*;
 use tokio::time::timeout;
 
-pub async fn write_all<T: AsyncWrite + Unpin>(stdin: &mut T, msg: &str) {
-    stdin
-        .write_all(msg.as_bytes())
-        .await
-        .unwrap_or_else(|msg| warn!("Could not write: {:?}", msg));
-    stdin
-        .flush()
-        .await
-        .unwrap_or_else(|msg| warn!("Could not flush: {:?}", msg));
+//Lines an engine sent last, kept for the error log
+pub const KEPT_LINES: usize = 10;
+
+//Why the expected line didn't arrive
+#[derive(Debug, PartialEq)]
+pub enum ReadFailure {
+    TimedOut,
+    Closed,
+}
+
+pub async fn write_all<T: AsyncWrite + Unpin>(stdin: &mut T, msg: &str) -> std::io::Result<()> {
+    let res = match stdin.write_all(msg.as_bytes()).await {
+        Ok(()) => stdin.flush().await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = &res {
+        warn!("Could not write: {:?}", e);
+    }
+    res
 }
 
 pub async fn stderr_listener<T: AsyncBufRead + Unpin>(mut stderr: T) {
@@ -32,16 +43,20 @@ pub async fn expect_output<T: AsyncBufRead + Unpin>(
     starts_with: &str,
     time_frame: u64,
     output: &mut T,
-) -> (Option<String>, usize) {
-    let res = expect_output_and_listen_for_info(starts_with, "", time_frame, output).await;
+    received: &mut VecDeque<String>,
+) -> (Result<String, ReadFailure>, usize) {
+    let res =
+        expect_output_and_listen_for_info(starts_with, "", time_frame, output, received).await;
     (res.0, res.2)
 }
+//Every line read is also appended to received, which keeps the last KEPT_LINES
 pub async fn expect_output_and_listen_for_info<T: AsyncBufRead + Unpin>(
     starts_with: &str,
     info_starts_with: &str,
     time_frame: u64,
     output: &mut T,
-) -> (Option<String>, String, usize) {
+    received: &mut VecDeque<String>,
+) -> (Result<String, ReadFailure>, String, usize) {
     let now = Instant::now();
     let mut info = String::new();
     let res = timeout(Duration::from_millis(time_frame), async {
@@ -50,20 +65,24 @@ pub async fn expect_output_and_listen_for_info<T: AsyncBufRead + Unpin>(
             warn!("Could not read next line from reader: {:?}", msg);
             None
         }) {
+            if received.len() == KEPT_LINES {
+                received.pop_front();
+            }
+            received.push_back(line.clone());
             if line.starts_with(info_starts_with) {
                 info.push_str(&format!("{}\n", line));
             }
             if line.starts_with(starts_with) {
-                return Some(line);
+                return Ok(line);
             }
         }
-        None
+        Err(ReadFailure::Closed)
     })
     .await;
     let time_spent = Instant::now().duration_since(now).as_millis() as usize;
     if let Ok(s) = res {
         (s, info, time_spent)
     } else {
-        (None, info, time_spent)
+        (Err(ReadFailure::TimedOut), info, time_spent)
     }
 }
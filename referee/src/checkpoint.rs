@@ -0,0 +1,250 @@
+use crate::engine::{Engine, EngineResult, PlayTask, TaskResult};
+use crate::queue::ThreadSafeQueue;
+use core_sdk::board_representation::game_state::*;
+use core_sdk::move_generation::makemove::make_move;
+use core_sdk::move_generation::movegen;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+pub const CHECKPOINT_PATH: &str = "referee_checkpoint.json";
+
+//A game of the run, the gauntlet engine plays against the enemy with the given id
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct TaskRecord {
+    pub id: usize,
+    pub opening_sequence: Vec<String>, //UCI moves from the starting position
+    pub p1_is_white: bool,
+    pub enemy: usize,
+}
+
+impl TaskRecord {
+    pub fn from_task(task: &PlayTask) -> Self {
+        TaskRecord {
+            id: task.id,
+            opening_sequence: task
+                .opening_sequence
+                .iter()
+                .map(|mv| format!("{:?}", mv))
+                .collect(),
+            p1_is_white: task.p1_is_white,
+            enemy: task.engine2.id,
+        }
+    }
+
+    pub fn to_task(&self, gauntlet_engine: &Engine, enemies: &[Engine]) -> PlayTask {
+        let mut opening = GameState::standard();
+        let mut opening_sequence = Vec::with_capacity(self.opening_sequence.len());
+        let mut movelist = movegen::MoveList::default();
+        for uci in self.opening_sequence.iter() {
+            movegen::generate_moves(&opening, false, &mut movelist);
+            let mv = movelist
+                .move_list
+                .iter()
+                .map(|gmv| gmv.0)
+                .find(|mv| format!("{:?}", mv) == *uci)
+                .unwrap_or_else(|| panic!("Invalid opening move {} in checkpoint", uci));
+            opening = make_move(&opening, mv);
+            opening_sequence.push(mv);
+        }
+        PlayTask {
+            opening,
+            opening_sequence,
+            p1_is_white: self.p1_is_white,
+            id: self.id,
+            engine1: gauntlet_engine.clone(),
+            engine2: enemies[self.enemy].clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct ResultRecord {
+    pub id: usize,
+    pub enemy: usize,
+    pub engine1: EngineResult,
+    pub engine2: EngineResult,
+}
+
+impl ResultRecord {
+    pub fn from_result(result: &TaskResult) -> Self {
+        ResultRecord {
+            id: result.task.id,
+            enemy: result.task.engine2.id,
+            engine1: result.task.engine1.result(),
+            engine2: result.task.engine2.result(),
+        }
+    }
+
+    pub fn add_to(&self, gauntlet_engine: &mut Engine, enemies: &mut [Engine]) {
+        gauntlet_engine.add(&self.engine1);
+        enemies[self.enemy].add(&self.engine2);
+    }
+}
+
+//Everything needed to continue an interrupted run: the games which haven't finished, including the ones being
+//played at the time, and the results of the finished ones
+#[derive(Serialize, Deserialize, Default)]
+pub struct Checkpoint {
+    pub remaining: Vec<TaskRecord>,
+    pub results: Vec<ResultRecord>,
+}
+
+impl Checkpoint {
+    pub fn new(queue: &ThreadSafeQueue<PlayTask>) -> Self {
+        Checkpoint {
+            remaining: queue.map(TaskRecord::from_task),
+            results: Vec::new(),
+        }
+    }
+
+    pub fn finish(&mut self, result: ResultRecord) {
+        self.remaining.retain(|task| task.id != result.id);
+        self.results.push(result);
+    }
+
+    //The engines have to be fresh, the tasks start with the statistics of the engines they are cloned from
+    pub fn tasks(&self, gauntlet_engine: &Engine, enemies: &[Engine]) -> ThreadSafeQueue<PlayTask> {
+        ThreadSafeQueue::new(
+            self.remaining
+                .iter()
+                .map(|task| task.to_task(gauntlet_engine, enemies))
+                .collect(),
+        )
+    }
+
+    pub fn aggregate(&self, gauntlet_engine: &mut Engine, enemies: &mut [Engine]) {
+        for result in self.results.iter() {
+            result.add_to(gauntlet_engine, enemies);
+        }
+    }
+
+    //Written to a temporary file first, so a crash while saving doesn't destroy the last checkpoint
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let tmp_path = format!("{}.tmp", path);
+        fs::write(&tmp_path, serde_json::to_string(self)?)?;
+        fs::rename(&tmp_path, path)
+    }
+
+    pub fn load(path: &str) -> Self {
+        let content = fs::read_to_string(path).expect("Unable to read checkpoint file!");
+        serde_json::from_str(&content).expect("Invalid checkpoint file!")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Checkpoint, ResultRecord, TaskRecord};
+    use crate::engine::{Engine, EngineResult, EngineStats};
+    use crate::queue::ThreadSafeQueue;
+    use core_sdk::board_representation::game_state::GameState;
+    use core_sdk::move_generation::makemove::make_move;
+    use core_sdk::search::timecontrol::TimeControl;
+    use std::collections::{HashMap, VecDeque};
+
+    fn engine(name: &str, id: usize) -> Engine {
+        Engine {
+            name: name.to_owned(),
+            path: format!("./{}", name),
+            id,
+            wins: 0,
+            draws: 0,
+            losses: 0,
+            disqs: 0,
+            time_control: TimeControl::Incremental(10000, 100),
+            node_limit: None,
+            stats: EngineStats::default(),
+            uci_options: HashMap::new(),
+            last_lines: VecDeque::new(),
+        }
+    }
+
+    fn game_result(outcome: usize, seed: usize) -> EngineResult {
+        EngineResult {
+            wins: (outcome == 0) as usize,
+            draws: (outcome == 1) as usize,
+            losses: (outcome == 2) as usize,
+            disqs: (seed == 7) as usize,
+            stats: EngineStats {
+                moves_played: 30 + seed % 50,
+                avg_depth: 9.25 + (seed % 5) as f64 / 3.,
+                avg_nps: 1_234_567. / (1 + seed % 3) as f64,
+                avg_nodes: 98_765.4 + seed as f64,
+                avg_timeleft: 2_000. / (1 + seed % 4) as f64,
+            },
+        }
+    }
+
+    #[test]
+    fn checkpoint_test() {
+        let gauntlet = engine("gauntlet", 999);
+        let enemies = vec![engine("enemy0", 0), engine("enemy1", 1)];
+        let mut tasks = Vec::new();
+        for id in 0..20 {
+            tasks.push(
+                TaskRecord {
+                    id,
+                    opening_sequence: vec!["e2e4".to_owned(), "e7e5".to_owned()],
+                    p1_is_white: id % 2 == 0,
+                    enemy: id / 2 % 2,
+                }
+                .to_task(&gauntlet, &enemies),
+            );
+        }
+        let mut checkpoint = Checkpoint::new(&ThreadSafeQueue::new(tasks));
+        assert_eq!(checkpoint.remaining.len(), 20);
+
+        //Twelve games finished in a different order than they were started
+        let (mut live_gauntlet, mut live_enemies) = (gauntlet.clone(), enemies.clone());
+        for id in (0..12).rev() {
+            let outcome = id * 5 % 3;
+            let result = ResultRecord {
+                id,
+                enemy: id / 2 % 2,
+                engine1: game_result(outcome, id),
+                engine2: game_result(2 - outcome, id + 3),
+            };
+            result.add_to(&mut live_gauntlet, &mut live_enemies);
+            checkpoint.finish(result);
+        }
+        let path = std::env::temp_dir().join(format!("referee_checkpoint_{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        checkpoint.save(path).unwrap();
+
+        let reloaded = Checkpoint::load(path);
+        std::fs::remove_file(path).unwrap();
+        assert!(reloaded.remaining == checkpoint.remaining);
+        assert!(reloaded.results == checkpoint.results);
+        let queue = reloaded.tasks(&gauntlet, &enemies);
+        assert_eq!(queue.len(), 8);
+        let task = queue.pop().unwrap();
+        assert_eq!(task.id, 12);
+        assert_eq!(task.engine2.id, 0);
+        let opening = make_move(
+            &make_move(&GameState::standard(), task.opening_sequence[0]),
+            task.opening_sequence[1],
+        );
+        assert_eq!(task.opening.get_hash(), opening.get_hash());
+        assert_eq!(
+            task.opening.get_hash(),
+            GameState::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2")
+                .get_hash()
+        );
+
+        //The same W/D/L and Elo as the interrupted run
+        let (mut resumed_gauntlet, mut resumed_enemies) = (gauntlet.clone(), enemies.clone());
+        reloaded.aggregate(&mut resumed_gauntlet, &mut resumed_enemies);
+        assert_eq!(
+            resumed_gauntlet.get_elo_gain(),
+            live_gauntlet.get_elo_gain()
+        );
+        assert_eq!(resumed_gauntlet.result(), live_gauntlet.result());
+        for (resumed, live) in resumed_enemies.iter().zip(live_enemies.iter()) {
+            assert_eq!(resumed.get_elo_gain(), live.get_elo_gain());
+            assert_eq!(resumed.result(), live.result());
+        }
+        assert_eq!(
+            resumed_gauntlet.wins + resumed_gauntlet.draws + resumed_gauntlet.losses,
+            12
+        );
+    }
+}
@@ -1,11 +1,12 @@
 use crate::async_communication::{
-    expect_output, expect_output_and_listen_for_info, stderr_listener, write_all,
+    expect_output, expect_output_and_listen_for_info, stderr_listener, write_all, ReadFailure,
 };
 use core_sdk::board_representation::game_state::*;
 use core_sdk::move_generation::movegen::MoveList;
 use core_sdk::search::timecontrol::TimeControl;
 use log::{info, warn};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
 use std::fmt::{Display, Formatter, Result};
 use std::process::Stdio;
 use tokio::io::{BufReader, BufWriter};
@@ -13,7 +14,58 @@ use tokio::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command};
 
 pub enum EngineReaction<T> {
     ContinueGame(T),
-    DisqualifyEngine,
+    DisqualifyEngine(Disqualification),
+}
+
+//What went wrong talking to an engine
+#[derive(Debug, PartialEq)]
+pub enum EngineCommError {
+    Timeout(&'static str, u64), //The expected line didn't arrive within the ms
+    BrokenPipe,                 //The engine closed its input or output
+    UnparseableInfo(String),
+    IllegalBestmove(String),
+    NoBestmove(String), //A bestmove line without a move
+    NodeLimitExceeded(u64, u64),
+}
+
+impl Display for EngineCommError {
+    fn fmt(&self, formatter: &mut Formatter) -> Result {
+        match self {
+            EngineCommError::Timeout(expected, time_frame) => write!(
+                formatter,
+                "Timeout, no {} within {}ms",
+                expected, time_frame
+            ),
+            EngineCommError::BrokenPipe => write!(formatter, "Broken pipe"),
+            EngineCommError::UnparseableInfo(line) => {
+                write!(formatter, "Unparseable info line ({})", line)
+            }
+            EngineCommError::IllegalBestmove(line) => {
+                write!(formatter, "Illegal bestmove ({})", line)
+            }
+            EngineCommError::NoBestmove(line) => write!(formatter, "No bestmove in ({})", line),
+            EngineCommError::NodeLimitExceeded(nodes, limit) => write!(
+                formatter,
+                "Searched {} nodes with a limit of {}",
+                nodes, limit
+            ),
+        }
+    }
+}
+
+pub struct Disqualification {
+    pub error: EngineCommError,
+    pub last_lines: Vec<String>, //The lines the engine sent last, oldest first
+}
+
+impl Display for Disqualification {
+    fn fmt(&self, formatter: &mut Formatter) -> Result {
+        write!(formatter, "{}, last lines received:", self.error)?;
+        for line in self.last_lines.iter() {
+            write!(formatter, "\n    {}", line)?;
+        }
+        Ok(())
+    }
 }
 #[derive(Debug)]
 pub enum EngineStatus {
@@ -22,42 +74,52 @@ pub enum EngineStatus {
     ProclaimsDraw,
     ProclaimsNothing,
 }
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum EndConditionInformation {
-    HundredMoveDraw,
-    ThreeFoldRepetition,
-    DrawByadjudication,
-    DrawByMissingPieces,
-    StaleMate,
-    Mate,
-    MateByadjudication,
+    Checkmate,
+    Stalemate,
+    FiftyMoveRule,
+    ThreefoldRepetition,
+    InsufficientMaterial,
+    DrawByAdjudication,
+    DrawByAgreement,
+    WinByAdjudication,
+    MaxGamePlies,
+    Disqualification,
 }
 
 impl Display for EndConditionInformation {
     fn fmt(&self, formatter: &mut Formatter) -> Result {
         let mut res_str: String = String::new();
         res_str.push_str(match *self {
-            EndConditionInformation::HundredMoveDraw => "Hundred Move Draw",
-            EndConditionInformation::ThreeFoldRepetition => "Draw by Three Fold Repetition",
-            EndConditionInformation::DrawByadjudication => "Draw by adjudication",
-            EndConditionInformation::DrawByMissingPieces => "Draw by missing pieces",
-            EndConditionInformation::StaleMate => "Draw by Stalemate",
-            EndConditionInformation::Mate => "Win by Mate",
-            EndConditionInformation::MateByadjudication => "Win by adjudication",
+            EndConditionInformation::Checkmate => "Win by Checkmate",
+            EndConditionInformation::Stalemate => "Draw by Stalemate",
+            EndConditionInformation::FiftyMoveRule => "Draw by Fifty Move Rule",
+            EndConditionInformation::ThreefoldRepetition => "Draw by Threefold Repetition",
+            EndConditionInformation::InsufficientMaterial => "Draw by Insufficient Material",
+            EndConditionInformation::DrawByAdjudication => "Draw by adjudication",
+            EndConditionInformation::DrawByAgreement => "Draw by mutual agreement",
+            EndConditionInformation::WinByAdjudication => "Win by adjudication",
+            EndConditionInformation::MaxGamePlies => "Draw by game length limit",
+            EndConditionInformation::Disqualification => "Disqualification",
         });
         write!(formatter, "{}", res_str)
     }
 }
 
+//Engines only poll their node counter every so often, so allow some overshoot of a node limit
+pub const NODE_LIMIT_TOLERANCE: f64 = 0.1;
+
 pub fn get_elo_gain(p_a: f64) -> f64 {
     -1.0 * (1.0 / p_a - 1.0).ln() * 400.0 / (10.0 as f64).ln()
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
 pub struct EngineStats {
     pub moves_played: usize,
     pub avg_depth: f64,
     pub avg_nps: f64,
+    pub avg_nodes: f64,
     pub avg_timeleft: f64,
 }
 
@@ -65,6 +127,7 @@ impl EngineStats {
     pub fn divide(&mut self) {
         self.avg_depth /= self.moves_played as f64;
         self.avg_nps /= self.moves_played as f64;
+        self.avg_nodes /= self.moves_played as f64;
     }
     pub fn add(&mut self, other: &EngineStats) {
         let sum = (self.moves_played + other.moves_played) as f64;
@@ -72,6 +135,8 @@ impl EngineStats {
             + other.avg_depth * other.moves_played as f64 / sum;
         self.avg_nps = self.avg_nps * self.moves_played as f64 / sum
             + other.avg_nps * other.moves_played as f64 / sum;
+        self.avg_nodes = self.avg_nodes * self.moves_played as f64 / sum
+            + other.avg_nodes * other.moves_played as f64 / sum;
         self.moves_played += other.moves_played;
     }
 }
@@ -82,11 +147,25 @@ impl Default for EngineStats {
             moves_played: 0,
             avg_depth: 0.,
             avg_nps: 0.,
+            avg_nodes: 0.,
             avg_timeleft: 0.,
         }
     }
 }
 
+pub const EVAL_RANDOMNESS_OPTION: &str = "EvalRandomness";
+pub const EVAL_RANDOM_SEED_OPTION: &str = "EvalRandomSeed";
+
+//Every game gets its own random seed, otherwise randomized games would still repeat each other
+pub fn game_option_value(name: &str, value: &str, task_id: usize) -> String {
+    if name == EVAL_RANDOM_SEED_OPTION {
+        let seed = value.parse::<u64>().expect("Invalid EvalRandomSeed");
+        (seed.wrapping_add(task_id as u64) % (i32::MAX as u64 + 1)).to_string()
+    } else {
+        value.to_owned()
+    }
+}
+
 #[derive(Clone)]
 pub struct Engine {
     pub name: String,
@@ -97,12 +176,34 @@ pub struct Engine {
     pub losses: usize,
     pub disqs: usize,
     pub time_control: TimeControl,
+    pub node_limit: Option<u64>,
     pub stats: EngineStats,
     pub uci_options: HashMap<String, String>,
+    pub last_lines: VecDeque<String>,
+}
+
+//The outcome of the games of an engine, without anything needed to run the engine
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct EngineResult {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    pub disqs: usize,
+    pub stats: EngineStats,
 }
 
 impl Engine {
-    pub fn add(&mut self, other: &Engine) {
+    pub fn result(&self) -> EngineResult {
+        EngineResult {
+            wins: self.wins,
+            draws: self.draws,
+            losses: self.losses,
+            disqs: self.disqs,
+            stats: self.stats.clone(),
+        }
+    }
+
+    pub fn add(&mut self, other: &EngineResult) {
         let games = self.wins + self.draws + self.losses;
         let other_games = other.wins + other.draws + other.losses;
         self.stats.add(&other.stats);
@@ -144,11 +245,12 @@ impl Engine {
                     / (self.wins + self.draws + self.losses) as f64,
             ),
             format!(
-                "{:25}disq {} dep {:.2} nps {:.0} time {:.0}",
+                "{:25}disq {} dep {:.2} nps {:.0} nodes {:.0} time {:.0}",
                 self.name,
                 self.disqs,
                 self.stats.avg_depth,
                 self.stats.avg_nps,
+                self.stats.avg_nodes,
                 self.stats.avg_timeleft
             ),
             elo_gain,
@@ -170,14 +272,22 @@ impl Engine {
             losses: 0,
             disqs: 0,
             time_control: tc,
+            node_limit: None,
             stats: EngineStats::default(),
             uci_options: options,
+            last_lines: VecDeque::new(),
         };
         let (mut child, mut input, mut output, err) = res.get_handles().await;
         let err_listener = tokio::spawn(stderr_listener(err));
-        write_all(&mut input, "uci\n").await;
-        let output =
-            expect_output_and_listen_for_info("uciok", "id name", 10000, &mut output).await;
+        let _ = write_all(&mut input, "uci\n").await;
+        let output = expect_output_and_listen_for_info(
+            "uciok",
+            "id name",
+            10000,
+            &mut output,
+            &mut res.last_lines,
+        )
+        .await;
         child
             .kill()
             .unwrap_or_else(|msg| warn!("Could not kill child: {}", msg));
@@ -197,69 +307,82 @@ impl Engine {
         res
     }
 
+    fn disqualify<T>(&self, error: EngineCommError) -> EngineReaction<T> {
+        EngineReaction::DisqualifyEngine(Disqualification {
+            error,
+            last_lines: self.last_lines.iter().cloned().collect(),
+        })
+    }
+
+    fn read_failure<T>(
+        &self,
+        failure: ReadFailure,
+        expected: &'static str,
+        time_frame: u64,
+    ) -> EngineReaction<T> {
+        match failure {
+            ReadFailure::TimedOut => {
+                self.disqualify(EngineCommError::Timeout(expected, time_frame))
+            }
+            ReadFailure::Closed => self.disqualify(EngineCommError::BrokenPipe),
+        }
+    }
+
     pub async fn request_move(
         &mut self,
         position_description: &str,
         go_string: &str,
         stdin: &mut BufWriter<ChildStdin>,
         stdout: &mut BufReader<ChildStdout>,
-        task_id: usize,
         movelist: &MoveList,
-    ) -> EngineReaction<(GameMove, EngineStatus)> {
-        write_all(stdin, position_description).await;
-        let reaction = self.valid_isready_reaction(stdin, stdout, task_id).await;
-        if let EngineReaction::DisqualifyEngine = reaction {
-            return EngineReaction::DisqualifyEngine;
+    ) -> EngineReaction<(GameMove, EngineStatus, MoveRecord)> {
+        if write_all(stdin, position_description).await.is_err() {
+            return self.disqualify(EngineCommError::BrokenPipe);
+        }
+        if let EngineReaction::DisqualifyEngine(disq) =
+            self.valid_isready_reaction(stdin, stdout).await
+        {
+            return EngineReaction::DisqualifyEngine(disq);
+        }
+        if write_all(stdin, go_string).await.is_err() {
+            return self.disqualify(EngineCommError::BrokenPipe);
         }
-        write_all(stdin, go_string).await;
+        let time_left = self.time_control.time_left();
         let output = expect_output_and_listen_for_info(
             "bestmove",
             "info",
-            self.time_control.time_left(),
+            time_left,
             stdout,
+            &mut self.last_lines,
         )
         .await;
-        if output.0.is_none() {
-            info!(
-                "Engine {} didn't send bestmove in time in game {}! It had {}ms left!\n",
-                self.name,
-                task_id,
-                self.time_control.time_left(),
-            );
-            return EngineReaction::DisqualifyEngine;
-        }
-        if output.2 as u64 > self.time_control.time_left() {
-            warn!("Mistake in Referee! Bestmove found but it took longer than time still left ({}) for engine {}! Disqualifying engine illegitimately in game {}\n",self.time_control.time_left(),self.name ,task_id);
-            return EngineReaction::DisqualifyEngine;
+        let line = match output.0 {
+            Ok(line) => line,
+            Err(failure) => return self.read_failure(failure, "bestmove", time_left),
+        };
+        if output.2 as u64 > time_left {
+            warn!("Mistake in Referee! Bestmove found but it took longer than time still left ({}) for engine {}! Disqualifying engine illegitimately\n", time_left, self.name);
+            return self.disqualify(EngineCommError::Timeout("bestmove", time_left));
         }
         self.time_control.update(output.2 as u64, None);
 
         //Parse the move
-        let line = output.0.unwrap();
         let split_line: Vec<&str> = line.split_whitespace().collect();
-        let game_move: GameMove = if split_line[0] == "bestmove" {
-            let mv = GameMove::string_to_move(split_line[1]);
-            let found_move = find_move(mv.0, mv.1, mv.2, &movelist);
-            if found_move.is_none() {
-                info!(
-                    "Engine {} sent illegal move ({}) in game {}\n",
-                    self.name, line, task_id
-                );
-                return EngineReaction::DisqualifyEngine;
-            }
-            found_move.unwrap()
-        } else {
-            info!(
-                "Bestmove wasn't first argument after bestmove keyword! Disqualifiying engine {} in game {}\n",
-                self.name,task_id
-            );
-            return EngineReaction::DisqualifyEngine;
+        if split_line[0] != "bestmove" || split_line.len() < 2 {
+            return self.disqualify(EngineCommError::NoBestmove(line));
+        }
+        let game_move = match find_move(split_line[1], movelist) {
+            Some(mv) => mv,
+            None => return self.disqualify(EngineCommError::IllegalBestmove(line)),
         };
 
         //Get additional info about engine e.g. how deep it saw, nps, and its evaluation
+        let info = match fetch_info(&output.1) {
+            Ok(info) => info,
+            Err(info_line) => return self.disqualify(EngineCommError::UnparseableInfo(info_line)),
+        };
         let mut status = EngineStatus::ProclaimsNothing;
         self.stats.moves_played += 1;
-        let info = fetch_info(&output.1);
         if info.negative_mate_found {
             status = EngineStatus::ProclaimsLoss;
         } else if info.positive_mate_found {
@@ -282,42 +405,74 @@ impl Engine {
         if let Some(nps) = info.nps {
             self.stats.avg_nps += nps as f64;
         }
+        if let Some(nodes) = info.nodes {
+            self.stats.avg_nodes += nodes as f64;
+            if self.exceeds_node_limit(nodes) {
+                return self.disqualify(EngineCommError::NodeLimitExceeded(
+                    nodes,
+                    self.node_limit.unwrap(),
+                ));
+            }
+        }
+
+        let record = MoveRecord {
+            mv: split_line[1].to_owned(),
+            engine: self.name.clone(),
+            depth: info.depth,
+            cp_score: info.cp_score,
+            mate_score: info.mate_score,
+            time: output.2 as u64,
+        };
+        EngineReaction::ContinueGame((game_move, status, record))
+    }
 
-        EngineReaction::ContinueGame((game_move, status))
+    //Searching fewer nodes is fine, e.g. for forced moves or found mates
+    pub fn exceeds_node_limit(&self, nodes: u64) -> bool {
+        match self.node_limit {
+            Some(limit) => nodes as f64 > limit as f64 * (1. + NODE_LIMIT_TOLERANCE),
+            None => false,
+        }
     }
 
     pub async fn valid_isready_reaction(
-        &self,
+        &mut self,
         stdin: &mut BufWriter<ChildStdin>,
         stdout: &mut BufReader<ChildStdout>,
-        task_id: usize,
     ) -> EngineReaction<()> {
-        write_all(stdin, "isready\n").await;
-        let output = expect_output("readyok", 10000, stdout).await;
-        if output.0.is_none() {
-            info!("Engine {} didn't readyok in game {}!\n", self.name, task_id);
-            return EngineReaction::DisqualifyEngine;
+        if write_all(stdin, "isready\n").await.is_err() {
+            return self.disqualify(EngineCommError::BrokenPipe);
+        }
+        let output = expect_output("readyok", 10000, stdout, &mut self.last_lines).await;
+        if let Err(failure) = output.0 {
+            return self.read_failure(failure, "readyok", 10000);
         }
         EngineReaction::ContinueGame(())
     }
     pub async fn valid_uci_isready_reaction(
-        &self,
+        &mut self,
         stdin: &mut BufWriter<ChildStdin>,
         stdout: &mut BufReader<ChildStdout>,
         task_id: usize,
     ) -> EngineReaction<()> {
-        write_all(stdin, "uci\n").await;
-        let output = expect_output("uciok", 10000, stdout).await;
-        if output.0.is_none() {
-            info!("Engine {} didn't uciok in game {}!\n", self.name, task_id);
-            return EngineReaction::DisqualifyEngine;
+        if write_all(stdin, "uci\n").await.is_err() {
+            return self.disqualify(EngineCommError::BrokenPipe);
+        }
+        let output = expect_output("uciok", 10000, stdout, &mut self.last_lines).await;
+        if let Err(failure) = output.0 {
+            return self.read_failure(failure, "uciok", 10000);
         }
         let mut msg = String::new();
         for pair in &self.uci_options {
-            msg.push_str(&format!("setoption name {} value {}\n", pair.0, pair.1));
+            msg.push_str(&format!(
+                "setoption name {} value {}\n",
+                pair.0,
+                game_option_value(pair.0, pair.1, task_id)
+            ));
+        }
+        if write_all(stdin, &msg).await.is_err() {
+            return self.disqualify(EngineCommError::BrokenPipe);
         }
-        write_all(stdin, &msg).await;
-        self.valid_isready_reaction(stdin, stdout, task_id).await
+        self.valid_isready_reaction(stdin, stdout).await
     }
 
     pub async fn get_handles(
@@ -340,87 +495,98 @@ impl Engine {
     }
 }
 
-pub fn find_move(
-    from: usize,
-    to: usize,
-    promo_pieces: Option<PieceType>,
-    move_list: &MoveList,
-) -> Option<GameMove> {
-    for gmv in move_list.move_list.iter() {
-        let mv = gmv.0;
-        if mv.from as usize == from && mv.to as usize == to {
-            if let GameMoveType::Promotion(ps, _) = mv.move_type {
-                match promo_pieces {
-                    Some(piece) => {
-                        if piece != ps {
-                            continue;
-                        }
-                    }
-                    None => {
-                        continue;
-                    }
-                }
-            }
-            return Some(mv);
-        }
-    }
-    None
+//Looks the move up by its UCI notation, so any garbage sent as a move just isn't found
+pub fn find_move(uci: &str, move_list: &MoveList) -> Option<GameMove> {
+    move_list
+        .move_list
+        .iter()
+        .map(|gmv| gmv.0)
+        .find(|mv| format!("{:?}", mv) == uci)
 }
 
-pub fn fetch_info(info: &str) -> UCIInfo {
-    let split_line: Vec<&str> = info.split_whitespace().collect();
+//Info strings are free text and skipped, a known keyword has to be followed by a valid value. Returns the
+//offending line otherwise
+pub fn fetch_info(info: &str) -> std::result::Result<UCIInfo, String> {
     let mut depth = None;
     let mut nps = None;
+    let mut nodes = None;
     let mut cp_score = None;
+    let mut mate_score = None;
     let mut positive_mate_found = false;
     let mut negative_mate_found = false;
-    let mut index = 0;
-    while index < split_line.len() {
-        match split_line[index] {
-            "depth" => {
-                depth = split_line[index + 1].parse::<usize>().ok();
-                index += 1;
-            }
-            "cp" => {
-                cp_score = split_line[index + 1].parse::<isize>().ok();
-                index += 1;
-            }
-            "nps" => {
-                nps = split_line[index + 1].parse::<usize>().ok();
-                index += 1;
-            }
-            "mate" => {
-                let mate_score = match split_line[index + 1].parse::<isize>() {
-                    Ok(s) => s,
-                    _ => 0,
-                };
-                if mate_score < 0 {
-                    negative_mate_found = true;
-                } else if mate_score > 0 {
-                    positive_mate_found = true;
+    for line in info.lines() {
+        let split_line: Vec<&str> = line.split_whitespace().collect();
+        if split_line.get(1) == Some(&"string") {
+            continue;
+        }
+        let mut index = 0;
+        while index < split_line.len() {
+            let value = split_line.get(index + 1).copied().unwrap_or("");
+            match split_line[index] {
+                "depth" => {
+                    depth = Some(value.parse::<usize>().map_err(|_| line.to_owned())?);
+                    index += 1;
+                }
+                "cp" => {
+                    cp_score = Some(value.parse::<isize>().map_err(|_| line.to_owned())?);
+                    index += 1;
+                }
+                "nps" => {
+                    nps = Some(value.parse::<usize>().map_err(|_| line.to_owned())?);
+                    index += 1;
                 }
+                "nodes" => {
+                    nodes = Some(value.parse::<u64>().map_err(|_| line.to_owned())?);
+                    index += 1;
+                }
+                "mate" => {
+                    let mate = value.parse::<isize>().map_err(|_| line.to_owned())?;
+                    if mate < 0 {
+                        negative_mate_found = true;
+                    } else if mate > 0 {
+                        positive_mate_found = true;
+                    }
+                    mate_score = Some(mate);
+                    index += 1;
+                }
+                _ => {}
             }
-            _ => {}
+            index += 1;
         }
-        index += 1;
     }
-    UCIInfo {
+    Ok(UCIInfo {
         depth,
         nps,
+        nodes,
         cp_score,
+        mate_score,
         positive_mate_found,
         negative_mate_found,
-    }
+    })
 }
 
 pub struct UCIInfo {
     depth: Option<usize>,
     nps: Option<usize>,
+    nodes: Option<u64>,
     cp_score: Option<isize>,
+    mate_score: Option<isize>,
     positive_mate_found: bool,
     negative_mate_found: bool,
 }
 
+//What an engine reported for one of its moves, for analysing lost games afterwards. The score is from the
+//engine's point of view and the time in ms
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct MoveRecord {
+    pub mv: String,
+    pub engine: String,
+    pub depth: Option<usize>,
+    pub cp_score: Option<isize>,
+    pub mate_score: Option<isize>,
+    pub time: u64,
+}
+
 pub struct PlayTask {
     pub opening: GameState,
     pub opening_sequence: Vec<GameMove>,
@@ -434,6 +600,7 @@ pub struct TaskResult {
     pub task: PlayTask,
     pub endcondition: Option<EndConditionInformation>,
     pub move_sequence: Vec<GameMove>,
+    pub move_records: Vec<MoveRecord>,
     pub final_status: GameResult,
 }
 
@@ -442,8 +609,20 @@ impl TaskResult {
         mut task: PlayTask,
         p1: bool,
         move_sequence: Vec<GameMove>,
+        move_records: Vec<MoveRecord>,
         final_status: GameResult,
+        reason: &Disqualification,
     ) -> Self {
+        info!(
+            "Disqualifying engine {} in game {}: {}\n",
+            if p1 {
+                &task.engine1.name
+            } else {
+                &task.engine2.name
+            },
+            task.id,
+            reason
+        );
         if p1 {
             task.engine1.disqs += 1;
         } else {
@@ -453,9 +632,198 @@ impl TaskResult {
         task.engine2.stats.divide();
         TaskResult {
             task,
-            endcondition: None,
+            endcondition: Some(EndConditionInformation::Disqualification),
             move_sequence,
+            move_records,
             final_status,
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{Engine, EngineCommError, EngineReaction, EngineStats, EngineStatus, MoveRecord};
+    use core_sdk::board_representation::game_state::{GameMove, GameState};
+    use core_sdk::move_generation::movegen;
+    use core_sdk::search::timecontrol::TimeControl;
+    use std::collections::{HashMap, VecDeque};
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    //Answers every go command with the given shell commands, the last position command is kept in $pos
+    pub(crate) fn mock_engine(name: &str, go_reply: &str) -> Engine {
+        let mut script = String::from("#!/bin/sh\nwhile read -r line; do\ncase \"$line\" in\n");
+        script.push_str(
+            "uci) echo uciok ;;\nisready) echo readyok ;;\nposition*) pos=\"$line\" ;;\ngo*)\n",
+        );
+        script.push_str(go_reply);
+        script.push_str(" ;;\nquit) exit 0 ;;\nesac\ndone\n");
+        let path =
+            std::env::temp_dir().join(format!("referee_mock_{}_{}", name, std::process::id()));
+        fs::write(&path, script).expect("Could not write mock engine");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+            .expect("Could not make mock engine executable");
+        Engine {
+            name: name.to_owned(),
+            path: path.to_str().unwrap().to_owned(),
+            id: 0,
+            wins: 0,
+            draws: 0,
+            losses: 0,
+            disqs: 0,
+            time_control: TimeControl::Incremental(10000, 0),
+            node_limit: Some(1000),
+            stats: EngineStats::default(),
+            uci_options: HashMap::new(),
+            last_lines: VecDeque::new(),
+        }
+    }
+
+    //The given node counts, the last one being the final info line
+    fn node_reply(nodes: &[u64]) -> String {
+        let mut reply = String::new();
+        for (depth, n) in nodes.iter().enumerate() {
+            reply.push_str(&format!(
+                "echo \"info depth {} nodes {} nps 100000 score cp 20\"\n",
+                depth + 1,
+                n
+            ));
+        }
+        reply.push_str("echo \"bestmove e2e4\"");
+        reply
+    }
+
+    async fn request_startpos_move(
+        engine: &mut Engine,
+    ) -> EngineReaction<(GameMove, EngineStatus, MoveRecord)> {
+        let state = GameState::standard();
+        let mut movelist = movegen::MoveList::default();
+        movegen::generate_moves(&state, false, &mut movelist);
+        let (mut child, mut stdin, mut stdout, _) = engine.get_handles().await;
+        let reaction = engine
+            .request_move(
+                "position startpos\n",
+                "go wtime 10000 btime 10000 nodes 1000\n",
+                &mut stdin,
+                &mut stdout,
+                &movelist,
+            )
+            .await;
+        let _ = child.kill();
+        fs::remove_file(&engine.path).expect("Could not remove mock engine");
+        reaction
+    }
+
+    pub(crate) fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new()
+            .basic_scheduler()
+            .enable_all()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn node_limit_test() {
+        runtime().block_on(async {
+            let mut obeying = mock_engine("obeying", &node_reply(&[300, 1050]));
+            let reaction = request_startpos_move(&mut obeying).await;
+            assert!(matches!(reaction, EngineReaction::ContinueGame(_)));
+            obeying.stats.divide();
+            assert_eq!(obeying.stats.moves_played, 1);
+            assert!((obeying.stats.avg_nodes - 1050.).abs() < 1e-9);
+
+            //Only the final info line counts
+            let mut ignoring = mock_engine("ignoring", &node_reply(&[900, 5000]));
+            match request_startpos_move(&mut ignoring).await {
+                EngineReaction::DisqualifyEngine(disq) => {
+                    assert_eq!(disq.error, EngineCommError::NodeLimitExceeded(5000, 1000))
+                }
+                _ => panic!("Node limit ignored"),
+            }
+
+            let mut unlimited = mock_engine("unlimited", &node_reply(&[5000]));
+            unlimited.node_limit = None;
+            let reaction = request_startpos_move(&mut unlimited).await;
+            assert!(matches!(reaction, EngineReaction::ContinueGame(_)));
+        });
+    }
+
+    #[test]
+    fn comm_error_test() {
+        runtime().block_on(async {
+            let failure =
+                |reaction: EngineReaction<(GameMove, EngineStatus, MoveRecord)>| match reaction {
+                    EngineReaction::DisqualifyEngine(disq) => disq,
+                    _ => panic!("Engine wasn't disqualified"),
+                };
+
+            //Sends neither a bestmove nor closes its output
+            let mut silent = mock_engine("silent", "echo \"info depth 1 nodes 10\"");
+            silent.time_control = TimeControl::Incremental(300, 0);
+            let disq = failure(request_startpos_move(&mut silent).await);
+            assert_eq!(disq.error, EngineCommError::Timeout("bestmove", 300));
+            assert_eq!(disq.last_lines, ["readyok", "info depth 1 nodes 10"]);
+
+            let mut crashing = mock_engine("crashing", "echo \"info depth 3 score cp 5\"; exit 1");
+            let disq = failure(request_startpos_move(&mut crashing).await);
+            assert_eq!(disq.error, EngineCommError::BrokenPipe);
+            assert_eq!(disq.last_lines.last().unwrap(), "info depth 3 score cp 5");
+
+            let mut garbled = mock_engine(
+                "garbled",
+                "echo \"info depth 2 nodes 1x0 score cp 5\"; echo \"bestmove e2e4\"",
+            );
+            let disq = failure(request_startpos_move(&mut garbled).await);
+            assert_eq!(
+                disq.error,
+                EngineCommError::UnparseableInfo("info depth 2 nodes 1x0 score cp 5".to_owned())
+            );
+
+            let mut illegal = mock_engine("illegal", "echo \"bestmove e2e5 ponder e7e5\"");
+            let disq = failure(request_startpos_move(&mut illegal).await);
+            assert_eq!(
+                disq.error,
+                EngineCommError::IllegalBestmove("bestmove e2e5 ponder e7e5".to_owned())
+            );
+            let mut nonsense = mock_engine("nonsense", "echo \"bestmove ?\"");
+            let disq = failure(request_startpos_move(&mut nonsense).await);
+            assert_eq!(
+                disq.error,
+                EngineCommError::IllegalBestmove("bestmove ?".to_owned())
+            );
+
+            let mut moveless = mock_engine("moveless", "echo \"bestmove\"");
+            let disq = failure(request_startpos_move(&mut moveless).await);
+            assert_eq!(
+                disq.error,
+                EngineCommError::NoBestmove("bestmove".to_owned())
+            );
+            assert_eq!(
+                format!("{}", disq),
+                "No bestmove in (bestmove), last lines received:\n    readyok\n    bestmove"
+            );
+
+            //Info strings are free text
+            let mut chatty = mock_engine(
+                "chatty",
+                "echo \"info string depth unknown\"; echo \"info depth 4 score mate 2\"; \
+                 echo \"bestmove e2e4\"",
+            );
+            match request_startpos_move(&mut chatty).await {
+                EngineReaction::ContinueGame((_, status, record)) => {
+                    assert!(matches!(status, EngineStatus::ProclaimsWin));
+                    assert_eq!(
+                        (
+                            record.mv.as_str(),
+                            record.depth,
+                            record.cp_score,
+                            record.mate_score
+                        ),
+                        ("e2e4", Some(4), None, Some(2))
+                    );
+                }
+                _ => panic!("Info string rejected"),
+            }
+        });
+    }
+}
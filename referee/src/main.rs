@@ -1,15 +1,19 @@
+use selfplay::DrawAgreement;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::env;
 use std::fs;
+use training_data::FenFilter;
 
 pub mod async_communication;
+pub mod checkpoint;
 pub mod engine;
 pub mod logging;
 pub mod openings;
 pub mod queue;
 pub mod selfplay;
 pub mod selfplay_splitter;
+pub mod training_data;
 
 //STS
 pub const STS_SUB_SUITS: [&str; 15] = [
@@ -42,6 +46,30 @@ pub struct Config {
     pub timecontrol_engine_inc: u64,
     pub timecontrol_enemies_time: u64,
     pub timecontrol_enemies_inc: u64,
+    #[serde(default)]
+    pub nodes_per_move: Option<u64>,
+    #[serde(default)]
+    pub gen_fens: Option<String>, //Training positions of every finished game are appended to this file
+    #[serde(default)]
+    pub fen_filter: FenFilter,
+    #[serde(default)]
+    pub move_log: Option<String>, //What the engines reported for each move of lost or adjudicated games goes here
+    #[serde(default)]
+    pub eval_randomness: i16, //Random offset of root move scores in cp for opening variety, 0 disables it
+    #[serde(default)]
+    pub eval_random_seed: u64,
+    #[serde(default)]
+    pub random_first_move_seed: Option<u64>, //Replaces the opening databases by one random first move per game pair
+    #[serde(default)]
+    pub partition_queue: bool, //One task queue per processor with work stealing, less lock contention
+    #[serde(default)]
+    pub max_game_plies: Option<usize>, //Games still running after this many plies are adjudicated a draw
+    #[serde(default)]
+    pub draw_agreement: Option<DrawAgreement>, //Adjudicate a draw once both engines keep reporting near zero scores
+    #[serde(default)]
+    pub duplicate_deviation_ply: Option<usize>, //Replay duplicate games, leaving them at this ply with a random move
+    #[serde(default)]
+    pub resume: bool, //Continue the run saved in the checkpoint file instead of preparing new games
 }
 /*
 Error-Margin in +/- (95% Confidence)
@@ -61,6 +89,8 @@ Win/Elo Gain:-------------------------------------------------------------------
 */
 fn main() {
     let mut config_path = "REFEREE_CONFIG.json";
+    let mut gen_fens = None;
+    let mut resume = false;
     let args: Vec<String> = env::args().collect();
     let mut index: usize = 1;
     while index < args.len() {
@@ -70,6 +100,16 @@ fn main() {
                 index += 2;
                 continue;
             }
+            "--gen-fens" => {
+                gen_fens = Some(args[index + 1].clone());
+                index += 2;
+                continue;
+            }
+            "--resume" => {
+                resume = true;
+                index += 1;
+                continue;
+            }
             _ => {
                 println!(
                     "Invalid argument {}, use config CONFIG_FILE to specify",
@@ -80,7 +120,11 @@ fn main() {
         }
     }
     let config_content = fs::read_to_string(config_path).expect("Unable to read config file!");
-    let config: Config = serde_json::from_str(&config_content).unwrap();
+    let mut config: Config = serde_json::from_str(&config_content).unwrap();
+    if gen_fens.is_some() {
+        config.gen_fens = gen_fens;
+    }
+    config.resume |= resume;
     let mut runtime = tokio::runtime::Builder::new()
         .threaded_scheduler()
         .core_threads(config.processors)
@@ -1,7 +1,10 @@
 use crate::engine::{Engine, PlayTask};
 use crate::queue::ThreadSafeQueue;
 use core_sdk::board_representation::game_state::*;
-use rand::Rng;
+use core_sdk::move_generation::makemove::make_move;
+use core_sdk::move_generation::movegen;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 pub fn load_openings_into_queue(
     n: usize,
@@ -12,7 +15,6 @@ pub fn load_openings_into_queue(
 ) -> ThreadSafeQueue<PlayTask> {
     let mut rng = rand::thread_rng();
     let mut res: Vec<PlayTask> = Vec::with_capacity(n);
-    let mut id = 0;
     for _ in 0..n {
         loop {
             if db.is_empty() {
@@ -22,26 +24,7 @@ pub fn load_openings_into_queue(
             let state = db.remove(index);
             let sequence = db_sequences.remove(index);
             if !contains(&res, &state) {
-                for enemy_engine in enemies {
-                    res.push(PlayTask {
-                        opening: state.clone(),
-                        opening_sequence: sequence.clone(),
-                        p1_is_white: true,
-                        id,
-                        engine1: gauntlet_engine.clone(),
-                        engine2: enemy_engine.clone(),
-                    });
-                    id += 1;
-                    res.push(PlayTask {
-                        opening: state.clone(),
-                        opening_sequence: sequence.clone(),
-                        p1_is_white: false,
-                        id,
-                        engine1: gauntlet_engine.clone(),
-                        engine2: enemy_engine.clone(),
-                    });
-                    id += 1;
-                }
+                push_tasks(&mut res, &state, &sequence, gauntlet_engine, enemies);
                 break;
             }
         }
@@ -49,8 +32,88 @@ pub fn load_openings_into_queue(
     ThreadSafeQueue::new(res)
 }
 
+//Instead of an opening database, a single random first move is forced on both engines. This tests the opening play
+//of the engines themselves
+pub fn load_random_first_moves_into_queue(
+    n: usize,
+    seed: u64,
+    gauntlet_engine: &Engine,
+    enemies: &[Engine],
+) -> ThreadSafeQueue<PlayTask> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut res: Vec<PlayTask> = Vec::with_capacity(n);
+    for _ in 0..n {
+        let mv = random_first_move(&mut rng);
+        let state = make_move(&GameState::standard(), mv);
+        push_tasks(&mut res, &state, &[mv], gauntlet_engine, enemies);
+    }
+    ThreadSafeQueue::new(res)
+}
+
+pub fn random_first_move(rng: &mut StdRng) -> GameMove {
+    let mut movelist = movegen::MoveList::default();
+    movegen::generate_moves(&GameState::standard(), false, &mut movelist);
+    movelist.move_list[rng.gen_range(0, movelist.move_list.len())].0
+}
+
+//Every enemy plays the opening once with each color against the gauntlet engine
+fn push_tasks(
+    res: &mut Vec<PlayTask>,
+    state: &GameState,
+    sequence: &[GameMove],
+    gauntlet_engine: &Engine,
+    enemies: &[Engine],
+) {
+    for enemy_engine in enemies {
+        for &p1_is_white in [true, false].iter() {
+            res.push(PlayTask {
+                opening: state.clone(),
+                opening_sequence: sequence.to_vec(),
+                p1_is_white,
+                id: res.len(),
+                engine1: gauntlet_engine.clone(),
+                engine2: enemy_engine.clone(),
+            });
+        }
+    }
+}
+
 pub fn contains(queue: &[PlayTask], state: &GameState) -> bool {
     queue
         .iter()
         .any(|other| other.opening.get_hash() == state.get_hash())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::random_first_move;
+    use core_sdk::board_representation::game_state::GameState;
+    use core_sdk::move_generation::movegen;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::HashSet;
+
+    #[test]
+    fn random_first_move_test() {
+        let mut movelist = movegen::MoveList::default();
+        movegen::generate_moves(&GameState::standard(), false, &mut movelist);
+        let first_moves = |seed: u64| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..10)
+                .map(|_| random_first_move(&mut rng))
+                .collect::<Vec<_>>()
+        };
+        let mut seen = HashSet::new();
+        for seed in 0..10 {
+            let moves = first_moves(seed);
+            for mv in moves.iter() {
+                assert!(movelist.move_list.iter().any(|gmv| gmv.0 == *mv));
+                seen.insert(format!("{:?}", mv));
+            }
+            //Reproducible with the same seed
+            assert!(moves == first_moves(seed));
+            assert!(moves != first_moves(seed + 100));
+        }
+        assert!(seen.len() > 10);
+    }
+}
@@ -18,6 +18,9 @@ impl<T> ThreadSafeQueue<T> {
         }
         Some((*data).remove(0))
     }
+    pub fn pop_back(&self) -> Option<T> {
+        self.queue.lock().unwrap().pop()
+    }
     pub fn push(&self, item: T) {
         let mut data = self.queue.lock().unwrap();
         (*data).push(item);
@@ -31,6 +34,52 @@ impl<T> ThreadSafeQueue<T> {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    pub fn map<R, F: FnMut(&T) -> R>(&self, f: F) -> Vec<R> {
+        let data = self.queue.lock().unwrap();
+        data.iter().map(f).collect()
+    }
+
+    pub fn into_inner(self) -> Vec<T> {
+        self.queue.into_inner().unwrap()
+    }
+}
+
+//One queue per worker, so the workers don't contend for a single lock. Items are dealt out round robin, a worker
+//whose queue ran empty steals from the back of the other queues
+pub struct PartitionedQueue<T> {
+    queues: Vec<ThreadSafeQueue<T>>,
+}
+
+impl<T> PartitionedQueue<T> {
+    pub fn new(vec: Vec<T>, partitions: usize) -> Self {
+        let mut parts: Vec<Vec<T>> = (0..partitions.max(1)).map(|_| Vec::new()).collect();
+        let amount = parts.len();
+        for (index, item) in vec.into_iter().enumerate() {
+            parts[index % amount].push(item);
+        }
+        PartitionedQueue {
+            queues: parts.into_iter().map(ThreadSafeQueue::new).collect(),
+        }
+    }
+
+    pub fn pop(&self, worker: usize) -> Option<T> {
+        let own = worker % self.queues.len();
+        if let Some(item) = self.queues[own].pop() {
+            return Some(item);
+        }
+        (1..self.queues.len())
+            .map(|offset| &self.queues[(own + offset) % self.queues.len()])
+            .find_map(|queue| queue.pop_back())
+    }
+
+    pub fn len(&self) -> usize {
+        self.queues.iter().map(|queue| queue.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 pub struct ThreadSafeString {
@@ -55,3 +104,42 @@ impl ThreadSafeString {
         self.string.lock().unwrap().clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{PartitionedQueue, ThreadSafeQueue};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn partitioned_queue_test() {
+        for &(workers, partitions) in [(8, 8), (4, 3), (3, 1), (1, 4)].iter() {
+            let tasks = 200;
+            let queue = Arc::new(PartitionedQueue::new((0..tasks).collect(), partitions));
+            assert_eq!(queue.len(), tasks);
+            let results = Arc::new(ThreadSafeQueue::new(Vec::new()));
+            let mut handles = Vec::new();
+            for worker in 0..workers {
+                let (queue, results) = (queue.clone(), results.clone());
+                handles.push(thread::spawn(move || {
+                    while let Some(task) = queue.pop(worker) {
+                        //Uneven work, so the fast workers have to steal
+                        if worker == 0 {
+                            thread::sleep(Duration::from_micros(200));
+                        }
+                        results.push(task);
+                    }
+                }));
+            }
+            for handle in handles {
+                handle.join().unwrap();
+            }
+            assert!(queue.is_empty());
+            let mut played = Arc::try_unwrap(results).ok().unwrap().into_inner();
+            assert_eq!(played.len(), tasks);
+            played.sort_unstable();
+            assert!(played.into_iter().eq(0..tasks));
+        }
+    }
+}
@@ -1,14 +1,19 @@
 use crate::async_communication::{stderr_listener, write_all};
-use crate::engine::{EndConditionInformation, EngineReaction, EngineStatus, PlayTask, TaskResult};
+use crate::engine::{
+    EndConditionInformation, EngineReaction, EngineStatus, MoveRecord, PlayTask, TaskResult,
+};
 use core_sdk::board_representation::game_state::*;
 use core_sdk::move_generation::makemove::make_move;
 use core_sdk::move_generation::movegen;
 use log::warn;
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use tokio::process::Child;
 use tokio::task::JoinHandle;
 use tokio::time::delay_for;
 
+const LIGHT_SQUARES: u64 = 0x55AA_55AA_55AA_55AA;
+
 pub async fn cleanup(mut e1: Child, mut e2: Child, e1_err: JoinHandle<()>, e2_err: JoinHandle<()>) {
     let _ = e1.kill();
     let _ = e2.kill();
@@ -19,14 +24,40 @@ pub async fn cleanup(mut e1: Child, mut e2: Child, e1_err: JoinHandle<()>, e2_er
         .await
         .unwrap_or_else(|msg| warn!("Could not join e2_err task: {}", msg));
 }
-pub async fn play_game(mut task: PlayTask) -> TaskResult {
+//Both engines agree on a dead draw: every one of the last plies moves was played with a cp score of at most
+//max_score in absolute value, and the game is at least min_ply plies long. As the engines move alternately, a
+//single engine misjudging the position can't end the game on its own
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Debug)]
+pub struct DrawAgreement {
+    pub min_ply: usize,
+    pub plies: usize,
+    pub max_score: isize,
+}
+
+impl DrawAgreement {
+    pub fn agrees(&self, record: &MoveRecord) -> bool {
+        record.mate_score.is_none() && record.cp_score.is_some_and(|cp| cp.abs() <= self.max_score)
+    }
+
+    pub fn reached(&self, game_plies: usize, agreeing_plies: usize) -> bool {
+        game_plies >= self.min_ply && agreeing_plies >= self.plies.max(2)
+    }
+}
+
+//Games still running after max_game_plies plies of the engines are adjudicated a draw, so shuffling games can't
+//stall a match
+pub async fn play_game(
+    mut task: PlayTask,
+    max_game_plies: Option<usize>,
+    draw_agreement: Option<DrawAgreement>,
+) -> TaskResult {
     let mut movelist = movegen::MoveList::default();
     //-------------------------------------------------------------
     //Set game up
     let opening_fen = task.opening.to_fen();
     let agsi = movegen::generate_moves(&task.opening, false, &mut movelist);
     let mut history: Vec<GameState> = Vec::with_capacity(100);
-    let mut status = check_end_condition(
+    let mut status = game_result(
         &task.opening,
         !movelist.move_list.is_empty(),
         agsi.stm_incheck,
@@ -36,6 +67,7 @@ pub async fn play_game(mut task: PlayTask) -> TaskResult {
     assert_eq!(status, GameResult::Ingame);
     history.push(task.opening.clone());
     let mut move_history: Vec<GameMove> = Vec::with_capacity(100);
+    let mut move_records: Vec<MoveRecord> = Vec::with_capacity(100);
     let mut endcondition = None;
     //-------------------------------------------------------------
     //Set players up
@@ -47,13 +79,13 @@ pub async fn play_game(mut task: PlayTask) -> TaskResult {
         .engine1
         .valid_uci_isready_reaction(&mut e1_input, &mut e1_output, task.id)
         .await;
-    if let EngineReaction::DisqualifyEngine = reaction {
+    if let EngineReaction::DisqualifyEngine(reason) = reaction {
         e1.kill()
             .unwrap_or_else(|msg| warn!("Unable to kill engine 1: {}", msg));
         e1_err.await.unwrap_or_else(|msg| {
             warn!("Could not join err reading task: {:?}", msg);
         });
-        return TaskResult::disq(task, true, move_history, status);
+        return TaskResult::disq(task, true, move_history, move_records, status, &reason);
     }
 
     let (e2, mut e2_input, mut e2_output, e2_err) = task.engine2.get_handles().await;
@@ -62,15 +94,16 @@ pub async fn play_game(mut task: PlayTask) -> TaskResult {
         .engine2
         .valid_uci_isready_reaction(&mut e2_input, &mut e2_output, task.id)
         .await;
-    if let EngineReaction::DisqualifyEngine = reaction {
+    if let EngineReaction::DisqualifyEngine(reason) = reaction {
         cleanup(e1, e2, e1_err, e2_err).await;
-        return TaskResult::disq(task, false, move_history, status);
+        return TaskResult::disq(task, false, move_history, move_records, status, &reason);
     }
     //-------------------------------------------------------------
     //Adjudications
     let mut draw_adjudication = 0usize;
     let mut win_adjudication = 0usize;
     let mut win_adjudication_for_p1 = true;
+    let mut agreeing_plies = 0usize;
 
     while let GameResult::Ingame = status {
         //Request move
@@ -103,6 +136,14 @@ pub async fn play_game(mut task: PlayTask) -> TaskResult {
                 task.engine1.time_control.to_go(false)
             }
         ));
+        let node_limit = if player1_move {
+            task.engine1.node_limit
+        } else {
+            task.engine2.node_limit
+        };
+        if let Some(nodes) = node_limit {
+            go_string.insert_str(go_string.len() - 1, &format!(" nodes {}", nodes));
+        }
         let game_move: GameMove;
         if player1_move {
             let reaction = task
@@ -112,19 +153,26 @@ pub async fn play_game(mut task: PlayTask) -> TaskResult {
                     &go_string,
                     &mut e1_input,
                     &mut e1_output,
-                    task.id,
                     &movelist,
                 )
                 .await;
             let engine_status;
             match reaction {
-                EngineReaction::DisqualifyEngine => {
+                EngineReaction::DisqualifyEngine(reason) => {
                     cleanup(e1, e2, e1_err, e2_err).await;
-                    return TaskResult::disq(task, true, move_history, status);
+                    return TaskResult::disq(
+                        task,
+                        true,
+                        move_history,
+                        move_records,
+                        status,
+                        &reason,
+                    );
                 }
                 EngineReaction::ContinueGame(temp) => {
                     game_move = temp.0;
                     engine_status = temp.1;
+                    move_records.push(temp.2);
                 }
             }
             if let EngineStatus::ProclaimsNothing = &engine_status {
@@ -158,19 +206,26 @@ pub async fn play_game(mut task: PlayTask) -> TaskResult {
                     &go_string,
                     &mut e2_input,
                     &mut e2_output,
-                    task.id,
                     &movelist,
                 )
                 .await;
             let engine_status;
             match reaction {
-                EngineReaction::DisqualifyEngine => {
+                EngineReaction::DisqualifyEngine(reason) => {
                     cleanup(e1, e2, e1_err, e2_err).await;
-                    return TaskResult::disq(task, false, move_history, status);
+                    return TaskResult::disq(
+                        task,
+                        false,
+                        move_history,
+                        move_records,
+                        status,
+                        &reason,
+                    );
                 }
                 EngineReaction::ContinueGame(temp) => {
                     game_move = temp.0;
                     engine_status = temp.1;
+                    move_records.push(temp.2);
                 }
             }
             if let EngineStatus::ProclaimsNothing = &engine_status {
@@ -198,6 +253,12 @@ pub async fn play_game(mut task: PlayTask) -> TaskResult {
             }
         }
 
+        if draw_agreement.is_some_and(|agreement| agreement.agrees(move_records.last().unwrap())) {
+            agreeing_plies += 1;
+        } else {
+            agreeing_plies = 0;
+        }
+
         //Make new state with move
         move_history.push(game_move);
         let state = make_move(latest_state, game_move);
@@ -205,7 +266,7 @@ pub async fn play_game(mut task: PlayTask) -> TaskResult {
             draw_adjudication = 0;
         }
         let agsi = movegen::generate_moves(&state, false, &mut movelist);
-        let check = check_end_condition(
+        let check = game_result(
             &state,
             !movelist.move_list.is_empty(),
             agsi.stm_incheck,
@@ -219,9 +280,9 @@ pub async fn play_game(mut task: PlayTask) -> TaskResult {
             //Check adjudication values
             if draw_adjudication >= 20 {
                 status = GameResult::Draw;
-                endcondition = Some(EndConditionInformation::DrawByadjudication);
+                endcondition = Some(EndConditionInformation::DrawByAdjudication);
             } else if win_adjudication >= 10 {
-                endcondition = Some(EndConditionInformation::MateByadjudication);
+                endcondition = Some(EndConditionInformation::WinByAdjudication);
                 if win_adjudication_for_p1 {
                     if task.p1_is_white {
                         status = GameResult::WhiteWin;
@@ -233,14 +294,22 @@ pub async fn play_game(mut task: PlayTask) -> TaskResult {
                 } else {
                     status = GameResult::WhiteWin;
                 }
+            } else if draw_agreement
+                .is_some_and(|agreement| agreement.reached(move_history.len(), agreeing_plies))
+            {
+                status = GameResult::Draw;
+                endcondition = Some(EndConditionInformation::DrawByAgreement);
+            } else if max_game_plies.is_some_and(|max| move_history.len() >= max) {
+                status = GameResult::Draw;
+                endcondition = Some(EndConditionInformation::MaxGamePlies);
             }
         }
     }
 
     //-------------------------------------------------------------
     //Cleanup players' processes
-    write_all(&mut e1_input, "quit\n").await;
-    write_all(&mut e2_input, "quit\n").await;
+    let _ = write_all(&mut e1_input, "quit\n").await;
+    let _ = write_all(&mut e2_input, "quit\n").await;
     delay_for(Duration::from_millis(20)).await;
     cleanup(e1, e2, e1_err, e2_err).await;
 
@@ -266,11 +335,13 @@ pub async fn play_game(mut task: PlayTask) -> TaskResult {
         task,
         endcondition,
         move_sequence: move_history,
+        move_records,
         final_status: status,
     }
 }
 
-pub fn check_end_condition(
+//Determines whether the game has ended in game_state and for which reason
+pub fn game_result(
     game_state: &GameState,
     has_legal_moves: bool,
     in_check: bool,
@@ -282,38 +353,50 @@ pub fn check_end_condition(
         GameResult::WhiteWin
     };
     if in_check && !has_legal_moves {
-        return (enemy_win, Some(EndConditionInformation::Mate));
+        return (enemy_win, Some(EndConditionInformation::Checkmate));
     }
     if !in_check && !has_legal_moves {
-        return (GameResult::Draw, Some(EndConditionInformation::StaleMate));
+        return (GameResult::Draw, Some(EndConditionInformation::Stalemate));
     }
-
-    //Missing pieces
-    if game_state.get_pieces_from_side_without_king(WHITE)
-        | game_state.get_pieces_from_side_without_king(BLACK)
-        == 0u64
-    {
+    if insufficient_material(game_state) {
         return (
             GameResult::Draw,
-            Some(EndConditionInformation::DrawByMissingPieces),
+            Some(EndConditionInformation::InsufficientMaterial),
         );
     }
     if game_state.get_half_moves() >= 100 {
         return (
             GameResult::Draw,
-            Some(EndConditionInformation::HundredMoveDraw),
+            Some(EndConditionInformation::FiftyMoveRule),
         );
     }
     if get_occurences(history, game_state) >= 2 {
         return (
             GameResult::Draw,
-            Some(EndConditionInformation::ThreeFoldRepetition),
+            Some(EndConditionInformation::ThreefoldRepetition),
         );
     }
 
     (GameResult::Ingame, None)
 }
 
+//No side can ever mate: bare kings, a single minor piece, or only bishops all on squares of one color
+pub fn insufficient_material(game_state: &GameState) -> bool {
+    if game_state.get_piece_bb(PieceType::Pawn)
+        | game_state.get_piece_bb(PieceType::Rook)
+        | game_state.get_piece_bb(PieceType::Queen)
+        != 0u64
+    {
+        return false;
+    }
+    let knights = game_state.get_piece_bb(PieceType::Knight);
+    let bishops = game_state.get_piece_bb(PieceType::Bishop);
+    if (knights | bishops).count_ones() <= 1 {
+        return true;
+    }
+    knights == 0u64 && (bishops & LIGHT_SQUARES == 0u64 || bishops & !LIGHT_SQUARES == 0u64)
+}
+
 pub fn get_occurences(history: &[GameState], state: &GameState) -> usize {
     let mut occ = 0;
     for other in history {
@@ -323,3 +406,260 @@ pub fn get_occurences(history: &[GameState], state: &GameState) -> usize {
     }
     occ
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{game_result, play_game, DrawAgreement, EndConditionInformation};
+    use crate::engine::tests::{mock_engine, runtime};
+    use crate::engine::{PlayTask, TaskResult};
+    use crate::selfplay_splitter::{diversified_task, move_log_entry, DuplicateGuard};
+    use core_sdk::board_representation::game_state::{GameResult, GameState};
+    use core_sdk::move_generation::makemove::make_move;
+    use core_sdk::move_generation::movegen;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::fs;
+    use std::sync::atomic::Ordering;
+
+    fn end_of(fen: &str, history: &[GameState]) -> (GameResult, Option<EndConditionInformation>) {
+        let state = GameState::from_fen(fen);
+        let mut movelist = movegen::MoveList::default();
+        let agsi = movegen::generate_moves(&state, false, &mut movelist);
+        game_result(
+            &state,
+            !movelist.move_list.is_empty(),
+            agsi.stm_incheck,
+            history,
+        )
+    }
+
+    #[test]
+    fn end_condition_test() {
+        let checkmate = "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3";
+        assert_eq!(
+            end_of(checkmate, &[]),
+            (
+                GameResult::BlackWin,
+                Some(EndConditionInformation::Checkmate)
+            )
+        );
+        let stalemate = "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1";
+        assert_eq!(
+            end_of(stalemate, &[]),
+            (GameResult::Draw, Some(EndConditionInformation::Stalemate))
+        );
+        let fifty_moves = "4k3/8/8/8/8/8/4P3/R3K3 w - - 100 80";
+        assert_eq!(
+            end_of(fifty_moves, &[]),
+            (
+                GameResult::Draw,
+                Some(EndConditionInformation::FiftyMoveRule)
+            )
+        );
+        let repeated = "4k3/8/8/8/8/8/4P3/R3K3 w - - 8 40";
+        let history = vec![GameState::from_fen(repeated)];
+        assert_eq!(end_of(repeated, &history), (GameResult::Ingame, None));
+        let history = vec![GameState::from_fen(repeated), GameState::from_fen(repeated)];
+        assert_eq!(
+            end_of(repeated, &history),
+            (
+                GameResult::Draw,
+                Some(EndConditionInformation::ThreefoldRepetition)
+            )
+        );
+        for fen in [
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/2N1K3 b - - 0 1",
+            "4k3/8/8/8/8/8/8/2B1K3 b - - 0 1",
+            "2b1k3/8/8/8/8/8/8/3BK3 w - - 0 1",
+        ]
+        .iter()
+        {
+            assert_eq!(
+                end_of(fen, &[]),
+                (
+                    GameResult::Draw,
+                    Some(EndConditionInformation::InsufficientMaterial)
+                )
+            );
+        }
+        for fen in [
+            "1n2k3/8/8/8/8/8/8/2N1K3 w - - 0 1",
+            "3bk3/8/8/8/8/8/8/3BK3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/2BBK3 w - - 0 1",
+        ]
+        .iter()
+        {
+            assert_eq!(end_of(fen, &[]), (GameResult::Ingame, None));
+        }
+    }
+
+    //Both sides shuffle a knight back and forth, which repeats the position every four plies. Every move comes
+    //with the given info line of each engine
+    fn shuffle_task(name: &str, infos: [&str; 2]) -> PlayTask {
+        let reply = |info: &str| {
+            format!(
+                "{}set -- $pos\nplies=$(($# > 9 ? $# - 9 : 0))\n\
+                 case $((plies % 4)) in\n\
+                 0) echo \"bestmove g1f3\" ;;\n1) echo \"bestmove b8c6\" ;;\n\
+                 2) echo \"bestmove f3g1\" ;;\n3) echo \"bestmove c6b8\" ;;\nesac",
+                info
+            )
+        };
+        let mut engine1 = mock_engine(&format!("{}_1", name), &reply(infos[0]));
+        let mut engine2 = mock_engine(&format!("{}_2", name), &reply(infos[1]));
+        engine1.node_limit = None;
+        engine2.node_limit = None;
+        engine2.id = 1;
+        PlayTask {
+            opening: GameState::standard(),
+            opening_sequence: Vec::new(),
+            p1_is_white: true,
+            id: 0,
+            engine1,
+            engine2,
+        }
+    }
+
+    fn play_shuffle_task(
+        task: PlayTask,
+        max_game_plies: Option<usize>,
+        draw_agreement: Option<DrawAgreement>,
+    ) -> TaskResult {
+        let result = runtime().block_on(play_game(task, max_game_plies, draw_agreement));
+        fs::remove_file(&result.task.engine1.path).expect("Could not remove mock engine");
+        fs::remove_file(&result.task.engine2.path).expect("Could not remove mock engine");
+        assert_eq!(result.final_status, GameResult::Draw);
+        result
+    }
+
+    fn shuffle_game(name: &str, max_game_plies: Option<usize>) -> TaskResult {
+        let result = play_shuffle_task(shuffle_task(name, ["", ""]), max_game_plies, None);
+        assert_eq!(
+            result.endcondition,
+            Some(if max_game_plies.is_some() {
+                EndConditionInformation::MaxGamePlies
+            } else {
+                EndConditionInformation::ThreefoldRepetition
+            })
+        );
+        result
+    }
+
+    #[test]
+    fn max_game_plies_test() {
+        let result = shuffle_game("shuffler", None);
+        assert_eq!(result.task.engine1.stats.moves_played, 4);
+        //Normal draws aren't logged
+        assert_eq!(move_log_entry(&result), None);
+        let result = shuffle_game("shuffler_limited", Some(6));
+        let task = &result.task;
+        assert_eq!(task.engine1.stats.moves_played, 3);
+        assert_eq!(task.engine2.stats.moves_played, 3);
+        for engine in [&task.engine1, &task.engine2].iter() {
+            assert_eq!((engine.wins, engine.draws, engine.losses), (0, 1, 0));
+        }
+
+        //Every move played gets recorded, with the engine which played it
+        assert_eq!(result.move_records.len(), result.move_sequence.len());
+        for (record, mv) in result.move_records.iter().zip(result.move_sequence.iter()) {
+            assert_eq!(record.mv, format!("{:?}", mv));
+            assert_eq!(record.depth, None);
+        }
+        assert_eq!(result.move_records[0].engine, task.engine1.name);
+        assert_eq!(result.move_records[1].engine, task.engine2.name);
+        let entry: serde_json::Value =
+            serde_json::from_str(&move_log_entry(&result).unwrap()).unwrap();
+        assert_eq!(entry["moves"].as_array().unwrap().len(), 6);
+        assert_eq!(entry["termination"], "Draw by game length limit");
+    }
+
+    #[test]
+    fn duplicate_game_test() {
+        let first = shuffle_game("duplicate", None);
+        let second = shuffle_game("duplicate", None);
+        let guard = DuplicateGuard::default();
+        assert!(!guard.register(&first));
+        assert!(guard.register(&second));
+        assert_eq!(guard.duplicates.load(Ordering::Relaxed), 1);
+
+        //The replay leaves the game at the given ply with another move
+        let mut rng = StdRng::seed_from_u64(0);
+        let (engine1, engine2) = (first.task.engine1.clone(), first.task.engine2.clone());
+        let replay = diversified_task(&second, 2, engine1, engine2, &mut rng).unwrap();
+        assert_eq!(replay.id, second.task.id);
+        assert_eq!(replay.opening_sequence.len(), 3);
+        assert_eq!(replay.opening_sequence[..2], second.move_sequence[..2]);
+        assert!(replay.opening_sequence[2] != second.move_sequence[2]);
+        let mut opening = GameState::standard();
+        for mv in replay.opening_sequence.iter() {
+            opening = make_move(&opening, *mv);
+        }
+        assert_eq!(opening.get_hash(), replay.opening.get_hash());
+
+        //The same moves from another opening are a different game
+        let mut other = second;
+        other.task.opening = replay.opening;
+        assert!(!guard.register(&other));
+        assert_eq!(guard.duplicates.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn draw_agreement_test() {
+        let agreement = DrawAgreement {
+            min_ply: 3,
+            plies: 3,
+            max_score: 10,
+        };
+        let near_zero = "echo \"info depth 5 score cp 4\"\n";
+        let result = play_shuffle_task(
+            shuffle_task(
+                "agreeing",
+                [near_zero, "echo \"info depth 5 score cp -7\"\n"],
+            ),
+            None,
+            Some(agreement),
+        );
+        assert_eq!(
+            result.endcondition,
+            Some(EndConditionInformation::DrawByAgreement)
+        );
+        assert_eq!(result.move_sequence.len(), 3);
+        assert_eq!(result.move_records[2].cp_score, Some(4));
+        let entry: serde_json::Value =
+            serde_json::from_str(&move_log_entry(&result).unwrap()).unwrap();
+        assert_eq!(entry["termination"], "Draw by mutual agreement");
+
+        //Not before min_ply
+        let late = DrawAgreement {
+            min_ply: 5,
+            ..agreement
+        };
+        let result = play_shuffle_task(
+            shuffle_task("agreeing_late", [near_zero, near_zero]),
+            None,
+            Some(late),
+        );
+        assert_eq!(
+            result.endcondition,
+            Some(EndConditionInformation::DrawByAgreement)
+        );
+        assert_eq!(result.move_sequence.len(), 5);
+
+        //One engine alone can't agree on a draw
+        for infos in [
+            [near_zero, "echo \"info depth 5 score cp 150\"\n"],
+            [near_zero, "echo \"info depth 5 score mate 9\"\n"],
+            [near_zero, ""],
+        ]
+        .iter()
+        {
+            let result =
+                play_shuffle_task(shuffle_task("disagreeing", *infos), None, Some(agreement));
+            assert_eq!(
+                result.endcondition,
+                Some(EndConditionInformation::ThreefoldRepetition)
+            );
+        }
+    }
+}
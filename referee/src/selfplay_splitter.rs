@@ -1,20 +1,123 @@
-use crate::engine::{EndConditionInformation, Engine};
+use crate::checkpoint::{Checkpoint, ResultRecord, CHECKPOINT_PATH};
+use crate::engine::{
+    EndConditionInformation, Engine, EVAL_RANDOMNESS_OPTION, EVAL_RANDOM_SEED_OPTION,
+};
 use crate::engine::{PlayTask, TaskResult};
 use crate::logging::FileLogger;
-use crate::openings::load_openings_into_queue;
-use crate::queue::ThreadSafeQueue;
-use crate::selfplay::play_game;
+use crate::openings::{load_openings_into_queue, load_random_first_moves_into_queue};
+use crate::queue::{PartitionedQueue, ThreadSafeQueue};
+use crate::selfplay::{play_game, DrawAgreement};
+use crate::training_data::training_lines;
 use crate::Config;
 use core_sdk::board_representation::game_state::*;
+use core_sdk::move_generation::makemove::make_move;
+use core_sdk::move_generation::movegen;
 use core_sdk::search::timecontrol::TimeControl;
 use extended_sdk::openings::load_db_until;
 use extended_sdk::pgn::pgn_writer::*;
+use rand::Rng;
+use serde_json::json;
 use std::cmp::Ordering;
-use std::sync::Arc;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{self, AtomicUsize};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 use tokio::time::delay_for;
 
+//A duplicate game is replayed with a new deviation at most this often, so a very narrow opening can't stall a worker
+pub const MAX_DUPLICATE_REPLAYS: usize = 3;
+
+//Remembers every finished game by the engines and their colors, the opening and all moves. Without eval randomness
+//the same opening played with the same colors can repeat a game exactly, which only costs time
+#[derive(Default)]
+pub struct DuplicateGuard {
+    games: Mutex<HashSet<u64>>,
+    pub duplicates: AtomicUsize,
+    pub replayed: AtomicUsize,
+}
+
+impl DuplicateGuard {
+    //Returns whether the same game was finished before. Disqualified games don't count
+    pub fn register(&self, result: &TaskResult) -> bool {
+        if result.endcondition.is_none() {
+            return false;
+        }
+        let mut hasher = DefaultHasher::new();
+        result.task.p1_is_white.hash(&mut hasher);
+        result.task.engine1.name.hash(&mut hasher);
+        result.task.engine2.name.hash(&mut hasher);
+        result.task.opening.get_hash().hash(&mut hasher);
+        for mv in result.move_sequence.iter() {
+            format!("{:?}", mv).hash(&mut hasher);
+        }
+        let duplicate = !self.games.lock().unwrap().insert(hasher.finish());
+        if duplicate {
+            self.duplicates.fetch_add(1, atomic::Ordering::Relaxed);
+        }
+        duplicate
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "Duplicate games: {} ({} replayed)",
+            self.duplicates.load(atomic::Ordering::Relaxed),
+            self.replayed.load(atomic::Ordering::Relaxed)
+        )
+    }
+}
+
+//The task of a duplicate game again, with an opening which leaves the game at the given ply with a random other
+//legal move. Earlier plies are tried if there is no other move there. The engines have to be fresh
+pub fn diversified_task<R: Rng>(
+    result: &TaskResult,
+    ply: usize,
+    engine1: Engine,
+    engine2: Engine,
+    rng: &mut R,
+) -> Option<PlayTask> {
+    let moves = &result.move_sequence;
+    let mut movelist = movegen::MoveList::default();
+    for ply in (0..=ply.min(moves.len().saturating_sub(1))).rev() {
+        let mut state = result.task.opening.clone();
+        for mv in moves[..ply].iter() {
+            state = make_move(&state, *mv);
+        }
+        movegen::generate_moves(&state, false, &mut movelist);
+        //The new opening must leave the game going
+        let deviations: Vec<(GameMove, GameState)> = movelist
+            .move_list
+            .iter()
+            .map(|gmv| gmv.0)
+            .filter(|mv| moves.get(ply) != Some(mv))
+            .map(|mv| (mv, make_move(&state, mv)))
+            .filter(|(_, next)| {
+                let mut next_movelist = movegen::MoveList::default();
+                movegen::generate_moves(next, false, &mut next_movelist);
+                !next_movelist.move_list.is_empty() && next.get_half_moves() < 100
+            })
+            .collect();
+        if deviations.is_empty() {
+            continue;
+        }
+        let (deviation, opening) = deviations[rng.gen_range(0, deviations.len())].clone();
+        let mut opening_sequence = result.task.opening_sequence.clone();
+        opening_sequence.extend_from_slice(&moves[..ply]);
+        opening_sequence.push(deviation);
+        return Some(PlayTask {
+            opening,
+            opening_sequence,
+            p1_is_white: result.task.p1_is_white,
+            id: result.task.id,
+            engine1,
+            engine2,
+        });
+    }
+    None
+}
+
 pub async fn start_self_play(config: Config) {
     FileLogger::new("referee_error_log.txt", false)
         .init()
@@ -30,49 +133,90 @@ pub async fn start_self_play(config: Config) {
         config.engine_path.1.clone(),
     )
     .await;
+    gauntlet_engine.node_limit = config.nodes_per_move;
     let tcp2 = TimeControl::Incremental(
         config.timecontrol_enemies_time,
         config.timecontrol_enemies_inc,
     );
     let mut engines: Vec<Engine> = Vec::new();
-    for (index, path) in config.enemies_paths.into_iter().enumerate() {
-        engines.push(Engine::from_path(&path.0, index, tcp2, path.1).await);
+    for (index, path) in config.enemies_paths.iter().enumerate() {
+        let mut engine = Engine::from_path(&path.0, index, tcp2, path.1.clone()).await;
+        engine.node_limit = config.nodes_per_move;
+        engines.push(engine);
     }
-    let mut db: Vec<GameState> = Vec::with_capacity(100_000);
-    let mut db_sequences: Vec<Vec<GameMove>> = Vec::with_capacity(100_000);
-    for database in config.opening_databases {
-        let mut database_loaded = load_db_until(&database, config.opening_load_untilply);
-        db.append(&mut database_loaded.0);
-        db_sequences.append(&mut database_loaded.1);
+    if config.eval_randomness > 0 {
+        for engine in engines
+            .iter_mut()
+            .chain(std::iter::once(&mut gauntlet_engine))
+        {
+            engine.uci_options.insert(
+                EVAL_RANDOMNESS_OPTION.to_owned(),
+                config.eval_randomness.to_string(),
+            );
+            engine.uci_options.insert(
+                EVAL_RANDOM_SEED_OPTION.to_owned(),
+                config.eval_random_seed.to_string(),
+            );
+        }
     }
-    println!(
-        "{}",
-        &format!(
-            "Loaded database with {} games found! Preparing games...",
-            db.len()
-        )
-    );
-    let queue: Arc<ThreadSafeQueue<PlayTask>> = Arc::new(load_openings_into_queue(
-        config.games / 2,
-        db,
-        db_sequences,
-        &gauntlet_engine,
-        &engines,
-    ));
+    let (queue, mut checkpoint) = if config.resume {
+        let checkpoint = Checkpoint::load(CHECKPOINT_PATH);
+        //The tasks take fresh copies of the engines, so the finished games are only aggregated afterwards
+        let queue = checkpoint.tasks(&gauntlet_engine, &engines);
+        checkpoint.aggregate(&mut gauntlet_engine, &mut engines);
+        println!(
+            "Resuming from checkpoint with {} finished games!",
+            checkpoint.results.len()
+        );
+        (queue, checkpoint)
+    } else {
+        let queue = prepare_games(&config, &gauntlet_engine, &engines);
+        let checkpoint = Checkpoint::new(&queue);
+        (queue, checkpoint)
+    };
+    let partitions = if config.partition_queue {
+        config.processors
+    } else {
+        1
+    };
+    let queue = Arc::new(PartitionedQueue::new(queue.into_inner(), partitions));
     let games = queue.len();
     println!("Prepared {} games! Starting...", games);
 
     let result_queue: Arc<ThreadSafeQueue<TaskResult>> =
         Arc::new(ThreadSafeQueue::new(Vec::with_capacity(100)));
     let pgn_log = FileLogger::new("pgns.pgn", true);
+    let fen_log = config
+        .gen_fens
+        .as_ref()
+        .map(|path| FileLogger::new(path, true));
+    let move_log = config
+        .move_log
+        .as_ref()
+        .map(|path| FileLogger::new(path, true));
+
+    let duplicate_guard = Arc::new(DuplicateGuard::default());
 
     //Start all childs
     let mut childs = Vec::with_capacity(config.processors);
-    for _ in 0..config.processors {
+    for worker in 0..config.processors {
         let queue_clone = queue.clone();
         let res_clone = result_queue.clone();
+        let guard_clone = duplicate_guard.clone();
+        let max_game_plies = config.max_game_plies;
+        let draw_agreement = config.draw_agreement;
+        let deviation_ply = config.duplicate_deviation_ply;
         childs.push(tokio::spawn(async move {
-            start_self_play_thread(queue_clone, res_clone).await
+            start_self_play_thread(
+                queue_clone,
+                worker,
+                res_clone,
+                max_game_plies,
+                draw_agreement,
+                guard_clone,
+                deviation_ply,
+            )
+            .await
         }));
     }
 
@@ -86,13 +230,15 @@ pub async fn start_self_play(config: Config) {
             println!("Game {} finished!", result.task.id);
             if let Some(reason) = result.endcondition {
                 println!("Reason: {}", reason);
-            } else {
-                println!("Reason: Disqualification");
             }
             println!("*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*-*");
             //Add engines
-            gauntlet_engine.add(&result.task.engine1);
-            engines[result.task.engine2.id].add(&result.task.engine2);
+            let record = ResultRecord::from_result(&result);
+            record.add_to(&mut gauntlet_engine, &mut engines);
+            checkpoint.finish(record);
+            if let Err(e) = checkpoint.save(CHECKPOINT_PATH) {
+                println!("Could not save checkpoint: {}", e);
+            }
 
             println!("-------------------------------------------------");
             let (rank, descr, _) = gauntlet_engine.get_elo_gain();
@@ -120,9 +266,27 @@ pub async fn start_self_play(config: Config) {
                 for desc in &other {
                     println!("{}", desc.1);
                 }
+                println!("{}", duplicate_guard.summary());
                 println!("+++++++++++++++++++++++++++++++++++++++++++++++++");
             }
 
+            //Disqualified games end without a result and don't produce any training positions
+            if let Some(fen_log) = &fen_log {
+                for line in training_lines(
+                    &result.task.opening,
+                    &result.move_sequence,
+                    &result.final_status,
+                    &config.fen_filter,
+                ) {
+                    fen_log.dump_msg(&format!("{}\n", line));
+                }
+            }
+            if let Some(move_log) = &move_log {
+                if let Some(entry) = move_log_entry(&result) {
+                    move_log.dump_msg(&format!("{}\n", entry));
+                }
+            }
+
             //Write all fens of game to pgn
             let opening_moves = Some(result.task.opening_sequence.len());
             let mut moves = result.task.opening_sequence;
@@ -133,17 +297,19 @@ pub async fn start_self_play(config: Config) {
                 metadata.event_name = Some("FabChess local gauntlet".to_owned());
                 metadata.round = Some(format!("{}", result.task.id));
                 metadata.result = Some(result.final_status.to_string());
-                metadata.termination = Some(if result.endcondition.is_none() {
-                    "rules infraction".to_owned()
-                } else {
-                    let temp = result.endcondition.unwrap();
-                    match temp {
-                        EndConditionInformation::DrawByadjudication
-                        | EndConditionInformation::MateByadjudication => "adjudication",
+                metadata.termination = Some(
+                    match result.endcondition {
+                        None | Some(EndConditionInformation::Disqualification) => {
+                            "rules infraction"
+                        }
+                        Some(EndConditionInformation::DrawByAdjudication)
+                        | Some(EndConditionInformation::DrawByAgreement)
+                        | Some(EndConditionInformation::WinByAdjudication)
+                        | Some(EndConditionInformation::MaxGamePlies) => "adjudication",
                         _ => "normal",
                     }
-                    .to_owned()
-                });
+                    .to_owned(),
+                );
                 metadata.white = Some(if result.task.p1_is_white {
                     result.task.engine1.name.clone()
                 } else {
@@ -161,19 +327,111 @@ pub async fn start_self_play(config: Config) {
     for child in childs {
         child.await.expect("Couldn't join thread");
     }
+    println!("{}", duplicate_guard.summary());
     println!("Testing finished!");
 }
 
+//Lost and adjudicated games as a json line, with the depth, score and time the engines reported for every move
+pub fn move_log_entry(result: &TaskResult) -> Option<String> {
+    let adjudicated = matches!(
+        result.endcondition,
+        Some(EndConditionInformation::DrawByAdjudication)
+            | Some(EndConditionInformation::DrawByAgreement)
+            | Some(EndConditionInformation::WinByAdjudication)
+            | Some(EndConditionInformation::MaxGamePlies)
+    );
+    let decisive =
+        result.final_status == GameResult::WhiteWin || result.final_status == GameResult::BlackWin;
+    if !adjudicated && !decisive {
+        return None;
+    }
+    let (white, black) = if result.task.p1_is_white {
+        (&result.task.engine1.name, &result.task.engine2.name)
+    } else {
+        (&result.task.engine2.name, &result.task.engine1.name)
+    };
+    Some(
+        json!({
+            "id": result.task.id,
+            "white": white,
+            "black": black,
+            "opening": result.task.opening.to_fen(),
+            "result": result.final_status.to_string(),
+            "termination": result.endcondition.map(|reason| reason.to_string()),
+            "moves": result.move_records,
+        })
+        .to_string(),
+    )
+}
+
+fn prepare_games(
+    config: &Config,
+    gauntlet_engine: &Engine,
+    engines: &[Engine],
+) -> ThreadSafeQueue<PlayTask> {
+    if let Some(seed) = config.random_first_move_seed {
+        println!(
+            "Forcing a random first move with seed {}! Preparing games...",
+            seed
+        );
+        load_random_first_moves_into_queue(config.games / 2, seed, gauntlet_engine, engines)
+    } else {
+        let mut db: Vec<GameState> = Vec::with_capacity(100_000);
+        let mut db_sequences: Vec<Vec<GameMove>> = Vec::with_capacity(100_000);
+        for database in config.opening_databases.iter() {
+            let mut database_loaded = load_db_until(database, config.opening_load_untilply);
+            db.append(&mut database_loaded.0);
+            db_sequences.append(&mut database_loaded.1);
+        }
+        println!(
+            "{}",
+            &format!(
+                "Loaded database with {} games found! Preparing games...",
+                db.len()
+            )
+        );
+        load_openings_into_queue(config.games / 2, db, db_sequences, gauntlet_engine, engines)
+    }
+}
+
+//A duplicate game is replayed right away by the same worker with deviation_ply, instead of going back to the queue
+//which the other workers may have left already
 pub async fn start_self_play_thread(
-    queue: Arc<ThreadSafeQueue<PlayTask>>,
+    queue: Arc<PartitionedQueue<PlayTask>>,
+    worker: usize,
     result_queue: Arc<ThreadSafeQueue<TaskResult>>,
+    max_game_plies: Option<usize>,
+    draw_agreement: Option<DrawAgreement>,
+    duplicate_guard: Arc<DuplicateGuard>,
+    deviation_ply: Option<usize>,
 ) {
-    while let Some(task) = queue.pop() {
-        println!("Starting game {}", task.id);
-        let res = play_game(task).await;
-        if res.endcondition.is_none() {
-            thread::sleep(Duration::from_millis(50));
+    while let Some(mut task) = queue.pop(worker) {
+        let mut replays = 0;
+        loop {
+            println!("Starting game {}", task.id);
+            let (engine1, engine2) = (task.engine1.clone(), task.engine2.clone());
+            let res = play_game(task, max_game_plies, draw_agreement).await;
+            if res.endcondition.is_none() {
+                thread::sleep(Duration::from_millis(50));
+            }
+            if duplicate_guard.register(&res) && replays < MAX_DUPLICATE_REPLAYS {
+                if let Some(next) = deviation_ply.and_then(|ply| {
+                    diversified_task(&res, ply, engine1, engine2, &mut rand::thread_rng())
+                }) {
+                    println!(
+                        "Game {} duplicates an earlier game, replaying it!",
+                        res.task.id
+                    );
+                    duplicate_guard
+                        .replayed
+                        .fetch_add(1, atomic::Ordering::Relaxed);
+                    replays += 1;
+                    task = next;
+                    continue;
+                }
+            }
+            result_queue.push(res);
+            break;
         }
-        result_queue.push(res);
     }
 }
@@ -0,0 +1,122 @@
+use core_sdk::board_representation::game_state::*;
+use core_sdk::move_generation::makemove::make_move;
+use core_sdk::move_generation::movegen;
+use serde::{Deserialize, Serialize};
+
+//Which positions of a finished game are worth to be used as training data
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FenFilter {
+    pub min_ply: usize, //Plies after the opening position that are skipped as too close to book
+    pub end_margin: usize, //Plies before the end of the game which are skipped
+    pub skip_in_check: bool,
+    pub skip_tactical: bool, //Skip positions in which a capture or promotion was played
+}
+
+impl Default for FenFilter {
+    fn default() -> Self {
+        FenFilter {
+            min_ply: 8,
+            end_margin: 4,
+            skip_in_check: true,
+            skip_tactical: true,
+        }
+    }
+}
+
+pub fn result_label(result: &GameResult) -> Option<&'static str> {
+    match result {
+        GameResult::WhiteWin => Some("[1.0]"),
+        GameResult::Draw => Some("[0.5]"),
+        GameResult::BlackWin => Some("[0.0]"),
+        GameResult::Ingame => None,
+    }
+}
+
+//One `FEN [result]` line per position a move was played in, readable by the tuning's EPD loader
+pub fn training_lines(
+    opening: &GameState,
+    moves: &[GameMove],
+    result: &GameResult,
+    filter: &FenFilter,
+) -> Vec<String> {
+    let label = match result_label(result) {
+        Some(label) => label,
+        None => return Vec::new(),
+    };
+    let mut res = Vec::new();
+    let mut movelist = movegen::MoveList::default();
+    let mut state = opening.clone();
+    for (ply, mv) in moves.iter().enumerate() {
+        let in_check = movegen::generate_moves(&state, false, &mut movelist).stm_incheck;
+        let tactical = mv.is_capture() || matches!(mv.move_type, GameMoveType::Promotion(_, _));
+        if ply >= filter.min_ply
+            && ply + filter.end_margin < moves.len()
+            && !(filter.skip_in_check && in_check)
+            && !(filter.skip_tactical && tactical)
+        {
+            res.push(format!("{} {}", state.to_fen(), label));
+        }
+        state = make_move(&state, *mv);
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{training_lines, FenFilter};
+    use core_sdk::board_representation::game_state::*;
+    use core_sdk::move_generation::makemove::make_move;
+    use core_sdk::move_generation::movegen;
+
+    #[test]
+    fn training_lines_test() {
+        //1.e4 d5 2.exd5 Nf6 3.Bb5+ Bd7 4.Bxd7+ Qxd7 5.c4 e6 6.dxe6 Qxe6+ 7.Qe2 Qxe2+ 8.Nxe2
+        let script = "e2e4 d7d5 e4d5 g8f6 f1b5 c8d7 b5d7 d8d7 c2c4 e7e6 d5e6 d7e6 d1e2 e6e2 g1e2";
+        let opening = GameState::standard();
+        let mut states = vec![opening.clone()];
+        let mut moves = Vec::new();
+        let mut movelist = movegen::MoveList::default();
+        for uci in script.split_whitespace() {
+            let state = states.last().unwrap();
+            movegen::generate_moves(state, false, &mut movelist);
+            let mv = movelist
+                .move_list
+                .iter()
+                .map(|gmv| gmv.0)
+                .find(|mv| format!("{:?}", mv) == uci)
+                .unwrap();
+            states.push(make_move(state, mv));
+            moves.push(mv);
+        }
+
+        let unfiltered = FenFilter {
+            min_ply: 0,
+            end_margin: 0,
+            skip_in_check: false,
+            skip_tactical: false,
+        };
+        let lines = training_lines(&opening, &moves, &GameResult::WhiteWin, &unfiltered);
+        assert_eq!(lines.len(), moves.len());
+        for (line, state) in lines.iter().zip(states.iter()) {
+            let (fen, label) = line.split_at(line.rfind(' ').unwrap());
+            assert_eq!(label, " [1.0]");
+            assert_eq!(GameState::from_fen(fen).get_hash(), state.get_hash());
+        }
+
+        let filter = FenFilter {
+            min_ply: 2,
+            end_margin: 2,
+            skip_in_check: true,
+            skip_tactical: true,
+        };
+        let lines = training_lines(&opening, &moves, &GameResult::Draw, &filter);
+        //Captures, the positions in check after 3.Bb5+ and 6...Qxe6+ and the last two plies are skipped
+        let expected: Vec<String> = [3, 4, 8, 9]
+            .iter()
+            .map(|&ply| format!("{} [0.5]", states[ply].to_fen()))
+            .collect();
+        assert_eq!(lines, expected);
+
+        assert!(training_lines(&opening, &moves, &GameResult::Ingame, &unfiltered).is_empty());
+    }
+}
@@ -1,19 +1,52 @@
 extern crate rand;
 #[cfg(test)]
 mod tests {
-    use core_sdk::board_representation::game_state::GameState;
+    use core_sdk::board_representation::game_state::{GameMoveType, GameState, PieceType};
     use core_sdk::board_representation::game_state::{BLACK, WHITE};
+    use core_sdk::evaluation::parameters::normal_parameters::{
+        IDX_BISHOP_BEHIND_PAWN_CHAIN, IDX_BISHOP_MOBILITY, IDX_BISHOP_PAIR_WITH_PAWNS,
+        IDX_BISHOP_TRAPPED, IDX_KNIGHT_BEHIND_PAWN_CHAIN, IDX_KNIGHT_TRAPPED_ON_RIM,
+        IDX_PAWN_CANDIDATE_PASSER, IDX_PAWN_DOUBLED, IDX_PAWN_DOUBLED_HALF_OPEN, IDX_PAWN_LEVER,
+        IDX_PAWN_PASSED_ESCORTED, IDX_PAWN_PASSED_KING_IN_SQUARE, IDX_PAWN_TRIPLED,
+        IDX_ROOKS_CONNECTED, IDX_ROOKS_DOUBLED_ON_OPEN, IDX_ROOK_TRAPPED_BY_KING,
+        SIZE_BISHOP_MOBILITY, SIZE_BISHOP_PAIR_WITH_PAWNS,
+    };
+    use core_sdk::evaluation::params::{
+        BISHOP_PIECE_VALUE, PAWN_CANDIDATE_PASSER_VALUE, PAWN_DOUBLED_HALF_OPEN_VALUE,
+        PAWN_DOUBLED_VALUE, PAWN_LEVER_VALUE, PAWN_TRIPLED_VALUE, TEMPO_BONUS,
+    };
     use core_sdk::evaluation::psqt_evaluation::psqt;
+    use core_sdk::evaluation::trace::LargeTrace;
+    use core_sdk::evaluation::{
+        eval_game_state, eval_game_state_detailed, fifty_move_scale, is_blockade_draw,
+        is_fortress_draw, is_guaranteed_draw, kbn_mating_corner, pawn_race, piece_values,
+        rook_endgame_scale, unstoppable_passer_plies, EvaluationScore, ROOK_ENDGAME_DRAW_SCALE,
+        ROOK_ENDGAME_SCALE,
+    };
     use core_sdk::move_generation::makemove::make_move;
     use core_sdk::move_generation::movegen;
     use core_sdk::move_generation::movegen::MoveList;
-    use core_sdk::perft;
+    use core_sdk::search::cache::Cache;
+    use core_sdk::search::history::History;
+    use core_sdk::search::info_sink::{InfoSink, SearchInfo};
     use core_sdk::search::reserved_memory::ReservedMoveList;
+    use core_sdk::search::searcher::{
+        search_move, uci_score_string, InterThreadCommunicationSystem,
+    };
+    use core_sdk::search::strength::deviation_window;
+    use core_sdk::search::timecontrol::TimeControl;
+    use core_sdk::search::{
+        check_for_draw, effective_contempt, PrincipalVariation, ScoreBound,
+        ScoredPrincipalVariation, SearchInstruction, MATED_IN_MAX,
+    };
+    use core_sdk::{perft, perft_parallel};
     use extended_sdk::misc::KING_BASE_PATH;
+    use extended_sdk::openings::{load_db_until, load_opening_db, save_opening_db};
     use extended_sdk::pgn::pgn_reader::{parse_move, GameParser, PGNParser};
     use rand::Rng;
     use std::fs::File;
     use std::io::BufReader;
+    use std::sync::{Arc, Mutex};
 
     #[test]
     fn fen_test() {
@@ -51,6 +84,110 @@ mod tests {
         assert_eq!(&g.to_fen(), fen);
     }
 
+    #[test]
+    fn fen_round_trip_test() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 37 60",
+        ];
+        let mut movelist = movegen::MoveList::default();
+        let mut rng = rand::thread_rng();
+        for fen in fens.iter() {
+            for _ in 0..200 {
+                let mut g = GameState::from_fen(fen);
+                for _ in 0..40 {
+                    let other = GameState::from_fen(&g.to_fen());
+                    assert_eq!(other.to_fen(), g.to_fen());
+                    assert_eq!(other.get_hash(), g.get_hash());
+                    assert_eq!(other.get_color_to_move(), g.get_color_to_move());
+                    assert_eq!(other.get_piece_bb_array(), g.get_piece_bb_array());
+                    assert_eq!(other.get_color_bb_array(), g.get_color_bb_array());
+                    assert_eq!(other.castle_permissions(), g.castle_permissions());
+                    assert_eq!(other.get_en_passant(), g.get_en_passant());
+                    assert_eq!(other.get_half_moves(), g.get_half_moves());
+                    assert_eq!(other.get_full_moves(), g.get_full_moves());
+                    assert!(other.get_psqt() == g.get_psqt());
+                    assert!((other.get_phase().phase - g.get_phase().phase).abs() < 1e-4);
+                    movegen::generate_moves(&g, false, &mut movelist);
+                    if movelist.move_list.is_empty() {
+                        break;
+                    }
+                    g = make_move(
+                        &g,
+                        movelist.move_list[rng.gen_range(0, movelist.move_list.len())].0,
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn uncapturable_en_passant_test() {
+        let en_passant_moves = |g: &GameState| {
+            let mut movelist = movegen::MoveList::default();
+            movegen::generate_moves(g, false, &mut movelist);
+            movelist
+                .move_list
+                .iter()
+                .filter(|gmv| gmv.0.move_type == GameMoveType::EnPassant)
+                .count()
+        };
+        //No white pawn next to the d-pawn, the en passant square is kept but not hashed
+        let fen = "4k3/8/8/3p4/8/8/8/4K3 w - d6 0 1";
+        let g = GameState::from_fen(fen);
+        assert_eq!(g.to_fen(), fen);
+        assert_eq!(g.get_en_passant(), 1u64 << 43);
+        assert_eq!(en_passant_moves(&g), 0);
+        assert_eq!(
+            g.get_hash(),
+            GameState::from_fen("4k3/8/8/3p4/8/8/8/4K3 w - - 0 1").get_hash()
+        );
+        //A pawn of the wrong color or on the wrong rank doesn't help
+        let g = GameState::from_fen("4k3/8/8/3pp3/4P3/8/8/4K3 w - d6 0 1");
+        assert_eq!(en_passant_moves(&g), 0);
+        assert_eq!(
+            g.get_hash(),
+            GameState::from_fen("4k3/8/8/3pp3/4P3/8/8/4K3 w - - 0 1").get_hash()
+        );
+        //Capturable
+        let g = GameState::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+        assert_eq!(en_passant_moves(&g), 1);
+        assert_ne!(
+            g.get_hash(),
+            GameState::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - - 0 1").get_hash()
+        );
+
+        //Making the double push gives the same hash as parsing the resulting position
+        for &(fen, expected) in [
+            (
+                "4k3/3p4/8/8/8/8/8/4K3 b - - 0 1",
+                "4k3/8/8/3p4/8/8/8/4K3 w - - 0 2",
+            ),
+            (
+                "4k3/3p4/8/4P3/8/8/8/4K3 b - - 0 1",
+                "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2",
+            ),
+        ]
+        .iter()
+        {
+            let g = GameState::from_fen(fen);
+            let mut movelist = movegen::MoveList::default();
+            movegen::generate_moves(&g, false, &mut movelist);
+            let d7d5 = movelist
+                .move_list
+                .iter()
+                .map(|gmv| gmv.0)
+                .find(|mv| format!("{:?}", mv) == "d7d5")
+                .unwrap();
+            let after = make_move(&g, d7d5);
+            assert_eq!(after.get_en_passant(), 1u64 << 43);
+            assert_eq!(after.get_hash(), GameState::from_fen(expected).get_hash());
+        }
+    }
+
     #[test]
     fn perft_test() {
         let mut movelist = ReservedMoveList::default();
@@ -154,6 +291,156 @@ mod tests {
         }
     }
 
+    #[test]
+    fn castling_test() {
+        //Castle moves of the side to move, as their destination squares
+        let castles = |fen: &str| {
+            let mut movelist = MoveList::default();
+            movegen::generate_moves(&GameState::from_fen(fen), false, &mut movelist);
+            let mut res: Vec<u8> = movelist
+                .move_list
+                .iter()
+                .filter(|gmv| gmv.0.move_type == GameMoveType::Castle)
+                .map(|gmv| gmv.0.to)
+                .collect();
+            res.sort_unstable();
+            res
+        };
+        assert_eq!(castles("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1"), [2, 6]);
+        //Not out of check
+        assert!(castles("4k3/4r3/8/8/8/8/8/R3K2R w KQ - 0 1").is_empty());
+        //Not through an attacked square
+        assert_eq!(castles("4kr2/8/8/8/8/8/8/R3K2R w KQ - 0 1"), [2]);
+        assert_eq!(castles("3rk3/8/8/8/8/8/8/R3K2R w KQ - 0 1"), [6]);
+        //Not into check
+        assert_eq!(castles("4k1r1/8/8/8/8/8/8/R3K2R w KQ - 0 1"), [2]);
+        assert_eq!(castles("2r1k3/8/8/8/8/8/8/R3K2R w KQ - 0 1"), [6]);
+        //An attacked rook or b1, which the king doesn't cross, don't matter, but a piece in between does
+        assert_eq!(castles("4k2r/8/8/8/8/8/8/R3K2R w KQ - 0 1"), [2, 6]);
+        assert_eq!(castles("1r2k3/8/8/8/8/8/8/R3K2R w KQ - 0 1"), [2, 6]);
+        assert_eq!(castles("4k3/8/8/8/8/8/8/RN2K2R w KQ - 0 1"), [6]);
+        assert_eq!(castles("r3k2r/8/8/8/8/8/8/4K3 b kq - 0 1"), [58, 62]);
+        assert_eq!(castles("r3k2r/8/8/8/8/8/8/5RK1 b kq - 0 1"), [58]);
+
+        //Rights are lost by moving the king or the rook, or by losing the rook
+        let rights_after = |fen: &str, mv: &str| {
+            let state = GameState::from_fen(fen);
+            let mut movelist = MoveList::default();
+            let next = make_move(&state, parse_move(&state, mv, &mut movelist).0);
+            (
+                next.castle_white_kingside(),
+                next.castle_white_queenside(),
+                next.castle_black_kingside(),
+                next.castle_black_queenside(),
+            )
+        };
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(rights_after(fen, "e1f1"), (false, false, true, true));
+        assert_eq!(rights_after(fen, "h1h2"), (false, true, true, true));
+        assert_eq!(rights_after(fen, "a1a8"), (true, false, true, false));
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1";
+        assert_eq!(rights_after(fen, "h8h1"), (false, true, false, true));
+        assert_eq!(rights_after(fen, "e8c8"), (true, true, false, false));
+        assert_eq!(
+            rights_after("4k2b/8/8/8/8/8/8/R3K2R b KQ - 0 1", "h8a1"),
+            (true, false, false, false)
+        );
+        //Returning to the corner doesn't bring them back
+        let mut state = GameState::from_fen("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+        for mv in ["h1h2", "e8d8", "h2h1", "d8e8"].iter() {
+            let mut movelist = MoveList::default();
+            state = make_move(&state, parse_move(&state, mv, &mut movelist).0);
+        }
+        assert!(!state.castle_white_kingside());
+        assert_eq!(castles(&state.to_fen()), [2]);
+
+        //Castling positions of the well known perftsuite.epd
+        let mut movelist = ReservedMoveList::default();
+        #[rustfmt::skip]
+        let cases = [
+            (314_346, 4, "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1"),
+            (328_965, 4, "r3k2r/8/8/8/8/8/8/1R2K2R w Kkq - 0 1"),
+            (312_835, 4, "r3k2r/8/8/8/8/8/8/2R1K2R w Kkq - 0 1"),
+            (316_214, 4, "r3k2r/8/8/8/8/8/8/R3K1R1 w Qkq - 0 1"),
+            (334_705, 4, "1r2k2r/8/8/8/8/8/8/R3K2R w KQk - 0 1"),
+            (317_324, 4, "2r1k2r/8/8/8/8/8/8/R3K2R w KQk - 0 1"),
+            (320_792, 4, "r3k1r1/8/8/8/8/8/8/R3K2R w KQq - 0 1"),
+            (532_933, 5, "4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1"),
+            (118_882, 5, "r3k2r/8/8/8/8/8/8/4K3 w kq - 0 1"),
+            (37735, 5, "8/8/8/8/8/8/6k1/4K2R w K - 0 1"),
+            (80619, 5, "8/8/8/8/8/8/1k6/R3K3 w Q - 0 1"),
+            (10485, 5, "4k2r/6K1/8/8/8/8/8/8 w k - 0 1"),
+            (20780, 5, "r3k3/1K6/8/8/8/8/8/8 w q - 0 1"),
+        ];
+        for case in cases.iter() {
+            assert_eq!(
+                case.0,
+                perft(&GameState::from_fen(case.2), case.1, &mut movelist),
+                "{}",
+                case.2
+            );
+        }
+    }
+
+    #[test]
+    fn promotion_capture_test() {
+        //A pawn which can promote straight ahead and by capturing on both diagonals
+        for (fen, left, right) in [
+            ("r1n5/1P2k3/8/8/8/8/8/4K3 w - - 0 1", 56, 58),
+            ("4k3/8/8/8/8/8/1p2K3/R1N5 b - - 0 1", 0, 2),
+        ]
+        .iter()
+        {
+            let mut movelist = MoveList::default();
+            movegen::generate_moves(&GameState::from_fen(fen), false, &mut movelist);
+            let promotions: Vec<_> = movelist
+                .move_list
+                .iter()
+                .filter_map(|gmv| match gmv.0.move_type {
+                    GameMoveType::Promotion(piece, captured) => Some((gmv.0.to, piece, captured)),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(promotions.len(), 12);
+            for piece in [
+                PieceType::Queen,
+                PieceType::Rook,
+                PieceType::Bishop,
+                PieceType::Knight,
+            ]
+            .iter()
+            {
+                assert!(promotions.contains(&(*left, *piece, Some(PieceType::Rook))));
+                assert!(promotions.contains(&(*right, *piece, Some(PieceType::Knight))));
+                assert!(promotions.contains(&((left + right) / 2, *piece, None)));
+            }
+        }
+
+        let mut movelist = ReservedMoveList::default();
+        //https://www.chessprogramming.org/Perft_Results, both colors promoting with and without captures
+        #[rustfmt::skip]
+        let cases = [
+            (24, 1, "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1"),
+            (496, 2, "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1"),
+            (9483, 3, "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1"),
+            (182_838, 4, "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1"),
+            (3_605_103, 5, "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1"),
+            (24, 1, "5n1n/4kPPP/8/8/8/8/pppK4/N1N5 w - - 0 1"),
+            (496, 2, "5n1n/4kPPP/8/8/8/8/pppK4/N1N5 w - - 0 1"),
+            (9483, 3, "5n1n/4kPPP/8/8/8/8/pppK4/N1N5 w - - 0 1"),
+            (182_838, 4, "5n1n/4kPPP/8/8/8/8/pppK4/N1N5 w - - 0 1"),
+            (3_605_103, 5, "5n1n/4kPPP/8/8/8/8/pppK4/N1N5 w - - 0 1"),
+        ];
+        for case in cases.iter() {
+            assert_eq!(
+                case.0,
+                perft(&GameState::from_fen(case.2), case.1, &mut movelist),
+                "{}",
+                case.2
+            );
+        }
+    }
+
     #[test]
     fn zobrist_hash_test() {
         //Tests incremental update of hash
@@ -179,6 +466,51 @@ mod tests {
         }
     }
 
+    #[test]
+    fn material_incremental() {
+        let mut rng = rand::thread_rng();
+        let mut movelist = movegen::MoveList::default();
+        let counters = |g: &GameState| {
+            (
+                [
+                    g.get_non_pawn_material(WHITE),
+                    g.get_non_pawn_material(BLACK),
+                ],
+                g.get_piece_count() as u8,
+            )
+        };
+        for _i in 0..1000 {
+            let mut g = GameState::standard();
+            assert_eq!(counters(&g), g.recount_material());
+            for _j in 0..200 {
+                movegen::generate_moves(&g, false, &mut movelist);
+                if movelist.move_list.is_empty() {
+                    break;
+                }
+                g = make_move(
+                    &g,
+                    movelist.move_list[rng.gen_range(0, movelist.move_list.len())].0,
+                );
+                assert_eq!(counters(&g), g.recount_material());
+            }
+        }
+        //Capturing promotion: black loses the rook, white trades the pawn for a queen
+        let g = GameState::from_fen("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1");
+        movegen::generate_moves(&g, false, &mut movelist);
+        let promotion = movelist
+            .move_list
+            .iter()
+            .map(|gmv| gmv.0)
+            .find(|mv| {
+                mv.move_type == GameMoveType::Promotion(PieceType::Queen, Some(PieceType::Rook))
+            })
+            .unwrap();
+        let next = make_move(&g, promotion);
+        assert_eq!(counters(&next), next.recount_material());
+        assert_eq!(counters(&next), ([PieceType::Queen.to_phase_score(), 0], 3));
+        assert!(!next.has_non_pawns(BLACK));
+    }
+
     #[test]
     fn phase_incremental() {
         let mut rng = rand::thread_rng();
@@ -277,6 +609,102 @@ mod tests {
             }
         }
     }
+    #[test]
+    fn pgn_openings_test() {
+        let pgn = "[Event \"Casual game\"]
+[Result \"1-0\"]
+
+1. e4 {Best by test} e5 2. Nf3 (2. f4 exf4 3. Nf3 (3. Bc4) g5) 2... Nc6 $1
+3. Bb5 a6!? ; The Morphy defence
+4. Ba4 Nf6 5. O-O 1-0
+
+[Event \"Unfinished\"]
+[Result \"*\"]
+
+1. d4 d5 2. c4 *
+
+[Event \"Short\"]
+[Result \"0-1\"]
+
+1. f3 e5 2. g4?? Qh4# 0-1
+
+[Event \"Draw\"]
+[Result \"1/2-1/2\"]
+
+1.d4 Nf6 2.c4 e6 3.Nc3 {Nimzo} 3...Bb4 4.Qc2 O-O 1/2-1/2
+";
+        let path =
+            std::env::temp_dir().join(format!("fabchess_openings_{}.pgn", std::process::id()));
+        std::fs::write(&path, pgn).unwrap();
+        let (states, sequences) = load_db_until(path.to_str().unwrap(), 6);
+        std::fs::remove_file(&path).unwrap();
+        //The unfinished game is skipped, the short one has no position after six plies
+        let fens: Vec<String> = states.iter().map(|state| state.to_fen()).collect();
+        assert_eq!(
+            fens,
+            [
+                "r1bqkbnr/1ppp1ppp/p1n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 0 4",
+                "rnbqk2r/pppp1ppp/4pn2/8/1bPP4/2N5/PP2PPPP/R1BQKBNR w KQkq - 2 4",
+            ]
+        );
+        assert_eq!(sequences.len(), 2);
+        assert!(sequences.iter().all(|sequence| sequence.len() == 6));
+        assert_eq!(
+            format!("{:?}", sequences[0]),
+            "[e2e4, e7e5, g1f3, b8c6, f1b5, a7a6]"
+        );
+    }
+
+    #[test]
+    fn binary_openings_test() {
+        let pgn = "[Result \"1-0\"]
+
+1. e4 a6 2. e5 d5 3. exd6 Nf6 4. dxc7 e6 5. cxd8=Q+ Kxd8 1-0
+
+[Result \"1/2-1/2\"]
+
+1. d4 Nf6 2. c4 e6 3. Nc3 Bb4 4. Qc2 O-O 5. a3 Bxc3+ 1/2-1/2
+
+[Result \"0-1\"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Ba4 Nf6 5. O-O Be7 0-1
+";
+        let dir = std::env::temp_dir();
+        let pgn_path = dir.join(format!(
+            "fabchess_binary_openings_{}.pgn",
+            std::process::id()
+        ));
+        let db_path = dir.join(format!(
+            "fabchess_binary_openings_{}.bin",
+            std::process::id()
+        ));
+        std::fs::write(&pgn_path, pgn).unwrap();
+        let (states, sequences) = load_db_until(pgn_path.to_str().unwrap(), 9);
+        std::fs::remove_file(&pgn_path).unwrap();
+        assert_eq!(states.len(), 3);
+
+        //En passant, promotion and castling moves survive the round trip
+        let db = db_path.to_str().unwrap();
+        save_opening_db(db, &sequences).unwrap();
+        let (loaded_states, loaded_sequences) = load_opening_db(db).unwrap();
+        assert_eq!(
+            format!("{:?}", loaded_sequences),
+            format!("{:?}", sequences)
+        );
+        for (state, loaded) in states.iter().zip(loaded_states.iter()) {
+            assert_eq!(state.to_fen(), loaded.to_fen());
+            assert_eq!(state.get_hash(), loaded.get_hash());
+        }
+        assert_eq!(loaded_states.len(), states.len());
+
+        //A flipped bit is caught by the checksum
+        let mut bytes = std::fs::read(&db_path).unwrap();
+        bytes[14] ^= 1;
+        std::fs::write(&db_path, &bytes).unwrap();
+        assert!(load_opening_db(db).is_err());
+        std::fs::remove_file(&db_path).unwrap();
+    }
+
     #[test]
     fn make_test() {
         let g = GameState::from_fen("4k3/6P1/8/1Pp5/6b1/8/2B5/4K2R w K c6 0 2");
@@ -310,4 +738,1160 @@ mod tests {
             GameState::from_fen("4k3/6P1/8/1Pp5/6b1/3B4/8/4K2R b K - 1 2").get_hash()
         );
     }
+
+    #[test]
+    fn ponder_test() {
+        let g = GameState::standard();
+        let mut movelist = MoveList::default();
+        let e2e4 = parse_move(&g, "e2e4", &mut movelist).0;
+        let e7e5 = parse_move(&make_move(&g, e2e4), "e7e5", &mut movelist).0;
+        let mut pv = PrincipalVariation::new(2);
+        pv.pv[0] = Some(e2e4);
+        pv.pv[1] = Some(e7e5);
+
+        let itcs = InterThreadCommunicationSystem::default();
+        *itcs.best_pv.lock().unwrap() = ScoredPrincipalVariation {
+            score: 0,
+            pv,
+            depth: 2,
+        };
+        assert_eq!(itcs.bestmove_string(), "bestmove e2e4");
+        itcs.uci_options().ponder = true;
+        assert_eq!(itcs.bestmove_string(), "bestmove e2e4 ponder e7e5");
+        itcs.best_pv.lock().unwrap().pv.pv[1] = None;
+        assert_eq!(itcs.bestmove_string(), "bestmove e2e4");
+    }
+
+    #[test]
+    fn king_psqt_endgame_test() {
+        //e4, d4, d5, e5 (from whites point of view)
+        let center = [28, 27, 35, 36];
+        //a1, h1, a8, h8
+        let corners = [0, 7, 56, 63];
+        for &side in [WHITE, BLACK].iter() {
+            let sign = if side == WHITE { 1 } else { -1 };
+            let relative = |sq: usize| if side == WHITE { sq } else { sq ^ 56 };
+            let worst_center = center
+                .iter()
+                .map(|&sq| PieceType::King.to_psqt(side, relative(sq)).1 * sign)
+                .min()
+                .unwrap();
+            let best_corner = corners
+                .iter()
+                .map(|&sq| PieceType::King.to_psqt(side, relative(sq)).1 * sign)
+                .max()
+                .unwrap();
+            assert!(worst_center - best_corner >= 50);
+        }
+        //In a pure king endgame, the psqt of the central king outweighs the one of the king in the corner
+        let central = GameState::from_fen("8/8/8/4k3/8/8/8/K7 w - - 0 1");
+        assert!(central.get_psqt().1 < -50);
+        let central = GameState::from_fen("k7/8/8/8/3K4/8/8/8 w - - 0 1");
+        assert!(central.get_psqt().1 > 50);
+    }
+
+    #[test]
+    fn repetition_test() {
+        let mut movelist = MoveList::default();
+        let mut states = vec![GameState::standard()];
+        for mv in [
+            "g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6", "f3g1", "f6g8",
+        ]
+        .iter()
+        {
+            let last = states.last().unwrap();
+            let next = make_move(last, parse_move(last, mv, &mut movelist).0);
+            states.push(next);
+        }
+        let is_draw = |history: &History, state: &GameState| {
+            matches!(
+                check_for_draw(state, history),
+                SearchInstruction::StopSearching(_)
+            )
+        };
+        //The start position occured twice in the game history, and occurs once more in the search
+        let mut history = History::default();
+        for state in states[..8].iter() {
+            history.push(state.get_hash(), false);
+        }
+        history.set_root();
+        assert!(is_draw(&history, &states[8]));
+
+        //Occuring once in the game history is not enough to claim a draw
+        let mut history = History::default();
+        for state in states[..4].iter() {
+            history.push(state.get_hash(), false);
+        }
+        history.set_root();
+        assert_eq!(history.game_occurrences(&states[4]), 1);
+        assert_eq!(history.search_path_occurrences(&states[4]), 0);
+        assert!(!is_draw(&history, &states[4]));
+
+        //Nor is a prior game occurence followed by a search path that doesn't repeat it
+        let mut history = History::default();
+        for state in states[..4].iter() {
+            history.push(state.get_hash(), false);
+        }
+        history.set_root();
+        for state in states[4..7].iter() {
+            history.push(state.get_hash(), false);
+        }
+        assert_eq!(history.game_occurrences(&states[7]), 1);
+        assert_eq!(history.search_path_occurrences(&states[7]), 0);
+        assert!(!is_draw(&history, &states[7]));
+        assert_eq!(history.search_path_occurrences(&states[8]), 1);
+        assert!(is_draw(&history, &states[8]));
+
+        //Positions before an irreversible move can't be repeated
+        let mut history = History::default();
+        history.push(states[0].get_hash(), false);
+        history.push(states[1].get_hash(), true);
+        for state in states[2..4].iter() {
+            history.push(state.get_hash(), false);
+        }
+        history.set_root();
+        assert_eq!(history.game_occurrences(&states[4]), 0);
+        assert_eq!(history.game_occurrences(&states[5]), 1);
+
+        //But a repetition in the search path is
+        let mut history = History::default();
+        history.set_root();
+        for state in states[..4].iter() {
+            history.push(state.get_hash(), false);
+        }
+        assert!(is_draw(&history, &states[4]));
+    }
+
+    #[test]
+    fn winning_side_avoids_repetition_test() {
+        let search = |state: &GameState, history: &[GameState]| {
+            let itcs = Arc::new(InterThreadCommunicationSystem::default());
+            InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+            *itcs.cache() = Cache::with_size_threaded(1, 1);
+            itcs.uci_options().quiet = true;
+            itcs.uci_options().contempt = 0;
+            let score = search_move(
+                Arc::clone(&itcs),
+                6,
+                state.clone(),
+                history.to_vec(),
+                TimeControl::Infinite,
+            );
+            (score, itcs.played_pv().pv.pv[0].unwrap())
+        };
+        let play = |state: &GameState, mv: &str| {
+            let mut movelist = MoveList::default();
+            movegen::generate_moves(state, false, &mut movelist);
+            movelist
+                .move_list
+                .iter()
+                .map(|gmv| gmv.0)
+                .find(|other| format!("{:?}", other) == mv)
+                .map(|other| make_move(state, other))
+        };
+        let reverse = |mv: &str| format!("{}{}", &mv[2..4], &mv[0..2]);
+        let root = GameState::from_fen("4k3/8/8/8/8/8/R7/4K3 w - - 0 1");
+        let (winning_score, best_move) = search(&root, &[]);
+        assert!(winning_score > 300);
+        let best_move = format!("{:?}", best_move);
+
+        //Build a game in which the best move reaches a position for the third time: the best move, a black
+        //king move, both moves taken back, and the same once more
+        let after_best = play(&root, &best_move).unwrap();
+        let mut movelist = MoveList::default();
+        movegen::generate_moves(&after_best, false, &mut movelist);
+        let (states, black_move) = movelist
+            .move_list
+            .iter()
+            .map(|gmv| format!("{:?}", gmv.0))
+            .find_map(|black_move| {
+                let mut states = vec![root.clone()];
+                for _ in 0..2 {
+                    for mv in [
+                        best_move.clone(),
+                        black_move.clone(),
+                        reverse(&best_move),
+                        reverse(&black_move),
+                    ]
+                    .iter()
+                    {
+                        states.push(play(states.last().unwrap(), mv)?);
+                    }
+                }
+                if states.last().unwrap().get_hash() == root.get_hash() {
+                    Some((states, black_move))
+                } else {
+                    None
+                }
+            })
+            .unwrap();
+
+        //Winning, the repetition is avoided and the score stays
+        let (score, mv) = search(&states[8], &states[..8]);
+        assert_ne!(format!("{:?}", mv), best_move);
+        assert!(score > 300);
+
+        //Losing, every move other than the repetition is worse
+        let (score, mv) = search(&states[7], &states[..7]);
+        assert_eq!(format!("{:?}", mv), reverse(&black_move));
+        assert_eq!(score, 0);
+    }
+
+    #[test]
+    fn hash_zero_test() {
+        let search = |hash_size: usize, fen: &str| {
+            let itcs = Arc::new(InterThreadCommunicationSystem::default());
+            InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+            *itcs.cache() = Cache::with_size_threaded(hash_size, 1);
+            search_move(
+                Arc::clone(&itcs),
+                3,
+                GameState::from_fen(fen),
+                Vec::new(),
+                TimeControl::Infinite,
+            );
+            let best_pv = itcs.best_pv.lock().unwrap();
+            (best_pv.score, best_pv.pv.pv[0])
+        };
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -",
+            "r1bqr1k1/pp1p1ppp/2p5/8/3N1Q2/P2BB3/1PP2PPP/R3K2n b Q - 1 12",
+            "8/8/1p1k2p1/p1prp2p/P2n3P/6P1/1P1R1PK1/4R3 b - - 5 49",
+        ];
+        for fen in fens.iter() {
+            let (score, best_move) = search(0, fen);
+            let (cached_score, cached_best_move) = search(1, fen);
+            assert_eq!(score, cached_score);
+            assert!(best_move == cached_best_move);
+        }
+        let cache = Cache::with_size_threaded(0, 4);
+        assert_eq!(cache.entries, 0);
+        assert_eq!(cache.fill_status(), 0);
+    }
+
+    #[test]
+    fn score_bound_test() {
+        let report = |score: i16, alpha: i16, beta: i16| {
+            uci_score_string(score, ScoreBound::from_window(score, alpha, beta))
+        };
+        //Resolved scores inside the window, including the full window of depth 1
+        for (score, alpha, beta) in [(30, 10, 40), (-15, -20, 0), (250, -16000, 16000)].iter() {
+            let report = report(*score, *alpha, *beta);
+            assert!(!report.contains("lowerbound") && !report.contains("upperbound"));
+        }
+        //Fail-high of an aspiration window
+        for (score, alpha, beta) in [(40, 10, 40), (120, 10, 40)].iter() {
+            let report = report(*score, *alpha, *beta);
+            assert!(report.ends_with(" lowerbound"));
+            assert!(!report.contains("upperbound"));
+        }
+        //Fail-low of an aspiration window
+        for (score, alpha, beta) in [(10, 10, 40), (-80, 10, 40)].iter() {
+            let report = report(*score, *alpha, *beta);
+            assert!(report.ends_with(" upperbound"));
+            assert!(!report.contains("lowerbound"));
+        }
+    }
+
+    #[test]
+    fn perft_parallel_test() {
+        let mut movelist = ReservedMoveList::default();
+        let cases = [
+            (
+                4,
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            ),
+            (
+                3,
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -",
+            ),
+            (4, "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -"),
+            (3, "3r4/6k1/pN1q2p1/Pp6/1PPpp3/4brPP/1Q2R1RK/8 b - c3 0 1"),
+            (
+                0,
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            ),
+        ];
+        for (depth, fen) in cases.iter() {
+            let state = GameState::from_fen(fen);
+            let expected = perft(&state, *depth, &mut movelist);
+            for threads in [1, 2, 3, 8].iter() {
+                assert_eq!(expected, perft_parallel(&state, *depth, *threads));
+            }
+        }
+    }
+
+    #[test]
+    fn fortress_draw_test() {
+        let eval = |fen: &str| eval_game_state(&GameState::from_fen(fen)).final_eval;
+        //KQvKP with a rook or bishop pawn on the seventh, supported by its king and the white king far away
+        for fen in [
+            "8/8/6K1/8/8/8/pk6/4Q3 w - - 0 1",
+            "8/8/6K1/8/8/8/1kp5/4Q3 w - - 0 1",
+            "8/8/8/8/8/8/1kp5/4Q2K b - - 0 1",
+            "4q3/PK6/8/8/8/8/6k1/8 b - - 0 1",
+        ]
+        .iter()
+        {
+            assert!(is_fortress_draw(&GameState::from_fen(fen)));
+            assert!(eval(fen).abs() < 50);
+        }
+        //KRvKP with a supported pawn on the sixth
+        assert!(is_fortress_draw(&GameState::from_fen(
+            "7K/8/8/8/8/1p6/2k5/4R3 w - - 0 1"
+        )));
+        //Center pawns, a close white king, an unsupported pawn or extra material still win
+        for fen in [
+            "8/8/6K1/8/8/8/2kp4/4Q3 w - - 0 1",
+            "8/8/8/8/8/3K4/pk6/4Q3 w - - 0 1",
+            "8/8/6K1/8/8/2k5/p7/4Q3 w - - 0 1",
+            "8/8/6K1/8/8/8/pk6/3NQ3 w - - 0 1",
+            "7K/8/8/8/8/1p6/2k5/4Q3 w - - 0 1",
+        ]
+        .iter()
+        {
+            assert!(!is_fortress_draw(&GameState::from_fen(fen)));
+            assert!(eval(fen) > 500);
+        }
+    }
+
+    #[test]
+    fn fifty_move_scale_test() {
+        let eval = |fen: &str| eval_game_state(&GameState::from_fen(fen)).final_eval;
+        let g = |half_moves: usize| format!("4k3/8/8/8/8/8/3R1PP1/4K3 w - - {} 60", half_moves);
+        assert_eq!(fifty_move_scale(&GameState::from_fen(&g(10))), None);
+        assert_eq!(fifty_move_scale(&GameState::from_fen(&g(80))), None);
+        assert_eq!(fifty_move_scale(&GameState::from_fen(&g(90))), Some(0.5));
+        assert_eq!(fifty_move_scale(&GameState::from_fen(&g(100))), Some(0.));
+        assert!(eval(&g(10)) > 500);
+        assert_eq!(eval(&g(80)), eval(&g(10)));
+        assert!(eval(&g(95)) < eval(&g(10)) / 2);
+        assert!(eval(&g(95)) > 0);
+        assert!(eval(&g(99)) < eval(&g(95)));
+        //Black's advantage is damped the same way
+        let black = "4k3/3r1pp1/8/8/8/8/8/4K3 b - - 95 60";
+        assert!(eval(black) > -eval(&g(10)) / 2 && eval(black) < 0);
+    }
+
+    #[test]
+    fn rook_endgame_test() {
+        let eval = |fen: &str| eval_game_state(&GameState::from_fen(fen)).final_eval;
+        let scale = |fen: &str| rook_endgame_scale(&GameState::from_fen(fen));
+        //Philidor defence, a rook pawn with the king in the corner and the king in front of a pawn on the third
+        for fen in [
+            "4k3/8/1r6/4PK2/8/8/8/3R4 b - - 0 1",
+            "k7/8/8/P7/8/1K6/1r6/4R3 b - - 0 1",
+            "8/5k2/8/8/2K5/4P3/r7/6R1 w - - 0 1",
+            "3r4/8/8/8/4kp2/8/4K3/1R6 w - - 0 1",
+        ]
+        .iter()
+        {
+            assert_eq!(scale(fen), Some(ROOK_ENDGAME_DRAW_SCALE));
+            assert!(eval(fen).abs() < 50);
+        }
+        //Lucena, the defending king is cut off and the white king shelters the pawn
+        let lucena = "1K6/1P1k4/8/8/8/8/r7/2R5 w - - 0 1";
+        assert_eq!(scale(lucena), Some(ROOK_ENDGAME_SCALE));
+        assert!(eval(lucena) > 300);
+        //Rook endgames a pawn up are only mildly scaled
+        assert_eq!(
+            scale("8/5kpp/8/8/8/8/4KPPP/r3R3 w - - 0 1"),
+            Some(ROOK_ENDGAME_SCALE)
+        );
+        //Two extra pawns, an extra rook or other pieces aren't scaled
+        for fen in [
+            "8/5kp1/8/8/8/8/4KPPP/r3R3 w - - 0 1",
+            "8/5kpp/8/8/8/8/4KPPP/r2RR3 w - - 0 1",
+            "8/5kpp/8/8/8/8/4KPPP/rn2RN2 w - - 0 1",
+            "8/5k2/8/8/8/8/4K3/r3R3 w - - 0 1",
+        ]
+        .iter()
+        {
+            assert_eq!(scale(fen), None);
+        }
+    }
+
+    #[test]
+    fn blockade_draw_test() {
+        //White is a pawn up, but the locked chain keeps both kings away from the enemy pawns
+        let locked = "4k3/8/8/1p1p1p2/pPpPpPp1/P1P1P1P1/P7/4K3 w - - 0 1";
+        let state = GameState::from_fen(locked);
+        assert!(is_blockade_draw(&state));
+        assert!(eval_game_state_detailed(&state).sum().1 > 50);
+        assert!(eval_game_state(&state).final_eval.abs() < 10);
+
+        for fen in [
+            //The white king is already behind the chain
+            "4k3/8/7K/1p1p1p2/pPpPpPp1/P1P1P1P1/P7/8 w - - 0 1",
+            //A piece can break through
+            "4k3/8/8/1p1p1p2/pPpPpPp1/P1P1P1P1/P7/4KN2 w - - 0 1",
+            //The a-pawn can still move
+            "4k3/8/8/1p1p1p2/1PpPpPp1/P1P1P1P1/8/4K3 w - - 0 1",
+            //The white pawn on b4 can capture on a5
+            "4k3/8/8/pp1p1p2/1PpPpPp1/P1P1P1P1/P7/4K3 w - - 0 1",
+        ]
+        .iter()
+        {
+            assert!(!is_blockade_draw(&GameState::from_fen(fen)));
+        }
+    }
+
+    #[test]
+    fn pawn_race_test() {
+        let plies =
+            |fen: &str, side: usize| unstoppable_passer_plies(&GameState::from_fen(fen), side);
+        //Rule of the square: the king catches the pawn only from inside the square
+        assert_eq!(plies("8/8/5k2/8/P7/8/8/7K w - - 0 1", WHITE), Some(7));
+        assert_eq!(plies("8/8/4k3/8/P7/8/8/7K w - - 0 1", WHITE), None);
+        assert_eq!(plies("8/8/5k2/8/P7/8/8/7K b - - 0 1", WHITE), None);
+        //Double push from the second rank
+        assert_eq!(plies("8/8/8/7k/8/8/1P6/K7 w - - 0 1", WHITE), Some(9));
+        assert_eq!(plies("8/8/8/6k1/8/8/1P6/K7 w - - 0 1", WHITE), None);
+        //Our own king in front of the pawn costs a tempo
+        assert_eq!(plies("8/8/K7/P3k3/8/8/8/8 w - - 0 1", WHITE), None);
+        assert_eq!(plies("8/8/8/P3k3/8/8/8/7K w - - 0 1", WHITE), Some(5));
+        //Pawn already on the seventh rank
+        assert_eq!(plies("8/P1k5/8/8/8/8/8/7K w - - 0 1", WHITE), Some(1));
+        assert_eq!(plies("8/P1k5/8/8/8/8/8/7K b - - 0 1", WHITE), None);
+        //Black pawns race towards the first rank
+        assert_eq!(plies("k7/8/8/8/7p/8/8/2K5 b - - 0 1", BLACK), Some(5));
+        assert_eq!(plies("k7/8/8/8/7p/8/8/4K3 b - - 0 1", BLACK), None);
+        //A pawn which can be captured by an enemy pawn is not passed
+        assert_eq!(plies("8/1p6/5k2/8/P7/8/8/7K w - - 0 1", WHITE), None);
+
+        assert_eq!(
+            pawn_race(&GameState::from_fen("8/8/5k2/8/P7/8/8/7K w - - 0 1")),
+            1
+        );
+        assert_eq!(
+            pawn_race(&GameState::from_fen("8/8/5k2/8/P7/8/8/7K b - - 0 1")),
+            0
+        );
+        assert_eq!(
+            pawn_race(&GameState::from_fen("k7/8/8/8/7p/8/8/2K5 b - - 0 1")),
+            -1
+        );
+        //Both sides queen at the same time
+        assert_eq!(
+            pawn_race(&GameState::from_fen("7k/8/8/7p/P7/8/8/K7 w - - 0 1")),
+            0
+        );
+        //Pieces on the board turn the race off
+        assert_eq!(
+            pawn_race(&GameState::from_fen("8/8/5k2/8/P7/8/8/6NK w - - 0 1")),
+            0
+        );
+
+        let winning = eval_game_state(&GameState::from_fen("8/8/5k2/8/P7/8/8/7K w - - 0 1"));
+        let caught = eval_game_state(&GameState::from_fen("8/8/4k3/8/P7/8/8/7K w - - 0 1"));
+        assert!(winning.final_eval > 300);
+        assert!(winning.final_eval > caught.final_eval + 200);
+    }
+
+    #[test]
+    fn tempo_sign_test() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR",
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R",
+            "8/5pk1/6p1/8/3R4/6P1/5PK1/8",
+        ]
+        .iter()
+        {
+            let white = GameState::from_fen(&format!("{} w - - 0 1", fen));
+            let black = GameState::from_fen(&format!("{} b - - 0 1", fen));
+            let detailed = eval_game_state_detailed(&white);
+            assert_eq!(detailed.tempo, TEMPO_BONUS);
+            assert_eq!(eval_game_state_detailed(&black).tempo, TEMPO_BONUS * -1);
+            //The eval is from white's point of view and gets multiplied by the color in the search, so the side
+            //to move always gets the bonus
+            let tapered = EvaluationScore(
+                2 * TEMPO_BONUS.0,
+                (f64::from(2 * TEMPO_BONUS.1) / 1.5) as i16,
+            )
+            .interpolate(detailed.phase);
+            let diff = eval_game_state(&white).final_eval - eval_game_state(&black).final_eval;
+            assert!(tapered > 0);
+            assert!(
+                (diff - tapered).abs() <= 2,
+                "{}: {} vs {}",
+                fen,
+                diff,
+                tapered
+            );
+        }
+        //In the symmetrical start position only the tempo is left, and it favours the side to move
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR";
+        let stm_eval = |fen: String, color: i16| {
+            eval_game_state(&GameState::from_fen(&fen)).final_eval * color
+        };
+        assert!(stm_eval(format!("{} w - - 0 1", start), 1) > 0);
+        assert!(stm_eval(format!("{} b - - 0 1", start), -1) > 0);
+    }
+
+    #[test]
+    fn kbn_mating_corner_test() {
+        let eval = |fen: &str| eval_game_state(&GameState::from_fen(fen)).final_eval;
+        //Dark squared bishop, so the king has to go to a1 or h8
+        assert!(!is_guaranteed_draw(&GameState::from_fen(
+            "8/8/8/3k4/8/8/8/2B1KN2 w - - 0 1"
+        )));
+        assert_eq!(
+            kbn_mating_corner(&GameState::from_fen("8/8/3k4/8/8/8/8/2BBK3 w - - 0 1")),
+            0
+        );
+        let walk = [
+            "8/8/8/3k4/8/4K3/8/2B2N2 w - - 0 1",
+            "8/8/8/8/2k5/4K3/8/2B2N2 w - - 0 1",
+            "8/8/8/8/8/1k2K3/8/2B2N2 w - - 0 1",
+            "8/8/8/8/8/4K3/1k6/2B2N2 w - - 0 1",
+            "8/8/8/8/8/4K3/8/k1B2N2 w - - 0 1",
+        ];
+        for pair in walk.windows(2) {
+            assert!(eval(pair[1]) > eval(pair[0]));
+        }
+        let right_corner = eval("8/8/8/8/8/2K5/8/k1B2N2 w - - 0 1");
+        let wrong_corner = eval("k7/8/2K5/8/8/8/8/2B2N2 w - - 0 1");
+        assert!(right_corner > wrong_corner);
+        assert!(right_corner < MATED_IN_MAX.abs());
+        //Mirrored for black
+        assert_eq!(
+            kbn_mating_corner(&GameState::from_fen("2b2n2/1K6/4k3/8/8/8/8/8 b - - 0 1")),
+            -kbn_mating_corner(&GameState::from_fen("8/8/8/8/8/4K3/1k6/2B2N2 w - - 0 1"))
+        );
+    }
+
+    #[test]
+    fn limit_strength_root_scores_test() {
+        let new_itcs = || {
+            let itcs = Arc::new(InterThreadCommunicationSystem::default());
+            InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+            *itcs.cache() = Cache::with_size_threaded(1, 1);
+            itcs.uci_options().quiet = true;
+            itcs
+        };
+        let itcs = new_itcs();
+        itcs.uci_options().limit_strength = true;
+        itcs.uci_options().elo = 1300;
+        let window = deviation_window(1300);
+        //Every king move loses the queen, every queen move keeps it
+        let state = GameState::from_fen("4k3/8/8/4p3/3Q4/8/8/4K3 w - - 0 1");
+        search_move(
+            Arc::clone(&itcs),
+            6,
+            state.clone(),
+            Vec::new(),
+            TimeControl::Infinite,
+        );
+        let root_scores = itcs.root_move_scores.lock().unwrap();
+        let best_score = root_scores
+            .scores
+            .iter()
+            .map(|(_, score)| *score)
+            .max()
+            .unwrap();
+        for (mv, score) in root_scores.scores.iter() {
+            let child_score = -search_move(
+                new_itcs(),
+                5,
+                make_move(&state, *mv),
+                vec![state.clone()],
+                TimeControl::Infinite,
+            );
+            if child_score >= best_score - window {
+                //Candidates for a deviation have an exact score, not the bound of a fail low
+                assert!((score - child_score).abs() <= 80);
+            } else {
+                assert!(*score < best_score - window);
+            }
+        }
+    }
+
+    #[test]
+    fn info_sink_test() {
+        #[derive(Default)]
+        struct RecordingSink(Mutex<Vec<SearchInfo>>);
+        impl InfoSink for RecordingSink {
+            fn on_iteration(&self, info: &SearchInfo) {
+                self.0.lock().unwrap().push(info.clone());
+            }
+        }
+        for &threads in [1, 3].iter() {
+            let itcs = Arc::new(InterThreadCommunicationSystem::default());
+            InterThreadCommunicationSystem::update_thread_count(&itcs, threads);
+            *itcs.cache() = Cache::with_size_threaded(1, threads);
+            let sink = Arc::new(RecordingSink::default());
+            itcs.set_info_sink(Some(sink.clone()));
+            let state = GameState::from_fen(
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -",
+            );
+            search_move(
+                Arc::clone(&itcs),
+                6,
+                state.clone(),
+                Vec::new(),
+                TimeControl::Infinite,
+            );
+            let infos = sink.0.lock().unwrap();
+            let depths: Vec<usize> = infos.iter().map(|info| info.depth).collect();
+            //Helper threads may finish a depth before the one below it is reported,
+            //so with more than one thread depths can be skipped but never repeated
+            if threads == 1 {
+                assert_eq!(depths, (1..=6).collect::<Vec<usize>>());
+            }
+            assert!(depths.windows(2).all(|pair| pair[0] < pair[1]));
+            assert_eq!(depths.last(), Some(&6));
+            for pair in infos.windows(2) {
+                assert!(pair[0].nodes <= pair[1].nodes);
+            }
+            for info in infos.iter() {
+                assert!(!info.pv.is_empty());
+                assert!(info.seldepth >= info.depth);
+                //The pv is playable from the root
+                let mut game_state = state.clone();
+                for mv in info.pv.iter() {
+                    let mut movelist = MoveList::default();
+                    movegen::generate_moves(&game_state, false, &mut movelist);
+                    assert!(movelist.move_list.iter().any(|gmv| gmv.0 == *mv));
+                    game_state = make_move(&game_state, *mv);
+                }
+            }
+            if threads == 1 {
+                let last = infos.last().unwrap();
+                assert_eq!(last.score, itcs.best_pv.lock().unwrap().score);
+            }
+        }
+    }
+
+    #[test]
+    fn dead_draw_root_test() {
+        for fen in [
+            "k7/8/1K6/8/8/8/8/2B5 w - - 0 1",
+            "k7/8/1K6/8/8/8/8/6N1 w - - 0 1",
+            "8/8/8/3k4/8/5n2/8/4K2B b - - 0 1",
+        ]
+        .iter()
+        {
+            let state = GameState::from_fen(fen);
+            let mut movelist = MoveList::default();
+            movegen::generate_moves(&state, false, &mut movelist);
+            //Pretend we've already been in most of the positions reachable from the root
+            let history: Vec<GameState> = movelist
+                .move_list
+                .iter()
+                .skip(1)
+                .take(movelist.move_list.len() - 2)
+                .map(|gmv| make_move(&state, gmv.0))
+                .chain(std::iter::once(state.clone()))
+                .collect();
+            let itcs = Arc::new(InterThreadCommunicationSystem::default());
+            InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+            *itcs.cache() = Cache::with_size_threaded(1, 1);
+            let score = search_move(
+                Arc::clone(&itcs),
+                100,
+                state.clone(),
+                history.clone(),
+                TimeControl::Infinite,
+            );
+            assert_eq!(score, 0);
+            let best_move = itcs.best_pv.lock().unwrap().pv.pv[0].unwrap();
+            assert!(movelist.move_list.iter().any(|gmv| gmv.0 == best_move));
+            let next_state = make_move(&state, best_move);
+            let mut replies = MoveList::default();
+            movegen::generate_moves(&next_state, false, &mut replies);
+            assert!(!replies.move_list.is_empty());
+            assert!(history
+                .iter()
+                .all(|gs| gs.get_hash() != next_state.get_hash()));
+        }
+    }
+
+    #[test]
+    fn contempt_test() {
+        assert_eq!(effective_contempt(50, 0.), 0);
+        assert_eq!(effective_contempt(50, 128.), 50);
+        assert_eq!(effective_contempt(-50, 128.), -50);
+        assert_eq!(effective_contempt(50, 64.), 25);
+        let opening = GameState::standard();
+        assert_eq!(effective_contempt(50, opening.get_phase().phase), 50);
+        let endgame = GameState::from_fen("8/8/4k3/8/8/3RK3/8/8 w - - 0 1");
+        assert_eq!(effective_contempt(50, endgame.get_phase().phase), 0);
+    }
+
+    #[test]
+    fn detailed_eval_test() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - -",
+            "8/8/5k2/8/P7/8/8/7K w - - 0 1",
+            "4k3/8/8/8/8/8/8/2N1K3 b - - 0 1",
+        ]
+        .iter()
+        {
+            let state = GameState::from_fen(fen);
+            let detailed = eval_game_state_detailed(&state);
+            assert_eq!(detailed.final_eval, eval_game_state(&state).final_eval);
+            assert_eq!(detailed.phase, state.get_phase().phase);
+            assert_eq!(detailed.evaluate(&state), detailed.final_eval);
+        }
+    }
+
+    #[test]
+    fn pawn_target_masking_test() {
+        //Only the diagonal onto an enemy piece is a capture, the other one holds an own piece or nothing
+        for fen in [
+            "4k3/8/8/8/8/1P6/2P5/4K3 w - - 0 1",
+            "4k3/8/8/8/8/1Pn5/2P5/4K3 w - - 0 1",
+            "4k3/2p5/1p1N4/8/8/8/8/4K3 b - - 0 1",
+        ]
+        .iter()
+        {
+            let state = GameState::from_fen(fen);
+            let mut movelist = MoveList::default();
+            movegen::generate_moves(&state, false, &mut movelist);
+            let pawn_captures = movelist
+                .move_list
+                .iter()
+                .filter(|gmv| gmv.0.piece_type == PieceType::Pawn && gmv.0.is_capture())
+                .count();
+            let enemy_pawn_targets = movegen::pawn_targets(
+                state.get_color_to_move(),
+                state.get_piece(PieceType::Pawn, state.get_color_to_move()),
+            ) & state.get_pieces_from_side(1 - state.get_color_to_move());
+            assert_eq!(pawn_captures, enemy_pawn_targets.count_ones() as usize);
+        }
+        //Both diagonals of the black pawn guard the white king's escape squares
+        let stalemate = GameState::from_fen("8/8/8/8/8/5k2/5p2/5K2 w - - 0 1");
+        let mut movelist = MoveList::default();
+        let agsi = movegen::generate_moves(&stalemate, false, &mut movelist);
+        assert!(movelist.move_list.is_empty());
+        assert!(!agsi.stm_incheck);
+        //While a pawn's own piece on one diagonal doesn't stop a capture on the other
+        let state = GameState::from_fen("4k3/8/8/8/8/1P1n4/2P5/4K3 w - - 0 1");
+        movegen::generate_moves(&state, false, &mut movelist);
+        assert!(movelist
+            .move_list
+            .iter()
+            .any(|gmv| gmv.0.piece_type == PieceType::Pawn && gmv.0.to == 19));
+        //Only the knight gives check, none of the pawns' diagonals leak into the attackers
+        assert_eq!(
+            state.square_attackers(4, state.get_all_pieces()),
+            1u64 << 19
+        );
+    }
+
+    #[test]
+    fn beta_cutoff_histogram_test() {
+        let itcs = Arc::new(InterThreadCommunicationSystem::default());
+        InterThreadCommunicationSystem::update_thread_count(&itcs, 2);
+        *itcs.cache() = Cache::with_size_threaded(1, 2);
+        let state =
+            GameState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -");
+        let search = |depth: i16| {
+            search_move(
+                Arc::clone(&itcs),
+                depth,
+                state.clone(),
+                Vec::new(),
+                TimeControl::Infinite,
+            );
+            itcs.beta_cutoff_histogram()
+        };
+        let deep = search(7);
+        assert!(deep.iter().sum::<u64>() > 0);
+        //Good move ordering cuts on the first move most of the time
+        assert!(deep[0] > deep[1] + deep[2] + deep[3]);
+        //Every search starts counting from scratch
+        let shallow = search(2);
+        assert!(shallow.iter().sum::<u64>() < deep.iter().sum::<u64>());
+    }
+
+    #[test]
+    fn stop_plays_completed_iteration_test() {
+        #[derive(Default)]
+        struct RecordingSink(Mutex<Vec<SearchInfo>>);
+        impl InfoSink for RecordingSink {
+            fn on_iteration(&self, info: &SearchInfo) {
+                self.0.lock().unwrap().push(info.clone());
+            }
+        }
+        let itcs = Arc::new(InterThreadCommunicationSystem::default());
+        InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+        *itcs.cache() = Cache::with_size_threaded(1, 1);
+        let sink = Arc::new(RecordingSink::default());
+        itcs.set_info_sink(Some(sink.clone()));
+        let state =
+            GameState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -");
+        //Running out of time aborts the search in the middle of an iteration
+        let score = search_move(
+            Arc::clone(&itcs),
+            100,
+            state.clone(),
+            Vec::new(),
+            TimeControl::MoveTime(200),
+        );
+        let last = sink.0.lock().unwrap().last().cloned().unwrap();
+        let played = itcs.played_pv();
+        assert_eq!(played.depth, last.depth);
+        assert_eq!(played.pv.pv[0], Some(last.pv[0]));
+        assert_eq!(score, last.score);
+        assert!(itcs
+            .bestmove_string()
+            .starts_with(&format!("bestmove {:?}", last.pv[0])));
+
+        //A deeper result of an unfinished iteration does not replace the completed one
+        let mut movelist = MoveList::default();
+        movegen::generate_moves(&state, false, &mut movelist);
+        let other_move = movelist
+            .move_list
+            .iter()
+            .map(|gmv| gmv.0)
+            .find(|mv| *mv != last.pv[0])
+            .unwrap();
+        let mut partial_pv = PrincipalVariation::new(1);
+        partial_pv.pv[0] = Some(other_move);
+        itcs.register_pv(
+            &ScoredPrincipalVariation {
+                score: 10000,
+                pv: partial_pv,
+                depth: last.depth + 1,
+            },
+            ScoreBound::Exact,
+        );
+        assert_eq!(itcs.played_pv().pv.pv[0], Some(last.pv[0]));
+    }
+
+    #[test]
+    fn trapped_pieces_test() {
+        let coefficients = |fen: &str| {
+            let trace = eval_game_state(&GameState::from_fen(fen)).trace;
+            (
+                trace.normal_coeffs[IDX_BISHOP_TRAPPED],
+                trace.normal_coeffs[IDX_ROOK_TRAPPED_BY_KING],
+                trace.normal_coeffs[IDX_KNIGHT_TRAPPED_ON_RIM],
+            )
+        };
+        assert_eq!(
+            coefficients("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            (0, 0, 0)
+        );
+        //Bxa7 b6 and Bxh7 g6
+        assert_eq!(coefficients("4k3/B4ppp/1p6/8/8/8/5PPP/4K3 w - - 0 1").0, 1);
+        assert_eq!(coefficients("4k3/Bp3ppp/8/8/8/8/5PPP/4K3 w - - 0 1").0, 0);
+        assert_eq!(coefficients("6k1/5p1B/6p1/8/8/8/8/6K1 w - - 0 1").0, 1);
+        assert_eq!(coefficients("4k3/5ppp/8/8/8/6P1/5PPb/4K3 w - - 0 1").0, -1);
+        //The king stepped aside instead of castling
+        assert_eq!(
+            coefficients("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1K1R w kq - 0 1").1,
+            1
+        );
+        assert_eq!(
+            coefficients("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQK2R w KQkq - 0 1").1,
+            0
+        );
+        assert_eq!(coefficients("4k3/8/8/8/8/8/PPP5/1K1R4 w - - 0 1").1, 0);
+        assert_eq!(coefficients("4k3/8/8/8/8/8/PPP5/RK6 w - - 0 1").1, 1);
+        assert_eq!(
+            coefficients("rnbq1k1r/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ - 0 1").1,
+            -1
+        );
+        //Every square of the knight is covered by enemy pawns or blocked by its own pieces
+        assert_eq!(coefficients("4k3/7p/4p3/8/6pN/8/6P1/4K3 w - - 0 1").2, 1);
+        assert_eq!(coefficients("4k3/7p/8/8/6pN/8/6P1/4K3 w - - 0 1").2, 0);
+        assert_eq!(coefficients("4k3/6p1/8/8/8/8/8/n3K3 w - - 0 1").2, 0);
+        assert_eq!(coefficients("4k3/1p6/2p5/n7/8/3P4/P7/4K3 w - - 0 1").2, -1);
+    }
+
+    #[test]
+    fn bishop_pair_test() {
+        let values = |fen: &str| {
+            let mut trace = LargeTrace::default();
+            let white = piece_values(true, &GameState::from_fen(fen), &mut trace);
+            (white, trace)
+        };
+        //Whatever the second bishop adds on top of its piece value
+        let pair_bonus = |with_pair: &str, without_pair: &str| {
+            values(with_pair).0 - values(without_pair).0 - BISHOP_PIECE_VALUE
+        };
+        let open = pair_bonus(
+            "4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4KB2 w - - 0 1",
+        );
+        let closed = pair_bonus(
+            "4k3/pppppppp/8/8/8/8/PPPPPPPP/2B1KB2 w - - 0 1",
+            "4k3/pppppppp/8/8/8/8/PPPPPPPP/4KB2 w - - 0 1",
+        );
+        assert!(open.0 > closed.0);
+        assert!(open.1 > closed.1);
+        assert!(closed.0 > 0 && closed.1 > 0);
+
+        let trace = values("4k3/pppppppp/8/8/8/8/PPPPPPPP/2B1KB2 w - - 0 1").1;
+        assert_eq!(trace.normal_coeffs[IDX_BISHOP_PAIR_WITH_PAWNS + 16], 1);
+        let trace = values("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1").1;
+        assert_eq!(trace.normal_coeffs[IDX_BISHOP_PAIR_WITH_PAWNS], 1);
+        //A single bishop gets nothing
+        let trace = values("4k3/8/8/8/8/8/8/4KB2 w - - 0 1").1;
+        assert!(trace.normal_coeffs
+            [IDX_BISHOP_PAIR_WITH_PAWNS..IDX_BISHOP_PAIR_WITH_PAWNS + SIZE_BISHOP_PAIR_WITH_PAWNS]
+            .iter()
+            .all(|&coeff| coeff == 0));
+    }
+
+    #[test]
+    fn behind_pawn_chain_test() {
+        let coefficients = |fen: &str| {
+            let trace = eval_game_state(&GameState::from_fen(fen)).trace;
+            (
+                trace.normal_coeffs[IDX_KNIGHT_BEHIND_PAWN_CHAIN],
+                trace.normal_coeffs[IDX_BISHOP_BEHIND_PAWN_CHAIN],
+                trace.normal_coeffs
+                    [IDX_BISHOP_MOBILITY..IDX_BISHOP_MOBILITY + SIZE_BISHOP_MOBILITY]
+                    .to_vec(),
+            )
+        };
+        let start = coefficients("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!((start.0, start.1), (0, 0));
+        //The bishop on b2 only sees a1 and c1 in both positions, but only behind rammed pawns it is boxed in
+        let boxed = coefficients("6k1/8/8/8/p1p5/P1P5/1B6/6K1 w - - 0 1");
+        let free = coefficients("6k1/8/8/p1p5/8/P1P5/1B6/6K1 w - - 0 1");
+        assert_eq!(boxed.1, 1);
+        assert_eq!(free.1, 0);
+        assert_eq!(boxed.2, free.2);
+        assert_eq!(coefficients("6k1/1b6/p1p5/P1P5/8/8/8/6K1 w - - 0 1").1, -1);
+        //Every square of the knight on b1 is taken by its own pawns
+        assert_eq!(coefficients("6k1/8/8/8/p1p5/P1P5/3P4/1N4K1 w - - 0 1").0, 1);
+        assert_eq!(
+            coefficients("1n4k1/3p4/p1p5/P1P5/8/8/8/6K1 w - - 0 1").0,
+            -1
+        );
+        assert_eq!(coefficients("6k1/8/8/8/8/P1P5/3P4/1N4K1 w - - 0 1").0, 0);
+
+        let eval = |fen: &str| eval_game_state(&GameState::from_fen(fen)).final_eval;
+        let boxed = eval("6k1/8/8/8/p1p5/P1P5/1B6/6K1 w - - 0 1");
+        let developed = eval("6k1/8/8/8/p1p1B3/P1P5/8/6K1 w - - 0 1");
+        assert!(boxed < developed);
+    }
+
+    #[test]
+    fn connected_rooks_test() {
+        let coefficients = |fen: &str| {
+            let trace = eval_game_state(&GameState::from_fen(fen)).trace;
+            (
+                trace.normal_coeffs[IDX_ROOKS_CONNECTED],
+                trace.normal_coeffs[IDX_ROOKS_DOUBLED_ON_OPEN],
+            )
+        };
+        //Connected on the back rank, or separated by the bishop
+        assert_eq!(
+            coefficients("6k1/5ppp/8/8/8/8/5PPP/R4RK1 w - - 0 1"),
+            (1, 0)
+        );
+        assert_eq!(
+            coefficients("6k1/5ppp/8/8/8/8/5PPP/R2B1RK1 w - - 0 1"),
+            (0, 0)
+        );
+        assert_eq!(
+            coefficients("6k1/5ppp/8/8/8/8/5PPP/R3K2R w - - 0 1"),
+            (0, 0)
+        );
+        //Doubled on the open d-file, on a file with an own pawn they are only connected
+        assert_eq!(
+            coefficients("6k1/5ppp/8/8/8/3R4/5PPP/3R2K1 w - - 0 1"),
+            (0, 1)
+        );
+        assert_eq!(
+            coefficients("6k1/5ppp/8/8/3P4/3R4/5PPP/3R2K1 w - - 0 1"),
+            (1, 0)
+        );
+        assert_eq!(
+            coefficients("6k1/5ppp/8/3R4/3P4/3R4/5PPP/6K1 w - - 0 1"),
+            (0, 0)
+        );
+        //Half-open for black
+        assert_eq!(
+            coefficients("3r2k1/3r1ppp/8/8/8/3P4/5PPP/6K1 w - - 0 1"),
+            (0, -1)
+        );
+        //Every pair is counted once
+        assert_eq!(
+            coefficients("6k1/5ppp/8/8/8/8/5PPP/RR1R2K1 w - - 0 1"),
+            (2, 0)
+        );
+    }
+
+    #[test]
+    fn passer_king_square_test() {
+        let coefficients = |fen: &str| {
+            let trace = eval_game_state(&GameState::from_fen(fen)).trace;
+            (
+                trace.normal_coeffs[IDX_PAWN_PASSED_ESCORTED],
+                trace.normal_coeffs[IDX_PAWN_PASSED_KING_IN_SQUARE],
+            )
+        };
+        //Our king escorts the passer from in front of it, not from behind or from far away
+        assert_eq!(coefficients("k7/8/3K4/4P3/8/8/8/8 w - - 0 1"), (1, 0));
+        assert_eq!(coefficients("k7/8/5K2/4P3/8/8/8/8 w - - 0 1"), (1, 0));
+        assert_eq!(coefficients("k7/8/8/4P3/8/3K4/8/8 w - - 0 1"), (0, 0));
+        assert_eq!(coefficients("k7/8/7K/4P3/8/8/8/8 w - - 0 1"), (0, 0));
+        assert_eq!(coefficients("8/8/8/8/4p3/5k2/8/K7 b - - 0 1"), (-1, 0));
+        //The enemy king is in the square, or can step into it being the side to move
+        assert_eq!(coefficients("8/8/8/4k3/P7/8/8/7K w - - 0 1"), (0, 1));
+        assert_eq!(coefficients("8/8/8/5k2/P7/8/8/7K w - - 0 1"), (0, 0));
+        assert_eq!(coefficients("8/8/8/5k2/P7/8/8/7K b - - 0 1"), (0, 1));
+        assert_eq!(coefficients("7k/8/8/p7/4K3/8/8/8 b - - 0 1"), (0, -1));
+        //Double push from the second rank
+        assert_eq!(coefficients("8/8/8/5k2/8/8/P7/7K w - - 0 1"), (0, 1));
+        assert_eq!(coefficients("8/8/8/6k1/8/8/P7/7K w - - 0 1"), (0, 0));
+        //Our own king in front of the pawn has to step out of the way first
+        assert_eq!(coefficients("k7/4K3/8/4P3/8/8/8/8 w - - 0 1"), (1, 1));
+        //With a piece the enemy doesn't rely on its king to stop the passer
+        assert_eq!(coefficients("n7/8/8/4k3/P7/8/8/7K w - - 0 1"), (0, 0));
+    }
+
+    #[test]
+    fn doubled_pawns_test() {
+        let coefficients = |fen: &str| {
+            let trace = eval_game_state(&GameState::from_fen(fen)).trace;
+            (
+                trace.normal_coeffs[IDX_PAWN_DOUBLED],
+                trace.normal_coeffs[IDX_PAWN_DOUBLED_HALF_OPEN],
+                trace.normal_coeffs[IDX_PAWN_TRIPLED],
+            )
+        };
+        let penalty = |fen: &str| {
+            let (doubled, half_open, tripled) = coefficients(fen);
+            PAWN_DOUBLED_VALUE * i16::from(doubled)
+                + PAWN_DOUBLED_HALF_OPEN_VALUE * i16::from(half_open)
+                + PAWN_TRIPLED_VALUE * i16::from(tripled)
+        };
+        let half_open = "4k3/8/8/8/3P4/3P4/8/4K3 w - - 0 1";
+        let closed = "4k3/3p4/8/8/3P4/3P4/8/4K3 w - - 0 1";
+        let tripled = "4k3/3p4/8/3P4/3P4/3P4/8/4K3 w - - 0 1";
+        let tripled_half_open = "4k3/8/8/3P4/3P4/3P4/8/4K3 w - - 0 1";
+        assert_eq!(coefficients(half_open), (1, 1, 0));
+        assert_eq!(coefficients(closed), (1, 0, 0));
+        assert_eq!(coefficients(tripled), (2, 0, 1));
+        assert_eq!(coefficients(tripled_half_open), (2, 2, 1));
+        assert_eq!(
+            coefficients("4k3/8/2p5/2p5/8/8/8/4K3 w - - 0 1"),
+            (-1, -1, 0)
+        );
+        assert_eq!(
+            coefficients("4k3/8/8/8/8/8/PPPPPPPP/4K3 w - - 0 1"),
+            (0, 0, 0)
+        );
+        //Half-open is less bad than closed, tripled is worse than doubled
+        for &(better, worse) in [
+            (half_open, closed),
+            (closed, tripled),
+            (tripled_half_open, tripled),
+            (half_open, tripled_half_open),
+        ]
+        .iter()
+        {
+            let (better, worse) = (penalty(better), penalty(worse));
+            assert!(better.0 > worse.0 && better.1 > worse.1);
+        }
+        assert!(penalty(closed).0 < 0 && penalty(closed).1 < 0);
+    }
+
+    #[test]
+    fn candidate_passer_and_lever_test() {
+        let coefficients = |fen: &str| {
+            let trace = eval_game_state(&GameState::from_fen(fen)).trace;
+            (
+                trace.normal_coeffs[IDX_PAWN_CANDIDATE_PASSER],
+                trace.normal_coeffs[IDX_PAWN_LEVER],
+            )
+        };
+        //c4 can get passed by trading b3 for b6, while c4 blocked by c5 can't
+        let candidate = "4k3/8/1p6/8/2P5/1P6/8/4K3 w - - 0 1";
+        let blocked = "4k3/8/8/2p5/2P5/1P6/8/4K3 w - - 0 1";
+        assert_eq!(coefficients(candidate), (1, 0));
+        assert_eq!(coefficients(blocked), (0, 0));
+        let bonus = |fen: &str| PAWN_CANDIDATE_PASSER_VALUE * i16::from(coefficients(fen).0);
+        assert!(bonus(candidate).0 > bonus(blocked).0);
+        //More sentries than helpers, and passers are no candidates
+        assert_eq!(
+            coefficients("4k3/8/1p1p4/8/2P5/1P6/8/4K3 w - - 0 1"),
+            (0, 0)
+        );
+        assert_eq!(coefficients("4k3/8/8/8/2P5/1P6/8/4K3 w - - 0 1"), (0, 0));
+        assert_eq!(coefficients("4k3/5p2/6p1/8/8/5P2/8/4K3 b - - 0 1"), (-1, 0));
+
+        //Both pawns of a mutual lever count, unless the attacked pawn is defended by a pawn. With e6 behind it
+        //d5 is a candidate as well
+        assert_eq!(coefficients("4k3/8/8/3p4/2P5/8/8/4K3 w - - 0 1"), (0, 0));
+        assert_eq!(
+            coefficients("4k3/8/4p3/3p4/2P5/8/8/4K3 w - - 0 1"),
+            (-1, -1)
+        );
+        assert_eq!(
+            coefficients("4k3/8/4p3/3p4/2P5/1P6/8/4K3 w - - 0 1"),
+            (0, 0)
+        );
+        let black_lever =
+            PAWN_LEVER_VALUE * i16::from(coefficients("4k3/8/4p3/3p4/2P5/8/8/4K3 w - - 0 1").1);
+        assert!(black_lever.0 < 0);
+    }
+
+    //Side to move gets mated within n moves of the opponent, counted without any pruning
+    fn mated_within(state: &GameState, n: usize) -> bool {
+        let mut movelist = MoveList::default();
+        let agsi = movegen::generate_moves(state, false, &mut movelist);
+        if movelist.move_list.is_empty() {
+            return agsi.stm_incheck;
+        }
+        n > 0
+            && movelist
+                .move_list
+                .iter()
+                .all(|gmv| mates_within(&make_move(state, gmv.0), n))
+    }
+
+    fn mates_within(state: &GameState, n: usize) -> bool {
+        let mut movelist = MoveList::default();
+        movegen::generate_moves(state, false, &mut movelist);
+        movelist
+            .move_list
+            .iter()
+            .any(|gmv| mated_within(&make_move(state, gmv.0), n - 1))
+    }
+
+    #[test]
+    fn longest_resistance_test() {
+        //Every position has a move that gets mated a move earlier than the others
+        let positions = [
+            ("8/8/8/4R3/8/2K5/k7/8 b - - 0 1", 2),
+            ("8/8/8/8/8/2K1Q3/k7/8 b - - 0 1", 2),
+            ("7k/8/6K1/8/8/8/8/Rn6 b - - 0 1", 2),
+            ("8/8/8/8/8/2K2R2/k7/8 b - - 0 1", 3),
+            ("8/8/8/8/8/2R2K2/7k/8 b - - 0 1", 3),
+        ];
+        for (fen, mated_in) in positions.iter() {
+            let state = GameState::from_fen(fen);
+            assert!(mated_within(&state, *mated_in));
+            assert!(!mated_within(&state, mated_in - 1));
+
+            let itcs = Arc::new(InterThreadCommunicationSystem::default());
+            InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+            *itcs.cache() = Cache::with_size_threaded(1, 1);
+            let score = search_move(
+                Arc::clone(&itcs),
+                10,
+                state.clone(),
+                Vec::new(),
+                TimeControl::Infinite,
+            );
+            assert_eq!(
+                uci_score_string(score, ScoreBound::Exact),
+                format!("score mate -{}", mated_in)
+            );
+            let mv = itcs.best_pv.lock().unwrap().pv.pv[0].unwrap();
+            assert!(!mates_within(&make_move(&state, mv), mated_in - 1));
+        }
+    }
 }
@@ -8,6 +8,7 @@ use core_sdk::board_representation::game_state::{BLACK, WHITE};
 pub use core_sdk::evaluation::parameters::{normal_parameters::*, special_parameters::*, *};
 use core_sdk::evaluation::trace::CollapsedTrace;
 use rand::{seq::SliceRandom, thread_rng};
+use std::thread;
 
 pub const POSITION_FILE: &str = "D:/Users/fabia/Schach/TuningData/E12.41-1M-D12-Resolved.epd";
 pub const PARAM_FILE: &str = "D:/Users/fabia/Schach/TuningData/E12.41-1M-D12-Resolved";
@@ -24,6 +25,7 @@ pub const TUNE_PASSED_PAWN_NOT_BLOCKED: bool = true;
 
 pub const TUNE_KNIGHTS: bool = true;
 pub const TUNE_FILES: bool = true;
+pub const TUNE_TRAPPED_PIECES: bool = true;
 
 pub const TUNE_PIECE_VALUES: bool = true;
 pub const TUNE_MOBILITY: bool = true;
@@ -35,7 +37,11 @@ pub const TUNE_PSQT: bool = true;
 pub const TUNABLE_PARAM: [bool; NORMAL_PARAMS] = init_tunable_param();
 
 pub const OPTIMIZE_K: bool = false;
+//Interval and precision of the K search of the tune-k mode
+pub const K_SEARCH_BOUNDS: (f32, f32) = (0.1, 4.);
+pub const K_SEARCH_PRECISION: f32 = 1e-4;
 pub const BATCH_SIZE: usize = 10000000;
+pub const PROCESSORS: usize = 4;
 pub const START_LEARNING_RATE: f32 = 2.;
 pub const L1_REGULARIZATION: f32 = 0.;
 pub const L2_REGULARIZATION: f32 = 0.;
@@ -59,8 +65,12 @@ pub const fn init_tunable_param() -> [bool; NORMAL_PARAMS] {
         }
         if TUNE_PAWNS {
             res[IDX_PAWN_DOUBLED] = true;
+            res[IDX_PAWN_DOUBLED_HALF_OPEN] = true;
+            res[IDX_PAWN_TRIPLED] = true;
             res[IDX_PAWN_ISOLATED] = true;
             res[IDX_PAWN_BACKWARD] = true;
+            res[IDX_PAWN_CANDIDATE_PASSER] = true;
+            res[IDX_PAWN_LEVER] = true;
             res[IDX_PAWN_ATTACK_CENTER] = true;
             res[IDX_PAWN_MOBILITY] = true;
             let mut i = 0;
@@ -98,6 +108,8 @@ pub const fn init_tunable_param() -> [bool; NORMAL_PARAMS] {
             res[IDX_ROOK_BEHIND_SUPPORT_PASSER] = true;
             res[IDX_ROOK_BEHIND_ENEMY_PASSER] = true;
             res[IDX_PAWN_PASSED_WEAK] = true;
+            res[IDX_PAWN_PASSED_ESCORTED] = true;
+            res[IDX_PAWN_PASSED_KING_IN_SQUARE] = true;
         }
         if TUNE_KNIGHTS {
             res[IDX_KNIGHT_SUPPORTED] = true;
@@ -113,12 +125,26 @@ pub const fn init_tunable_param() -> [bool; NORMAL_PARAMS] {
             res[IDX_QUEEN_ON_OPEN] = true;
             res[IDX_QUEEN_ON_SEMI_OPEN] = true;
             res[IDX_ROOK_ON_SEVENTH] = true;
+            res[IDX_ROOKS_CONNECTED] = true;
+            res[IDX_ROOKS_DOUBLED_ON_OPEN] = true;
+        }
+        if TUNE_TRAPPED_PIECES {
+            res[IDX_BISHOP_TRAPPED] = true;
+            res[IDX_ROOK_TRAPPED_BY_KING] = true;
+            res[IDX_KNIGHT_TRAPPED_ON_RIM] = true;
+            res[IDX_KNIGHT_BEHIND_PAWN_CHAIN] = true;
+            res[IDX_BISHOP_BEHIND_PAWN_CHAIN] = true;
         }
         if TUNE_PIECE_VALUES {
             res[IDX_PAWN_PIECE_VALUE] = true;
             res[IDX_KNIGHT_PIECE_VALUE] = true;
             res[IDX_BISHOP_PIECE_VALUE] = true;
             res[IDX_BISHOP_PAIR] = true;
+            let mut i = 0;
+            while i < SIZE_BISHOP_PAIR_WITH_PAWNS {
+                res[IDX_BISHOP_PAIR_WITH_PAWNS + i] = true;
+                i += 1;
+            }
             res[IDX_ROOK_PIECE_VALUE] = true;
             res[IDX_QUEEN_PIECE_VALUE] = true;
             let mut i = 0;
@@ -170,6 +196,7 @@ pub struct Tuner {
     pub k: f32,
     pub positions: Vec<TexelState>,
     pub params: Parameters,
+    pub processors: usize, //Threads the loss and gradient computation are split across
 }
 
 pub fn update_evaluations(tuner: &mut Tuner) {
@@ -196,16 +223,33 @@ pub fn regularization(term: f32) -> f32 {
 }
 
 pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Parameters {
+    let portion = 2. / (to - from) as f32;
+    let (params, k) = (&tuner.params, tuner.k);
+    let chunk_size = chunk_size(to - from, tuner.processors);
+    let partial_gradients = map_parallel(
+        tuner.positions[from..to].chunks_mut(chunk_size),
+        |positions| partial_gradient(params, k, positions),
+    );
+    let mut gradient = Parameters::zero();
+    for partial_gradient in partial_gradients.iter() {
+        gradient.add(partial_gradient, 1.);
+    }
+    gradient.scale(portion);
+    add_regularization(&mut gradient, &tuner.params, portion);
+    gradient
+}
+
+//Unscaled gradient of a chunk of positions, also updating their evaluations
+pub fn partial_gradient(params: &Parameters, k: f32, positions: &mut [TexelState]) -> Parameters {
     let mut gradient = Parameters::zero();
-    for pos in tuner.positions[from..to].iter_mut() {
+    for pos in positions.iter_mut() {
         //Step 1. Update evaluation
-        pos.eval = pos.trace.evaluate(&tuner.params);
+        pos.eval = pos.trace.evaluate(params);
     }
-    //let g = tuner.k * 10f32.ln() / 400.0;
-    let portion = 2. / (to - from) as f32;
-    for pos in tuner.positions[from..to].iter() {
+    //let g = k * 10f32.ln() / 400.0;
+    for pos in positions.iter() {
         //Step 2. Calculate first half of gradient
-        let s = sigmoid(tuner.k, pos.eval);
+        let s = sigmoid(k, pos.eval);
         let start_of_gradient = (pos.label - s) * s * (1. - s);
         let devaldmg = pos.trace.phase / 128.0;
         let devaldeg = (1. - pos.trace.phase / 128.0) / 1.5;
@@ -229,21 +273,21 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
             for i in 0..2 {
                 let devaldg = if i == 0 { devaldmg } else { devaldeg };
                 let attack_knight_white = f32::from(pos.trace.knight_attacked_sq[WHITE])
-                    * tuner.params.special[IDX_KNIGHT_ATTACK_VALUE + i];
+                    * params.special[IDX_KNIGHT_ATTACK_VALUE + i];
                 let attack_bishop_white = f32::from(pos.trace.bishop_attacked_sq[WHITE])
-                    * tuner.params.special[IDX_BISHOP_ATTACK_VALUE + i];
+                    * params.special[IDX_BISHOP_ATTACK_VALUE + i];
                 let attack_rook_white = f32::from(pos.trace.rook_attacked_sq[WHITE])
-                    * tuner.params.special[IDX_ROOK_ATTACK_VALUE + i];
+                    * params.special[IDX_ROOK_ATTACK_VALUE + i];
                 let attack_queen_white = f32::from(pos.trace.queen_attacked_sq[WHITE])
-                    * tuner.params.special[IDX_QUEEN_ATTACK_VALUE + i];
+                    * params.special[IDX_QUEEN_ATTACK_VALUE + i];
                 let knight_check_white = f32::from(pos.trace.knight_safe_check[WHITE])
-                    * tuner.params.special[IDX_KNIGHT_CHECK_VALUE + i];
+                    * params.special[IDX_KNIGHT_CHECK_VALUE + i];
                 let bishop_check_white = f32::from(pos.trace.bishop_safe_check[WHITE])
-                    * tuner.params.special[IDX_BISHOP_CHECK_VALUE + i];
+                    * params.special[IDX_BISHOP_CHECK_VALUE + i];
                 let rook_check_white = f32::from(pos.trace.rook_safe_check[WHITE])
-                    * tuner.params.special[IDX_ROOK_CHECK_VALUE + i];
+                    * params.special[IDX_ROOK_CHECK_VALUE + i];
                 let queen_check_white = f32::from(pos.trace.queen_safe_check[WHITE])
-                    * tuner.params.special[IDX_QUEEN_CHECK_VALUE + i];
+                    * params.special[IDX_QUEEN_CHECK_VALUE + i];
                 let attacker_value_white = (attack_knight_white
                     + attack_bishop_white
                     + attack_rook_white
@@ -255,21 +299,21 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                     .max(0.)
                     .min(99.);
                 let attack_knight_black = f32::from(pos.trace.knight_attacked_sq[BLACK])
-                    * tuner.params.special[IDX_KNIGHT_ATTACK_VALUE + i];
+                    * params.special[IDX_KNIGHT_ATTACK_VALUE + i];
                 let attack_bishop_black = f32::from(pos.trace.bishop_attacked_sq[BLACK])
-                    * tuner.params.special[IDX_BISHOP_ATTACK_VALUE + i];
+                    * params.special[IDX_BISHOP_ATTACK_VALUE + i];
                 let attack_rook_black = f32::from(pos.trace.rook_attacked_sq[BLACK])
-                    * tuner.params.special[IDX_ROOK_ATTACK_VALUE + i];
+                    * params.special[IDX_ROOK_ATTACK_VALUE + i];
                 let attack_queen_black = f32::from(pos.trace.queen_attacked_sq[BLACK])
-                    * tuner.params.special[IDX_QUEEN_ATTACK_VALUE + i];
+                    * params.special[IDX_QUEEN_ATTACK_VALUE + i];
                 let knight_check_black = f32::from(pos.trace.knight_safe_check[BLACK])
-                    * tuner.params.special[IDX_KNIGHT_CHECK_VALUE + i];
+                    * params.special[IDX_KNIGHT_CHECK_VALUE + i];
                 let bishop_check_black = f32::from(pos.trace.bishop_safe_check[BLACK])
-                    * tuner.params.special[IDX_BISHOP_CHECK_VALUE + i];
+                    * params.special[IDX_BISHOP_CHECK_VALUE + i];
                 let rook_check_black = f32::from(pos.trace.rook_safe_check[BLACK])
-                    * tuner.params.special[IDX_ROOK_CHECK_VALUE + i];
+                    * params.special[IDX_ROOK_CHECK_VALUE + i];
                 let queen_check_black = f32::from(pos.trace.queen_safe_check[BLACK])
-                    * tuner.params.special[IDX_QUEEN_CHECK_VALUE + i];
+                    * params.special[IDX_QUEEN_CHECK_VALUE + i];
                 let attacker_value_black = (attack_knight_black
                     + attack_bishop_black
                     + attack_rook_black
@@ -283,32 +327,30 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                 gradient.special
                     [IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[WHITE] as usize + i] +=
                     start_of_gradient * devaldg / 100.0
-                        * tuner.params.special
-                            [IDX_SAFETY_TABLE + 2 * attacker_value_white as usize + i];
+                        * params.special[IDX_SAFETY_TABLE + 2 * attacker_value_white as usize + i];
                 gradient.special[IDX_SAFETY_TABLE + 2 * attacker_value_white as usize + i] +=
                     start_of_gradient * devaldg / 100.0
-                        * tuner.params.special
+                        * params.special
                             [IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[WHITE] as usize + i];
                 gradient.special
                     [IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[BLACK] as usize + i] -=
                     start_of_gradient * devaldg / 100.0
-                        * tuner.params.special
-                            [IDX_SAFETY_TABLE + 2 * attacker_value_black as usize + i];
+                        * params.special[IDX_SAFETY_TABLE + 2 * attacker_value_black as usize + i];
                 gradient.special[IDX_SAFETY_TABLE + 2 * attacker_value_black as usize + i] +=
                     start_of_gradient * devaldg / 100.0
-                        * tuner.params.special
+                        * params.special
                             [IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[BLACK] as usize + i];
                 //Attack constants
                 if TUNE_ATTACK_INDEX {
                     //Knight
                     {
-                        let c = tuner.params.special[IDX_KNIGHT_ATTACK_VALUE + i];
+                        let c = params.special[IDX_KNIGHT_ATTACK_VALUE + i];
                         gradient.special[IDX_KNIGHT_ATTACK_VALUE + i] += start_of_gradient
                             * devaldg
-                            * tuner.params.special
+                            * params.special
                                 [IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[WHITE] as usize + i]
                             * dsafetytabledconstant(
-                                tuner,
+                                params,
                                 i,
                                 attacker_value_white - attack_knight_white,
                                 pos.trace.knight_attacked_sq[WHITE],
@@ -317,10 +359,10 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                             / 100.0;
                         gradient.special[IDX_KNIGHT_ATTACK_VALUE + i] -= start_of_gradient
                             * devaldg
-                            * tuner.params.special
+                            * params.special
                                 [IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[BLACK] as usize + i]
                             * dsafetytabledconstant(
-                                tuner,
+                                params,
                                 i,
                                 attacker_value_black - attack_knight_black,
                                 pos.trace.knight_attacked_sq[BLACK],
@@ -330,13 +372,13 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                     }
                     //Bishop
                     {
-                        let c = tuner.params.special[IDX_BISHOP_ATTACK_VALUE + i];
+                        let c = params.special[IDX_BISHOP_ATTACK_VALUE + i];
                         gradient.special[IDX_BISHOP_ATTACK_VALUE + i] += start_of_gradient
                             * devaldg
-                            * tuner.params.special
+                            * params.special
                                 [IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[WHITE] as usize + i]
                             * dsafetytabledconstant(
-                                tuner,
+                                params,
                                 i,
                                 attacker_value_white - attack_bishop_white,
                                 pos.trace.bishop_attacked_sq[WHITE],
@@ -345,10 +387,10 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                             / 100.0;
                         gradient.special[IDX_BISHOP_ATTACK_VALUE + i] -= start_of_gradient
                             * devaldg
-                            * tuner.params.special
+                            * params.special
                                 [IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[BLACK] as usize + i]
                             * dsafetytabledconstant(
-                                tuner,
+                                params,
                                 i,
                                 attacker_value_black - attack_bishop_black,
                                 pos.trace.bishop_attacked_sq[BLACK],
@@ -358,13 +400,13 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                     }
                     //Rook
                     {
-                        let c = tuner.params.special[IDX_ROOK_ATTACK_VALUE + i];
+                        let c = params.special[IDX_ROOK_ATTACK_VALUE + i];
                         gradient.special[IDX_ROOK_ATTACK_VALUE + i] += start_of_gradient
                             * devaldg
-                            * tuner.params.special
+                            * params.special
                                 [IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[WHITE] as usize + i]
                             * dsafetytabledconstant(
-                                tuner,
+                                params,
                                 i,
                                 attacker_value_white - attack_rook_white,
                                 pos.trace.rook_attacked_sq[WHITE],
@@ -373,10 +415,10 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                             / 100.0;
                         gradient.special[IDX_ROOK_ATTACK_VALUE + i] -= start_of_gradient
                             * devaldg
-                            * tuner.params.special
+                            * params.special
                                 [IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[BLACK] as usize + i]
                             * dsafetytabledconstant(
-                                tuner,
+                                params,
                                 i,
                                 attacker_value_black - attack_rook_black,
                                 pos.trace.rook_attacked_sq[BLACK],
@@ -386,13 +428,13 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                     }
                     //Queen
                     {
-                        let c = tuner.params.special[IDX_QUEEN_ATTACK_VALUE + i];
+                        let c = params.special[IDX_QUEEN_ATTACK_VALUE + i];
                         gradient.special[IDX_QUEEN_ATTACK_VALUE + i] += start_of_gradient
                             * devaldg
-                            * tuner.params.special
+                            * params.special
                                 [IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[WHITE] as usize + i]
                             * dsafetytabledconstant(
-                                tuner,
+                                params,
                                 i,
                                 attacker_value_white - attack_queen_white,
                                 pos.trace.queen_attacked_sq[WHITE],
@@ -401,10 +443,10 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                             / 100.0;
                         gradient.special[IDX_QUEEN_ATTACK_VALUE + i] -= start_of_gradient
                             * devaldg
-                            * tuner.params.special
+                            * params.special
                                 [IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[BLACK] as usize + i]
                             * dsafetytabledconstant(
-                                tuner,
+                                params,
                                 i,
                                 attacker_value_black - attack_queen_black,
                                 pos.trace.queen_attacked_sq[BLACK],
@@ -414,13 +456,13 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                     }
                     //Knight check
                     {
-                        let c = tuner.params.special[IDX_KNIGHT_CHECK_VALUE + i];
+                        let c = params.special[IDX_KNIGHT_CHECK_VALUE + i];
                         gradient.special[IDX_KNIGHT_CHECK_VALUE + i] += start_of_gradient
                             * devaldg
-                            * tuner.params.special
+                            * params.special
                                 [IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[WHITE] as usize + i]
                             * dsafetytabledconstant(
-                                tuner,
+                                params,
                                 i,
                                 attacker_value_white - knight_check_white,
                                 pos.trace.knight_safe_check[WHITE],
@@ -429,10 +471,10 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                             / 100.0;
                         gradient.special[IDX_KNIGHT_CHECK_VALUE + i] -= start_of_gradient
                             * devaldg
-                            * tuner.params.special
+                            * params.special
                                 [IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[BLACK] as usize + i]
                             * dsafetytabledconstant(
-                                tuner,
+                                params,
                                 i,
                                 attacker_value_black - knight_check_black,
                                 pos.trace.knight_safe_check[BLACK],
@@ -442,13 +484,13 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                     }
                     //Bishop check
                     {
-                        let c = tuner.params.special[IDX_BISHOP_CHECK_VALUE + i];
+                        let c = params.special[IDX_BISHOP_CHECK_VALUE + i];
                         gradient.special[IDX_BISHOP_CHECK_VALUE + i] += start_of_gradient
                             * devaldg
-                            * tuner.params.special
+                            * params.special
                                 [IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[WHITE] as usize + i]
                             * dsafetytabledconstant(
-                                tuner,
+                                params,
                                 i,
                                 attacker_value_white - bishop_check_white,
                                 pos.trace.bishop_safe_check[WHITE],
@@ -457,10 +499,10 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                             / 100.0;
                         gradient.special[IDX_BISHOP_CHECK_VALUE + i] -= start_of_gradient
                             * devaldg
-                            * tuner.params.special
+                            * params.special
                                 [IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[BLACK] as usize + i]
                             * dsafetytabledconstant(
-                                tuner,
+                                params,
                                 i,
                                 attacker_value_black - bishop_check_black,
                                 pos.trace.bishop_safe_check[BLACK],
@@ -470,13 +512,13 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                     }
                     //Rook check
                     {
-                        let c = tuner.params.special[IDX_ROOK_CHECK_VALUE + i];
+                        let c = params.special[IDX_ROOK_CHECK_VALUE + i];
                         gradient.special[IDX_ROOK_CHECK_VALUE + i] += start_of_gradient
                             * devaldg
-                            * tuner.params.special
+                            * params.special
                                 [IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[WHITE] as usize + i]
                             * dsafetytabledconstant(
-                                tuner,
+                                params,
                                 i,
                                 attacker_value_white - rook_check_white,
                                 pos.trace.rook_safe_check[WHITE],
@@ -485,10 +527,10 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                             / 100.0;
                         gradient.special[IDX_ROOK_CHECK_VALUE + i] -= start_of_gradient
                             * devaldg
-                            * tuner.params.special
+                            * params.special
                                 [IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[BLACK] as usize + i]
                             * dsafetytabledconstant(
-                                tuner,
+                                params,
                                 i,
                                 attacker_value_black - rook_check_black,
                                 pos.trace.rook_safe_check[BLACK],
@@ -498,13 +540,13 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                     }
                     //Queen check
                     {
-                        let c = tuner.params.special[IDX_QUEEN_CHECK_VALUE + i];
+                        let c = params.special[IDX_QUEEN_CHECK_VALUE + i];
                         gradient.special[IDX_QUEEN_CHECK_VALUE + i] += start_of_gradient
                             * devaldg
-                            * tuner.params.special
+                            * params.special
                                 [IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[WHITE] as usize + i]
                             * dsafetytabledconstant(
-                                tuner,
+                                params,
                                 i,
                                 attacker_value_white - queen_check_white,
                                 pos.trace.queen_safe_check[WHITE],
@@ -513,10 +555,10 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
                             / 100.0;
                         gradient.special[IDX_QUEEN_CHECK_VALUE + i] -= start_of_gradient
                             * devaldg
-                            * tuner.params.special
+                            * params.special
                                 [IDX_ATTACK_WEIGHT + 2 * pos.trace.attackers[BLACK] as usize + i]
                             * dsafetytabledconstant(
-                                tuner,
+                                params,
                                 i,
                                 attacker_value_black - queen_check_black,
                                 pos.trace.queen_safe_check[BLACK],
@@ -528,24 +570,41 @@ pub fn calculate_gradient(tuner: &mut Tuner, from: usize, to: usize) -> Paramete
             }
         }
     }
-    gradient.scale(portion);
-    add_regularization(&mut gradient, &tuner.params, portion);
     gradient
 }
 
+pub fn chunk_size(positions: usize, processors: usize) -> usize {
+    positions.div_ceil(processors.max(1)).max(1)
+}
+
+//Each chunk gets its own worker, the results are returned in chunk order
+pub fn map_parallel<C: Send, T: Send>(
+    chunks: impl Iterator<Item = C>,
+    f: impl Fn(C) -> T + Sync,
+) -> Vec<T> {
+    let f = &f;
+    thread::scope(|s| {
+        let workers: Vec<_> = chunks.map(|chunk| s.spawn(move || f(chunk))).collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().expect("Tuning worker panicked"))
+            .collect()
+    })
+}
+
 pub fn dsafetytabledconstant(
-    tuner: &Tuner,
+    params: &Parameters,
     phase: usize,
     other: f32,
     relevant_feature: u8,
     current_constant: f32,
 ) -> f32 {
-    let safety_table_inc = tuner.params.special[IDX_SAFETY_TABLE
+    let safety_table_inc = params.special[IDX_SAFETY_TABLE
         + 2 * ((other + f32::from(relevant_feature) * (current_constant + 1.)) as usize)
             .max(0)
             .min(99)
         + phase];
-    let safety_table_dec = tuner.params.special[IDX_SAFETY_TABLE
+    let safety_table_dec = params.special[IDX_SAFETY_TABLE
         + 2 * ((other + f32::from(relevant_feature) * (current_constant - 1.)) as usize)
             .max(0)
             .min(99)
@@ -607,11 +666,25 @@ pub fn texel_tuning(tuner: &mut Tuner) {
 }
 
 pub fn average_evaluation_error(tuner: &Tuner) -> f32 {
+    average_evaluation_error_at(tuner, tuner.k)
+}
+
+pub fn average_evaluation_error_at(tuner: &Tuner, k: f32) -> f32 {
+    let chunk_size = chunk_size(tuner.positions.len(), tuner.processors);
+    let res: f32 = map_parallel(tuner.positions.chunks(chunk_size), |positions| {
+        evaluation_error(k, positions)
+    })
+    .iter()
+    .sum();
+    res / tuner.positions.len() as f32
+}
+
+pub fn evaluation_error(k: f32, positions: &[TexelState]) -> f32 {
     let mut res = 0.;
-    for pos in &tuner.positions {
-        res += (pos.label - sigmoid(tuner.k, pos.eval)).powf(2.0);
+    for pos in positions {
+        res += (pos.label - sigmoid(k, pos.eval)).powf(2.0);
     }
-    res / tuner.positions.len() as f32
+    res
 }
 
 pub fn minimize_evaluation_error_fork(tuner: &mut Tuner) -> f32 {
@@ -656,6 +729,32 @@ pub fn minimize_evaluation_error_fork(tuner: &mut Tuner) -> f32 {
     best_k
 }
 
+//The evaluation error is unimodal in K, so a golden section search narrows the bounds down to the minimizing K
+pub fn find_optimal_k(tuner: &Tuner, bounds: (f32, f32)) -> f32 {
+    let inv_phi = (5f32.sqrt() - 1.) / 2.;
+    let (mut low, mut high) = bounds;
+    let mut left = high - inv_phi * (high - low);
+    let mut right = low + inv_phi * (high - low);
+    let mut left_error = average_evaluation_error_at(tuner, left);
+    let mut right_error = average_evaluation_error_at(tuner, right);
+    while high - low > K_SEARCH_PRECISION {
+        if left_error < right_error {
+            high = right;
+            right = left;
+            right_error = left_error;
+            left = high - inv_phi * (high - low);
+            left_error = average_evaluation_error_at(tuner, left);
+        } else {
+            low = left;
+            left = right;
+            left_error = right_error;
+            right = low + inv_phi * (high - low);
+            right_error = average_evaluation_error_at(tuner, right);
+        }
+    }
+    (low + high) / 2.
+}
+
 pub fn sigmoid(k: f32, s: f32) -> f32 {
     1. / (1. + 10f32.powf(-k * s / 400.0))
 }
@@ -663,3 +762,115 @@ pub fn sigmoid(k: f32, s: f32) -> f32 {
 pub fn dsigmoiddk(k: f32, s: f32) -> f32 {
     sigmoid(k, s).powf(2.0) * 10f32.ln() * s * 10f32.powf(-k * s / 400.0) / 400.0
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core_sdk::board_representation::game_state::GameState;
+    use core_sdk::evaluation::eval_game_state;
+
+    #[test]
+    fn parallel_tuning_test() {
+        let fens = [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                0.5,
+            ),
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -",
+                1.,
+            ),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -", 0.5),
+            (
+                "r1bqr1k1/pp1p1ppp/2p5/8/3N1Q2/P2BB3/1PP2PPP/R3K2n b Q - 1 12",
+                1.,
+            ),
+            ("8/8/1p1k2p1/p1prp2p/P2n3P/6P1/1P1R1PK1/4R3 b - - 5 49", 0.),
+            ("3Q4/5pk1/6p1/8/8/8/5qPP/7K w - - 0 1", 0.),
+            (
+                "r1b1kbnr/pppp1ppp/2n5/4p3/3PP2q/5N2/PPP2PPP/RNBQKB1R w KQkq -",
+                0.,
+            ),
+        ];
+        let positions = fens
+            .iter()
+            .map(|&(fen, label)| {
+                let eval = eval_game_state(&GameState::from_fen(fen));
+                TexelState {
+                    label,
+                    eval: eval.final_eval as f32,
+                    trace: eval.trace.collapse(),
+                }
+            })
+            .collect();
+        let mut tuner = Tuner {
+            k: 1.1155,
+            positions,
+            params: Parameters::default(),
+            processors: 1,
+        };
+        update_evaluations(&mut tuner);
+        let serial_loss = average_evaluation_error(&tuner);
+        let serial_gradient = calculate_gradient(&mut tuner, 0, fens.len());
+        //More processors than positions leave some workers without a chunk
+        for &processors in [2, 3, 16].iter() {
+            tuner.processors = processors;
+            assert!((average_evaluation_error(&tuner) - serial_loss).abs() < 1e-6);
+            let gradient = calculate_gradient(&mut tuner, 0, fens.len());
+            let serial = serial_gradient.normal.iter().flatten();
+            let parallel = gradient.normal.iter().flatten();
+            for (a, b) in serial
+                .zip(parallel)
+                .chain(serial_gradient.special.iter().zip(gradient.special.iter()))
+            {
+                assert!((a - b).abs() <= 1e-5 * a.abs().max(1.));
+            }
+        }
+        assert!(serial_loss > 0.);
+    }
+
+    #[test]
+    fn find_optimal_k_test() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -",
+            "3Q4/5pk1/6p1/8/8/8/5qPP/7K w - - 0 1",
+            "4k3/8/8/8/8/8/3R1PP1/4K3 w - - 0 60",
+            "4k3/3r1pp1/8/8/8/8/8/4K3 b - - 0 60",
+        ];
+        //Labels which the sigmoid with k reproduces exactly, so the error vanishes at k
+        let tuner_for = |k: f32| {
+            let positions = fens
+                .iter()
+                .map(|fen| {
+                    let eval = eval_game_state(&GameState::from_fen(fen));
+                    TexelState {
+                        label: sigmoid(k, eval.final_eval as f32),
+                        eval: eval.final_eval as f32,
+                        trace: eval.trace.collapse(),
+                    }
+                })
+                .collect();
+            Tuner {
+                k: 1.,
+                positions,
+                params: Parameters::default(),
+                processors: 2,
+            }
+        };
+        for &k in [0.6, 1.1155, 2.5].iter() {
+            let tuner = tuner_for(k);
+            let found = find_optimal_k(&tuner, K_SEARCH_BOUNDS);
+            assert!((found - k).abs() < 1e-2);
+            assert!(average_evaluation_error_at(&tuner, found) < 1e-6);
+        }
+        //Draws everywhere are explained best by a flat sigmoid, the search ends at the lower bound
+        let mut tuner = tuner_for(1.);
+        for pos in tuner.positions.iter_mut() {
+            pos.label = 0.5;
+        }
+        let found = find_optimal_k(&tuner, K_SEARCH_BOUNDS);
+        assert!(found - K_SEARCH_BOUNDS.0 < 1e-2);
+    }
+}
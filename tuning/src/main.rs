@@ -1,28 +1,32 @@
+use std::env;
 use tuning::*;
 
+//With "tune-k [FILE]" only the optimal K of the positions in FILE, POSITION_FILE by default, is searched for
 pub fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() > 1 && args[1] == "tune-k" {
+        let file = args.get(2).map(|file| &file[..]).unwrap_or(POSITION_FILE);
+        let tuner = Tuner {
+            k: 1.1155,
+            positions: load_positions(file),
+            params: Parameters::default(),
+            processors: PROCESSORS,
+        };
+        let k = find_optimal_k(&tuner, K_SEARCH_BOUNDS);
+        println!(
+            "Optimal K: {} (error {})",
+            k,
+            average_evaluation_error_at(&tuner, k)
+        );
+        return;
+    }
     //Step 1. Load all positions from a file. Those positions should already be the q-searched positions.
-    let mut positions: Vec<TexelState> = Vec::with_capacity(1);
-    tuning::loading::PositionLoader::new(
-        POSITION_FILE,
-        if POSITION_FILE.ends_with(".txt") {
-            FileFormatSupported::OwnEncoding
-        } else if POSITION_FILE.ends_with("epd") {
-            FileFormatSupported::EPD
-        } else {
-            panic!("Invalid position file encoding!")
-        },
-    )
-    .load_texel_positions(&mut positions);
-    println!(
-        "Loaded file {} with {} positions!",
-        POSITION_FILE,
-        positions.len()
-    );
+    let positions = load_positions(POSITION_FILE);
     let mut tuner = Tuner {
         k: 1.1155,
         positions,
         params: Parameters::default(),
+        processors: PROCESSORS,
     };
     println!("Start tuning for k");
     if OPTIMIZE_K {
@@ -31,3 +35,20 @@ pub fn main() {
     println!("Optimal K: {}", tuner.k);
     texel_tuning(&mut tuner);
 }
+
+fn load_positions(file: &str) -> Vec<TexelState> {
+    let mut positions: Vec<TexelState> = Vec::with_capacity(1);
+    tuning::loading::PositionLoader::new(
+        file,
+        if file.ends_with(".txt") {
+            FileFormatSupported::OwnEncoding
+        } else if file.ends_with("epd") {
+            FileFormatSupported::EPD
+        } else {
+            panic!("Invalid position file encoding!")
+        },
+    )
+    .load_texel_positions(&mut positions);
+    println!("Loaded file {} with {} positions!", file, positions.len());
+    positions
+}
@@ -0,0 +1,142 @@
+use core_sdk::board_representation::game_state::GameState;
+use core_sdk::move_generation::movegen;
+use core_sdk::search::cache::Cache;
+use core_sdk::search::searcher::{search_move, InterThreadCommunicationSystem};
+use core_sdk::search::timecontrol::TimeControl;
+use core_sdk::search::MAX_SEARCH_DEPTH;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::sync::Arc;
+
+//One request per line, e.g. {"fen": "...", "movetime": 1000}
+#[derive(Deserialize)]
+pub struct AnalysisRequest {
+    pub fen: String,
+    pub movetime: Option<u64>,
+    pub depth: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AnalysisResponse {
+    pub bestmove: String,
+    pub score: i16,
+    pub pv: Vec<String>,
+    pub depth: usize,
+}
+
+#[derive(Serialize)]
+pub struct AnalysisError {
+    pub error: String,
+}
+
+pub fn parse_loop() {
+    let itcs = Arc::new(InterThreadCommunicationSystem::default());
+    let threads = itcs.uci_options().threads;
+    InterThreadCommunicationSystem::update_thread_count(&itcs, threads);
+    *itcs.cache() = Cache::with_size_threaded(itcs.uci_options().hash_size, threads);
+    itcs.uci_options().quiet = true;
+
+    let stdin = io::stdin();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        stdin.read_line(&mut line).unwrap();
+        if line.is_empty() {
+            break;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        println!("{}", respond(&itcs, &line));
+    }
+}
+
+pub fn respond(itcs: &Arc<InterThreadCommunicationSystem>, line: &str) -> String {
+    let result = serde_json::from_str::<AnalysisRequest>(line)
+        .map_err(|e| format!("Invalid request: {}", e))
+        .and_then(|request| analyse(itcs, &request));
+    match result {
+        Ok(response) => serde_json::to_string(&response),
+        Err(error) => serde_json::to_string(&AnalysisError { error }),
+    }
+    .expect("Could not serialize response")
+}
+
+pub fn analyse(
+    itcs: &Arc<InterThreadCommunicationSystem>,
+    request: &AnalysisRequest,
+) -> Result<AnalysisResponse, String> {
+    if request.movetime.is_none() && request.depth.is_none() {
+        return Err("Either movetime or depth has to be given".to_owned());
+    }
+    let state = GameState::try_from_fen(&request.fen)?;
+    let mut movelist = movegen::MoveList::default();
+    movegen::generate_moves(&state, false, &mut movelist);
+    if movelist.move_list.is_empty() {
+        return Err("The position does not have any legal move".to_owned());
+    }
+    let tc = match request.movetime {
+        Some(movetime) => TimeControl::MoveTime(movetime),
+        None => TimeControl::Infinite,
+    };
+    let depth = request
+        .depth
+        .unwrap_or(MAX_SEARCH_DEPTH)
+        .min(MAX_SEARCH_DEPTH);
+    let score = search_move(Arc::clone(itcs), depth as i16, state, vec![], tc);
+
+    let best_pv = itcs.played_pv();
+    let pv: Vec<String> = best_pv
+        .pv
+        .pv
+        .iter()
+        .flatten()
+        .map(|mv| format!("{:?}", mv))
+        .collect();
+    Ok(AnalysisResponse {
+        bestmove: pv[0].clone(),
+        score,
+        pv,
+        depth: best_pv.depth,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{respond, AnalysisResponse};
+    use core_sdk::board_representation::game_state::GameState;
+    use core_sdk::move_generation::movegen;
+    use core_sdk::search::cache::Cache;
+    use core_sdk::search::searcher::InterThreadCommunicationSystem;
+    use core_sdk::search::MATE_SCORE;
+    use std::sync::Arc;
+
+    #[test]
+    fn json_analysis_test() {
+        let itcs = Arc::new(InterThreadCommunicationSystem::default());
+        InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+        *itcs.cache() = Cache::with_size_threaded(1, 1);
+        itcs.uci_options().quiet = true;
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -";
+        let request = format!("{{\"fen\": \"{}\", \"depth\": 5}}", fen);
+        let response: AnalysisResponse =
+            serde_json::from_str(&respond(&itcs, &request)).expect("Malformed response");
+        assert_eq!(response.depth, 5);
+        assert!(response.score.abs() < MATE_SCORE);
+        assert_eq!(response.pv[0], response.bestmove);
+        let state = GameState::from_fen(fen);
+        let mut movelist = movegen::MoveList::default();
+        movegen::generate_moves(&state, false, &mut movelist);
+        assert!(movelist
+            .move_list
+            .iter()
+            .any(|gmv| format!("{:?}", gmv.0) == response.bestmove));
+
+        let error: serde_json::Value =
+            serde_json::from_str(&respond(&itcs, "{\"movetime\": 1000}")).unwrap();
+        assert!(error["error"].is_string());
+        let error: serde_json::Value =
+            serde_json::from_str(&respond(&itcs, "{\"fen\": \"8/8/8 w\", \"depth\": 5}")).unwrap();
+        assert!(error["error"].as_str().unwrap().starts_with("Invalid FEN"));
+    }
+}
@@ -1,27 +1,53 @@
 use std::time::Instant;
 
+pub mod json_analysis;
+pub mod snapshot;
 pub mod uci_engine;
 pub mod uci_parser;
 
 fn main() {
-    let now = Instant::now();
-    let new_now = Instant::now();
-    println!(
-        "{}",
-        format!(
-            "info string Initialization Time: {}ms",
-            new_now.duration_since(now).as_secs() * 1000
-                + u64::from(new_now.duration_since(now).subsec_millis())
-        )
-    );
     let mut args = std::env::args();
-    if args.nth(1) == Some("bench".to_owned()) {
-        core_sdk::bench(
+    let mode = args.nth(1);
+    //Json analysis mode must only write responses to stdout, comparison runs only their results for diffing
+    if !matches!(mode.as_deref(), Some("json") | Some("compare")) {
+        let now = Instant::now();
+        let new_now = Instant::now();
+        println!(
+            "{}",
+            format!(
+                "info string Initialization Time: {}ms",
+                new_now.duration_since(now).as_secs() * 1000
+                    + u64::from(new_now.duration_since(now).subsec_millis())
+            )
+        );
+    }
+    match mode.as_deref() {
+        Some("bench") => core_sdk::bench(
             args.nth(2)
                 .and_then(|depth| depth.parse::<usize>().ok())
                 .unwrap_or(13),
-        );
-    } else {
-        uci_parser::parse_loop();
+        ),
+        Some("epd") => core_sdk::epd::run_epd_suite(
+            &args.next().expect("No EPD file given"),
+            args.next()
+                .and_then(|depth| depth.parse::<usize>().ok())
+                .unwrap_or(core_sdk::epd::DEFAULT_EPD_DEPTH),
+        ),
+        Some("sweep") => core_sdk::sweep::run_sweep(
+            &args.next().expect("No search parameter given"),
+            &args.next().expect("No values given"),
+            &args.next().expect("No EPD file given"),
+            args.next()
+                .and_then(|depth| depth.parse::<usize>().ok())
+                .unwrap_or(core_sdk::epd::DEFAULT_EPD_DEPTH),
+        ),
+        Some("compare") => core_sdk::compare::run_comparison(
+            &args.next().expect("No FEN file given"),
+            args.next()
+                .and_then(|depth| depth.parse::<usize>().ok())
+                .expect("No depth given"),
+        ),
+        Some("json") => json_analysis::parse_loop(),
+        _ => uci_parser::parse_loop(),
     }
 }
@@ -0,0 +1,203 @@
+use core_sdk::board_representation::game_state::GameState;
+use core_sdk::move_generation::makemove::make_move;
+use core_sdk::search::info_sink::{InfoSink, SearchInfo};
+use core_sdk::search::searcher::uci_score_string;
+use core_sdk::search::ScoreBound;
+use std::fs;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_SNAPSHOT_INTERVAL: u64 = 60;
+pub const MIN_SNAPSHOT_INTERVAL: u64 = 1;
+pub const MAX_SNAPSHOT_INTERVAL: u64 = 86400;
+
+//Keeps the last completed iteration around, so it can be written out at any time
+pub struct SnapshotSink {
+    root: GameState,
+    latest: Mutex<Option<SearchInfo>>,
+}
+
+impl SnapshotSink {
+    pub fn new(root: GameState) -> Self {
+        SnapshotSink {
+            root,
+            latest: Mutex::new(None),
+        }
+    }
+
+    pub fn snapshot(&self) -> Option<String> {
+        let latest = self.latest.lock().unwrap();
+        latest
+            .as_ref()
+            .map(|info| format_snapshot(&self.root, info))
+    }
+
+    //Returns false if there was no completed iteration yet
+    pub fn write(&self, path: &str) -> io::Result<bool> {
+        match self.snapshot() {
+            Some(snapshot) => fs::write(path, snapshot).map(|_| true),
+            None => Ok(false),
+        }
+    }
+}
+
+impl InfoSink for SnapshotSink {
+    fn on_iteration(&self, info: &SearchInfo) {
+        *self.latest.lock().unwrap() = Some(info.clone());
+    }
+}
+
+pub fn format_snapshot(root: &GameState, info: &SearchInfo) -> String {
+    let mut state = root.clone();
+    let mut san = Vec::with_capacity(info.pv.len());
+    for mv in info.pv.iter() {
+        san.push(mv.to_san(&state));
+        state = make_move(&state, *mv);
+    }
+    format!(
+        "depth {}\n{}\nnodes {}\npv {}\n",
+        info.depth,
+        uci_score_string(info.score, ScoreBound::Exact),
+        info.nodes,
+        san.join(" ")
+    )
+}
+
+//Overwrites the snapshot file every interval until finished, e.g. for long correspondence analysis
+pub struct SnapshotWriter {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<usize>,
+}
+
+impl SnapshotWriter {
+    pub fn spawn(sink: Arc<SnapshotSink>, path: String, interval: Duration) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            let mut written = 0;
+            //A failing write is reported once, the search goes on regardless
+            let mut failed = false;
+            let mut write = |written: &mut usize| match sink.write(&path) {
+                Ok(true) => *written += 1,
+                Ok(false) => {}
+                Err(e) => {
+                    if !failed {
+                        println!("info string Unable to write snapshot file {}: {}", path, e);
+                        failed = true;
+                    }
+                }
+            };
+            let mut next_write = Instant::now() + interval;
+            while !thread_stop.load(Ordering::Relaxed) {
+                let now = Instant::now();
+                if now >= next_write {
+                    write(&mut written);
+                    next_write += interval;
+                } else {
+                    thread::sleep((next_write - now).min(Duration::from_millis(10)));
+                }
+            }
+            //The final result of the search should not get lost
+            write(&mut written);
+            written
+        });
+        SnapshotWriter { stop, handle }
+    }
+
+    //Writes a last snapshot and returns the number of snapshots written
+    pub fn finish(self) -> usize {
+        self.stop.store(true, Ordering::Relaxed);
+        self.handle.join().expect("Snapshot writer panicked")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SnapshotSink, SnapshotWriter};
+    use core_sdk::board_representation::game_state::GameState;
+    use core_sdk::move_generation::movegen;
+    use core_sdk::search::cache::Cache;
+    use core_sdk::search::info_sink::{InfoSink, SearchInfo};
+    use core_sdk::search::searcher::{search_move, InterThreadCommunicationSystem};
+    use core_sdk::search::timecontrol::TimeControl;
+    use std::fs;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn snapshot_cadence_test() {
+        let itcs = Arc::new(InterThreadCommunicationSystem::default());
+        InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+        *itcs.cache() = Cache::with_size_threaded(1, 1);
+        itcs.uci_options().quiet = true;
+        let state =
+            GameState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -");
+        let sink = Arc::new(SnapshotSink::new(state.clone()));
+        itcs.set_info_sink(Some(sink.clone()));
+        let path =
+            std::env::temp_dir().join(format!("fabchess_snapshot_{}.txt", std::process::id()));
+        let interval = Duration::from_millis(100);
+
+        let start = Instant::now();
+        let writer = SnapshotWriter::spawn(sink, path.to_str().unwrap().to_owned(), interval);
+        search_move(
+            Arc::clone(&itcs),
+            100,
+            state.clone(),
+            Vec::new(),
+            TimeControl::MoveTime(550),
+        );
+        let written = writer.finish();
+        let elapsed = start.elapsed().as_millis() as i64;
+        //One snapshot per elapsed interval and a final one
+        let periodic = written as i64 - 1;
+        assert!((periodic - elapsed / 100).abs() <= 1);
+
+        let snapshot = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let lines: Vec<&str> = snapshot.lines().collect();
+        assert!(lines[0].starts_with("depth "));
+        assert!(lines[1].starts_with("score cp ") || lines[1].starts_with("score mate "));
+        assert!(lines[2].starts_with("nodes "));
+        let first_move = lines[3].split_whitespace().nth(1).unwrap();
+        let mut movelist = movegen::MoveList::default();
+        movegen::generate_moves(&state, false, &mut movelist);
+        assert!(movelist
+            .move_list
+            .iter()
+            .any(|gmv| gmv.0.to_san(&state) == first_move));
+    }
+
+    #[test]
+    fn unwritable_snapshot_test() {
+        let state = GameState::standard();
+        let sink = Arc::new(SnapshotSink::new(state.clone()));
+        let path = std::env::temp_dir()
+            .join(format!("fabchess_missing_dir_{}", std::process::id()))
+            .join("snapshot.txt");
+        let path = path.to_str().unwrap().to_owned();
+        //Nothing to write yet
+        assert!(!sink.write(&path).unwrap());
+        let mut movelist = movegen::MoveList::default();
+        movegen::generate_moves(&state, false, &mut movelist);
+        sink.on_iteration(&SearchInfo {
+            depth: 1,
+            seldepth: 1,
+            score: 20,
+            nodes: 20,
+            nps: 0,
+            time_elapsed: 0,
+            hashfull: 0,
+            pv: vec![movelist.move_list[0].0],
+        });
+        assert!(sink.write(&path).is_err());
+
+        //The writer keeps going and finishes normally
+        let writer = SnapshotWriter::spawn(sink, path, Duration::from_millis(5));
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(writer.finish(), 0);
+    }
+}
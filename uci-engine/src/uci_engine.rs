@@ -1,10 +1,15 @@
+use crate::snapshot::DEFAULT_SNAPSHOT_INTERVAL;
 use core_sdk::board_representation::game_state::GameState;
+use core_sdk::move_generation::makemove::make_move;
+use core_sdk::move_generation::movegen;
 
 pub struct UCIEngine<'a> {
     pub name: &'a str,
     pub author: &'a str,
     pub contributors: &'a [&'a str],
     pub internal_state: GameState,
+    pub snapshot_file: Option<String>,
+    pub snapshot_interval: u64, //In seconds
 }
 
 impl<'a> UCIEngine<'a> {
@@ -23,6 +28,8 @@ impl<'a> UCIEngine<'a> {
             author: &"Fabian von der Warth",
             contributors: &["Erik Imgrund", "Marcin Mielniczuk"],
             internal_state: GameState::standard(),
+            snapshot_file: None,
+            snapshot_interval: DEFAULT_SNAPSHOT_INTERVAL,
         }
     }
 
@@ -31,4 +38,72 @@ impl<'a> UCIEngine<'a> {
         println!("id author {}", self.author);
         println!("id contributors {}", self.contributors.join(", "))
     }
+
+    pub fn set_position(&mut self, game_state: GameState) {
+        self.internal_state = game_state;
+    }
+
+    pub fn position(&self) -> &GameState {
+        &self.internal_state
+    }
+
+    //Applies a move in long algebraic notation, e.g. `e2e4` or `e7e8q`, if it is legal
+    pub fn make_uci_move(&mut self, uci_move: &str) -> Result<(), String> {
+        let uci_move = uci_move.trim().to_lowercase();
+        let mut movelist = movegen::MoveList::default();
+        movegen::generate_moves(&self.internal_state, false, &mut movelist);
+        let mv = movelist
+            .move_list
+            .iter()
+            .map(|gmv| gmv.0)
+            .find(|mv| format!("{:?}", mv) == uci_move)
+            .ok_or_else(|| {
+                format!(
+                    "Illegal move {} in position {}",
+                    uci_move,
+                    self.internal_state.to_fen()
+                )
+            })?;
+        self.internal_state = make_move(&self.internal_state, mv);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UCIEngine;
+    use core_sdk::board_representation::game_state::GameState;
+
+    #[test]
+    fn position_api_test() {
+        let mut engine = UCIEngine::standard();
+        assert_eq!(engine.position().to_fen(), GameState::standard().to_fen());
+        for mv in [
+            "e2e4", "d7d5", "e4d5", "g8f6", "f1b5", "c7c6", "d5c6", "d8d2",
+        ]
+        .iter()
+        {
+            engine.make_uci_move(mv).unwrap();
+        }
+        assert_eq!(
+            engine.position().to_fen(),
+            "rnb1kb1r/pp2pppp/2P2n2/1B6/8/8/PPPq1PPP/RNBQK1NR w KQkq - 0 5"
+        );
+        //Moves ignoring the check, castling out of check and malformed moves are rejected
+        for mv in ["g1f3", "e1g1", "b1c3", "e2", "z9z9", ""].iter() {
+            assert!(engine.make_uci_move(mv).is_err());
+        }
+        assert_eq!(
+            engine.position().to_fen(),
+            "rnb1kb1r/pp2pppp/2P2n2/1B6/8/8/PPPq1PPP/RNBQK1NR w KQkq - 0 5"
+        );
+
+        engine.set_position(GameState::from_fen("8/2P1k3/8/8/8/8/8/4K3 w - - 0 1"));
+        assert!(engine.make_uci_move("c7c8").is_err());
+        engine.make_uci_move("c7c8N").unwrap();
+        assert_eq!(
+            engine.position().to_fen(),
+            "2N5/4k3/8/8/8/8/8/4K3 b - - 0 1"
+        );
+    }
 }
@@ -1,21 +1,54 @@
+use super::snapshot::{SnapshotSink, SnapshotWriter, MAX_SNAPSHOT_INTERVAL, MIN_SNAPSHOT_INTERVAL};
 use super::uci_engine::UCIEngine;
-use core_sdk::board_representation::game_state::{GameMove, GameMoveType, GameState, PieceType};
-use core_sdk::move_generation::makemove::make_move;
-use core_sdk::move_generation::movegen;
+use core_sdk::board_representation::game_state::GameState;
+use core_sdk::evaluation::parameters::Parameters;
 use core_sdk::search::cache::{Cache, MAX_HASH_SIZE, MIN_HASH_SIZE};
 use core_sdk::search::searcher::{
     search_move, InterThreadCommunicationSystem, MAX_SKIP_RATIO, MAX_THREADS, MIN_SKIP_RATIO,
     MIN_THREADS,
 };
-use core_sdk::search::timecontrol::{TimeControl, MAX_MOVE_OVERHEAD, MIN_MOVE_OVERHEAD};
-use core_sdk::search::MAX_SEARCH_DEPTH;
-use std::io;
+use core_sdk::search::strength::{MAX_ELO, MAX_EVAL_RANDOMNESS, MIN_ELO};
+use core_sdk::search::timecontrol::{
+    TimeControl, MAX_BOOK_EXIT_BOOST, MAX_BOOK_EXIT_MOVES, MAX_MIN_THINK_TIME, MAX_MOVE_OVERHEAD,
+    MIN_MOVE_OVERHEAD,
+};
+use core_sdk::search::{MAX_CONTEMPT, MAX_SEARCH_DEPTH, MIN_CONTEMPT};
+use std::io::{self, BufRead};
+use std::str::FromStr;
 use std::sync::{atomic::Ordering, Arc};
-use std::thread;
-use std::time::Duration;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 use std::u64;
 
+//Everything else in front of a command is skipped, as the UCI protocol demands for unknown tokens
+const UCI_COMMANDS: [&str; 18] = [
+    "uci",
+    "debug",
+    "isready",
+    "setoption",
+    "register",
+    "ucinewgame",
+    "newgame",
+    "position",
+    "go",
+    "stop",
+    "ponderhit",
+    "quit",
+    "d",
+    "perft",
+    "static",
+    "eval",
+    "params",
+    "bench",
+];
+
 pub fn parse_loop() {
+    let stdin = io::stdin();
+    run_loop(stdin.lock());
+}
+
+//Returns the engine as it was left by the commands, once the input ends or quit is received
+pub fn run_loop<'a, R: BufRead>(mut input: R) -> UCIEngine<'a> {
     let mut history: Vec<GameState> = vec![];
 
     let mut us = UCIEngine::standard();
@@ -23,64 +56,68 @@ pub fn parse_loop() {
     let itcs = Arc::new(InterThreadCommunicationSystem::default());
     *itcs.cache() =
         Cache::with_size_threaded(itcs.uci_options().hash_size, itcs.uci_options().threads);
-    let mut movelist = movegen::MoveList::default();
+    let mut search = None;
 
-    let stdin = io::stdin();
     let mut line = String::new();
     loop {
         line.clear();
-        stdin.read_line(&mut line).unwrap();
-        if line.is_empty() {
-            break;
-        }
-        let arg: Vec<&str> = line.split_whitespace().collect();
-        if arg.is_empty() {
-            continue;
+        match input.read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            //Lines which aren't valid UTF-8 are dropped
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => continue,
+            Err(_) => break,
         }
-        let cmd = arg[0];
-        match cmd.trim() {
-            "" => {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let arg = match tokens.iter().position(|token| UCI_COMMANDS.contains(token)) {
+            Some(start) => &tokens[start..],
+            None => {
+                if !tokens.is_empty() {
+                    println!("info String Unknown command {}", line.trim());
+                }
                 continue;
             }
+        };
+        match arg[0] {
             "uci" => {
                 uci(&us, &itcs);
             }
-            "setoption" => setoption(&arg[1..], &itcs),
-
+            "setoption" => setoption(&mut us, &arg[1..], &itcs),
+            //The engine is free, so there is nothing to register
+            "register" => println!("registration ok"),
             "ucinewgame" | "newgame" => {
                 newgame(&mut us);
                 itcs.cache().clear_threaded(itcs.uci_options().threads);
                 itcs.saved_time.store(0, Ordering::Relaxed);
+                itcs.searches_since_newgame.store(0, Ordering::Relaxed);
             }
             "isready" => isready(&itcs, true),
-            "position" => {
-                history = position(&mut us, &arg[1..], &mut movelist);
-            }
+            "position" => match position(&mut us, &arg[1..]) {
+                Ok(new_history) => history = new_history,
+                Err(e) => println!("info String {}", e),
+            },
             "go" => {
+                stop_search(&itcs, &mut search);
                 isready(&itcs, false);
-                let (tc, depth) = go(&us, &arg[1..]);
-                let mut new_history = vec![];
-                for gs in &history {
-                    new_history.push(gs.clone());
-                }
-                let new_state = us.internal_state.clone();
-                let itcs = Arc::clone(&itcs);
-                thread::Builder::new()
-                    .stack_size(2 * 1024 * 1024)
-                    .spawn(move || {
-                        search_move(itcs, depth as i16, new_state, new_history, tc);
-                    })
-                    .expect("Couldn't start thread");
-            }
-            "stop" => {
-                *itcs.timeout_flag.write().unwrap() = true;
-                thread::sleep(Duration::from_millis(5));
+                let (tc, depth, nodes) = go(&us, &arg[1..]);
+                itcs.node_limit.store(nodes.unwrap_or(0), Ordering::Relaxed);
+                //A ponder search uses the real time control, but only once ponderhit arrives
+                itcs.pondering.store(
+                    arg[1..]
+                        .iter()
+                        .any(|token| token.eq_ignore_ascii_case("ponder")),
+                    Ordering::SeqCst,
+                );
+                search = Some(start_search(&us, &itcs, &history, tc, depth));
             }
+            "stop" => stop_search(&itcs, &mut search),
+            "ponderhit" => itcs.ponderhit(),
             "quit" => {
+                stop_search(&itcs, &mut search);
                 break;
             }
             "d" => {
-                print_internal_state(&us);
+                print_internal_state(&us, arg.get(1) == Some(&"flip"));
             }
             "perft" => perft(&us.internal_state, &arg[1..]),
             "static" => {
@@ -89,147 +126,197 @@ pub fn parse_loop() {
                     core_sdk::evaluation::eval_game_state(&us.internal_state).final_eval
                 );
             }
-            _ => {
-                println!("Unknown command {}", line);
+            "eval" => {
+                println!(
+                    "{}",
+                    core_sdk::evaluation::eval_game_state_detailed(&us.internal_state)
+                );
             }
+            "params" => print!("{}", eval_params()),
+            //debug and bench (only supported as command line argument) are ignored
+            _ => {}
         }
     }
+    us
+}
+
+//The search runs on its own thread, so commands like isready and stop are answered while it is running
+pub fn start_search(
+    engine: &UCIEngine,
+    itcs: &Arc<InterThreadCommunicationSystem>,
+    history: &[GameState],
+    tc: TimeControl,
+    depth: usize,
+) -> JoinHandle<()> {
+    let new_history = history.to_vec();
+    let new_state = engine.internal_state.clone();
+    let itcs = Arc::clone(itcs);
+    let snapshot = engine.snapshot_file.clone().map(|path| {
+        let sink = Arc::new(SnapshotSink::new(new_state.clone()));
+        itcs.set_info_sink(Some(sink.clone()));
+        SnapshotWriter::spawn(sink, path, Duration::from_secs(engine.snapshot_interval))
+    });
+    thread::Builder::new()
+        .stack_size(2 * 1024 * 1024)
+        .spawn(move || {
+            search_move(Arc::clone(&itcs), depth as i16, new_state, new_history, tc);
+            if let Some(writer) = snapshot {
+                writer.finish();
+                itcs.set_info_sink(None);
+            }
+        })
+        .expect("Couldn't start thread")
+}
+
+//Returns once the bestmove of the running search has been printed
+pub fn stop_search(itcs: &InterThreadCommunicationSystem, search: &mut Option<JoinHandle<()>>) {
+    if let Some(handle) = search.take() {
+        //The search clears the flag when it starts, so keep raising it until the search is done
+        while !handle.is_finished() {
+            *itcs.timeout_flag.write().unwrap() = true;
+            thread::sleep(Duration::from_millis(1));
+        }
+        handle.join().expect("Search thread panicked");
+    }
 }
 
 pub fn perft(game_state: &GameState, cmd: &[&str]) {
-    let depth = cmd[0].parse::<usize>().unwrap();
-    core_sdk::perft_div(&game_state, depth);
+    let depth = match cmd.first().and_then(|depth| depth.parse::<usize>().ok()) {
+        Some(depth) => depth,
+        None => {
+            println!("info String Invalid perft depth!");
+            return;
+        }
+    };
+    let threads = cmd
+        .get(1)
+        .and_then(|threads| threads.parse::<usize>().ok())
+        .unwrap_or(1);
+    if threads > 1 {
+        let now = Instant::now();
+        let count = core_sdk::perft_parallel(game_state, depth, threads);
+        let elapsed = now.elapsed();
+        println!("{}", count);
+        println!(
+            "Time {} ({} nps)",
+            elapsed.as_millis() as f64 / 1000.0,
+            count as f64 * 1e9 / elapsed.as_nanos().max(1) as f64
+        );
+    } else {
+        core_sdk::perft_div(game_state, depth);
+    }
 }
 
-pub fn print_internal_state(engine: &UCIEngine) {
-    println!("{}", engine.internal_state);
+pub fn print_internal_state(engine: &UCIEngine, flipped: bool) {
+    if flipped {
+        print!("{}", engine.internal_state.board_string(true));
+        println!("FEN: {}", engine.internal_state.to_fen());
+        println!("{}", side_to_move_eval(&engine.internal_state));
+    } else {
+        println!("{}", engine.internal_state);
+    }
 }
 
-pub fn go(engine: &UCIEngine, cmd: &[&str]) -> (TimeControl, usize) {
-    let mut wtime: u64 = 0;
-    let mut btime: u64 = 0;
+pub fn side_to_move_eval(game_state: &GameState) -> String {
+    let eval = core_sdk::evaluation::eval_game_state(game_state).final_eval;
+    if game_state.get_color_to_move() == 0 {
+        format!("Eval (white to move, white's perspective): cp {}", eval)
+    } else {
+        format!("Eval (black to move, black's perspective): cp {}", -eval)
+    }
+}
+
+//The evaluation parameters are compiled in, so this is the set every evaluation runs with
+pub fn eval_params() -> String {
+    Parameters::default().to_string()
+}
+
+//Returns the time control, the depth and the node limit of the search
+pub fn go(engine: &UCIEngine, cmd: &[&str]) -> (TimeControl, usize, Option<u64>) {
+    let mut wtime: Option<u64> = None;
+    let mut btime: Option<u64> = None;
     let mut winc: u64 = 0;
     let mut binc: u64 = 0;
+    let mut movetime: Option<u64> = None;
+    let mut movestogo: Option<usize> = None;
+    let mut infinite = false;
     let mut depth = MAX_SEARCH_DEPTH;
-    if cmd[0].to_lowercase() == "infinite" {
-        return (TimeControl::Infinite, depth);
-    } else if cmd[0].to_lowercase() == "depth" {
-        depth = cmd[1].parse::<usize>().unwrap();
-        return (TimeControl::Infinite, depth);
-    }
+    let mut nodes: Option<u64> = None;
+    let value = |index: usize| cmd.get(index + 1).and_then(|v| v.parse::<u64>().ok());
     let mut index = 0;
-    let mut movestogo: Option<usize> = None;
     while index < cmd.len() {
-        match cmd[index] {
-            "wtime" => {
-                wtime = cmd[index + 1].parse::<u64>().unwrap_or(0);
-            }
-            "btime" => {
-                btime = cmd[index + 1].parse::<u64>().unwrap_or(0);
-            }
-            "winc" => {
-                winc = cmd[index + 1].parse::<u64>().unwrap_or(0);
+        let token = cmd[index].to_lowercase();
+        match &token[..] {
+            "infinite" => infinite = true,
+            "ponder" => {}
+            "wtime" => wtime = value(index),
+            "btime" => btime = value(index),
+            "winc" => winc = value(index).unwrap_or(0),
+            "binc" => binc = value(index).unwrap_or(0),
+            "movetime" => movetime = value(index),
+            "movestogo" => movestogo = value(index).map(|mvs| mvs as usize),
+            //Depth 0 would leave no move to play
+            "depth" => {
+                depth = value(index).map_or(depth, |d| d.clamp(1, MAX_SEARCH_DEPTH as u64) as usize)
             }
-            "binc" => {
-                binc = cmd[index + 1].parse::<u64>().unwrap_or(0);
-            }
-            "movetime" => {
-                let mvtime = cmd[index + 1].parse::<u64>().unwrap_or(0);
-                return (TimeControl::MoveTime(mvtime), depth);
+            //At least one node, as 0 stands for no limit
+            "nodes" => nodes = value(index).map(|n| n.max(1)),
+            _ => {
+                println!("Some parts of the go command weren't recognized well.");
+                index += 1;
+                continue;
             }
-            "movestogo" => movestogo = Some(cmd[index + 1].parse::<usize>().unwrap_or(1)),
-            _ => println!("Some parts of the go command weren't recognized well."),
         };
-        index += 2;
-    }
-    if movestogo.is_none() {
-        if engine.internal_state.get_color_to_move() == 0 {
-            (TimeControl::Incremental(wtime, winc), depth)
+        //Flags stand alone, everything else is followed by its value
+        index += if token == "infinite" || token == "ponder" {
+            1
         } else {
-            (TimeControl::Incremental(btime, binc), depth)
-        }
-    } else if let Some(mvs) = movestogo {
-        if mvs == 0 {
-            panic!("movestogo = 0");
-        }
-        if engine.internal_state.get_color_to_move() == 0 {
-            (TimeControl::Tournament(wtime, winc, mvs), depth)
-        } else {
-            (TimeControl::Tournament(btime, binc, mvs), depth)
+            2
+        };
+    }
+    let (time, inc) = if engine.internal_state.get_color_to_move() == 0 {
+        (wtime, winc)
+    } else {
+        (btime, binc)
+    };
+    let tc = if infinite {
+        TimeControl::Infinite
+    } else if let Some(movetime) = movetime {
+        TimeControl::MoveTime(movetime)
+    } else if let Some(time) = time {
+        //movestogo 0 or missing means sudden death
+        match movestogo {
+            Some(mvs) if mvs > 0 => TimeControl::Tournament(time, inc, mvs),
+            _ => TimeControl::Incremental(time, inc),
         }
     } else {
-        panic!("Something went wrong in go!");
-    }
+        TimeControl::Infinite
+    };
+    (tc, depth, nodes)
 }
 
-pub fn position(
-    engine: &mut UCIEngine,
-    cmd: &[&str],
-    movelist: &mut movegen::MoveList,
-) -> Vec<GameState> {
-    let mut move_index = 1;
-    match cmd[0] {
-        "fen" => {
-            let mut fen_string = String::new();
-            while move_index < cmd.len() && cmd[move_index].to_lowercase() != "moves" {
-                fen_string.push_str(cmd[move_index]);
-                fen_string.push_str(" ");
-                move_index += 1;
-            }
-            engine.internal_state = GameState::from_fen(fen_string.trim_end());
-        }
-        "startpos" => {
-            engine.internal_state = GameState::standard();
-        }
-        _ => {
-            panic!("Illegal position cmd");
-        }
-    }
-    let mut history: Vec<GameState> = vec![];
-    history.push(engine.internal_state.clone());
-    if move_index < cmd.len() && cmd[move_index].to_lowercase() == "moves" {
-        move_index += 1;
-        while move_index < cmd.len() {
-            //Parse the move and make it
-            let mv = cmd[move_index];
-            let (from, to, promo) = GameMove::string_to_move(mv);
-            engine.internal_state =
-                scout_and_make_draftmove(from, to, promo, &engine.internal_state, movelist);
-            history.push(engine.internal_state.clone());
-            move_index += 1;
+pub fn position(engine: &mut UCIEngine, cmd: &[&str]) -> Result<Vec<GameState>, String> {
+    let moves_index = cmd
+        .iter()
+        .position(|token| token.to_lowercase() == "moves")
+        .unwrap_or(cmd.len());
+    let state = match cmd.first() {
+        Some(&"fen") => GameState::try_from_fen(&cmd[1..moves_index].join(" "))?,
+        Some(&"startpos") => GameState::standard(),
+        _ => return Err(format!("Illegal position command: {}", cmd.join(" "))),
+    };
+    //A position with an illegal move is rejected as a whole
+    let previous_state = std::mem::replace(&mut engine.internal_state, state);
+    let mut history: Vec<GameState> = vec![engine.internal_state.clone()];
+    for mv in cmd.iter().skip(moves_index + 1) {
+        if let Err(e) = engine.make_uci_move(mv) {
+            engine.internal_state = previous_state;
+            return Err(e);
         }
+        history.push(engine.internal_state.clone());
     }
     history.pop();
-    history
-}
-
-pub fn scout_and_make_draftmove(
-    from: usize,
-    to: usize,
-    promo_pieces: Option<PieceType>,
-    game_state: &GameState,
-    movelist: &mut movegen::MoveList,
-) -> GameState {
-    movegen::generate_moves(&game_state, false, movelist);
-    for gmv in movelist.move_list.iter() {
-        let mv = gmv.0;
-        if mv.from as usize == from && mv.to as usize == to {
-            if let GameMoveType::Promotion(ps, _) = mv.move_type {
-                match promo_pieces {
-                    Some(piece) => {
-                        if piece != ps {
-                            continue;
-                        }
-                    }
-                    None => {
-                        continue;
-                    }
-                }
-            }
-            return make_move(&game_state, mv);
-        }
-    }
-    panic!("Invalid move; not found in list!");
+    Ok(history)
 }
 
 pub fn isready(itcs: &Arc<InterThreadCommunicationSystem>, print_rdy: bool) {
@@ -263,6 +350,21 @@ pub fn uci(engine: &UCIEngine, itcs: &InterThreadCommunicationSystem) {
         MIN_MOVE_OVERHEAD,
         MAX_MOVE_OVERHEAD
     );
+    println!(
+        "option name MinThinkTime type spin default {} min 0 max {}",
+        itcs.uci_options().min_think_time,
+        MAX_MIN_THINK_TIME
+    );
+    println!(
+        "option name BookExitBoost type spin default {} min 0 max {}",
+        itcs.uci_options().book_exit_boost,
+        MAX_BOOK_EXIT_BOOST
+    );
+    println!(
+        "option name BookExitMoves type spin default {} min 1 max {}",
+        itcs.uci_options().book_exit_moves,
+        MAX_BOOK_EXIT_MOVES
+    );
     println!(
         "option name DebugSMPPrint type check default {}",
         itcs.uci_options().debug_print
@@ -273,18 +375,74 @@ pub fn uci(engine: &UCIEngine, itcs: &InterThreadCommunicationSystem) {
         MIN_SKIP_RATIO,
         MAX_SKIP_RATIO
     );
+    println!(
+        "option name Ponder type check default {}",
+        itcs.uci_options().ponder
+    );
+    println!(
+        "option name UCI_LimitStrength type check default {}",
+        itcs.uci_options().limit_strength
+    );
+    println!(
+        "option name UCI_Elo type spin default {} min {} max {}",
+        itcs.uci_options().elo,
+        MIN_ELO,
+        MAX_ELO
+    );
+    println!(
+        "option name Contempt type spin default {} min {} max {}",
+        itcs.uci_options().contempt,
+        MIN_CONTEMPT,
+        MAX_CONTEMPT
+    );
+    println!(
+        "option name EvalRandomness type spin default {} min 0 max {}",
+        itcs.uci_options().eval_randomness,
+        MAX_EVAL_RANDOMNESS
+    );
+    println!(
+        "option name EvalRandomSeed type spin default {} min 0 max {}",
+        itcs.uci_options().eval_random_seed,
+        i32::MAX
+    );
+    println!(
+        "option name VerifyFailHigh type check default {}",
+        itcs.uci_options().verify_fail_high
+    );
+    println!(
+        "option name ScorePerspective type combo default {} var stm var white",
+        if itcs.uci_options().white_relative_score {
+            "white"
+        } else {
+            "stm"
+        }
+    );
+    println!(
+        "option name SanPv type check default {}",
+        itcs.uci_options().san_pv
+    );
+    println!(
+        "option name ReportBranchingFactor type check default {}",
+        itcs.uci_options().report_branching_factor
+    );
+    println!("option name SnapshotFile type string default <empty>");
+    println!(
+        "option name SnapshotInterval type spin default {} min {} max {}",
+        engine.snapshot_interval, MIN_SNAPSHOT_INTERVAL, MAX_SNAPSHOT_INTERVAL
+    );
     println!("uciok");
 }
 
-pub fn setoption(cmd: &[&str], itcs: &Arc<InterThreadCommunicationSystem>) {
+pub fn setoption(engine: &mut UCIEngine, cmd: &[&str], itcs: &Arc<InterThreadCommunicationSystem>) {
     let mut index = 0;
     while index < cmd.len() {
         let arg = cmd[index];
         match arg.to_lowercase().as_str() {
             "hash" => {
-                let num = cmd[index + 2]
-                    .parse::<usize>()
-                    .expect("Invalid Hash value!");
+                let num = match option_value::<usize>(cmd, index, "Hash") {
+                    Some(num) => num.min(MAX_HASH_SIZE),
+                    None => return,
+                };
                 itcs.uci_options().hash_size = num;
                 let num_threads = itcs.uci_options().threads;
                 *itcs.cache() = Cache::with_size_threaded(num, num_threads);
@@ -297,37 +455,286 @@ pub fn setoption(cmd: &[&str], itcs: &Arc<InterThreadCommunicationSystem>) {
                 return;
             }
             "threads" => {
-                let num = cmd[index + 2]
-                    .parse::<usize>()
-                    .expect("Invalid Threads value!");
+                let num = match option_value::<usize>(cmd, index, "Threads") {
+                    Some(num) => num.clamp(MIN_THREADS, MAX_THREADS),
+                    None => return,
+                };
                 InterThreadCommunicationSystem::update_thread_count(&itcs, num);
                 println!("info String Succesfully set Threads to {}", num);
                 return;
             }
             "moveoverhead" => {
-                let num = cmd[index + 2]
-                    .parse::<u64>()
-                    .expect("Invalid MoveOverhead value!");
+                let num = match option_value::<u64>(cmd, index, "MoveOverhead") {
+                    Some(num) => num.clamp(MIN_MOVE_OVERHEAD, MAX_MOVE_OVERHEAD),
+                    None => return,
+                };
                 itcs.uci_options().move_overhead = num;
                 println!("info String Succesfully set MoveOverhad to {}", num);
                 return;
             }
+            "minthinktime" => {
+                let num = match option_value::<u64>(cmd, index, "MinThinkTime") {
+                    Some(num) => num.min(MAX_MIN_THINK_TIME),
+                    None => return,
+                };
+                itcs.uci_options().min_think_time = num;
+                println!("info String Succesfully set MinThinkTime to {}", num);
+                return;
+            }
+            "bookexitboost" => {
+                let num = match option_value::<u64>(cmd, index, "BookExitBoost") {
+                    Some(num) => num.min(MAX_BOOK_EXIT_BOOST),
+                    None => return,
+                };
+                itcs.uci_options().book_exit_boost = num;
+                println!("info String Succesfully set BookExitBoost to {}", num);
+                return;
+            }
+            "bookexitmoves" => {
+                let num = match option_value::<usize>(cmd, index, "BookExitMoves") {
+                    Some(num) => num.clamp(1, MAX_BOOK_EXIT_MOVES),
+                    None => return,
+                };
+                itcs.uci_options().book_exit_moves = num;
+                println!("info String Succesfully set BookExitMoves to {}", num);
+                return;
+            }
             "debugsmpprint" => {
-                let val = cmd[index + 2]
-                    .parse::<bool>()
-                    .expect("Invalid DebugSMPPrint value!");
+                let val = match option_value::<bool>(cmd, index, "DebugSMPPrint") {
+                    Some(val) => val,
+                    None => return,
+                };
                 itcs.uci_options().debug_print = val;
                 println!("info String Succesfully set DebugSMPPrint to {}", val);
                 return;
             }
             "smpskipratio" => {
-                let num = cmd[index + 2]
-                    .parse::<usize>()
-                    .expect("Invalid SMPSkipRatio value!");
+                let num = match option_value::<usize>(cmd, index, "SMPSkipRatio") {
+                    Some(num) => num.clamp(MIN_SKIP_RATIO, MAX_SKIP_RATIO),
+                    None => return,
+                };
                 itcs.uci_options().skip_ratio = num;
                 println!("info String Succesfully set SMPSkipRatio to {}", num);
                 return;
             }
+            "ponder" => {
+                let val = match option_value::<bool>(cmd, index, "Ponder") {
+                    Some(val) => val,
+                    None => return,
+                };
+                itcs.uci_options().ponder = val;
+                println!("info String Succesfully set Ponder to {}", val);
+                return;
+            }
+            "verifyfailhigh" => {
+                let val = match option_value::<bool>(cmd, index, "VerifyFailHigh") {
+                    Some(val) => val,
+                    None => return,
+                };
+                itcs.uci_options().verify_fail_high = val;
+                println!("info String Succesfully set VerifyFailHigh to {}", val);
+                return;
+            }
+            "sanpv" => {
+                let val = match option_value::<bool>(cmd, index, "SanPv") {
+                    Some(val) => val,
+                    None => return,
+                };
+                itcs.uci_options().san_pv = val;
+                println!("info String Succesfully set SanPv to {}", val);
+                return;
+            }
+            "reportbranchingfactor" => {
+                let val = match option_value::<bool>(cmd, index, "ReportBranchingFactor") {
+                    Some(val) => val,
+                    None => return,
+                };
+                itcs.uci_options().report_branching_factor = val;
+                println!(
+                    "info String Succesfully set ReportBranchingFactor to {}",
+                    val
+                );
+                return;
+            }
+            "uci_limitstrength" => {
+                let val = match option_value::<bool>(cmd, index, "UCI_LimitStrength") {
+                    Some(val) => val,
+                    None => return,
+                };
+                itcs.uci_options().limit_strength = val;
+                println!("info String Succesfully set UCI_LimitStrength to {}", val);
+                return;
+            }
+            "uci_elo" => {
+                let num = match option_value::<usize>(cmd, index, "UCI_Elo") {
+                    Some(num) => num.clamp(MIN_ELO, MAX_ELO),
+                    None => return,
+                };
+                itcs.uci_options().elo = num;
+                println!("info String Succesfully set UCI_Elo to {}", num);
+                return;
+            }
+            "contempt" => {
+                let num = match option_value::<i16>(cmd, index, "Contempt") {
+                    Some(num) => num,
+                    None => return,
+                };
+                itcs.uci_options().contempt = num.clamp(MIN_CONTEMPT, MAX_CONTEMPT);
+                println!("info String Succesfully set Contempt to {}", num);
+                return;
+            }
+            "evalrandomness" => {
+                let num = match option_value::<i16>(cmd, index, "EvalRandomness") {
+                    Some(num) => num,
+                    None => return,
+                };
+                itcs.uci_options().eval_randomness = num.clamp(0, MAX_EVAL_RANDOMNESS);
+                println!("info String Succesfully set EvalRandomness to {}", num);
+                return;
+            }
+            "evalrandomseed" => {
+                let num = match option_value::<u64>(cmd, index, "EvalRandomSeed") {
+                    Some(num) => num,
+                    None => return,
+                };
+                itcs.uci_options().eval_random_seed = num;
+                println!("info String Succesfully set EvalRandomSeed to {}", num);
+                return;
+            }
+            "scoreperspective" => {
+                let white = match cmd.get(index + 2).map(|value| value.to_lowercase()) {
+                    Some(value) if value == "stm" => false,
+                    Some(value) if value == "white" => true,
+                    _ => {
+                        println!("info String Invalid ScorePerspective value!");
+                        return;
+                    }
+                };
+                itcs.uci_options().white_relative_score = white;
+                println!(
+                    "info String Succesfully set ScorePerspective to {}",
+                    cmd[index + 2]
+                );
+                return;
+            }
+            "snapshotfile" => {
+                //Paths may contain spaces
+                let path = cmd.get(index + 2..).unwrap_or_default().join(" ");
+                engine.snapshot_file = if path.is_empty() || path == "<empty>" {
+                    None
+                } else {
+                    Some(path)
+                };
+                println!(
+                    "info String Succesfully set SnapshotFile to {}",
+                    engine.snapshot_file.as_deref().unwrap_or("<empty>")
+                );
+                return;
+            }
+            "snapshotinterval" => {
+                let num = match option_value::<u64>(cmd, index, "SnapshotInterval") {
+                    Some(num) => num,
+                    None => return,
+                };
+                engine.snapshot_interval = num.clamp(MIN_SNAPSHOT_INTERVAL, MAX_SNAPSHOT_INTERVAL);
+                println!("info String Succesfully set SnapshotInterval to {}", num);
+                return;
+            }
+            //Hidden options for tuning
+            "lmrmindepth" => {
+                let num = match option_value::<i16>(cmd, index, "LMRMinDepth") {
+                    Some(num) => num,
+                    None => return,
+                };
+                itcs.uci_options().search_params.lmr_min_depth = num;
+                println!("info String Succesfully set LMRMinDepth to {}", num);
+                return;
+            }
+            "lmrminmoveindex" => {
+                let num = match option_value::<usize>(cmd, index, "LMRMinMoveIndex") {
+                    Some(num) => num,
+                    None => return,
+                };
+                itcs.uci_options().search_params.lmr_min_move_index = num;
+                println!("info String Succesfully set LMRMinMoveIndex to {}", num);
+                return;
+            }
+            "aspirationdelta" => {
+                let num = match option_value::<i16>(cmd, index, "AspirationDelta") {
+                    Some(num) => num.max(1),
+                    None => return,
+                };
+                itcs.uci_options().search_params.aspiration_delta = num;
+                println!("info String Succesfully set AspirationDelta to {}", num);
+                return;
+            }
+            "aspirationwidening" => {
+                let num = match option_value::<f64>(cmd, index, "AspirationWidening") {
+                    Some(num) => num.max(1.1),
+                    None => return,
+                };
+                itcs.uci_options().search_params.aspiration_widening = num;
+                println!("info String Succesfully set AspirationWidening to {}", num);
+                return;
+            }
+            "maxqsearchply" => {
+                let num = match option_value::<i16>(cmd, index, "MaxQSearchPly") {
+                    Some(num) => num,
+                    None => return,
+                };
+                itcs.uci_options().search_params.max_qsearch_ply = num.max(1);
+                println!("info String Succesfully set MaxQSearchPly to {}", num);
+                return;
+            }
+            "qsearchstandpatmargin" => {
+                let num = match option_value::<i16>(cmd, index, "QSearchStandPatMargin") {
+                    Some(num) => num,
+                    None => return,
+                };
+                itcs.uci_options().search_params.stand_pat_margin = num;
+                println!(
+                    "info String Succesfully set QSearchStandPatMargin to {}",
+                    num
+                );
+                return;
+            }
+            //Hidden options for debugging the search
+            "nullmove" => {
+                let val = match option_value::<bool>(cmd, index, "NullMove") {
+                    Some(val) => val,
+                    None => return,
+                };
+                itcs.uci_options().search_params.null_move_pruning = val;
+                println!("info String Succesfully set NullMove to {}", val);
+                return;
+            }
+            "staticnullmove" => {
+                let val = match option_value::<bool>(cmd, index, "StaticNullMove") {
+                    Some(val) => val,
+                    None => return,
+                };
+                itcs.uci_options().search_params.static_null_move_pruning = val;
+                println!("info String Succesfully set StaticNullMove to {}", val);
+                return;
+            }
+            "futility" => {
+                let val = match option_value::<bool>(cmd, index, "Futility") {
+                    Some(val) => val,
+                    None => return,
+                };
+                itcs.uci_options().search_params.futility_pruning = val;
+                println!("info String Succesfully set Futility to {}", val);
+                return;
+            }
+            "lmr" => {
+                let val = match option_value::<bool>(cmd, index, "LMR") {
+                    Some(val) => val,
+                    None => return,
+                };
+                itcs.uci_options().search_params.late_move_reductions = val;
+                println!("info String Succesfully set LMR to {}", val);
+                return;
+            }
             _ => {
                 index += 1;
             }
@@ -335,6 +742,443 @@ pub fn setoption(cmd: &[&str], itcs: &Arc<InterThreadCommunicationSystem>) {
     }
 }
 
+//The value follows the `value` token. Malformed values are reported instead of crashing the engine
+fn option_value<T: FromStr>(cmd: &[&str], index: usize, name: &str) -> Option<T> {
+    let value = cmd.get(index + 2).and_then(|value| value.parse::<T>().ok());
+    if value.is_none() {
+        println!("info String Invalid {} value!", name);
+    }
+    value
+}
+
 pub fn newgame(engine: &mut UCIEngine) {
     engine.internal_state = GameState::standard();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        eval_params, go, isready, run_loop, setoption, side_to_move_eval, start_search, stop_search,
+    };
+    use crate::uci_engine::UCIEngine;
+    use core_sdk::board_representation::game_state::GameState;
+    use core_sdk::evaluation::params::{PAWN_PIECE_VALUE, TEMPO_BONUS};
+    use core_sdk::move_generation::makemove::make_move;
+    use core_sdk::move_generation::movegen::{generate_moves, MoveList};
+    use core_sdk::search::cache::Cache;
+    use core_sdk::search::info_sink::{InfoSink, SearchInfo};
+    use core_sdk::search::searcher::{
+        search_move, InterThreadCommunicationSystem, MAX_SKIP_RATIO, MIN_SKIP_RATIO,
+    };
+    use core_sdk::search::timecontrol::{TimeControl, DEFAULT_MOVE_OVERHEAD, MAX_MOVE_OVERHEAD};
+    use core_sdk::search::{PrincipalVariation, MAX_SEARCH_DEPTH};
+    use std::sync::atomic::Ordering;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn go_test() {
+        let mut engine = UCIEngine::standard();
+        let black_to_move =
+            GameState::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+        let budget = |engine: &UCIEngine, cmd: &str| {
+            let cmd: Vec<&str> = cmd.split_whitespace().collect();
+            let (tc, depth, _) = go(engine, &cmd);
+            let budget = match tc {
+                TimeControl::Infinite => None,
+                tc => Some(tc.normal_time(0, DEFAULT_MOVE_OVERHEAD) + DEFAULT_MOVE_OVERHEAD),
+            };
+            (budget, depth)
+        };
+        let sudden_death = "wtime 60000 btime 30000 winc 1000 binc 500";
+        assert_eq!(
+            budget(&engine, sudden_death),
+            (Some(3000), MAX_SEARCH_DEPTH)
+        );
+        //Zero increments
+        assert_eq!(
+            budget(&engine, "wtime 3000 btime 3000 winc 0 binc 0"),
+            (Some(100), MAX_SEARCH_DEPTH)
+        );
+        //The time left is split among the moves to go
+        assert_eq!(
+            budget(
+                &engine,
+                "wtime 60000 btime 60000 winc 0 binc 0 movestogo 20"
+            ),
+            (Some(3000), MAX_SEARCH_DEPTH)
+        );
+        assert_eq!(
+            budget(&engine, "wtime 60000 btime 60000 movestogo 0"),
+            (Some(2000), MAX_SEARCH_DEPTH)
+        );
+        //Movetime is exact
+        assert_eq!(
+            budget(&engine, "movetime 1500"),
+            (Some(1500), MAX_SEARCH_DEPTH)
+        );
+        assert_eq!(budget(&engine, "infinite"), (None, MAX_SEARCH_DEPTH));
+        assert_eq!(budget(&engine, "depth 7"), (None, 7));
+        assert_eq!(budget(&engine, "depth 0"), (None, 1));
+        assert_eq!(budget(&engine, "depth 100000"), (None, MAX_SEARCH_DEPTH));
+        assert_eq!(budget(&engine, ""), (None, MAX_SEARCH_DEPTH));
+        assert_eq!(
+            budget(&engine, "ponder wtime 60000 btime 30000 winc 1000 binc 500"),
+            (Some(3000), MAX_SEARCH_DEPTH)
+        );
+
+        //Black uses its own clock
+        engine.internal_state = black_to_move;
+        assert_eq!(
+            budget(&engine, sudden_death),
+            (Some(1500), MAX_SEARCH_DEPTH)
+        );
+        assert_eq!(
+            budget(
+                &engine,
+                "wtime 60000 btime 20000 winc 0 binc 0 movestogo 10"
+            ),
+            (Some(2000), MAX_SEARCH_DEPTH)
+        );
+    }
+
+    #[test]
+    fn go_nodes_test() {
+        let engine = UCIEngine::standard();
+        let parse = |cmd: &str| {
+            let cmd: Vec<&str> = cmd.split_whitespace().collect();
+            let (tc, _, nodes) = go(&engine, &cmd);
+            (tc, nodes)
+        };
+        let (tc, nodes) = parse("wtime 10000 btime 10000 winc 0 binc 0 nodes 5000");
+        assert!(matches!(tc, TimeControl::Incremental(10000, 0)));
+        assert_eq!(nodes, Some(5000));
+        assert_eq!(parse("nodes 0").1, Some(1));
+        assert_eq!(parse("depth 5").1, None);
+
+        let itcs = Arc::new(InterThreadCommunicationSystem::default());
+        InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+        *itcs.cache() = Cache::with_size_threaded(1, 1);
+        itcs.uci_options().quiet = true;
+        let state = GameState::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        );
+        let mut movelist = MoveList::default();
+        generate_moves(&state, false, &mut movelist);
+        for &limit in [1, 1000, 20000].iter() {
+            itcs.node_limit.store(limit, Ordering::Relaxed);
+            itcs.cache().clear_threaded(1);
+            search_move(
+                Arc::clone(&itcs),
+                MAX_SEARCH_DEPTH as i16,
+                state.clone(),
+                Vec::new(),
+                TimeControl::Infinite,
+            );
+            //Within the tolerance of the referee once the first iteration is done, and still with a move to play
+            if limit > 1 {
+                assert!(itcs.get_nodes_sum() as f64 <= limit as f64 * 1.1);
+            }
+            let mv = itcs.played_pv().pv.pv[0].unwrap();
+            assert!(movelist.move_list.iter().any(|gmv| gmv.0 == mv));
+        }
+        assert!(itcs.get_nodes_sum() > 10000);
+    }
+
+    #[test]
+    fn eval_params_test() {
+        let params = eval_params();
+        let line = |name: &str| {
+            params
+                .lines()
+                .find(|line| line.starts_with(&format!("pub const {}:", name)))
+                .unwrap()
+                .to_owned()
+        };
+        for (name, score) in [
+            ("TEMPO_BONUS", TEMPO_BONUS),
+            ("PAWN_PIECE_VALUE", PAWN_PIECE_VALUE),
+        ]
+        .iter()
+        {
+            assert_eq!(
+                line(name),
+                format!(
+                    "pub const {}: EvaluationScore = EvaluationScore({}, {});",
+                    name, score.0, score.1
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn flipped_board_test() {
+        let state =
+            GameState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq -");
+        let cells = |board: String| -> Vec<Vec<String>> {
+            board
+                .lines()
+                .filter(|line| line.starts_with('|'))
+                .map(|line| {
+                    line.split('|')
+                        .map(|cell| cell.trim().to_owned())
+                        .filter(|cell| !cell.is_empty())
+                        .collect()
+                })
+                .collect()
+        };
+        let normal = cells(state.board_string(false));
+        let mut flipped = cells(state.board_string(true));
+        assert_eq!(flipped.len(), 8);
+        assert_ne!(normal, flipped);
+        flipped.reverse();
+        for rank in flipped.iter_mut() {
+            rank.reverse();
+        }
+        assert_eq!(normal, flipped);
+
+        let eval = core_sdk::evaluation::eval_game_state(&state).final_eval;
+        assert_eq!(
+            side_to_move_eval(&state),
+            format!("Eval (black to move, black's perspective): cp {}", -eval)
+        );
+    }
+
+    #[test]
+    fn isready_during_search_test() {
+        let engine = UCIEngine::standard();
+        let itcs = Arc::new(InterThreadCommunicationSystem::default());
+        InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+        *itcs.cache() = Cache::with_size_threaded(1, 1);
+        itcs.uci_options().quiet = true;
+        let mut search = Some(start_search(
+            &engine,
+            &itcs,
+            &[],
+            TimeControl::Infinite,
+            MAX_SEARCH_DEPTH,
+        ));
+        thread::sleep(Duration::from_millis(50));
+
+        //readyok is printed right away and the search keeps going
+        let start = Instant::now();
+        isready(&itcs, true);
+        assert!(start.elapsed() < Duration::from_millis(20));
+        thread::sleep(Duration::from_millis(50));
+        assert!(!search.as_ref().unwrap().is_finished());
+
+        stop_search(&itcs, &mut search);
+        assert!(search.is_none());
+        assert!(itcs.played_pv().pv.pv[0].is_some());
+        //Stopping without a running search does nothing
+        stop_search(&itcs, &mut search);
+    }
+
+    #[test]
+    fn ponder_test() {
+        let engine = UCIEngine::standard();
+        let itcs = Arc::new(InterThreadCommunicationSystem::default());
+        InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+        *itcs.cache() = Cache::with_size_threaded(1, 1);
+        itcs.uci_options().quiet = true;
+        let ponder = |tc: TimeControl, depth: usize| {
+            itcs.pondering.store(true, Ordering::SeqCst);
+            let search = start_search(&engine, &itcs, &[], tc, depth);
+            //Neither the time control nor the depth limit end a ponder search
+            thread::sleep(Duration::from_millis(200));
+            assert!(!search.is_finished());
+            search
+        };
+
+        //After ponderhit the real time control applies, the time spent pondering counts
+        let search = ponder(TimeControl::MoveTime(50), MAX_SEARCH_DEPTH);
+        itcs.ponderhit();
+        let start = Instant::now();
+        search.join().unwrap();
+        assert!(start.elapsed() < Duration::from_millis(150));
+        assert!(itcs.played_pv().pv.pv[0].is_some());
+
+        //A completed ponder search waits for ponderhit
+        let search = ponder(TimeControl::Infinite, 2);
+        itcs.ponderhit();
+        search.join().unwrap();
+        assert_eq!(itcs.played_pv().depth, 2);
+
+        //Stop ends pondering with a bestmove
+        let mut search = Some(ponder(TimeControl::MoveTime(50), MAX_SEARCH_DEPTH));
+        stop_search(&itcs, &mut search);
+        assert!(itcs.played_pv().pv.pv[0].is_some());
+    }
+
+    #[test]
+    fn ponderhit_continues_test() {
+        struct DepthSink(Mutex<Vec<usize>>);
+        impl InfoSink for DepthSink {
+            fn on_iteration(&self, info: &SearchInfo) {
+                self.0.lock().unwrap().push(info.depth);
+            }
+        }
+        let engine = UCIEngine::standard();
+        let itcs = Arc::new(InterThreadCommunicationSystem::default());
+        InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+        *itcs.cache() = Cache::with_size_threaded(1, 1);
+        itcs.uci_options().quiet = true;
+        let sink = Arc::new(DepthSink(Mutex::new(Vec::new())));
+        itcs.set_info_sink(Some(sink.clone()));
+        itcs.pondering.store(true, Ordering::SeqCst);
+        let start = Instant::now();
+        let search = start_search(
+            &engine,
+            &itcs,
+            &[],
+            TimeControl::MoveTime(600),
+            MAX_SEARCH_DEPTH,
+        );
+        thread::sleep(Duration::from_millis(300));
+        let pondered_depth = *sink.0.lock().unwrap().last().unwrap();
+        itcs.ponderhit();
+        search.join().unwrap();
+        //The budget started with go ponder, and the iterations went on instead of starting over at depth 1
+        assert!(start.elapsed() < Duration::from_millis(800));
+        let depths = sink.0.lock().unwrap();
+        assert!(depths.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(itcs.played_pv().depth >= pondered_depth);
+        assert!(itcs.get_time_elapsed() >= 300);
+    }
+
+    #[test]
+    fn score_perspective_test() {
+        let mut engine = UCIEngine::standard();
+        let itcs = Arc::new(InterThreadCommunicationSystem::default());
+        InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+        *itcs.cache() = Cache::with_size_threaded(1, 1);
+        itcs.uci_options().quiet = true;
+        //Black to move and a queen down
+        let state = GameState::from_fen("4k3/8/8/8/8/8/8/Q3K3 b - - 0 1");
+        let mut score = |perspective: &str| {
+            setoption(
+                &mut engine,
+                &["name", "ScorePerspective", "value", perspective],
+                &itcs,
+            );
+            itcs.cache().clear_threaded(1);
+            search_move(
+                Arc::clone(&itcs),
+                4,
+                state.clone(),
+                Vec::new(),
+                TimeControl::Infinite,
+            );
+            let line = itcs.last_info_line.lock().unwrap().clone().unwrap().1;
+            line.split_whitespace()
+                .nth(2)
+                .unwrap()
+                .parse::<i16>()
+                .unwrap()
+        };
+        let stm = score("stm");
+        assert!(stm < 0);
+        assert_eq!(score("white"), -stm);
+        //Invalid values leave the setting untouched
+        assert_eq!(score("black"), -stm);
+    }
+
+    #[test]
+    fn san_pv_test() {
+        let mut engine = UCIEngine::standard();
+        let itcs = Arc::new(InterThreadCommunicationSystem::default());
+        InterThreadCommunicationSystem::update_thread_count(&itcs, 1);
+        *itcs.cache() = Cache::with_size_threaded(1, 1);
+        itcs.uci_options().quiet = true;
+        let state = GameState::from_fen("6k1/5ppp/8/8/8/1N3N2/8/4R1K1 w - - 0 1");
+        let mut pv_line = |san: &str| {
+            setoption(&mut engine, &["name", "SanPv", "value", san], &itcs);
+            itcs.cache().clear_threaded(1);
+            search_move(
+                Arc::clone(&itcs),
+                3,
+                state.clone(),
+                Vec::new(),
+                TimeControl::Infinite,
+            );
+            let line = itcs.last_info_line.lock().unwrap().clone().unwrap().1;
+            line.split(" pv ").nth(1).unwrap().trim().to_owned()
+        };
+        assert_eq!(pv_line("false"), "e1e8");
+        assert_eq!(pv_line("true"), "Re8#");
+
+        //Both knights can go to d4 from the root, but not anymore once one of them went to d2
+        let mut pv = PrincipalVariation::new(3);
+        let mut position = state.clone();
+        for (ply, lan) in ["b3d2", "h7h6", "f3d4"].iter().enumerate() {
+            let mut movelist = MoveList::default();
+            generate_moves(&position, false, &mut movelist);
+            let mv = movelist
+                .move_list
+                .iter()
+                .map(|gmv| gmv.0)
+                .find(|mv| format!("{:?}", mv) == *lan)
+                .unwrap();
+            pv.pv[ply] = Some(mv);
+            position = make_move(&position, mv);
+        }
+        assert_eq!(pv.to_san(&state), "Nbd2 h6 Nd4");
+    }
+
+    #[test]
+    fn setoption_clamp_test() {
+        let mut engine = UCIEngine::standard();
+        let itcs = Arc::new(InterThreadCommunicationSystem::default());
+        let mut set = |name: &str, value: &str| {
+            setoption(&mut engine, &["name", name, "value", value], &itcs);
+        };
+        //A skip ratio of 0 would divide by zero when picking the depth of a helper thread
+        set("SMPSkipRatio", "0");
+        set("MoveOverhead", "1000000");
+        assert_eq!(itcs.uci_options().skip_ratio, MIN_SKIP_RATIO);
+        assert_eq!(itcs.uci_options().move_overhead, MAX_MOVE_OVERHEAD);
+        set("SMPSkipRatio", "100000");
+        set("MoveOverhead", "30");
+        assert_eq!(itcs.uci_options().skip_ratio, MAX_SKIP_RATIO);
+        assert_eq!(itcs.uci_options().move_overhead, 30);
+    }
+
+    #[test]
+    fn malformed_input_test() {
+        let after_d4 = "rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq d3 0 1";
+        let input = "position startpos moves d2d4
+xyzzy
+
+register later
+register name Someone code 1234
+setoption name Hash value lots
+setoption name Threads value 0
+setoption name
+setoption
+position
+position fen garbage
+position fen 8/8/8/8/8/8/8/8 w - - 0 1
+position fen rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1
+position startpos moves e2e5
+position startpos moves e2e4 e7e5 a1a8
+perft
+perft abc
+go depth abc wtime
+stop
+quit
+position startpos
+";
+        let engine = run_loop(input.as_bytes());
+        //Rejected positions leave the last valid one untouched, and nothing after quit is read
+        assert_eq!(engine.position().to_fen(), after_d4);
+
+        //Unknown tokens in front of a command are skipped
+        let engine = run_loop("joho position startpos moves d2d4\n".as_bytes());
+        assert_eq!(engine.position().to_fen(), after_d4);
+        //Invalid UTF-8 doesn't end the input
+        let mut input = b"\xff\xfe\n".to_vec();
+        input.extend_from_slice(b"position startpos moves d2d4\n");
+        assert_eq!(run_loop(&input[..]).position().to_fen(), after_d4);
+    }
+}